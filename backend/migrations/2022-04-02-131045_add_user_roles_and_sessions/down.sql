@@ -0,0 +1,5 @@
+DROP TABLE user_sessions;
+
+ALTER TABLE users DROP COLUMN role;
+
+DROP TYPE USER_ROLE;
@@ -0,0 +1,19 @@
+CREATE TYPE USER_ROLE AS ENUM ('cashier', 'treasurer', 'admin');
+
+ALTER TABLE users ADD COLUMN role USER_ROLE NOT NULL DEFAULT 'cashier';
+
+COMMENT ON COLUMN users.role IS
+    'The access level of the user. Each role is granted everything the roles before it are.';
+
+COMMENT ON COLUMN users.salted_pass IS
+    'The hex-encoded salt and password hash, separated by a colon.';
+
+CREATE TABLE user_sessions (
+    token VARCHAR(64) PRIMARY KEY,
+    user_name VARCHAR(64) NOT NULL REFERENCES users(name) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+COMMENT ON TABLE user_sessions IS
+    'Active login sessions. A session is valid until expires_at, or until the user logs out.';
@@ -0,0 +1,2 @@
+DROP TABLE stock_take_counts;
+DROP TABLE stock_takes;
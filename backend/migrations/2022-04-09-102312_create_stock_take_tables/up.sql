@@ -0,0 +1,23 @@
+CREATE TABLE stock_takes (
+    id SERIAL PRIMARY KEY,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    started_by VARCHAR(64) REFERENCES users(name) ON DELETE SET NULL,
+    committed_at TIMESTAMP WITH TIME ZONE,
+    transaction_id INTEGER REFERENCES transactions(id)
+);
+
+COMMENT ON TABLE stock_takes IS
+    'A count of the physical inventory. When committed, the difference against inventory_stock
+    is recorded as a correction transaction.';
+
+COMMENT ON COLUMN stock_takes.transaction_id IS
+    'The correction transaction. NULL if the stock take is not committed, or if no correction was needed.';
+
+CREATE TABLE stock_take_counts (
+    stock_take_id INTEGER NOT NULL REFERENCES stock_takes(id) ON DELETE CASCADE,
+    item_id INTEGER NOT NULL REFERENCES inventory(id) ON DELETE CASCADE,
+    counted INTEGER NOT NULL CHECK (counted >= 0),
+    PRIMARY KEY (stock_take_id, item_id)
+);
+
+COMMENT ON TABLE stock_take_counts IS 'The counted quantity of an item in a stock take.';
@@ -0,0 +1,2 @@
+DROP TABLE purchase_lines;
+DROP TABLE purchases;
@@ -0,0 +1,23 @@
+CREATE TABLE purchases (
+    id SERIAL PRIMARY KEY,
+    transaction_id INTEGER NOT NULL UNIQUE REFERENCES transactions(id) ON DELETE CASCADE,
+    supplier TEXT NOT NULL,
+    invoice_reference TEXT,
+    recorded_by VARCHAR(64) REFERENCES users(name) ON DELETE SET NULL
+);
+
+COMMENT ON TABLE purchases IS
+    'A purchase of inventory from a supplier. The stock and the payment are recorded in the transaction.';
+
+COMMENT ON COLUMN purchases.invoice_reference IS 'The invoice or receipt number from the supplier.';
+
+CREATE TABLE purchase_lines (
+    id SERIAL PRIMARY KEY,
+    purchase_id INTEGER NOT NULL REFERENCES purchases(id) ON DELETE CASCADE,
+    item_id INTEGER NOT NULL REFERENCES inventory(id),
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    unit_cost INTEGER NOT NULL CHECK (unit_cost >= 0)
+);
+
+COMMENT ON TABLE purchase_lines IS 'A single inventory item in a purchase.';
+COMMENT ON COLUMN purchase_lines.unit_cost IS 'The cost price of one unit of the item, in öre.';
@@ -0,0 +1,5 @@
+DROP TRIGGER refresh_book_account_balances ON transactions;
+DROP TRIGGER refresh_book_account_balances ON book_accounts;
+DROP FUNCTION refresh_book_account_balances();
+DROP MATERIALIZED VIEW book_account_balances;
+DROP FUNCTION book_account_balances_at(TIMESTAMP WITH TIME ZONE);
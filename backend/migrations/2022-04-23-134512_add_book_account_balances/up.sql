@@ -0,0 +1,37 @@
+-- The balance of every book account, counting only transactions made at or before a point in time
+CREATE FUNCTION book_account_balances_at(balance_time TIMESTAMP WITH TIME ZONE)
+RETURNS TABLE (id INTEGER, balance INTEGER) LANGUAGE sql STABLE AS $$
+SELECT a.id, COALESCE(SUM(
+    -- debiting increases the balance of expense and asset accounts, crediting decreases it
+    (CASE WHEN t.debited_account = a.id THEN t.amount ELSE 0 END
+        - CASE WHEN t.credited_account = a.id THEN t.amount ELSE 0 END)
+    * CASE WHEN a.account_type IN ('expenses', 'assets') THEN 1 ELSE -1 END
+), 0)::INTEGER
+FROM book_accounts AS a
+    LEFT JOIN transactions AS t
+        ON (t.debited_account = a.id OR t.credited_account = a.id)
+        AND t.deleted_at IS NULL
+        AND t.time <= balance_time
+GROUP BY a.id;
+$$;
+
+CREATE MATERIALIZED VIEW book_account_balances AS
+SELECT * FROM book_account_balances_at('infinity');
+
+CREATE FUNCTION refresh_book_account_balances()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    REFRESH MATERIALIZED VIEW book_account_balances;
+    RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER refresh_book_account_balances
+AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE
+ON book_accounts
+EXECUTE PROCEDURE refresh_book_account_balances();
+
+CREATE TRIGGER refresh_book_account_balances
+AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE
+ON transactions
+EXECUTE PROCEDURE refresh_book_account_balances();
@@ -0,0 +1 @@
+DROP TABLE bas_accounts;
@@ -0,0 +1,8 @@
+CREATE TABLE bas_accounts (
+    book_account_id INTEGER PRIMARY KEY REFERENCES book_accounts(id) ON DELETE CASCADE,
+    bas_number INTEGER NOT NULL CHECK (bas_number BETWEEN 1000 AND 9999)
+);
+
+COMMENT ON TABLE bas_accounts IS
+'Maps book accounts to accounts in the BAS chart of accounts, for exporting to bookkeeping programs.
+Book accounts without a mapping are exported to a default account based on their type.';
@@ -0,0 +1,17 @@
+CREATE TABLE transaction_revisions (
+    id SERIAL PRIMARY KEY,
+    transaction_id INTEGER NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    revised_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    revised_by VARCHAR(64) REFERENCES users(name) ON DELETE SET NULL,
+    old_value JSONB NOT NULL,
+    new_value JSONB NOT NULL
+);
+
+COMMENT ON TABLE transaction_revisions IS
+'The history of changes made to transactions after they were created.';
+
+COMMENT ON COLUMN transaction_revisions.old_value IS
+'The description, accounts, amount and bundles of the transaction before the change.';
+
+COMMENT ON COLUMN transaction_revisions.new_value IS
+'The description, accounts, amount and bundles of the transaction after the change.';
@@ -0,0 +1,2 @@
+ALTER TABLE izettle_transaction DROP COLUMN terminal;
+DROP TABLE izettle_terminals;
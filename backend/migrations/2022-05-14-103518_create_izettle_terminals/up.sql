@@ -0,0 +1,13 @@
+CREATE TABLE izettle_terminals (
+    id VARCHAR(64) PRIMARY KEY,
+    name TEXT NOT NULL
+);
+
+COMMENT ON TABLE izettle_terminals IS
+'The card terminals which payment bridges may connect as.';
+
+ALTER TABLE izettle_transaction
+    ADD COLUMN terminal VARCHAR(64) REFERENCES izettle_terminals(id) ON DELETE SET NULL;
+
+COMMENT ON COLUMN izettle_transaction.terminal IS
+'The terminal which should charge the payment, or NULL if any terminal may.';
@@ -0,0 +1 @@
+DROP TABLE izettle_refunds;
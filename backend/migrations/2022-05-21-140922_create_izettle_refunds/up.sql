@@ -0,0 +1,21 @@
+CREATE TABLE izettle_refunds (
+    id SERIAL PRIMARY KEY,
+    izettle_transaction_id INTEGER NOT NULL
+        REFERENCES izettle_post_transaction(izettle_transaction_id),
+    terminal VARCHAR(64) REFERENCES izettle_terminals(id) ON DELETE SET NULL,
+    requested_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    requested_by VARCHAR(64) REFERENCES users(name) ON DELETE SET NULL,
+    status TEXT NOT NULL,
+    error TEXT,
+    transaction_id INTEGER REFERENCES transactions(id)
+);
+
+-- a payment can only be refunded once
+CREATE UNIQUE INDEX izettle_refunds_active_idx ON izettle_refunds (izettle_transaction_id)
+    WHERE status IN ('in_progress', 'refunded');
+
+COMMENT ON TABLE izettle_refunds IS
+'Requests to refund card payments through the payment terminal.';
+
+COMMENT ON COLUMN izettle_refunds.transaction_id IS
+'The transaction which reverses the payment, once it has been refunded.';
@@ -0,0 +1 @@
+DROP TABLE swish_payments;
@@ -0,0 +1,23 @@
+CREATE TABLE swish_payments (
+    id SERIAL PRIMARY KEY,
+    time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    new_transaction JSONB NOT NULL,
+    amount INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    error TEXT,
+    payment_reference TEXT,
+    payer_alias TEXT,
+    transaction_id INTEGER REFERENCES transactions(id)
+);
+
+COMMENT ON TABLE swish_payments IS
+'Payments which the customer pays by scanning a Swish QR code.';
+
+COMMENT ON COLUMN swish_payments.new_transaction IS
+'The transaction which is committed once the payment has been confirmed.';
+
+COMMENT ON COLUMN swish_payments.payment_reference IS
+'The reference of the payment at Swish, from the callback.';
+
+COMMENT ON COLUMN swish_payments.payer_alias IS
+'The phone number of the customer, from the callback.';
@@ -0,0 +1,29 @@
+CREATE TABLE cash_register_sessions (
+    id SERIAL PRIMARY KEY,
+    opened_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    opened_by VARCHAR(64) REFERENCES users(name) ON DELETE SET NULL,
+    opening_float INTEGER NOT NULL CHECK (opening_float >= 0),
+    opening_transaction_id INTEGER REFERENCES transactions(id),
+    closed_at TIMESTAMP WITH TIME ZONE,
+    closed_by VARCHAR(64) REFERENCES users(name) ON DELETE SET NULL,
+    expected_cash INTEGER,
+    counted_cash INTEGER CHECK (counted_cash >= 0),
+    closing_transaction_id INTEGER REFERENCES transactions(id),
+    CHECK ((closed_at IS NULL) = (expected_cash IS NULL)),
+    CHECK ((closed_at IS NULL) = (counted_cash IS NULL))
+);
+
+-- only one session may be open at a time
+CREATE UNIQUE INDEX cash_register_sessions_open
+    ON cash_register_sessions ((closed_at IS NULL))
+    WHERE closed_at IS NULL;
+
+COMMENT ON TABLE cash_register_sessions IS
+    'A shift at the cash register. When closed, the counted cash is compared against the opening
+    float plus the cash transactions made during the session.';
+
+COMMENT ON COLUMN cash_register_sessions.opening_transaction_id IS
+    'Correction transaction for when the opening float differed from the balance of the cash account.';
+
+COMMENT ON COLUMN cash_register_sessions.closing_transaction_id IS
+    'Correction transaction for when the counted cash differed from the expected cash.';
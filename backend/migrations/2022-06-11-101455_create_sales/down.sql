@@ -0,0 +1,3 @@
+DROP TABLE pending_sale_payments;
+DROP TABLE sale_transactions;
+DROP TABLE sales;
@@ -0,0 +1,28 @@
+CREATE TABLE sales (
+    id SERIAL PRIMARY KEY,
+    time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE sales IS
+    'A purchase which was paid from several sources, e.g. partly by card and partly in cash.';
+
+CREATE TABLE sale_transactions (
+    transaction_id INTEGER PRIMARY KEY REFERENCES transactions(id) ON DELETE CASCADE,
+    sale_id INTEGER NOT NULL REFERENCES sales(id) ON DELETE CASCADE
+);
+
+COMMENT ON TABLE sale_transactions IS 'The transactions, one per payment, which make up a sale.';
+
+CREATE TABLE pending_sale_payments (
+    id SERIAL PRIMARY KEY,
+    izettle_transaction_id INTEGER
+        REFERENCES izettle_post_transaction(izettle_transaction_id) ON DELETE CASCADE,
+    swish_payment_id INTEGER REFERENCES swish_payments(id) ON DELETE CASCADE,
+    debited_account INTEGER NOT NULL REFERENCES book_accounts(id),
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    CHECK ((izettle_transaction_id IS NULL) <> (swish_payment_id IS NULL))
+);
+
+COMMENT ON TABLE pending_sale_payments IS
+    'The remaining payments of a sale which is waiting for a card or Swish payment. They are
+    recorded as transactions together with the card or Swish payment.';
@@ -0,0 +1,2 @@
+DROP TABLE credit_policy;
+ALTER TABLE book_accounts DROP COLUMN credit_limit;
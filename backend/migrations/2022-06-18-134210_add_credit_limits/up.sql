@@ -0,0 +1,10 @@
+-- how far below zero the balance of a tillgodo account may go, NULL means the default applies
+ALTER TABLE book_accounts ADD COLUMN credit_limit INTEGER CHECK (credit_limit >= 0);
+
+-- a single row holding the default credit limit of tillgodo accounts, NULL means no limit
+CREATE TABLE credit_policy (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    default_credit_limit INTEGER CHECK (default_credit_limit >= 0)
+);
+
+INSERT INTO credit_policy (default_credit_limit) VALUES (0);
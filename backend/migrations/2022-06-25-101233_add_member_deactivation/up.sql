@@ -0,0 +1,2 @@
+-- deactivated members are hidden from the store, but their history is kept
+ALTER TABLE members ADD COLUMN deactivated_at TIMESTAMP WITH TIME ZONE;
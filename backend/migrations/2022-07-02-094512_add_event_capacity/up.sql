@@ -0,0 +1,23 @@
+-- how many may attend the event, later signups are put on the waitlist. NULL means no limit
+ALTER TABLE events ADD COLUMN capacity INTEGER CHECK (capacity > 0);
+
+-- recreate the view to include the new column
+DROP VIEW events_with_signups;
+
+CREATE VIEW events_with_signups AS
+SELECT
+    events.*,
+    COALESCE(t_signup_count.count, 0) AS signups
+FROM
+    events
+    LEFT JOIN
+        (
+            SELECT
+                count(id),
+                event
+            FROM
+                event_signups
+            GROUP BY
+                event
+        ) t_signup_count
+    ON events.id = t_signup_count.event;
@@ -0,0 +1,23 @@
+DROP VIEW events_with_signups;
+
+ALTER TABLE event_signups DROP COLUMN transaction_id;
+ALTER TABLE event_signups DROP COLUMN member;
+ALTER TABLE events DROP COLUMN cancellation_deadline;
+
+CREATE VIEW events_with_signups AS
+SELECT
+    events.*,
+    COALESCE(t_signup_count.count, 0) AS signups
+FROM
+    events
+    LEFT JOIN
+        (
+            SELECT
+                count(id),
+                event
+            FROM
+                event_signups
+            GROUP BY
+                event
+        ) t_signup_count
+    ON events.id = t_signup_count.event;
@@ -0,0 +1,27 @@
+-- signups which are refunded if cancelled before this time, NULL means until the event starts
+ALTER TABLE events ADD COLUMN cancellation_deadline TIMESTAMP WITH TIME ZONE;
+
+-- the member who signed up, and the transaction which paid for the ticket
+ALTER TABLE event_signups ADD COLUMN member INTEGER REFERENCES members(id);
+ALTER TABLE event_signups ADD COLUMN transaction_id INTEGER REFERENCES transactions(id);
+
+-- recreate the view to include the new column
+DROP VIEW events_with_signups;
+
+CREATE VIEW events_with_signups AS
+SELECT
+    events.*,
+    COALESCE(t_signup_count.count, 0) AS signups
+FROM
+    events
+    LEFT JOIN
+        (
+            SELECT
+                count(id),
+                event
+            FROM
+                event_signups
+            GROUP BY
+                event
+        ) t_signup_count
+    ON events.id = t_signup_count.event;
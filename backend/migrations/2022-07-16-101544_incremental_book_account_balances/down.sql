@@ -0,0 +1,32 @@
+DROP TRIGGER flip_book_account_balance ON book_accounts;
+DROP FUNCTION flip_book_account_balance();
+DROP TRIGGER add_book_account_balance ON book_accounts;
+DROP FUNCTION add_book_account_balance();
+DROP TRIGGER clear_book_account_balances ON transactions;
+DROP FUNCTION clear_book_account_balances();
+DROP TRIGGER update_book_account_balances_on_change ON transactions;
+DROP TRIGGER update_book_account_balances ON transactions;
+DROP FUNCTION update_book_account_balances();
+DROP FUNCTION debit_book_account_balance(INTEGER, INTEGER);
+DROP TABLE book_account_balances;
+
+CREATE MATERIALIZED VIEW book_account_balances AS
+SELECT * FROM book_account_balances_at('infinity');
+
+CREATE FUNCTION refresh_book_account_balances()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    REFRESH MATERIALIZED VIEW book_account_balances;
+    RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER refresh_book_account_balances
+AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE
+ON book_accounts
+EXECUTE PROCEDURE refresh_book_account_balances();
+
+CREATE TRIGGER refresh_book_account_balances
+AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE
+ON transactions
+EXECUTE PROCEDURE refresh_book_account_balances();
@@ -0,0 +1,104 @@
+-- Keep the balances in a table which is updated row by row, instead of re-folding every
+-- transaction into a materialized view on each write.
+DROP TRIGGER refresh_book_account_balances ON transactions;
+DROP TRIGGER refresh_book_account_balances ON book_accounts;
+DROP FUNCTION refresh_book_account_balances();
+DROP MATERIALIZED VIEW book_account_balances;
+
+CREATE TABLE book_account_balances (
+    id INTEGER PRIMARY KEY REFERENCES book_accounts(id) ON DELETE CASCADE,
+    balance INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO book_account_balances (id, balance)
+SELECT id, balance FROM book_account_balances_at('infinity');
+
+-- Add the debit of `amount` to the balance of an account.
+-- Debiting increases the balance of expense and asset accounts, and decreases it for the others.
+CREATE FUNCTION debit_book_account_balance(account INTEGER, amount INTEGER)
+RETURNS VOID LANGUAGE sql AS $$
+UPDATE book_account_balances AS b
+SET balance = b.balance
+    + amount * CASE WHEN a.account_type IN ('expenses', 'assets') THEN 1 ELSE -1 END
+FROM book_accounts AS a
+WHERE a.id = account AND b.id = account;
+$$;
+
+CREATE FUNCTION update_book_account_balances()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    -- take back the old row, unless it was deleted
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        IF OLD.deleted_at IS NULL THEN
+            PERFORM debit_book_account_balance(OLD.debited_account, -OLD.amount);
+            PERFORM debit_book_account_balance(OLD.credited_account, OLD.amount);
+        END IF;
+    END IF;
+
+    -- and apply the new row, unless it is deleted
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        IF NEW.deleted_at IS NULL THEN
+            PERFORM debit_book_account_balance(NEW.debited_account, NEW.amount);
+            PERFORM debit_book_account_balance(NEW.credited_account, -NEW.amount);
+        END IF;
+    END IF;
+
+    RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER update_book_account_balances
+AFTER INSERT OR DELETE
+ON transactions
+FOR EACH ROW
+EXECUTE PROCEDURE update_book_account_balances();
+
+CREATE TRIGGER update_book_account_balances_on_change
+AFTER UPDATE OF amount, debited_account, credited_account, deleted_at
+ON transactions
+FOR EACH ROW
+EXECUTE PROCEDURE update_book_account_balances();
+
+CREATE FUNCTION clear_book_account_balances()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    UPDATE book_account_balances SET balance = 0;
+    RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER clear_book_account_balances
+AFTER TRUNCATE
+ON transactions
+EXECUTE PROCEDURE clear_book_account_balances();
+
+CREATE FUNCTION add_book_account_balance()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    INSERT INTO book_account_balances (id, balance) VALUES (NEW.id, 0);
+    RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER add_book_account_balance
+AFTER INSERT
+ON book_accounts
+FOR EACH ROW
+EXECUTE PROCEDURE add_book_account_balance();
+
+-- The balance is signed by the account type, so flip it if the type changes side
+CREATE FUNCTION flip_book_account_balance()
+RETURNS TRIGGER LANGUAGE plpgsql AS $$
+BEGIN
+    IF (OLD.account_type IN ('expenses', 'assets')) <> (NEW.account_type IN ('expenses', 'assets')) THEN
+        UPDATE book_account_balances SET balance = -balance WHERE id = NEW.id;
+    END IF;
+    RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER flip_book_account_balance
+AFTER UPDATE OF account_type
+ON book_accounts
+FOR EACH ROW
+EXECUTE PROCEDURE flip_book_account_balance();
@@ -0,0 +1,6 @@
+-- the tokens can't be recovered from their hashes
+DELETE FROM user_sessions;
+
+COMMENT ON COLUMN user_sessions.token_hash IS NULL;
+
+ALTER TABLE user_sessions RENAME COLUMN token_hash TO token;
@@ -0,0 +1,7 @@
+ALTER TABLE user_sessions RENAME COLUMN token TO token_hash;
+
+UPDATE user_sessions SET token_hash = encode(sha256(token_hash::bytea), 'hex');
+
+COMMENT ON COLUMN user_sessions.token_hash IS
+    'The hex-encoded SHA-256 hash of the session token. The token itself is only known to the
+    client, so that it can''t be read from the database.';
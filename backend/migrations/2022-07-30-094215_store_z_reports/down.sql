@@ -0,0 +1 @@
+ALTER TABLE cash_register_sessions DROP COLUMN z_report;
@@ -0,0 +1,5 @@
+ALTER TABLE cash_register_sessions ADD COLUMN z_report JSONB;
+
+COMMENT ON COLUMN cash_register_sessions.z_report IS
+    'The cash transactions of the session as they were when it was closed, which the Z-report is
+    printed from. NULL for sessions closed before the reports were stored.';
@@ -0,0 +1,117 @@
+use strecklistan_api::member::NewMember;
+
+/// A member to import, and the name of its tillgodo account if it isn't the default
+pub struct MemberRow {
+    pub member: NewMember,
+    pub account_name: Option<String>,
+}
+
+/// Parse members from CSV, with the columns `first_name,last_name,nickname,account_name`.
+///
+/// The first line is a header and is skipped. The last two columns may be left out or empty.
+pub fn parse_members(csv: &str) -> Result<Vec<MemberRow>, String> {
+    csv.lines()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_row(line).map_err(|e| format!("Line {}: {}", i + 1, e)))
+        .collect()
+}
+
+fn parse_row(line: &str) -> Result<MemberRow, String> {
+    let mut fields = split_fields(line)?.into_iter().map(|field| {
+        let field = field.trim().to_string();
+        if field.is_empty() {
+            None
+        } else {
+            Some(field)
+        }
+    });
+
+    let mut next = || fields.next().flatten();
+    let first_name = next().ok_or("Missing first name")?;
+    let last_name = next().ok_or("Missing last name")?;
+    let nickname = next();
+    let account_name = next();
+
+    if fields.next().is_some() {
+        return Err("Too many columns".to_string());
+    }
+
+    Ok(MemberRow {
+        member: NewMember {
+            first_name,
+            last_name,
+            nickname,
+        },
+        account_name,
+    })
+}
+
+/// Split a line of CSV into its fields.
+///
+/// Fields may be quoted, and a quote inside a quoted field is written as two quotes.
+fn split_fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err("Unterminated quote".to_string());
+    }
+
+    fields.push(field);
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_fields() {
+        assert_eq!(split_fields("a,b,,c").unwrap(), vec!["a", "b", "", "c"]);
+        assert_eq!(
+            split_fields(r#"Kalle,"Anka, Jr.","sa ""hej""""#).unwrap(),
+            vec!["Kalle", "Anka, Jr.", r#"sa "hej""#]
+        );
+        assert!(split_fields(r#"Kalle,"Anka"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_members() {
+        let csv = "first_name,last_name,nickname,account_name\n\
+                   Kalle,Anka\n\
+                   \n\
+                   Joakim,von Anka,Farbror,Tillgodo/Joakim\n\
+                   Kajsa,Anka,,\n";
+
+        let rows = parse_members(csv).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].member.first_name, "Kalle");
+        assert_eq!(rows[0].member.nickname, None);
+        assert_eq!(rows[1].member.nickname.as_deref(), Some("Farbror"));
+        assert_eq!(rows[1].account_name.as_deref(), Some("Tillgodo/Joakim"));
+        assert_eq!(rows[2].account_name, None);
+
+        let error = parse_members("header\nKalle,Anka\nKajsa\n").err().unwrap();
+        assert_eq!(error, "Line 3: Missing last name");
+    }
+}
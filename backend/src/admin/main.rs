@@ -0,0 +1,347 @@
+//! A command-line tool for administrating a strecklistan deployment.
+//!
+//! It works directly against the database, so the server doesn't need to be running. Changes are
+//! not pushed to connected clients, which will see them after reloading.
+
+mod import;
+
+use crate::import::parse_members;
+use chrono::{DateTime, Utc};
+use clap::{ArgEnum, Parser, Subcommand};
+use diesel::Connection;
+use dotenv::dotenv;
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use strecklistan_api::book_account::{BookAccount, BookAccountId, BookAccountType, NewBookAccount};
+use strecklistan_api::member::{Member, NewMember};
+use strecklistan_api::transaction::{Transaction, TransactionId};
+use strecklistan_backend::database::transaction::{
+    objectify_transations, query_transaction, TransactionFilter,
+};
+use strecklistan_backend::database::{self, DatabaseConn};
+use strecklistan_backend::routes::rest::export::export_fiscal_year;
+
+type AdminResult<T = ()> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+pub struct Opt {
+    /// Database url specified as a postgres:// uri
+    #[clap(long, short, env = "DATABASE_URL")]
+    database: String,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the pending database migrations
+    Migrate,
+
+    /// Manage book accounts
+    #[clap(subcommand)]
+    Account(AccountCommand),
+
+    /// Manage members
+    #[clap(subcommand)]
+    Member(MemberCommand),
+
+    /// Delete and restore transactions
+    #[clap(subcommand)]
+    Transaction(TransactionCommand),
+
+    /// Export the bookkeeping
+    #[clap(subcommand)]
+    Export(ExportCommand),
+}
+
+#[derive(Subcommand)]
+enum AccountCommand {
+    /// List all book accounts with their balances
+    List {
+        /// List the balances as of this time, in RFC 3339
+        #[clap(long)]
+        at: Option<DateTime<Utc>>,
+    },
+
+    /// Create a new book account
+    Create {
+        name: String,
+
+        #[clap(long = "type", arg_enum)]
+        account_type: AccountType,
+    },
+
+    /// Change the name of a book account
+    Rename { id: BookAccountId, name: String },
+}
+
+#[derive(Subcommand)]
+enum MemberCommand {
+    /// Add a member along with a tillgodo account
+    Add {
+        first_name: String,
+        last_name: String,
+
+        #[clap(long)]
+        nickname: Option<String>,
+
+        /// Name of the tillgodo account, defaults to "Tillgodo/<nickname or first name>"
+        #[clap(long)]
+        account_name: Option<String>,
+    },
+
+    /// Add members from a CSV file, with the columns `first_name,last_name,nickname,account_name`
+    ///
+    /// The first line is a header and is skipped. Either all members are added, or none.
+    Import { file: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum TransactionCommand {
+    /// Mark a transaction as deleted
+    Delete { id: TransactionId },
+
+    /// Undo the deletion of a transaction
+    Restore { id: TransactionId },
+}
+
+#[derive(Subcommand)]
+enum ExportCommand {
+    /// Export the transactions of a fiscal year as an SIE4 file
+    Sie4 {
+        year: i32,
+
+        /// The month which the fiscal year starts in
+        #[clap(long, default_value = "1")]
+        start_month: u32,
+
+        /// Name of the organization
+        #[clap(long, env = "ORGANIZATION_NAME", default_value = "Strecklistan")]
+        organization_name: String,
+
+        /// File to write to, defaults to "strecklistan_<year>.se"
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Export all book accounts, members and transactions as JSON
+    Json {
+        /// File to write to, defaults to stdout
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum AccountType {
+    Expenses,
+    Assets,
+    Liabilities,
+    Revenue,
+}
+
+impl From<AccountType> for BookAccountType {
+    fn from(account_type: AccountType) -> Self {
+        match account_type {
+            AccountType::Expenses => BookAccountType::Expenses,
+            AccountType::Assets => BookAccountType::Assets,
+            AccountType::Liabilities => BookAccountType::Liabilities,
+            AccountType::Revenue => BookAccountType::Revenue,
+        }
+    }
+}
+
+/// The contents of a JSON export
+#[derive(Serialize)]
+struct Export {
+    exported_at: DateTime<Utc>,
+    accounts: Vec<BookAccount>,
+    members: Vec<Member>,
+    transactions: Vec<Transaction>,
+}
+
+fn main() {
+    dotenv().ok();
+
+    let opt = Opt::parse();
+
+    if let Err(e) = run(opt) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(opt: Opt) -> AdminResult {
+    let db_pool = database::create_pool(&opt.database)?;
+
+    match opt.command {
+        Command::Migrate => {
+            database::run_migrations(&db_pool);
+            Ok(())
+        }
+        Command::Account(command) => account(&db_pool.get()?, command),
+        Command::Member(command) => member(&db_pool.get()?, command),
+        Command::Transaction(command) => transaction(&db_pool.get()?, command),
+        Command::Export(command) => export(&db_pool.get()?, command),
+    }
+}
+
+fn account(connection: &DatabaseConn, command: AccountCommand) -> AdminResult {
+    match command {
+        AccountCommand::List { at } => {
+            let accounts = database::report::load_accounts(connection)?;
+            let balances = match at {
+                Some(at) => database::book_account::get_balances_at(connection, at)?,
+                None => database::book_account::get_balances(connection)?,
+            };
+
+            println!(
+                "{:>5}  {:<12} {:<32} {:>12}",
+                "id", "type", "name", "balance"
+            );
+            for account in accounts {
+                let balance = balances.get(&account.id).copied().unwrap_or_default();
+                println!(
+                    "{:>5}  {:<12} {:<32} {:>12}",
+                    account.id,
+                    format!("{:?}", account.account_type),
+                    account.name,
+                    balance.to_string(),
+                );
+            }
+        }
+        AccountCommand::Create { name, account_type } => {
+            let id = database::book_account::insert_account(
+                connection,
+                &NewBookAccount {
+                    name,
+                    account_type: account_type.into(),
+                    creditor: None,
+                },
+            )?;
+            println!("Created book account {}", id);
+        }
+        AccountCommand::Rename { id, name } => {
+            database::book_account::rename_account(connection, id, &name)?;
+            println!("Renamed book account {} to \"{}\"", id, name);
+        }
+    }
+
+    Ok(())
+}
+
+fn member(connection: &DatabaseConn, command: MemberCommand) -> AdminResult {
+    match command {
+        MemberCommand::Add {
+            first_name,
+            last_name,
+            nickname,
+            account_name,
+        } => {
+            let member = NewMember {
+                first_name,
+                last_name,
+                nickname,
+            };
+            let account_name = account_name.unwrap_or_else(|| default_account_name(&member));
+            let (member_id, account_id) = connection.transaction(|| {
+                database::member::insert_member(connection, &member, &account_name)
+            })?;
+            println!(
+                "Added member {} with book account {}",
+                member_id, account_id
+            );
+        }
+        MemberCommand::Import { file } => {
+            let rows = parse_members(&fs::read_to_string(file)?)?;
+            connection.transaction(|| {
+                for row in &rows {
+                    let account_name = match &row.account_name {
+                        Some(name) => name.clone(),
+                        None => default_account_name(&row.member),
+                    };
+                    database::member::insert_member(connection, &row.member, &account_name)?;
+                }
+                Ok::<_, diesel::result::Error>(())
+            })?;
+            println!("Imported {} members", rows.len());
+        }
+    }
+
+    Ok(())
+}
+
+/// The name the store gives to the tillgodo account of a new member
+fn default_account_name(member: &NewMember) -> String {
+    format!(
+        "Tillgodo/{}",
+        member.nickname.as_deref().unwrap_or(&member.first_name)
+    )
+}
+
+fn transaction(connection: &DatabaseConn, command: TransactionCommand) -> AdminResult {
+    match command {
+        TransactionCommand::Delete { id } => {
+            database::transaction::delete_transaction(connection, id)?;
+            println!("Deleted transaction {}", id);
+        }
+        TransactionCommand::Restore { id } => {
+            database::transaction::restore_transaction(connection, id)?;
+            println!("Restored transaction {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+fn export(connection: &DatabaseConn, command: ExportCommand) -> AdminResult {
+    match command {
+        ExportCommand::Sie4 {
+            year,
+            start_month,
+            organization_name,
+            output,
+        } => {
+            let file = export_fiscal_year(connection, &organization_name, year, start_month)
+                .map_err(|e| e.description)?;
+            let output =
+                output.unwrap_or_else(|| PathBuf::from(format!("strecklistan_{}.se", year)));
+            fs::write(&output, file.to_bytes())?;
+            eprintln!("Wrote {}", output.display());
+        }
+        ExportCommand::Json { output } => {
+            let export = connection.transaction::<_, diesel::result::Error, _>(|| {
+                let mut accounts = database::report::load_accounts(connection)?;
+                let balances = database::book_account::get_balances(connection)?;
+                for account in &mut accounts {
+                    account.balance = balances.get(&account.id).copied().unwrap_or_default();
+                }
+
+                Ok(Export {
+                    exported_at: Utc::now(),
+                    accounts,
+                    members: database::member::get_members(connection)?,
+                    transactions: objectify_transations(query_transaction(
+                        connection,
+                        TransactionFilter::default(),
+                    )?),
+                })
+            })?;
+
+            let json = serde_json::to_vec_pretty(&export)?;
+            match output {
+                Some(output) => {
+                    fs::write(&output, json)?;
+                    eprintln!("Wrote {}", output.display());
+                }
+                None => io::stdout().write_all(&json)?,
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use crate::database::book_account::get_master_accounts;
+use crate::database::DatabaseConn;
+use crate::util::sie::BasNumber;
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+use diesel::result::QueryResult as Result;
+use std::collections::HashMap;
+use strecklistan_api::book_account::{BookAccount, BookAccountId, BookAccountType, MasterAccounts};
+
+/// Names of the BAS accounts which are used by default
+pub const DEFAULT_BAS_ACCOUNTS: &[(BasNumber, &str)] = &[
+    (1910, "Kassa"),
+    (1930, "Företagskonto"),
+    (2890, "Övriga kortfristiga skulder"),
+    (3000, "Försäljning"),
+    (3740, "Öres- och kronutjämning"),
+    (4000, "Inköp av varor"),
+    (4010, "Lagerförändring"),
+];
+
+/// The BAS account that a book account is exported to, if it hasn't been mapped explicitly
+fn default_bas_number(account: &BookAccount, masters: &MasterAccounts) -> BasNumber {
+    if account.id == masters.cash_account_id {
+        1910
+    } else if account.id == masters.shrinkage_account_id {
+        4010
+    } else if account.id == masters.cash_difference_account_id {
+        3740
+    } else {
+        match account.account_type {
+            BookAccountType::Assets => 1930,
+            BookAccountType::Liabilities => 2890,
+            BookAccountType::Revenue => 3000,
+            BookAccountType::Expenses => 4000,
+        }
+    }
+}
+
+/// Get the BAS account of every book account, falling back to a default for unmapped accounts
+pub fn get_bas_numbers(
+    connection: &DatabaseConn,
+    accounts: &[BookAccount],
+) -> Result<HashMap<BookAccountId, BasNumber>> {
+    let masters = get_master_accounts(connection)?;
+
+    let mapped: HashMap<BookAccountId, i32> = {
+        use crate::schema::tables::bas_accounts::dsl::*;
+        bas_accounts
+            .select((book_account_id, bas_number))
+            .load(connection)?
+            .into_iter()
+            .collect()
+    };
+
+    Ok(accounts
+        .iter()
+        .map(|account| {
+            let number = mapped
+                .get(&account.id)
+                .map(|&number| number as BasNumber)
+                .unwrap_or_else(|| default_bas_number(account, &masters));
+            (account.id, number)
+        })
+        .collect())
+}
+
+/// Explicitly map book accounts to BAS accounts
+pub fn set_bas_numbers(
+    connection: &DatabaseConn,
+    numbers: &HashMap<BookAccountId, BasNumber>,
+) -> Result<()> {
+    use crate::schema::tables::bas_accounts::dsl::*;
+
+    let rows: Vec<_> = numbers
+        .iter()
+        .map(|(&account, &number)| (book_account_id.eq(account), bas_number.eq(number as i32)))
+        .collect();
+
+    diesel::insert_into(bas_accounts)
+        .values(&rows)
+        .on_conflict(book_account_id)
+        .do_update()
+        .set(bas_number.eq(excluded(bas_number)))
+        .execute(connection)?;
+
+    Ok(())
+}
@@ -0,0 +1,401 @@
+use crate::database::DatabaseConn;
+use crate::models::book_account as relational;
+use crate::schema::tables::book_account_balances;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::result::QueryResult as Result;
+use diesel::sql_types::Timestamptz;
+use std::collections::{BTreeMap, HashMap};
+use strecklistan_api::book_account::{
+    BookAccount, BookAccountId, BookAccountType, CreditPolicy, MasterAccounts, NewBookAccount,
+};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::transaction::NewTransaction;
+
+// TODO: Get the values for the master accounts from some configuration.
+pub const BANK_ACCOUNT_NAME: &str = "Bankkonto";
+pub const CASH_ACCOUNT_NAME: &str = "Kontantkassa";
+pub const SALES_ACCOUNT_NAME: &str = "Försäljning";
+pub const PURCHASES_ACCOUNT_NAME: &str = "Inköp";
+pub const SHRINKAGE_ACCOUNT_NAME: &str = "Svinn";
+pub const CASH_DIFFERENCE_ACCOUNT_NAME: &str = "Kassadifferenser";
+pub const EVENTS_ACCOUNT_NAME: &str = "Evenemang";
+
+/// Get the id of the book account with the given name, creating it if it doesn't exist.
+fn get_or_create_account(
+    connection: &DatabaseConn,
+    account_name: &str,
+    account_kind: BookAccountType,
+) -> Result<BookAccountId> {
+    use crate::schema::tables::book_accounts::dsl::*;
+
+    diesel::insert_into(book_accounts)
+        .values((name.eq(account_name), account_type.eq(account_kind)))
+        .on_conflict_do_nothing()
+        .execute(connection)?;
+
+    book_accounts
+        .filter(name.eq(account_name))
+        .select(id)
+        .get_result(connection)
+}
+
+/// Create a new book account
+pub fn insert_account(
+    connection: &DatabaseConn,
+    account: &NewBookAccount,
+) -> Result<BookAccountId> {
+    use crate::schema::tables::book_accounts::dsl::*;
+
+    diesel::insert_into(book_accounts)
+        .values((
+            name.eq(&account.name),
+            account_type.eq(&account.account_type),
+            creditor.eq(&account.creditor),
+        ))
+        .returning(id)
+        .get_result(connection)
+}
+
+/// Change the name of a book account
+pub fn rename_account(
+    connection: &DatabaseConn,
+    account: BookAccountId,
+    account_name: &str,
+) -> Result<()> {
+    use crate::schema::tables::book_accounts::dsl::*;
+
+    diesel::update(book_accounts.find(account))
+        .set(name.eq(account_name))
+        .returning(id)
+        .get_result::<BookAccountId>(connection)?;
+
+    Ok(())
+}
+
+/// Get the master accounts, making sure that they exist in the database
+pub fn get_master_accounts(connection: &DatabaseConn) -> Result<MasterAccounts> {
+    connection.transaction(|| {
+        Ok(MasterAccounts {
+            bank_account_id: get_or_create_account(
+                connection,
+                BANK_ACCOUNT_NAME,
+                BookAccountType::Assets,
+            )?,
+            cash_account_id: get_or_create_account(
+                connection,
+                CASH_ACCOUNT_NAME,
+                BookAccountType::Assets,
+            )?,
+            sales_account_id: get_or_create_account(
+                connection,
+                SALES_ACCOUNT_NAME,
+                BookAccountType::Revenue,
+            )?,
+            purchases_account_id: get_or_create_account(
+                connection,
+                PURCHASES_ACCOUNT_NAME,
+                BookAccountType::Expenses,
+            )?,
+            shrinkage_account_id: get_or_create_account(
+                connection,
+                SHRINKAGE_ACCOUNT_NAME,
+                BookAccountType::Expenses,
+            )?,
+            cash_difference_account_id: get_or_create_account(
+                connection,
+                CASH_DIFFERENCE_ACCOUNT_NAME,
+                BookAccountType::Expenses,
+            )?,
+            events_account_id: get_or_create_account(
+                connection,
+                EVENTS_ACCOUNT_NAME,
+                BookAccountType::Revenue,
+            )?,
+        })
+    })
+}
+
+#[derive(QueryableByName)]
+#[table_name = "book_account_balances"]
+struct AccountBalance {
+    id: BookAccountId,
+    balance: i32,
+}
+
+/// Get the current balance of every book account.
+pub fn get_balances(connection: &DatabaseConn) -> Result<HashMap<BookAccountId, Currency>> {
+    use crate::schema::tables::book_account_balances::dsl::*;
+
+    Ok(book_account_balances
+        .select((id, balance))
+        .load(connection)?
+        .into_iter()
+        .map(|(account, account_balance): (_, i32)| (account, account_balance.into()))
+        .collect())
+}
+
+/// Get the balance of every book account, counting only transactions made at or before `time`.
+pub fn get_balances_at(
+    connection: &DatabaseConn,
+    time: DateTime<Utc>,
+) -> Result<HashMap<BookAccountId, Currency>> {
+    Ok(
+        diesel::sql_query("SELECT id, balance FROM book_account_balances_at($1)")
+            .bind::<Timestamptz, _>(time)
+            .load(connection)?
+            .into_iter()
+            .map(|row: AccountBalance| (row.id, row.balance.into()))
+            .collect(),
+    )
+}
+
+/// Get the default credit limit of tillgodo accounts
+pub fn get_credit_policy(connection: &DatabaseConn) -> Result<CreditPolicy> {
+    use crate::schema::tables::credit_policy::dsl::*;
+
+    let limit: Option<i32> = credit_policy
+        .select(default_credit_limit)
+        .first(connection)
+        .optional()?
+        .flatten();
+
+    Ok(CreditPolicy {
+        default_credit_limit: limit.map(Currency::from),
+    })
+}
+
+/// Set the default credit limit of tillgodo accounts
+pub fn set_credit_policy(connection: &DatabaseConn, policy: CreditPolicy) -> Result<()> {
+    use crate::schema::tables::credit_policy::dsl::*;
+
+    let limit = policy.default_credit_limit.map(i32::from);
+    diesel::insert_into(credit_policy)
+        .values((id.eq(true), default_credit_limit.eq(limit)))
+        .on_conflict(id)
+        .do_update()
+        .set(default_credit_limit.eq(limit))
+        .execute(connection)?;
+
+    Ok(())
+}
+
+/// Set the credit limit of a single account, or make it use the default if `None`
+pub fn set_credit_limit(
+    connection: &DatabaseConn,
+    account: BookAccountId,
+    limit: Option<Currency>,
+) -> Result<()> {
+    use crate::schema::tables::book_accounts::dsl::*;
+
+    diesel::update(book_accounts.find(account))
+        .set(credit_limit.eq(limit.map(i32::from)))
+        .returning(id)
+        .get_result::<BookAccountId>(connection)?;
+
+    Ok(())
+}
+
+/// Find the first tillgodo account which the transactions would overdraw beyond its credit limit
+///
+/// The rows of the affected accounts are locked until the end of the database transaction, so
+/// that concurrent checks of the same accounts can't both pass on the same balance. This should
+/// be called from within the database transaction which inserts the transactions.
+pub fn find_exceeded_credit_limit(
+    connection: &DatabaseConn,
+    transactions: &[NewTransaction],
+) -> Result<Option<BookAccount>> {
+    let account_ids: Vec<BookAccountId> = transactions
+        .iter()
+        .flat_map(|t| vec![t.debited_account, t.credited_account])
+        .collect();
+
+    // lock the rows in a consistent order, so that concurrent checks can't deadlock
+    let locked: Vec<relational::BookAccount> = {
+        use crate::schema::tables::book_accounts::dsl::*;
+        book_accounts
+            .filter(id.eq_any(account_ids))
+            .order_by(id)
+            .for_update()
+            .load(connection)?
+    };
+
+    // the balances are read after locking, so they include any transaction committed meanwhile
+    let policy = get_credit_policy(connection)?;
+    let balances = get_balances(connection)?;
+
+    let mut accounts: HashMap<BookAccountId, BookAccount> = locked
+        .into_iter()
+        .map(|account| {
+            let mut account: BookAccount = account.into();
+            account.balance = balances.get(&account.id).copied().unwrap_or_default();
+            (account.id, account)
+        })
+        .collect();
+
+    Ok(exceeded_credit_limit(&mut accounts, &policy, transactions)
+        .and_then(|account| accounts.remove(&account)))
+}
+
+/// The extra amount which an amendment of a transaction from `old` to `new` debits each account
+/// with, as transactions from the account to itself
+///
+/// Checking these for exceeded credit limits only considers the final balance of each account,
+/// so that e.g. changing the description of a deposit isn't rejected.
+pub fn amendment_debits(old: &NewTransaction, new: &NewTransaction) -> Vec<NewTransaction> {
+    let mut debits: BTreeMap<BookAccountId, i64> = BTreeMap::new();
+    let mut add = |account, amount: Currency| {
+        *debits.entry(account).or_default() += i64::from(i32::from(amount));
+    };
+
+    add(new.debited_account, new.amount);
+    add(new.credited_account, -new.amount);
+    add(old.debited_account, -old.amount);
+    add(old.credited_account, old.amount);
+
+    debits
+        .into_iter()
+        .filter(|&(_, amount)| amount > 0)
+        .map(|(account, amount)| NewTransaction {
+            description: None,
+            bundles: vec![],
+            debited_account: account,
+            credited_account: account,
+            amount: i32::try_from(amount).unwrap_or(i32::MAX).into(),
+        })
+        .collect()
+}
+
+/// Apply the transactions to the accounts one at a time, and return the id of the first account
+/// whose credit limit would be exceeded
+fn exceeded_credit_limit(
+    accounts: &mut HashMap<BookAccountId, BookAccount>,
+    policy: &CreditPolicy,
+    transactions: &[NewTransaction],
+) -> Option<BookAccountId> {
+    for transaction in transactions {
+        if let Some(account) = accounts.get_mut(&transaction.debited_account) {
+            if account.exceeds_credit_limit(transaction.amount, policy) {
+                return Some(account.id);
+            }
+            account.debit(transaction.amount);
+        }
+
+        if let Some(account) = accounts.get_mut(&transaction.credited_account) {
+            account.credit(transaction.amount);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BANK: BookAccountId = 1;
+    const SALES: BookAccountId = 2;
+    const TILLGODO: BookAccountId = 3;
+
+    fn accounts() -> HashMap<BookAccountId, BookAccount> {
+        let account = |id, account_type, creditor| BookAccount {
+            id,
+            name: format!("account {}", id),
+            account_type,
+            creditor,
+            balance: 0.into(),
+            credit_limit: None,
+        };
+
+        let mut tillgodo = account(TILLGODO, BookAccountType::Liabilities, Some(1));
+        tillgodo.balance = 1000.into();
+
+        vec![
+            account(BANK, BookAccountType::Assets, None),
+            account(SALES, BookAccountType::Revenue, None),
+            tillgodo,
+        ]
+        .into_iter()
+        .map(|account| (account.id, account))
+        .collect()
+    }
+
+    fn transaction(debited: BookAccountId, credited: BookAccountId, amount: i32) -> NewTransaction {
+        NewTransaction {
+            description: None,
+            bundles: vec![],
+            debited_account: debited,
+            credited_account: credited,
+            amount: amount.into(),
+        }
+    }
+
+    #[test]
+    fn test_exceeded_credit_limit() {
+        let policy = CreditPolicy {
+            default_credit_limit: Some(500.into()),
+        };
+
+        let within = [transaction(TILLGODO, SALES, 1500)];
+        assert_eq!(
+            exceeded_credit_limit(&mut accounts(), &policy, &within),
+            None
+        );
+
+        // the payments of a sale are counted together
+        let split = [
+            transaction(TILLGODO, SALES, 1000),
+            transaction(TILLGODO, SALES, 600),
+        ];
+        assert_eq!(
+            exceeded_credit_limit(&mut accounts(), &policy, &split),
+            Some(TILLGODO)
+        );
+
+        // a deposit made first is counted as well
+        let deposit = [
+            transaction(BANK, TILLGODO, 1000),
+            transaction(TILLGODO, SALES, 2500),
+        ];
+        assert_eq!(
+            exceeded_credit_limit(&mut accounts(), &policy, &deposit),
+            None
+        );
+    }
+    #[test]
+    fn test_amendment_debits() {
+        let debits = |old, new| {
+            amendment_debits(&old, &new)
+                .into_iter()
+                .map(|t| (t.debited_account, i32::from(t.amount)))
+                .collect::<Vec<_>>()
+        };
+
+        // only the increase is debited
+        assert_eq!(
+            debits(
+                transaction(TILLGODO, SALES, 50),
+                transaction(TILLGODO, SALES, 60)
+            ),
+            vec![(TILLGODO, 10)]
+        );
+
+        // an unchanged deposit debits nothing
+        assert_eq!(
+            debits(
+                transaction(BANK, TILLGODO, 50),
+                transaction(BANK, TILLGODO, 50)
+            ),
+            vec![]
+        );
+
+        // a smaller deposit debits the difference
+        assert_eq!(
+            debits(
+                transaction(BANK, TILLGODO, 50),
+                transaction(BANK, TILLGODO, 20)
+            ),
+            vec![(TILLGODO, 30)]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use crate::database::report::load_accounts;
+use crate::database::DatabaseConn;
+use crate::models::transaction::relational::Transaction;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::result::QueryResult as Result;
+use serde::{Deserialize, Serialize};
+use strecklistan_api::book_account::{BookAccountId, MasterAccounts};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::transaction::{NewTransaction, TransactionId};
+
+/// Load the transactions on the cash account made at or after `from`, and at or before `to`.
+///
+/// The transactions in `excluded`, i.e. the correction transactions of the session, are left out.
+pub fn load_cash_transactions(
+    connection: &DatabaseConn,
+    cash_account: BookAccountId,
+    from: DateTime<Utc>,
+    to: Option<DateTime<Utc>>,
+    excluded: &[TransactionId],
+) -> Result<Vec<Transaction>> {
+    use crate::schema::tables::transactions::dsl::*;
+
+    let mut query = transactions
+        .filter(deleted_at.is_null())
+        .filter(
+            debited_account
+                .eq(cash_account)
+                .or(credited_account.eq(cash_account)),
+        )
+        .filter(time.ge(from))
+        .filter(diesel::dsl::not(id.eq_any(excluded)))
+        .order_by((time, id))
+        .into_boxed();
+
+    if let Some(to) = to {
+        query = query.filter(time.le(to));
+    }
+
+    query.load(connection)
+}
+
+/// The total amount put into, and taken out of, the cash account by the transactions
+pub fn cash_flow(
+    cash_account: BookAccountId,
+    transactions: &[Transaction],
+) -> (Currency, Currency) {
+    transactions.iter().fold(
+        (Currency::from(0), Currency::from(0)),
+        |(mut cash_in, mut cash_out), transaction| {
+            let amount = Currency::from(transaction.amount);
+            if transaction.debited_account == cash_account {
+                cash_in += amount;
+            }
+            if transaction.credited_account == cash_account {
+                cash_out += amount;
+            }
+            (cash_in, cash_out)
+        },
+    )
+}
+
+/// The cash transactions of a session as they were when it was closed, which the Z-report is
+/// printed from
+#[derive(Serialize, Deserialize)]
+pub struct ZReport {
+    pub cash_in: Currency,
+    pub cash_out: Currency,
+    pub entries: Vec<ZReportEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ZReportEntry {
+    pub transaction_id: TransactionId,
+    pub time: DateTime<Utc>,
+    pub description: Option<String>,
+
+    /// The name of the account which the cash came from or went to
+    pub counter_account: String,
+    pub cash_in: Currency,
+    pub cash_out: Currency,
+}
+
+/// Summarize the cash transactions of a session, oldest first, for its Z-report
+pub fn build_z_report(
+    connection: &DatabaseConn,
+    cash_account: BookAccountId,
+    transactions: Vec<Transaction>,
+) -> Result<ZReport> {
+    let accounts = load_accounts(connection)?;
+    let account_name = |id| {
+        accounts
+            .iter()
+            .find(|account| account.id == id)
+            .map(|account| account.name.clone())
+            .unwrap_or_default()
+    };
+
+    let (cash_in, cash_out) = cash_flow(cash_account, &transactions);
+    let entries = transactions
+        .into_iter()
+        .map(|transaction| {
+            let (cash_in, cash_out) = cash_flow(cash_account, std::slice::from_ref(&transaction));
+            let counter_account = if transaction.debited_account == cash_account {
+                transaction.credited_account
+            } else {
+                transaction.debited_account
+            };
+            ZReportEntry {
+                transaction_id: transaction.id,
+                time: transaction.time,
+                description: transaction.description,
+                counter_account: account_name(counter_account),
+                cash_in,
+                cash_out,
+            }
+        })
+        .collect();
+
+    Ok(ZReport {
+        cash_in,
+        cash_out,
+        entries,
+    })
+}
+
+/// The transaction which corrects the cash account when the counted cash differs from the
+/// expected cash. `difference` is the counted minus the expected cash.
+///
+/// Returns `None` if no correction is needed.
+pub fn correction_transaction(
+    masters: &MasterAccounts,
+    difference: Currency,
+    description: String,
+) -> Option<NewTransaction> {
+    let zero = Currency::from(0);
+    let (debited_account, credited_account, amount) = if difference > zero {
+        (
+            masters.cash_account_id,
+            masters.cash_difference_account_id,
+            difference,
+        )
+    } else if difference < zero {
+        (
+            masters.cash_difference_account_id,
+            masters.cash_account_id,
+            -difference,
+        )
+    } else {
+        return None;
+    };
+
+    Some(NewTransaction {
+        description: Some(description),
+        bundles: vec![],
+        debited_account,
+        credited_account,
+        amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CASH: BookAccountId = 2;
+    const DIFFERENCE: BookAccountId = 6;
+
+    fn masters() -> MasterAccounts {
+        MasterAccounts {
+            bank_account_id: 1,
+            cash_account_id: CASH,
+            sales_account_id: 3,
+            purchases_account_id: 4,
+            shrinkage_account_id: 5,
+            cash_difference_account_id: DIFFERENCE,
+            events_account_id: 7,
+        }
+    }
+
+    fn transaction(id: i32, debited: i32, credited: i32, amount: i32) -> Transaction {
+        Transaction {
+            id,
+            description: None,
+            time: "2022-06-04T12:00:00Z".parse().unwrap(),
+            debited_account: debited,
+            credited_account: credited,
+            amount,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_cash_flow() {
+        let transactions = vec![
+            transaction(1, CASH, 3, 2500),
+            transaction(2, CASH, 3, 1000),
+            transaction(3, 4, CASH, 800),
+            transaction(4, CASH, CASH, 100),
+        ];
+
+        assert_eq!(
+            cash_flow(CASH, &transactions),
+            (Currency::from(3600), Currency::from(900))
+        );
+        assert_eq!(cash_flow(CASH, &[]), (Currency::from(0), Currency::from(0)));
+    }
+
+    #[test]
+    fn test_correction_transaction() {
+        let masters = masters();
+
+        assert!(correction_transaction(&masters, 0.into(), String::new()).is_none());
+
+        let over = correction_transaction(&masters, 150.into(), String::new()).unwrap();
+        assert_eq!(over.debited_account, CASH);
+        assert_eq!(over.credited_account, DIFFERENCE);
+        assert_eq!(over.amount, Currency::from(150));
+
+        let short = correction_transaction(&masters, (-250).into(), String::new()).unwrap();
+        assert_eq!(short.debited_account, DIFFERENCE);
+        assert_eq!(short.credited_account, CASH);
+        assert_eq!(short.amount, Currency::from(250));
+    }
+}
@@ -1,8 +1,17 @@
+use crate::database::book_account::get_master_accounts;
+use crate::database::member::get_member_account;
+use crate::database::transaction::insert_transaction;
 use crate::database::DatabaseConn;
-use crate::models::event::EventWithSignups as EventWS;
-use chrono::Local;
+use chrono::{DateTime, Local, Utc};
 use diesel::prelude::*;
 use diesel::result::QueryResult as Result;
+use strecklistan_api::book_account::{BookAccountId, MasterAccounts};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::event::{
+    Event as EventWS, EventId, EventSignup, EventSignupId, EventSignupWithStatus, NewEvent,
+    NewEventSignup, SignupStatus,
+};
+use strecklistan_api::transaction::{NewTransaction, TransactionId};
 
 pub fn get_event_ws(connection: DatabaseConn, id: i32, published_only: bool) -> Result<EventWS> {
     use crate::schema::views::events_with_signups::dsl::{events_with_signups, published};
@@ -68,3 +77,310 @@ pub fn get_event_ws_range(
     upcoming.append(&mut previous);
     Ok(upcoming)
 }
+
+/// Get an event, published or not
+pub fn load_event(connection: &DatabaseConn, event: EventId) -> Result<EventWS> {
+    use crate::schema::views::events_with_signups::dsl::*;
+
+    events_with_signups.find(event).first(connection)
+}
+
+/// Get all events, published or not, the latest first
+pub fn get_all_events(connection: &DatabaseConn) -> Result<Vec<EventWS>> {
+    use crate::schema::views::events_with_signups::dsl::*;
+
+    events_with_signups
+        .order_by((start_time.desc(), id.desc()))
+        .load(connection)
+}
+
+/// Create a new, unpublished, event
+pub fn insert_event(connection: &DatabaseConn, event: &NewEvent) -> Result<EventId> {
+    use crate::schema::tables::events::dsl::*;
+
+    diesel::insert_into(events)
+        .values((
+            title.eq(&event.title),
+            background.eq(&event.background),
+            location.eq(&event.location),
+            start_time.eq(event.start_time),
+            end_time.eq(event.end_time),
+            price.eq(event.price),
+            capacity.eq(event.capacity),
+            cancellation_deadline.eq(event.cancellation_deadline),
+        ))
+        .returning(id)
+        .get_result(connection)
+}
+
+/// Change the details of an event
+///
+/// Signups which get a place because the capacity is raised are charged for their tickets, and
+/// signups which lose their place are refunded. Returns the new transactions.
+pub fn update_event(
+    connection: &DatabaseConn,
+    event: EventId,
+    details: &NewEvent,
+) -> Result<Vec<TransactionId>> {
+    let before = get_signups(connection, event)?;
+
+    {
+        use crate::schema::tables::events::dsl::*;
+
+        diesel::update(events.find(event))
+            .set((
+                title.eq(&details.title),
+                background.eq(&details.background),
+                location.eq(&details.location),
+                start_time.eq(details.start_time),
+                end_time.eq(details.end_time),
+                price.eq(details.price),
+                capacity.eq(details.capacity),
+                cancellation_deadline.eq(details.cancellation_deadline),
+            ))
+            .returning(id)
+            .get_result::<EventId>(connection)?;
+    }
+
+    settle_moved_signups(connection, event, &before)
+}
+
+/// Publish an event, which opens it for signups, or unpublish it
+pub fn set_event_published(connection: &DatabaseConn, event: EventId, publish: bool) -> Result<()> {
+    use crate::schema::tables::events::dsl::*;
+
+    diesel::update(events.find(event))
+        .set(published.eq(publish))
+        .returning(id)
+        .get_result::<EventId>(connection)?;
+
+    Ok(())
+}
+
+/// Get the signups of an event in the order they were made, and whether they are on the waitlist
+pub fn get_signups(
+    connection: &DatabaseConn,
+    event: EventId,
+) -> Result<Vec<EventSignupWithStatus>> {
+    let event_capacity = {
+        use crate::schema::tables::events::dsl::*;
+        events.find(event).select(capacity).first(connection)?
+    };
+
+    use crate::schema::tables::event_signups::dsl;
+    let signups: Vec<EventSignup> = dsl::event_signups
+        .filter(dsl::event.eq(event))
+        .order_by(dsl::id)
+        .load(connection)?;
+
+    Ok(signups
+        .into_iter()
+        .enumerate()
+        .map(|(index, signup)| EventSignupWithStatus {
+            signup,
+            status: SignupStatus::of(index, event_capacity),
+        })
+        .collect())
+}
+
+/// The transaction which pays for a ticket to the event from a tillgodo account.
+///
+/// Returns `None` if the event is free.
+pub fn ticket_transaction(
+    event: &EventWS,
+    account: BookAccountId,
+    masters: &MasterAccounts,
+) -> Result<Option<NewTransaction>> {
+    // the price is validated when the event is stored
+    let amount = event
+        .ticket_price()
+        .map_err(|e| diesel::result::Error::DeserializationError(e.into()))?;
+    if amount <= Currency::from(0) {
+        return Ok(None);
+    }
+
+    Ok(Some(NewTransaction {
+        description: Some(format!("Biljett: {}", event.title)),
+        bundles: vec![],
+        debited_account: account,
+        credited_account: masters.events_account_id,
+        amount,
+    }))
+}
+
+/// Sign someone up for a published event.
+///
+/// Returns the new signup, which is put on the waitlist if the event is full. The `payment` for
+/// the ticket is only recorded if the signup gets a place, a signup on the waitlist is charged
+/// when it gets one.
+pub fn insert_signup(
+    connection: &DatabaseConn,
+    event_id: EventId,
+    signup: &NewEventSignup,
+    payment: Option<NewTransaction>,
+) -> Result<EventSignupWithStatus> {
+    let signup_id: EventSignupId = {
+        use crate::schema::tables::event_signups::dsl::*;
+
+        diesel::insert_into(event_signups)
+            .values((
+                event.eq(event_id),
+                name.eq(&signup.name),
+                email.eq(&signup.email),
+                member.eq(signup.member),
+            ))
+            .returning(id)
+            .get_result(connection)?
+    };
+
+    let mut signup = get_signups(connection, event_id)?
+        .into_iter()
+        .find(|signup| signup.signup.id == signup_id)
+        .ok_or(diesel::result::Error::NotFound)?;
+
+    if let (SignupStatus::Attending, Some(payment)) = (signup.status, payment) {
+        let payment = insert_transaction(connection, payment)?;
+        set_signup_payment(connection, signup_id, Some(payment))?;
+        signup.signup.transaction_id = Some(payment);
+    }
+
+    Ok(signup)
+}
+
+/// Cancel a signup. The first signup on the waitlist, if any, takes its place.
+///
+/// If the ticket was paid for, it is refunded if the signup is cancelled at `now` before the
+/// deadline of the event. The signup which takes the place is charged for its ticket. Returns
+/// the new transactions.
+pub fn delete_signup(
+    connection: &DatabaseConn,
+    event_id: EventId,
+    signup_id: EventSignupId,
+    now: DateTime<Utc>,
+) -> Result<Vec<TransactionId>> {
+    let mut before = get_signups(connection, event_id)?;
+    let index = before
+        .iter()
+        .position(|signup| signup.signup.id == signup_id)
+        .ok_or(diesel::result::Error::NotFound)?;
+    let signup = before.remove(index);
+
+    let event = load_event(connection, event_id)?;
+
+    {
+        use crate::schema::tables::event_signups::dsl::*;
+        diesel::delete(event_signups.find(signup_id))
+            .returning(id)
+            .get_result::<EventSignupId>(connection)?;
+    }
+
+    let mut new_transactions = vec![];
+    if let Some(payment) = signup.signup.transaction_id {
+        if event.is_refundable(signup.status, now) {
+            new_transactions.extend(refund_ticket(connection, &event, payment)?);
+        }
+    }
+
+    new_transactions.extend(settle_moved_signups(connection, event_id, &before)?);
+    Ok(new_transactions)
+}
+
+/// Charge the signups which have got a place since `before` was loaded, and refund the ones which
+/// have lost theirs. Returns the new transactions.
+///
+/// A signup is charged to the tillgodo account of its member, even if that exceeds the credit
+/// limit of the account, since the member agreed to pay when signing up.
+fn settle_moved_signups(
+    connection: &DatabaseConn,
+    event_id: EventId,
+    before: &[EventSignupWithStatus],
+) -> Result<Vec<TransactionId>> {
+    let event = load_event(connection, event_id)?;
+    let masters = get_master_accounts(connection)?;
+    let mut new_transactions = vec![];
+
+    for signup in get_signups(connection, event_id)? {
+        let was_attending = before
+            .iter()
+            .find(|before| before.signup.id == signup.signup.id)
+            .map(|before| before.status == SignupStatus::Attending);
+
+        match (was_attending, signup.status, signup.signup.transaction_id) {
+            (Some(false), SignupStatus::Attending, None) => {
+                let account = match signup.signup.member {
+                    Some(member) => get_member_account(connection, member)?,
+                    None => None,
+                };
+                let account = match account {
+                    Some(account) => account,
+                    None => continue,
+                };
+
+                if let Some(payment) = ticket_transaction(&event, account, &masters)? {
+                    let payment = insert_transaction(connection, payment)?;
+                    set_signup_payment(connection, signup.signup.id, Some(payment))?;
+                    new_transactions.push(payment);
+                }
+            }
+            (Some(true), SignupStatus::Waitlisted { .. }, Some(payment)) => {
+                new_transactions.extend(refund_ticket(connection, &event, payment)?);
+                set_signup_payment(connection, signup.signup.id, None)?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(new_transactions)
+}
+
+/// Set the transaction which paid for the ticket of a signup
+fn set_signup_payment(
+    connection: &DatabaseConn,
+    signup_id: EventSignupId,
+    payment: Option<TransactionId>,
+) -> Result<()> {
+    use crate::schema::tables::event_signups::dsl::*;
+
+    diesel::update(event_signups.find(signup_id))
+        .set(transaction_id.eq(payment))
+        .returning(id)
+        .get_result::<EventSignupId>(connection)?;
+
+    Ok(())
+}
+
+/// Pay back a ticket to the account which paid for it. Returns the refund transaction, unless the
+/// payment has been deleted.
+fn refund_ticket(
+    connection: &DatabaseConn,
+    event: &EventWS,
+    payment: TransactionId,
+) -> Result<Option<TransactionId>> {
+    let paid: Option<(BookAccountId, BookAccountId, i32)> = {
+        use crate::schema::tables::transactions::dsl::*;
+        transactions
+            .find(payment)
+            .filter(deleted_at.is_null())
+            .select((debited_account, credited_account, amount))
+            .first(connection)
+            .optional()?
+    };
+
+    // a deleted payment has already been undone
+    let (debited, credited, paid_amount) = match paid {
+        Some(paid) => paid,
+        None => return Ok(None),
+    };
+
+    insert_transaction(
+        connection,
+        NewTransaction {
+            description: Some(format!("Återbetalning: {}", event.title)),
+            bundles: vec![],
+            debited_account: credited,
+            credited_account: debited,
+            amount: paid_amount.into(),
+        },
+    )
+    .map(Some)
+}
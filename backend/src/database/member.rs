@@ -0,0 +1,370 @@
+use crate::database::sale::{discard_pending_sale, PendingSale};
+use crate::database::transaction::{
+    insert_revision, objectify_transations, query_transaction, DeletedFilter, TransactionFilter,
+};
+use crate::database::DatabaseConn;
+use crate::models::izettle_transaction::{TRANSACTION_FAILED, TRANSACTION_IN_PROGRESS};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::result::{Error, QueryResult as Result};
+use strecklistan_api::book_account::{BookAccountId, BookAccountType};
+use strecklistan_api::member::{EditMember, Member, MemberId, NewMember};
+use strecklistan_api::transaction::{NewTransaction, TransactionId};
+
+/// Load all members, ordered by id
+pub fn get_members(connection: &DatabaseConn) -> Result<Vec<Member>> {
+    use crate::schema::tables::members::dsl::*;
+    members.order_by(id).load(connection)
+}
+
+/// Get the tillgodo account of a member, if it has one
+pub fn get_member_account(
+    connection: &DatabaseConn,
+    member: MemberId,
+) -> Result<Option<BookAccountId>> {
+    use crate::schema::tables::book_accounts::dsl::*;
+
+    book_accounts
+        .filter(creditor.eq(member))
+        .select(id)
+        .first(connection)
+        .optional()
+}
+
+/// Add a member along with a tillgodo account called `account_name`
+///
+/// This should be called from within a database transaction.
+pub fn insert_member(
+    connection: &DatabaseConn,
+    new_member: &NewMember,
+    account_name: &str,
+) -> Result<(MemberId, BookAccountId)> {
+    let member_id = {
+        use crate::schema::tables::members::dsl::*;
+
+        diesel::insert_into(members)
+            .values((
+                first_name.eq(&new_member.first_name),
+                last_name.eq(&new_member.last_name),
+                nickname.eq(&new_member.nickname),
+            ))
+            .returning(id)
+            .get_result(connection)?
+    };
+
+    let acc_id = {
+        use crate::schema::tables::book_accounts::dsl::*;
+
+        diesel::insert_into(book_accounts)
+            .values((
+                name.eq(account_name),
+                account_type.eq(&BookAccountType::Liabilities),
+                creditor.eq(&Some(member_id)),
+            ))
+            .returning(id)
+            .get_result(connection)?
+    };
+
+    Ok((member_id, acc_id))
+}
+
+/// Change the details of a member, and rename its tillgodo account if requested
+pub fn update_member(
+    connection: &DatabaseConn,
+    member: MemberId,
+    edit: &EditMember,
+) -> Result<Member> {
+    let updated = {
+        use crate::schema::tables::members::dsl::*;
+        diesel::update(members.find(member))
+            .set((
+                first_name.eq(&edit.first_name),
+                last_name.eq(&edit.last_name),
+                nickname.eq(&edit.nickname),
+            ))
+            .get_result(connection)?
+    };
+
+    if let Some(account_name) = &edit.account_name {
+        use crate::schema::tables::book_accounts::dsl::*;
+        diesel::update(book_accounts.filter(creditor.eq(member)))
+            .set(name.eq(account_name))
+            .execute(connection)?;
+    }
+
+    Ok(updated)
+}
+
+/// Deactivate a member, or reactivate it if `active` is set
+pub fn set_member_active(
+    connection: &DatabaseConn,
+    member: MemberId,
+    active: bool,
+) -> Result<Member> {
+    use crate::schema::tables::members::dsl::*;
+
+    let deactivated = if active { None } else { Some(Utc::now()) };
+    diesel::update(members.find(member))
+        .set(deactivated_at.eq(deactivated))
+        .get_result(connection)
+}
+
+/// The transactions which were changed by merging two members
+#[derive(Default)]
+pub struct MergedTransactions {
+    /// Transactions which were moved from the account of the duplicate
+    pub moved: Vec<TransactionId>,
+
+    /// Transactions between the two accounts, which were deleted
+    pub deleted: Vec<TransactionId>,
+}
+
+/// Find out why a duplicate member can not be merged into another member, if it can't.
+///
+/// The members may not both be signed up for the same event, and there may be no pending card
+/// payment between their tillgodo accounts, since it would go from and to the same account.
+pub fn find_merge_conflict(
+    connection: &DatabaseConn,
+    duplicate: MemberId,
+    target: MemberId,
+) -> Result<Option<&'static str>> {
+    {
+        use crate::schema::tables::event_signups::dsl::*;
+        let target_events: Vec<i32> = event_signups
+            .filter(member.eq(target))
+            .select(event)
+            .load(connection)?;
+        let shared: i64 = event_signups
+            .filter(member.eq(duplicate))
+            .filter(event.eq_any(target_events))
+            .count()
+            .get_result(connection)?;
+        if shared > 0 {
+            return Ok(Some("Both members are signed up for the same event"));
+        }
+    }
+
+    if let (Some(from), Some(to)) = (
+        get_member_account(connection, duplicate)?,
+        get_member_account(connection, target)?,
+    ) {
+        use crate::schema::tables::izettle_transaction::dsl::*;
+        let pending: i64 = izettle_transaction
+            .filter(
+                (debited_account.eq(from).and(credited_account.eq(to)))
+                    .or(debited_account.eq(to).and(credited_account.eq(from))),
+            )
+            .count()
+            .get_result(connection)?;
+        if pending > 0 {
+            return Ok(Some(
+                "A card payment between the accounts of the members is pending",
+            ));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Merge a duplicate member into another member.
+///
+/// The transactions of the tillgodo account of the duplicate are moved to the account of the
+/// other member, which moves the balance along with them. If the other member has no account, it
+/// takes over the account of the duplicate instead. Event signups of the duplicate are moved
+/// along, and the duplicate is then deleted.
+///
+/// This should be called from within a database transaction, after checking that the members
+/// can be merged with [find_merge_conflict].
+pub fn merge_members(
+    connection: &DatabaseConn,
+    duplicate: MemberId,
+    target: MemberId,
+    revised_by: &str,
+) -> Result<MergedTransactions> {
+    {
+        // make sure that the target exists before anything is moved to it
+        use crate::schema::tables::members::dsl::*;
+        members
+            .find(target)
+            .select(id)
+            .first::<MemberId>(connection)?;
+    }
+
+    let mut merged = MergedTransactions::default();
+    match (
+        get_member_account(connection, duplicate)?,
+        get_member_account(connection, target)?,
+    ) {
+        (Some(from), Some(to)) => {
+            merged = move_account_history(connection, from, to, revised_by)?;
+
+            use crate::schema::tables::book_accounts::dsl::*;
+            let referenced: i64 = {
+                use crate::schema::tables::transactions::dsl::*;
+                transactions
+                    .filter(debited_account.eq(from).or(credited_account.eq(from)))
+                    .count()
+                    .get_result(connection)?
+            };
+
+            if referenced == 0 {
+                diesel::delete(book_accounts.find(from)).execute(connection)?;
+            } else {
+                // the deleted transactions between the accounts still refer to the account of
+                // the duplicate, so it is kept but no longer belongs to anyone
+                diesel::update(book_accounts.find(from))
+                    .set(creditor.eq(None::<MemberId>))
+                    .execute(connection)?;
+            }
+        }
+        (Some(from), None) => {
+            use crate::schema::tables::book_accounts::dsl::*;
+            diesel::update(book_accounts.find(from))
+                .set(creditor.eq(target))
+                .execute(connection)?;
+        }
+        (None, _) => {}
+    }
+
+    {
+        use crate::schema::tables::event_signups::dsl::*;
+        diesel::update(event_signups.filter(member.eq(duplicate)))
+            .set(member.eq(target))
+            .execute(connection)?;
+    }
+
+    use crate::schema::tables::members::dsl::*;
+    diesel::delete(members.find(duplicate))
+        .returning(id)
+        .get_result::<MemberId>(connection)?;
+
+    Ok(merged)
+}
+
+/// Move all transactions and pending payments of one book account to another
+///
+/// Transactions between the two accounts would go from and to the same account, so they are
+/// deleted instead, which nets them out the same way.
+fn move_account_history(
+    connection: &DatabaseConn,
+    from: BookAccountId,
+    to: BookAccountId,
+    revised_by: &str,
+) -> Result<MergedTransactions> {
+    let mut merged = MergedTransactions::default();
+
+    let history = objectify_transations(query_transaction(
+        connection,
+        TransactionFilter {
+            deleted: DeletedFilter::Include,
+            account: Some(from),
+            ..Default::default()
+        },
+    )?);
+
+    let now = Utc::now();
+    for transaction in history {
+        let id = transaction.id;
+        let old = NewTransaction::from(transaction);
+        let moved = NewTransaction {
+            debited_account: move_account(old.debited_account, from, to),
+            credited_account: move_account(old.credited_account, from, to),
+            ..old.clone()
+        };
+
+        use crate::schema::tables::transactions::dsl;
+        if moved.debited_account == moved.credited_account {
+            let deleted = diesel::update(dsl::transactions.find(id))
+                .filter(dsl::deleted_at.is_null())
+                .set(dsl::deleted_at.eq(Some(now)))
+                .execute(connection)?;
+            if deleted > 0 {
+                merged.deleted.push(id);
+            }
+        } else {
+            diesel::update(dsl::transactions.find(id))
+                .set((
+                    dsl::debited_account.eq(moved.debited_account),
+                    dsl::credited_account.eq(moved.credited_account),
+                ))
+                .execute(connection)?;
+            insert_revision(connection, id, &old, &moved, revised_by)?;
+            merged.moved.push(id);
+        }
+    }
+
+    {
+        use crate::schema::tables::izettle_transaction::dsl::*;
+        diesel::update(izettle_transaction.filter(debited_account.eq(from)))
+            .set(debited_account.eq(to))
+            .execute(connection)?;
+        diesel::update(izettle_transaction.filter(credited_account.eq(from)))
+            .set(credited_account.eq(to))
+            .execute(connection)?;
+    }
+
+    {
+        use crate::schema::tables::pending_sale_payments::dsl::*;
+        diesel::update(pending_sale_payments.filter(debited_account.eq(from)))
+            .set(debited_account.eq(to))
+            .execute(connection)?;
+    }
+
+    move_pending_swish_payments(connection, from, to)?;
+
+    Ok(merged)
+}
+
+fn move_account(account: BookAccountId, from: BookAccountId, to: BookAccountId) -> BookAccountId {
+    if account == from {
+        to
+    } else {
+        account
+    }
+}
+
+/// Point the transactions of pending Swish payments at the account `to` instead of `from`
+///
+/// A payment which would then go from and to the same account is failed, along with the rest of
+/// the sale it is a part of.
+fn move_pending_swish_payments(
+    connection: &DatabaseConn,
+    from: BookAccountId,
+    to: BookAccountId,
+) -> Result<()> {
+    use crate::schema::tables::swish_payments::dsl::*;
+
+    let pending: Vec<(i32, serde_json::Value)> = swish_payments
+        .filter(status.eq(TRANSACTION_IN_PROGRESS))
+        .select((id, new_transaction))
+        .load(connection)?;
+
+    for (payment, json) in pending {
+        let mut transaction: NewTransaction =
+            serde_json::from_value(json).map_err(|e| Error::DeserializationError(Box::new(e)))?;
+        if transaction.debited_account != from && transaction.credited_account != from {
+            continue;
+        }
+
+        transaction.debited_account = move_account(transaction.debited_account, from, to);
+        transaction.credited_account = move_account(transaction.credited_account, from, to);
+
+        if transaction.debited_account == transaction.credited_account {
+            diesel::update(swish_payments.find(payment))
+                .set((
+                    status.eq(TRANSACTION_FAILED),
+                    error.eq("The book accounts of the payment were merged"),
+                ))
+                .execute(connection)?;
+            discard_pending_sale(connection, PendingSale::Swish(payment))?;
+        } else {
+            let json = serde_json::to_value(&transaction)
+                .map_err(|e| Error::SerializationError(Box::new(e)))?;
+            diesel::update(swish_payments.find(payment))
+                .set(new_transaction.eq(json))
+                .execute(connection)?;
+        }
+    }
+
+    Ok(())
+}
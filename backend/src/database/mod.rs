@@ -1,7 +1,13 @@
+pub mod bas_account;
+pub mod book_account;
+pub mod cash_register;
 pub mod event;
+pub mod member;
+pub mod report;
+pub mod sale;
 pub mod transaction;
+pub mod user;
 
-use crate::Opt;
 use diesel::pg::PgConnection;
 use diesel::r2d2::ConnectionManager;
 use diesel_migrations::{
@@ -13,8 +19,9 @@ use std::error::Error;
 pub type DatabasePool = Pool<ConnectionManager<PgConnection>>;
 pub type DatabaseConn = PooledConnection<ConnectionManager<PgConnection>>;
 
-pub fn create_pool(opt: &Opt) -> Result<DatabasePool, Box<dyn Error>> {
-    let db_manager: ConnectionManager<PgConnection> = ConnectionManager::new(&opt.database);
+/// Create a connection pool to the database at `database_url`, a postgres:// uri
+pub fn create_pool(database_url: &str) -> Result<DatabasePool, Box<dyn Error>> {
+    let db_manager: ConnectionManager<PgConnection> = ConnectionManager::new(database_url);
     let db_pool: Pool<ConnectionManager<PgConnection>> =
         Pool::builder().max_size(15).build(db_manager)?;
     Ok(db_pool)
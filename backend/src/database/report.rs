@@ -0,0 +1,296 @@
+use crate::database::DatabaseConn;
+use crate::models::book_account as relational;
+use crate::models::transaction::relational::Transaction;
+use diesel::prelude::*;
+use diesel::result::QueryResult as Result;
+use std::collections::HashMap;
+use strecklistan_api::book_account::{BookAccount, BookAccountId, BookAccountType};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::report::{
+    AccountAmount, FinancialStatement, GeneralLedger, LedgerEntry, ReportPeriod, TrialBalance,
+    TrialBalanceRow,
+};
+
+/// Load all book accounts, ordered by id
+pub fn load_accounts(connection: &DatabaseConn) -> Result<Vec<BookAccount>> {
+    use crate::schema::tables::book_accounts::dsl::*;
+
+    Ok(book_accounts
+        .order_by(id)
+        .load(connection)?
+        .into_iter()
+        .map(|account: relational::BookAccount| account.into())
+        .collect())
+}
+
+/// Load all transactions made before the end of the period, oldest first
+///
+/// Transactions before the start of the period are included as well, since they are needed to
+/// compute the opening balances.
+pub fn load_transactions(
+    connection: &DatabaseConn,
+    period: ReportPeriod,
+) -> Result<Vec<Transaction>> {
+    use crate::schema::tables::transactions::dsl::*;
+
+    let mut query = transactions
+        .filter(deleted_at.is_null())
+        .order_by((time, id))
+        .into_boxed();
+
+    if let Some(to) = period.to {
+        query = query.filter(time.lt(to));
+    }
+
+    query.load(connection)
+}
+
+fn in_period(period: &ReportPeriod, transaction: &Transaction) -> bool {
+    period
+        .from
+        .map(|from| transaction.time >= from)
+        .unwrap_or(true)
+}
+
+/// Check that `expenses + assets = liabilities + revenue`
+fn is_balanced<'a>(balances: impl Iterator<Item = (&'a BookAccount, Currency)>) -> bool {
+    let (mut left, mut right) = (Currency::default(), Currency::default());
+    for (account, balance) in balances {
+        match account.account_type {
+            BookAccountType::Expenses | BookAccountType::Assets => left += balance,
+            BookAccountType::Liabilities | BookAccountType::Revenue => right += balance,
+        }
+    }
+    left == right
+}
+
+pub fn general_ledger(
+    account: &BookAccount,
+    transactions: &[Transaction],
+    period: ReportPeriod,
+) -> GeneralLedger {
+    let mut balance = Currency::default();
+    let mut opening_balance = Currency::default();
+    let mut entries = vec![];
+
+    for tr in transactions {
+        let (debit, credit, counter_account) = if tr.debited_account == account.id {
+            (tr.amount.into(), Currency::default(), tr.credited_account)
+        } else if tr.credited_account == account.id {
+            (Currency::default(), tr.amount.into(), tr.debited_account)
+        } else {
+            continue;
+        };
+
+        balance += account.debit_diff(debit) + account.credit_diff(credit);
+
+        if in_period(&period, tr) {
+            entries.push(LedgerEntry {
+                transaction_id: tr.id,
+                time: tr.time,
+                description: tr.description.clone(),
+                counter_account,
+                debit,
+                credit,
+                balance,
+            });
+        } else {
+            opening_balance = balance;
+        }
+    }
+
+    GeneralLedger {
+        period,
+        account_id: account.id,
+        account_name: account.name.clone(),
+        account_type: account.account_type,
+        opening_balance,
+        entries,
+        closing_balance: balance,
+    }
+}
+
+pub fn trial_balance(
+    accounts: &[BookAccount],
+    transactions: &[Transaction],
+    period: ReportPeriod,
+) -> TrialBalance {
+    let mut totals: HashMap<BookAccountId, (Currency, Currency)> = HashMap::new();
+
+    for tr in transactions.iter().filter(|tr| in_period(&period, tr)) {
+        totals.entry(tr.debited_account).or_default().0 += tr.amount.into();
+        totals.entry(tr.credited_account).or_default().1 += tr.amount.into();
+    }
+
+    let rows: Vec<TrialBalanceRow> = accounts
+        .iter()
+        .map(|account| {
+            let (debit, credit) = totals.get(&account.id).copied().unwrap_or_default();
+            TrialBalanceRow {
+                account_id: account.id,
+                account_name: account.name.clone(),
+                account_type: account.account_type,
+                debit,
+                credit,
+                balance: account.debit_diff(debit) + account.credit_diff(credit),
+            }
+        })
+        .collect();
+
+    let total_debit = rows
+        .iter()
+        .fold(Currency::default(), |sum, row| sum + row.debit);
+    let total_credit = rows
+        .iter()
+        .fold(Currency::default(), |sum, row| sum + row.credit);
+    let balanced = total_debit == total_credit
+        && is_balanced(accounts.iter().zip(rows.iter().map(|row| row.balance)));
+
+    TrialBalance {
+        period,
+        rows,
+        total_debit,
+        total_credit,
+        balanced,
+    }
+}
+
+pub fn financial_statement(
+    accounts: &[BookAccount],
+    transactions: &[Transaction],
+    period: ReportPeriod,
+) -> FinancialStatement {
+    // account -> (balance at the end of the period, change during the period)
+    let mut balances: HashMap<BookAccountId, (Currency, Currency)> = HashMap::new();
+    let accounts_by_id: HashMap<BookAccountId, &BookAccount> = accounts
+        .iter()
+        .map(|account| (account.id, account))
+        .collect();
+
+    let mut apply = |id, diff: fn(&BookAccount, Currency) -> Currency, tr: &Transaction| {
+        if let Some(account) = accounts_by_id.get(&id) {
+            let diff = diff(account, tr.amount.into());
+            let (closing, change) = balances.entry(id).or_default();
+            *closing += diff;
+            if in_period(&period, tr) {
+                *change += diff;
+            }
+        }
+    };
+
+    for tr in transactions {
+        apply(tr.debited_account, BookAccount::debit_diff, tr);
+        apply(tr.credited_account, BookAccount::credit_diff, tr);
+    }
+
+    let section = |account_type: BookAccountType, for_period: bool| -> Vec<AccountAmount> {
+        accounts
+            .iter()
+            .filter(|account| account.account_type == account_type)
+            .map(|account| {
+                let (closing, change) = balances.get(&account.id).copied().unwrap_or_default();
+                AccountAmount {
+                    account_id: account.id,
+                    account_name: account.name.clone(),
+                    amount: if for_period { change } else { closing },
+                }
+            })
+            .collect()
+    };
+    let total = |amounts: &[AccountAmount]| {
+        amounts
+            .iter()
+            .fold(Currency::default(), |sum, row| sum + row.amount)
+    };
+
+    let revenue = section(BookAccountType::Revenue, true);
+    let expenses = section(BookAccountType::Expenses, true);
+    let assets = section(BookAccountType::Assets, false);
+    let liabilities = section(BookAccountType::Liabilities, false);
+
+    let (total_revenue, total_expenses) = (total(&revenue), total(&expenses));
+
+    FinancialStatement {
+        period,
+        total_assets: total(&assets),
+        total_liabilities: total(&liabilities),
+        total_revenue,
+        total_expenses,
+        net_income: total_revenue - total_expenses,
+        balanced: is_balanced(accounts.iter().map(|account| {
+            let (closing, _) = balances.get(&account.id).copied().unwrap_or_default();
+            (account, closing)
+        })),
+        revenue,
+        expenses,
+        assets,
+        liabilities,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    fn account(id: BookAccountId, account_type: BookAccountType) -> BookAccount {
+        BookAccount {
+            id,
+            name: format!("account {}", id),
+            account_type,
+            creditor: None,
+            balance: 0.into(),
+            credit_limit: None,
+        }
+    }
+
+    fn transaction(id: i32, day: u32, debited: i32, credited: i32, amount: i32) -> Transaction {
+        Transaction {
+            id,
+            description: None,
+            time: format!("2022-04-{:02}T12:00:00Z", day).parse().unwrap(),
+            debited_account: debited,
+            credited_account: credited,
+            amount,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_reports() {
+        let accounts = vec![
+            account(1, BookAccountType::Assets),
+            account(2, BookAccountType::Revenue),
+            account(3, BookAccountType::Expenses),
+            account(4, BookAccountType::Liabilities),
+        ];
+        let transactions = vec![
+            transaction(1, 1, 1, 4, 10000), // deposit
+            transaction(2, 2, 3, 1, 3000),  // purchase
+            transaction(3, 3, 4, 2, 500),   // sale paid with deposit
+            transaction(4, 4, 1, 2, 1500),  // sale paid in cash
+        ];
+        let period = ReportPeriod {
+            from: Some("2022-04-02T00:00:00Z".parse::<DateTime<Utc>>().unwrap()),
+            to: None,
+        };
+
+        let ledger = general_ledger(&accounts[0], &transactions, period);
+        assert_eq!(ledger.opening_balance, 10000.into());
+        assert_eq!(ledger.entries.len(), 2);
+        assert_eq!(ledger.closing_balance, 8500.into());
+
+        let trial = trial_balance(&accounts, &transactions, period);
+        assert!(trial.balanced);
+        assert_eq!(trial.total_debit, 5000.into());
+        assert_eq!(trial.total_credit, 5000.into());
+
+        let statement = financial_statement(&accounts, &transactions, period);
+        assert!(statement.balanced);
+        assert_eq!(statement.total_revenue, 2000.into());
+        assert_eq!(statement.total_expenses, 3000.into());
+        assert_eq!(statement.net_income, (-1000).into());
+        assert_eq!(statement.total_assets, 8500.into());
+        assert_eq!(statement.total_liabilities, 9500.into());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::database::book_account::find_exceeded_credit_limit;
+use crate::database::transaction::insert_transaction;
+use crate::database::DatabaseConn;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::result::QueryResult as Result;
+use strecklistan_api::book_account::{BookAccount, BookAccountId};
+use strecklistan_api::sale::{NewSale, Sale, SaleId, SalePayment};
+use strecklistan_api::swish::SwishPaymentId;
+use strecklistan_api::transaction::{NewTransaction, TransactionId};
+
+/// A card or Swish payment which the remaining payments of a sale are waiting on
+#[derive(Clone, Copy)]
+pub enum PendingSale {
+    IZettle(i32),
+    Swish(SwishPaymentId),
+}
+
+/// Record every payment of the sale as a transaction, and link them together as one sale
+pub fn insert_sale(connection: &DatabaseConn, sale: NewSale) -> Result<Sale> {
+    let transaction_ids = sale
+        .into_transactions()
+        .into_iter()
+        .map(|transaction| insert_transaction(connection, transaction))
+        .collect::<Result<Vec<_>>>()?;
+
+    link_sale(connection, transaction_ids)
+}
+
+/// Link the transactions together as one sale
+fn link_sale(connection: &DatabaseConn, transaction_ids: Vec<TransactionId>) -> Result<Sale> {
+    let (new_sale_id, sale_time): (SaleId, DateTime<Utc>) = {
+        use crate::schema::tables::sales::dsl::*;
+        diesel::insert_into(sales)
+            .default_values()
+            .returning((id, time))
+            .get_result(connection)?
+    };
+
+    {
+        use crate::schema::tables::sale_transactions::dsl::*;
+        let rows: Vec<_> = transaction_ids
+            .iter()
+            .map(|&transaction| (sale_id.eq(new_sale_id), transaction_id.eq(transaction)))
+            .collect();
+        diesel::insert_into(sale_transactions)
+            .values(&rows)
+            .execute(connection)?;
+    }
+
+    Ok(Sale {
+        id: new_sale_id,
+        time: sale_time,
+        transaction_ids,
+    })
+}
+
+/// Get a sale and the ids of its transactions
+pub fn load_sale(connection: &DatabaseConn, sale: SaleId) -> Result<Sale> {
+    use crate::schema::tables::sale_transactions::dsl::{
+        sale_id, sale_transactions, transaction_id,
+    };
+    use crate::schema::tables::sales::dsl::{sales, time};
+
+    let sale_time = sales.find(sale).select(time).first(connection)?;
+    let transaction_ids = sale_transactions
+        .filter(sale_id.eq(sale))
+        .select(transaction_id)
+        .order_by(transaction_id)
+        .load(connection)?;
+
+    Ok(Sale {
+        id: sale,
+        time: sale_time,
+        transaction_ids,
+    })
+}
+
+/// Store the remaining payments of a sale, to be recorded once the card or Swish payment is paid
+pub fn insert_pending_sale_payments(
+    connection: &DatabaseConn,
+    pending: PendingSale,
+    payments: &[SalePayment],
+) -> Result<()> {
+    use crate::schema::tables::pending_sale_payments::dsl::*;
+
+    let (izettle, swish) = match pending {
+        PendingSale::IZettle(reference) => (Some(reference), None),
+        PendingSale::Swish(payment) => (None, Some(payment)),
+    };
+
+    let rows: Vec<_> = payments
+        .iter()
+        .map(|payment| {
+            (
+                izettle_transaction_id.eq(izettle),
+                swish_payment_id.eq(swish),
+                debited_account.eq(payment.debited_account),
+                amount.eq(i32::from(payment.amount)),
+            )
+        })
+        .collect();
+
+    diesel::insert_into(pending_sale_payments)
+        .values(&rows)
+        .execute(connection)?;
+
+    Ok(())
+}
+
+/// Find the first tillgodo account which the remaining payments of a sale would overdraw beyond its
+/// credit limit, if they were recorded now as credited to `credited_account`
+///
+/// Like [find_exceeded_credit_limit], this locks the affected accounts until the end of the
+/// database transaction.
+pub fn find_exceeded_sale_credit_limit(
+    connection: &DatabaseConn,
+    pending: PendingSale,
+    credited_account: BookAccountId,
+) -> Result<Option<BookAccount>> {
+    let transactions: Vec<NewTransaction> = load_pending_payments(connection, pending)?
+        .into_iter()
+        .map(|(debited_account, amount)| NewTransaction {
+            description: None,
+            bundles: vec![],
+            debited_account,
+            credited_account,
+            amount: amount.into(),
+        })
+        .collect();
+
+    if transactions.is_empty() {
+        return Ok(None);
+    }
+
+    find_exceeded_credit_limit(connection, &transactions)
+}
+
+/// Record the remaining payments of a sale, now that its card or Swish payment has been recorded
+/// as `transaction`. The new transactions get the description and credited account of it.
+///
+/// Returns `None` if the payment was not part of a sale.
+pub fn complete_pending_sale(
+    connection: &DatabaseConn,
+    pending: PendingSale,
+    transaction: TransactionId,
+) -> Result<Option<Sale>> {
+    let payments = load_pending_payments(connection, pending)?;
+
+    if payments.is_empty() {
+        return Ok(None);
+    }
+
+    let (transaction_description, transaction_credited): (Option<String>, BookAccountId) = {
+        use crate::schema::tables::transactions::dsl::*;
+        transactions
+            .find(transaction)
+            .select((description, credited_account))
+            .first(connection)?
+    };
+
+    let mut transaction_ids = vec![transaction];
+    for (debited_account, amount) in payments {
+        transaction_ids.push(insert_transaction(
+            connection,
+            NewTransaction {
+                description: transaction_description.clone(),
+                bundles: vec![],
+                debited_account,
+                credited_account: transaction_credited,
+                amount: amount.into(),
+            },
+        )?);
+    }
+
+    link_sale(connection, transaction_ids).map(Some)
+}
+
+/// Delete the remaining payments of a sale whose card or Swish payment failed or was cancelled
+pub fn discard_pending_sale(connection: &DatabaseConn, pending: PendingSale) -> Result<()> {
+    use crate::schema::tables::pending_sale_payments::dsl::*;
+    match pending {
+        PendingSale::IZettle(reference) => {
+            diesel::delete(pending_sale_payments.filter(izettle_transaction_id.eq(reference)))
+                .execute(connection)?
+        }
+        PendingSale::Swish(payment) => {
+            diesel::delete(pending_sale_payments.filter(swish_payment_id.eq(payment)))
+                .execute(connection)?
+        }
+    };
+
+    Ok(())
+}
+
+/// Load the debited accounts and amounts of the remaining payments of a sale
+fn load_pending_payments(
+    connection: &DatabaseConn,
+    pending: PendingSale,
+) -> Result<Vec<(BookAccountId, i32)>> {
+    use crate::schema::tables::pending_sale_payments::dsl::*;
+    let query = pending_sale_payments
+        .select((debited_account, amount))
+        .order_by(id)
+        .into_boxed();
+    match pending {
+        PendingSale::IZettle(reference) => query
+            .filter(izettle_transaction_id.eq(reference))
+            .load(connection),
+        PendingSale::Swish(payment) => query.filter(swish_payment_id.eq(payment)).load(connection),
+    }
+}
@@ -1,10 +1,16 @@
 use crate::database::DatabaseConn;
 use crate::models::transaction::{object, relational};
+use crate::schema::tables::transactions;
+use chrono::{DateTime, Utc};
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel::result::Error;
 use itertools::Itertools;
 use std::collections::HashMap;
-use strecklistan_api::transaction::TransactionId;
+use strecklistan_api::book_account::BookAccountId;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::transaction::{TransactionId, TransactionRevisionId};
 
 pub type TransactionJoined = Vec<(
     relational::Transaction,
@@ -12,19 +18,128 @@ pub type TransactionJoined = Vec<(
     Option<relational::TransactionItem>,
 )>;
 
+/// Which rows to yield, depending on whether they are marked as deleted
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeletedFilter {
+    /// Only yield rows which are not marked as deleted
+    #[default]
+    Exclude,
+
+    /// Yield rows whether they are marked as deleted or not
+    Include,
+
+    /// Only yield rows marked as deleted
+    Only,
+}
+
 #[derive(Default)]
 #[non_exhaustive]
 pub struct TransactionFilter {
-    /// Whether to include rows marked as deleted
-    pub deleted: bool,
+    pub deleted: DeletedFilter,
 
     /// Only yield rows with this transaction id
     pub id: Option<TransactionId>,
+
+    /// Only yield rows made at or after this time
+    pub from: Option<DateTime<Utc>>,
+
+    /// Only yield rows made before this time
+    pub to: Option<DateTime<Utc>>,
+
+    /// Only yield rows which debit or credit this account
+    pub account: Option<BookAccountId>,
+
+    /// Only yield rows which contain this inventory item
+    pub item: Option<InventoryItemId>,
+
+    /// Only yield rows with an amount of at least this
+    pub min_amount: Option<Currency>,
+
+    /// Only yield rows with an amount of at most this
+    pub max_amount: Option<Currency>,
+
+    /// Only yield rows whose description contains this string, ignoring case
+    pub description: Option<String>,
 }
 
-pub fn query_transaction(
+type BoxedTransactions<'a> = transactions::BoxedQuery<'a, Pg>;
+
+/// Build a query over all transactions matching the filter
+fn filter_transactions(filter: &TransactionFilter) -> BoxedTransactions<'_> {
+    use crate::schema::tables::transaction_bundles::dsl::{
+        id as bundle_id, transaction_bundles, transaction_id as bundle_transaction_id,
+    };
+    use crate::schema::tables::transaction_items::dsl::{
+        bundle_id as item_bundle_id, item_id, transaction_items,
+    };
+    use crate::schema::tables::transactions::dsl::*;
+
+    let mut query = transactions.into_boxed();
+
+    match filter.deleted {
+        DeletedFilter::Exclude => query = query.filter(deleted_at.is_null()),
+        DeletedFilter::Include => {}
+        DeletedFilter::Only => query = query.filter(deleted_at.is_not_null()),
+    }
+
+    if let Some(filter_id) = filter.id {
+        query = query.filter(id.eq(filter_id));
+    }
+
+    if let Some(from) = filter.from {
+        query = query.filter(time.ge(from));
+    }
+
+    if let Some(to) = filter.to {
+        query = query.filter(time.lt(to));
+    }
+
+    if let Some(account) = filter.account {
+        query = query.filter(debited_account.eq(account).or(credited_account.eq(account)));
+    }
+
+    if let Some(item) = filter.item {
+        query = query.filter(
+            id.eq_any(
+                transaction_bundles
+                    .inner_join(transaction_items.on(bundle_id.eq(item_bundle_id)))
+                    .filter(item_id.eq(item))
+                    .select(bundle_transaction_id),
+            ),
+        );
+    }
+
+    if let Some(min_amount) = filter.min_amount {
+        query = query.filter(amount.ge(i32::from(min_amount)));
+    }
+
+    if let Some(max_amount) = filter.max_amount {
+        query = query.filter(amount.le(i32::from(max_amount)));
+    }
+
+    if let Some(text) = &filter.description {
+        query = query.filter(description.ilike(format!("%{}%", escape_like(text))));
+    }
+
+    query
+}
+
+/// Escape the wildcard characters of a LIKE pattern
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Load the joined rows of the transactions with the given ids
+fn load_joined(
     connection: &DatabaseConn,
-    filter: TransactionFilter,
+    transaction_ids: &[TransactionId],
 ) -> Result<TransactionJoined, Error> {
     use crate::schema::tables::transaction_bundles::dsl::{
         id as bundle_id, transaction_bundles, transaction_id as bundle_transaction_id,
@@ -32,24 +147,396 @@ pub fn query_transaction(
     use crate::schema::tables::transaction_items::dsl::{
         bundle_id as item_bundle_id, transaction_items,
     };
-    use crate::schema::tables::transactions::dsl::{
-        deleted_at, id as transaction_id, time, transactions,
-    };
+    use crate::schema::tables::transactions::dsl::{id as transaction_id, time, transactions};
 
     transactions
-        .filter(deleted_at.is_null().or(filter.deleted))
-        .filter(
-            transaction_id
-                .eq(filter.id.unwrap_or(-1))
-                .or(filter.id.is_none()),
-        )
+        .filter(transaction_id.eq_any(transaction_ids))
         .left_join(transaction_bundles.on(transaction_id.eq(bundle_transaction_id)))
         .left_join(transaction_items.on(bundle_id.eq(item_bundle_id)))
-        .order_by(time.desc())
-        .order_by(transaction_id.desc())
+        .order_by((time.desc(), transaction_id.desc(), bundle_id))
         .load(connection)
 }
 
+pub fn query_transaction(
+    connection: &DatabaseConn,
+    filter: TransactionFilter,
+) -> Result<TransactionJoined, Error> {
+    use crate::schema::tables::transactions::dsl::{id, time};
+
+    let ids: Vec<TransactionId> = filter_transactions(&filter)
+        .select(id)
+        .order_by((time.desc(), id.desc()))
+        .load(connection)?;
+
+    load_joined(connection, &ids)
+}
+
+/// A page of transactions, newest first
+pub struct TransactionPageJoined {
+    pub transactions: TransactionJoined,
+
+    /// The number of transactions matching the filter, on all pages
+    pub total: i64,
+
+    /// The cursor of the next page, if there is one
+    pub next: Option<TransactionId>,
+}
+
+/// Query a page of transactions
+///
+/// The page starts after the transaction `after`, if set, and contains at most `limit`
+/// transactions. A `limit` of `None` yields all remaining transactions.
+pub fn query_transaction_page(
+    connection: &DatabaseConn,
+    filter: TransactionFilter,
+    after: Option<TransactionId>,
+    limit: Option<i64>,
+) -> Result<TransactionPageJoined, Error> {
+    use crate::schema::tables::transactions::dsl::{id, time, transactions};
+
+    let total: i64 = filter_transactions(&filter)
+        .count()
+        .get_result(connection)?;
+
+    let mut query = filter_transactions(&filter)
+        .select(id)
+        .order_by((time.desc(), id.desc()));
+
+    if let Some(after) = after {
+        let after_time: DateTime<Utc> = transactions.find(after).select(time).first(connection)?;
+        query = query.filter(
+            time.lt(after_time)
+                .or(time.eq(after_time).and(id.lt(after))),
+        );
+    }
+
+    if let Some(limit) = limit {
+        // fetch one extra row to find out if there is a next page
+        query = query.limit(limit + 1);
+    }
+
+    let mut ids: Vec<TransactionId> = query.load(connection)?;
+
+    let next = match limit {
+        Some(limit) if ids.len() as i64 > limit => {
+            ids.truncate(limit as usize);
+            ids.last().copied()
+        }
+        _ => None,
+    };
+
+    Ok(TransactionPageJoined {
+        transactions: load_joined(connection, &ids)?,
+        total,
+        next,
+    })
+}
+
+/// Insert a transaction together with its bundles and items
+///
+/// This should be called from within a database transaction.
+pub fn insert_transaction(
+    connection: &DatabaseConn,
+    transaction: object::NewTransaction,
+) -> Result<TransactionId, Error> {
+    let object::NewTransaction {
+        description,
+        bundles,
+        debited_account,
+        credited_account,
+        amount,
+    } = transaction;
+
+    let transaction = relational::NewTransaction {
+        description,
+        time: None,
+        debited_account,
+        credited_account,
+        amount: amount.into(),
+    };
+
+    let transaction_id = {
+        use crate::schema::tables::transactions::dsl::*;
+        diesel::insert_into(transactions)
+            .values(transaction)
+            .returning(id)
+            .get_result(connection)?
+    };
+
+    insert_bundles(connection, transaction_id, bundles)?;
+
+    Ok(transaction_id)
+}
+
+fn insert_bundles(
+    connection: &DatabaseConn,
+    transaction_id: TransactionId,
+    bundles: Vec<object::TransactionBundle>,
+) -> Result<(), Error> {
+    for bundle in bundles.into_iter() {
+        let new_bundle = relational::NewTransactionBundle {
+            transaction_id,
+            description: bundle.description,
+            price: bundle.price.map(|p| p.into()),
+            change: bundle.change,
+        };
+
+        let bundle_id = {
+            use crate::schema::tables::transaction_bundles::dsl::*;
+            diesel::insert_into(transaction_bundles)
+                .values(&new_bundle)
+                .returning(id)
+                .get_result(connection)?
+        };
+
+        let item_ids: Vec<_> = bundle
+            .item_ids
+            .into_iter()
+            .flat_map(|(item_id, count)| std::iter::repeat(item_id).take(count as usize))
+            .map(|item_id| relational::NewTransactionItem { bundle_id, item_id })
+            .collect();
+
+        {
+            use crate::schema::tables::transaction_items::dsl::*;
+            diesel::insert_into(transaction_items)
+                .values(&item_ids)
+                .execute(connection)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Describe what a transaction is the bookkeeping of, if it was recorded along with a purchase,
+/// a payment, a sale, an event signup, a stock take or a cash register count.
+///
+/// The amount and accounts of such a transaction have to match that record, so it can't be
+/// amended on its own.
+pub fn find_linked_record(
+    connection: &DatabaseConn,
+    transaction_id: TransactionId,
+) -> Result<Option<&'static str>, Error> {
+    use diesel::dsl::exists;
+
+    let purchase = {
+        use crate::schema::tables::purchases::dsl;
+        diesel::select(exists(
+            dsl::purchases.filter(dsl::transaction_id.eq(transaction_id)),
+        ))
+    };
+    let card_payment = {
+        use crate::schema::tables::izettle_post_transaction::dsl;
+        diesel::select(exists(
+            dsl::izettle_post_transaction.filter(dsl::transaction_id.eq(transaction_id)),
+        ))
+    };
+    let card_refund = {
+        use crate::schema::tables::izettle_refunds::dsl;
+        diesel::select(exists(
+            dsl::izettle_refunds.filter(dsl::transaction_id.eq(transaction_id)),
+        ))
+    };
+    let swish_payment = {
+        use crate::schema::tables::swish_payments::dsl;
+        diesel::select(exists(
+            dsl::swish_payments.filter(dsl::transaction_id.eq(transaction_id)),
+        ))
+    };
+    let sale = {
+        use crate::schema::tables::sale_transactions::dsl;
+        diesel::select(exists(
+            dsl::sale_transactions.filter(dsl::transaction_id.eq(transaction_id)),
+        ))
+    };
+    let event_signup = {
+        use crate::schema::tables::event_signups::dsl;
+        diesel::select(exists(
+            dsl::event_signups.filter(dsl::transaction_id.eq(transaction_id)),
+        ))
+    };
+    let stock_take = {
+        use crate::schema::tables::stock_takes::dsl;
+        diesel::select(exists(
+            dsl::stock_takes.filter(dsl::transaction_id.eq(transaction_id)),
+        ))
+    };
+    let cash_correction = {
+        use crate::schema::tables::cash_register_sessions::dsl;
+        diesel::select(exists(
+            dsl::cash_register_sessions.filter(
+                dsl::opening_transaction_id
+                    .eq(transaction_id)
+                    .or(dsl::closing_transaction_id.eq(transaction_id)),
+            ),
+        ))
+    };
+
+    Ok(if purchase.get_result(connection)? {
+        Some("purchase")
+    } else if card_payment.get_result(connection)? {
+        Some("card payment")
+    } else if card_refund.get_result(connection)? {
+        Some("card refund")
+    } else if swish_payment.get_result(connection)? {
+        Some("Swish payment")
+    } else if sale.get_result(connection)? {
+        Some("sale")
+    } else if event_signup.get_result(connection)? {
+        Some("event signup")
+    } else if stock_take.get_result(connection)? {
+        Some("stock take")
+    } else if cash_correction.get_result(connection)? {
+        Some("cash register correction")
+    } else {
+        None
+    })
+}
+
+/// Load the current contents of a transaction, in the form it is amended with
+pub fn load_contents(
+    connection: &DatabaseConn,
+    transaction_id: TransactionId,
+) -> Result<object::NewTransaction, Error> {
+    Ok(objectify_transations(query_transaction(
+        connection,
+        TransactionFilter {
+            id: Some(transaction_id),
+            ..Default::default()
+        },
+    )?)
+    .into_iter()
+    .next()
+    .ok_or(Error::NotFound)?
+    .into())
+}
+
+/// Replace the contents of a transaction, and record the change in its revision history
+///
+/// `old` are the current contents, see [load_contents]. This should be called from within a
+/// database transaction. Transactions which were recorded along with a purchase or a payment
+/// should not be amended, see [find_linked_record].
+pub fn amend_transaction(
+    connection: &DatabaseConn,
+    transaction_id: TransactionId,
+    old: &object::NewTransaction,
+    transaction: object::NewTransaction,
+    revised_by: &str,
+) -> Result<TransactionRevisionId, Error> {
+    {
+        use crate::schema::tables::transactions::dsl::*;
+        diesel::update(transactions.find(transaction_id))
+            .set((
+                description.eq(&transaction.description),
+                debited_account.eq(transaction.debited_account),
+                credited_account.eq(transaction.credited_account),
+                amount.eq(i32::from(transaction.amount)),
+            ))
+            .execute(connection)?;
+    }
+
+    {
+        use crate::schema::tables::transaction_bundles::dsl;
+        // the items of the bundles are deleted by cascade
+        diesel::delete(dsl::transaction_bundles.filter(dsl::transaction_id.eq(transaction_id)))
+            .execute(connection)?;
+    }
+
+    insert_bundles(connection, transaction_id, transaction.bundles.clone())?;
+
+    insert_revision(connection, transaction_id, old, &transaction, revised_by)
+}
+
+/// Record a change of a transaction in its revision history
+pub fn insert_revision(
+    connection: &DatabaseConn,
+    transaction_id: TransactionId,
+    old: &object::NewTransaction,
+    new: &object::NewTransaction,
+    revised_by: &str,
+) -> Result<TransactionRevisionId, Error> {
+    let to_json = |value: &object::NewTransaction| {
+        serde_json::to_value(value).map_err(|e| Error::SerializationError(Box::new(e)))
+    };
+
+    use crate::schema::tables::transaction_revisions::dsl;
+    diesel::insert_into(dsl::transaction_revisions)
+        .values((
+            dsl::transaction_id.eq(transaction_id),
+            dsl::revised_by.eq(revised_by),
+            dsl::old_value.eq(to_json(old)?),
+            dsl::new_value.eq(to_json(new)?),
+        ))
+        .returning(dsl::id)
+        .get_result(connection)
+}
+
+/// Mark a transaction as deleted, which excludes it from balances and reports
+pub fn delete_transaction(
+    connection: &DatabaseConn,
+    transaction: TransactionId,
+) -> Result<TransactionId, Error> {
+    use crate::schema::tables::transactions::dsl::{deleted_at, id, transactions};
+    diesel::update(transactions)
+        .set(deleted_at.eq(Some(Utc::now().naive_utc())))
+        .filter(id.eq(transaction))
+        .returning(id)
+        .get_result(connection)
+}
+
+/// Undo the deletion of a transaction
+///
+/// Fails with `NotFound` if the transaction isn't deleted.
+pub fn restore_transaction(
+    connection: &DatabaseConn,
+    transaction: TransactionId,
+) -> Result<TransactionId, Error> {
+    use crate::schema::tables::transactions::dsl::{deleted_at, id, transactions};
+    diesel::update(transactions)
+        .set(deleted_at.eq(None::<DateTime<Utc>>))
+        .filter(id.eq(transaction))
+        .filter(deleted_at.is_not_null())
+        .returning(id)
+        .get_result(connection)
+}
+
+type RevisionRow = (
+    TransactionRevisionId,
+    TransactionId,
+    DateTime<Utc>,
+    Option<String>,
+    serde_json::Value,
+    serde_json::Value,
+);
+
+/// Get the revision history of a transaction, oldest first
+pub fn query_revisions(
+    connection: &DatabaseConn,
+    transaction: TransactionId,
+) -> Result<Vec<object::TransactionRevision>, Error> {
+    use crate::schema::tables::transaction_revisions::dsl::*;
+
+    let rows: Vec<RevisionRow> = transaction_revisions
+        .filter(transaction_id.eq(transaction))
+        .order_by(id)
+        .load(connection)?;
+
+    let from_json =
+        |value| serde_json::from_value(value).map_err(|e| Error::DeserializationError(Box::new(e)));
+
+    rows.into_iter()
+        .map(|(row_id, row_transaction, time, user, old, new)| {
+            Ok(object::TransactionRevision {
+                id: row_id,
+                transaction_id: row_transaction,
+                revised_at: time,
+                revised_by: user,
+                old: from_json(old)?,
+                new: from_json(new)?,
+            })
+        })
+        .collect()
+}
+
 /// Convert the flat joined rows of a transaction in the database, to a hierarchical object.
 pub fn objectify_transations(transactions: TransactionJoined) -> Vec<object::Transaction> {
     transactions
@@ -91,3 +578,15 @@ pub fn objectify_transations(transactions: TransactionJoined) -> Vec<object::Tra
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::escape_like;
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("kaffe"), "kaffe");
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("a_b\\c"), "a\\_b\\\\c");
+    }
+}
@@ -0,0 +1,193 @@
+use crate::database::DatabaseConn;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel::result::QueryResult as Result;
+use sha2::{Digest, Sha256};
+use strecklistan_api::user::{NewUser, Session, User, UserRole};
+use uuid::Uuid;
+
+/// The number of hashing rounds used for new passwords
+pub const HASH_ITERATIONS: i32 = 10_000;
+
+/// Hash a password using iterated, salted SHA-256
+///
+/// Returns the hex-encoded hash.
+pub fn hash_password(salt: &str, password: &str, iterations: i32) -> String {
+    let mut hash = Sha256::new()
+        .chain(salt.as_bytes())
+        .chain(password.as_bytes())
+        .finalize();
+
+    for _ in 1..iterations {
+        hash = Sha256::new().chain(salt.as_bytes()).chain(hash).finalize();
+    }
+
+    hex::encode(hash)
+}
+
+/// Generate a new salt and return it together with the password hash, in the format stored in
+/// the `salted_pass` column.
+pub fn salt_password(password: &str, iterations: i32) -> String {
+    let salt = Uuid::new_v4().to_simple().to_string();
+    let hash = hash_password(&salt, password, iterations);
+    format!("{}:{}", salt, hash)
+}
+
+/// Check a password against a value from the `salted_pass` column
+pub fn verify_password(salted_pass: &str, password: &str, iterations: i32) -> bool {
+    let (salt, expected) = match salted_pass.split_once(':') {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    let hash = hash_password(salt, password, iterations);
+
+    // compare the whole string to avoid leaking the length of the matching prefix
+    hash.len() == expected.len()
+        && hash
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+pub fn create_user(connection: &DatabaseConn, new_user: &NewUser) -> Result<User> {
+    use crate::schema::tables::users::dsl::*;
+
+    diesel::insert_into(users)
+        .values((
+            name.eq(&new_user.name),
+            display_name.eq(&new_user.display_name),
+            salted_pass.eq(salt_password(&new_user.password, HASH_ITERATIONS)),
+            hash_iterations.eq(HASH_ITERATIONS),
+            role.eq(new_user.role),
+        ))
+        .returning((name, display_name, role))
+        .get_result(connection)
+}
+
+/// Check the credentials of a user and start a new session
+///
+/// Returns `None` if the user does not exist or if the password is wrong.
+pub fn login(
+    connection: &DatabaseConn,
+    login_name: &str,
+    password: &str,
+    lifetime: Duration,
+) -> Result<Option<Session>> {
+    let (user, salted, iterations): (User, String, i32) = {
+        use crate::schema::tables::users::dsl::*;
+        match users
+            .find(login_name)
+            .select(((name, display_name, role), salted_pass, hash_iterations))
+            .first(connection)
+            .optional()?
+        {
+            Some(row) => row,
+            None => return Ok(None),
+        }
+    };
+
+    if !verify_password(&salted, password, iterations) {
+        return Ok(None);
+    }
+
+    use crate::schema::tables::user_sessions::dsl::*;
+
+    let new_token = format!(
+        "{}{}",
+        Uuid::new_v4().to_simple(),
+        Uuid::new_v4().to_simple()
+    );
+    let expires = Utc::now() + lifetime;
+
+    diesel::insert_into(user_sessions)
+        .values((
+            token_hash.eq(hash_session_token(&new_token)),
+            user_name.eq(&user.name),
+            expires_at.eq(expires),
+        ))
+        .execute(connection)?;
+
+    Ok(Some(Session {
+        token: new_token,
+        user,
+        expires_at: expires,
+    }))
+}
+
+/// Hash a session token, the tokens are only stored as their hex-encoded SHA-256 hash
+fn hash_session_token(session_token: &str) -> String {
+    hex::encode(Sha256::digest(session_token.as_bytes()))
+}
+
+/// Get the user of a session, if the session exists and has not expired
+pub fn get_session_user(connection: &DatabaseConn, session_token: &str) -> Result<Option<User>> {
+    use crate::schema::tables::user_sessions::dsl::{expires_at, token_hash, user_sessions};
+    use crate::schema::tables::users::dsl::{display_name, name, role, users};
+
+    user_sessions
+        .inner_join(users)
+        .filter(token_hash.eq(hash_session_token(session_token)))
+        .filter(expires_at.gt(Utc::now()))
+        .select((name, display_name, role))
+        .first(connection)
+        .optional()
+}
+
+pub fn end_session(connection: &DatabaseConn, session_token: &str) -> Result<()> {
+    use crate::schema::tables::user_sessions::dsl::*;
+
+    diesel::delete(user_sessions.filter(token_hash.eq(hash_session_token(session_token))))
+        .execute(connection)?;
+
+    // take the opportunity to clean up old sessions
+    diesel::delete(user_sessions.filter(expires_at.le(Utc::now()))).execute(connection)?;
+
+    Ok(())
+}
+
+/// Create the user `admin` with the given password, if no users exist.
+///
+/// Returns whether the user was created.
+pub fn bootstrap_admin(connection: &DatabaseConn, password: &str) -> Result<bool> {
+    use crate::schema::tables::users::dsl::*;
+
+    connection.transaction(|| {
+        let user_count: i64 = users.count().get_result(connection)?;
+        if user_count > 0 {
+            return Ok(false);
+        }
+
+        create_user(
+            connection,
+            &NewUser {
+                name: "admin".into(),
+                display_name: None,
+                password: password.into(),
+                role: UserRole::Admin,
+            },
+        )?;
+
+        Ok(true)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_salted_password() {
+        let salted = salt_password("hunter2", 100);
+        assert!(verify_password(&salted, "hunter2", 100));
+        assert!(!verify_password(&salted, "hunter3", 100));
+        assert!(!verify_password(&salted, "hunter2", 99));
+        assert!(!verify_password("not a salted password", "hunter2", 100));
+    }
+
+    #[test]
+    fn salts_are_unique() {
+        assert_ne!(salt_password("hunter2", 1), salt_password("hunter2", 1));
+    }
+}
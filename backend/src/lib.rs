@@ -0,0 +1,11 @@
+#[macro_use]
+extern crate diesel;
+
+#[macro_use]
+extern crate log;
+
+pub mod database;
+pub mod models;
+pub mod routes;
+pub mod schema;
+pub mod util;
@@ -1,25 +1,23 @@
-#[macro_use]
-extern crate diesel;
-
-#[macro_use]
-extern crate log;
-
-mod database;
-pub mod models;
-pub mod routes;
-mod schema;
-pub mod util;
-
-use crate::routes::rest;
-use crate::routes::rest::izettle::IZettleNotifier;
-use crate::util::{catchers, FileResponder};
+use strecklistan_backend::database;
+use strecklistan_backend::routes::rest;
+use strecklistan_backend::routes::rest::changes::ChangeNotifier;
+use strecklistan_backend::routes::rest::izettle::izettle_bridge_socket::{self, BridgeState};
+use strecklistan_backend::routes::rest::izettle::izettle_pending;
+use strecklistan_backend::routes::rest::izettle::IZettleNotifier;
+use strecklistan_backend::routes::rest::swish::SwishConfig;
+use strecklistan_backend::util::auth::AuthConfig;
+use strecklistan_backend::util::sie::SieConfig;
+use strecklistan_backend::util::{catchers, FileResponder};
 
+use chrono::Duration;
 use clap::Parser;
 use dotenv::dotenv;
+use log::info;
 use rocket::routes;
 use rocket_dyn_templates::Template;
+use std::net::SocketAddr;
 
-#[derive(Default, Parser)]
+#[derive(Parser)]
 pub struct Opt {
     /// Database url specified as a postgres:// uri
     #[clap(long, short, env = "DATABASE_URL")]
@@ -36,6 +34,48 @@ pub struct Opt {
     /// Time until a cached static file must be invalidated
     #[clap(long, env = "STATIC_FILES_MAX_AGE", default_value_t)]
     max_age: usize,
+
+    /// Password for the `admin` user, which is created on startup if there are no users
+    #[clap(long, env = "BOOTSTRAP_ADMIN_PASSWORD")]
+    bootstrap_admin_password: Option<String>,
+
+    /// Number of hours that a login session is valid
+    #[clap(long, env = "SESSION_LIFETIME_HOURS", default_value = "12")]
+    session_lifetime_hours: i64,
+
+    /// Only send the session cookie over HTTPS. Disable it to log in over plain HTTP, e.g. when
+    /// developing locally.
+    #[clap(
+        long,
+        env = "SECURE_SESSION_COOKIE",
+        default_value = "true",
+        parse(try_from_str)
+    )]
+    secure_session_cookie: bool,
+
+    /// Address to listen for WebSocket connections from the iZettle payment bridge on
+    #[clap(long, env = "IZETTLE_BRIDGE_ADDRESS", default_value = "0.0.0.0:8001")]
+    izettle_bridge_address: SocketAddr,
+
+    /// Number of seconds that a card payment may be pending before it is marked as failed
+    #[clap(long, env = "IZETTLE_PAYMENT_TIMEOUT_SECONDS", default_value = "120")]
+    izettle_payment_timeout_seconds: i64,
+
+    /// The Swish number which Swish payments are made to, Swish payments are disabled if not set
+    #[clap(long, env = "SWISH_PAYEE")]
+    swish_payee: Option<String>,
+
+    /// Secret which Swish payment callbacks are posted to, as `/api/swish/callback/<secret>`
+    #[clap(long, env = "SWISH_CALLBACK_SECRET")]
+    swish_callback_secret: Option<String>,
+
+    /// Number of seconds that a Swish payment may be pending before it is marked as failed
+    #[clap(long, env = "SWISH_PAYMENT_TIMEOUT_SECONDS", default_value = "600")]
+    swish_payment_timeout_seconds: i64,
+
+    /// Name of the organization, used in accounting exports
+    #[clap(long, env = "ORGANIZATION_NAME", default_value = "Strecklistan")]
+    organization_name: String,
 }
 
 #[rocket::main]
@@ -44,15 +84,55 @@ async fn main() {
 
     let opt = Opt::parse();
 
-    let db_pool = database::create_pool(&opt).expect("Could not create database pool");
+    let db_pool = database::create_pool(&opt.database).expect("Could not create database pool");
 
     if opt.run_migrations {
         database::run_migrations(&db_pool);
     }
 
+    if let Some(password) = &opt.bootstrap_admin_password {
+        let connection = db_pool.get().expect("Could not connect to database");
+        if database::user::bootstrap_admin(&connection, password)
+            .expect("Could not create admin user")
+        {
+            info!("Created user \"admin\"");
+        }
+    }
+
+    let izettle_notifier = IZettleNotifier::default();
+    let change_notifier = ChangeNotifier::default();
+
+    tokio::spawn(izettle_bridge_socket::serve(
+        opt.izettle_bridge_address,
+        BridgeState {
+            db_pool: db_pool.clone(),
+            notifier: izettle_notifier.clone(),
+            changes: change_notifier.clone(),
+        },
+    ));
+
+    tokio::spawn(izettle_pending::expire_payments(
+        db_pool.clone(),
+        izettle_notifier.clone(),
+        Duration::seconds(opt.izettle_payment_timeout_seconds),
+        Duration::seconds(opt.swish_payment_timeout_seconds),
+    ));
+
     let rocket = rocket::build()
         .manage(db_pool)
-        .manage(IZettleNotifier::default())
+        .manage(izettle_notifier)
+        .manage(change_notifier)
+        .manage(AuthConfig {
+            session_lifetime: Duration::hours(opt.session_lifetime_hours),
+            secure_cookie: opt.secure_session_cookie,
+        })
+        .manage(SieConfig {
+            organization_name: opt.organization_name.clone(),
+        })
+        .manage(SwishConfig {
+            payee: opt.swish_payee.clone(),
+            callback_secret: opt.swish_callback_secret.clone(),
+        })
         .register("/", catchers())
         .attach(FileResponder {
             folder: "www",
@@ -63,31 +143,97 @@ async fn main() {
         .mount(
             "/api/",
             routes![
+                rest::auth::login,
+                rest::auth::logout,
+                rest::auth::get_session,
+                rest::auth::get_users,
+                rest::auth::add_user,
+                rest::changes::get_changes,
                 rest::event::get_event,
                 rest::event::get_event_range,
+                rest::event::get_events_ical,
+                rest::event::get_event_ical,
+                rest::event::get_all,
+                rest::event::post_event,
+                rest::event::put_event,
+                rest::event::publish_event,
+                rest::event::unpublish_event,
+                rest::event::get_event_signups,
+                rest::event::post_event_signup,
+                rest::event::delete_event_signup,
+                rest::export::get_bas_accounts,
+                rest::export::put_bas_accounts,
+                rest::export::export_sie4,
                 rest::inventory::get_items,
+                rest::inventory::get_deleted_items,
                 rest::inventory::post_item,
                 rest::inventory::put_item,
                 rest::inventory::delete_item,
+                rest::inventory::restore_item,
                 rest::inventory::get_tags,
                 rest::inventory::get_bundles,
                 rest::inventory::put_bundle,
                 rest::inventory::post_bundle,
                 rest::inventory::delete_inventory_bundle,
                 rest::transaction::get_transactions,
+                rest::transaction::get_transaction_page,
                 rest::transaction::post_transaction,
+                rest::sale::post_sale,
+                rest::sale::get_sale,
+                rest::transaction::put_transaction,
+                rest::transaction::get_transaction_history,
                 rest::transaction::delete_transaction,
+                rest::transaction::restore_transaction,
                 rest::book_account::get_accounts,
                 rest::book_account::get_master_accounts,
                 rest::book_account::add_account,
+                rest::book_account::get_default_credit_limit,
+                rest::book_account::put_default_credit_limit,
+                rest::book_account::put_credit_limit,
                 rest::member::get_members,
                 rest::member::add_member_with_book_account,
+                rest::member::put_member,
+                rest::member::deactivate_member,
+                rest::member::reactivate_member,
+                rest::member::merge_member,
+                rest::purchase::post_purchase,
+                rest::purchase::get_purchases,
+                rest::purchase::get_item_costs,
                 rest::receipt::get_receipt,
+                rest::report::get_ledger,
+                rest::report::get_trial_balance,
+                rest::report::get_statement,
+                rest::report::print_ledger,
+                rest::report::print_trial_balance,
+                rest::report::print_statement,
+                rest::stock_take::get_stock_takes,
+                rest::stock_take::post_stock_take,
+                rest::stock_take::get_stock_take,
+                rest::stock_take::delete_stock_take,
+                rest::stock_take::put_stock_take_counts,
+                rest::stock_take::get_stock_take_diff,
+                rest::stock_take::commit_stock_take,
+                rest::cash_register::get_cash_register_sessions,
+                rest::cash_register::get_cash_register_session,
+                rest::cash_register::open_cash_register_session,
+                rest::cash_register::close_cash_register_session,
+                rest::cash_register::print_z_report,
                 rest::get_api_version,
-                rest::izettle::izettle_bridge_poll::poll_for_transaction,
-                rest::izettle::izettle_bridge_result::complete_izettle_transaction,
                 rest::izettle::izettle_transaction::begin_izettle_transaction,
+                rest::izettle::izettle_transaction::begin_izettle_sale,
                 rest::izettle::izettle_transaction_poll::poll_for_izettle,
+                rest::izettle::izettle_pending::get_pending_payments,
+                rest::izettle::izettle_pending::cancel_pending_payment,
+                rest::izettle::izettle_terminal::get_terminals,
+                rest::izettle::izettle_terminal::post_terminal,
+                rest::izettle::izettle_terminal::delete_terminal,
+                rest::izettle::izettle_refund::request_refund,
+                rest::izettle::izettle_refund::poll_refund,
+                rest::swish::begin_swish_payment,
+                rest::swish::begin_swish_sale,
+                rest::swish::poll_for_swish,
+                rest::swish::cancel_swish_payment,
+                rest::swish::swish_callback,
             ],
         );
 
@@ -7,6 +7,7 @@ pub struct BookAccount {
     pub name: String,
     pub account_type: BookAccountType,
     pub creditor: Option<i32>,
+    pub credit_limit: Option<i32>,
 }
 
 impl From<BookAccount> for BookAccountCommon {
@@ -17,6 +18,7 @@ impl From<BookAccount> for BookAccountCommon {
             account_type: val.account_type,
             creditor: val.creditor,
             balance: 0.into(),
+            credit_limit: val.credit_limit.map(Into::into),
         }
     }
 }
@@ -3,8 +3,8 @@ use serde::{Deserialize, Serialize};
 pub use strecklistan_api::transaction as object;
 
 use crate::schema::tables::{
-    izettle_post_transaction, izettle_transaction, izettle_transaction_bundle,
-    izettle_transaction_item,
+    izettle_post_transaction, izettle_refunds, izettle_terminals, izettle_transaction,
+    izettle_transaction_bundle, izettle_transaction_item,
 };
 
 #[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
@@ -21,6 +21,7 @@ pub struct IZettleTransaction {
     pub debited_account: i32,
     pub credited_account: i32,
     pub amount: i32,
+    pub terminal: Option<String>,
 }
 
 #[derive(Queryable, Serialize, Deserialize, Debug, Default, PartialEq)]
@@ -43,6 +44,14 @@ pub struct NewIZettleTransaction {
     pub debited_account: i32,
     pub credited_account: i32,
     pub amount: i32,
+    pub terminal: Option<String>,
+}
+
+#[derive(Queryable, Insertable, Serialize, Deserialize, Debug, PartialEq)]
+#[table_name = "izettle_terminals"]
+pub struct IZettleTerminalRow {
+    pub id: String,
+    pub name: String,
 }
 
 #[derive(Insertable, Serialize, Deserialize, Debug, PartialEq)]
@@ -74,7 +83,39 @@ pub struct NewIZettlePostTransaction {
     pub error: Option<String>,
 }
 
+#[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
+pub struct IZettleRefund {
+    pub id: i32,
+    pub izettle_transaction_id: i32,
+    pub terminal: Option<String>,
+    pub requested_at: DateTime<Utc>,
+    pub requested_by: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+    pub transaction_id: Option<i32>,
+}
+
+/// A refund as it is sent to the payment terminal
+#[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
+pub struct IZettleRefundPartial {
+    pub id: i32,
+
+    /// The reference of the payment to refund
+    pub izettle_transaction_id: i32,
+    pub amount: i32,
+}
+
+#[derive(Insertable, Serialize, Deserialize, Debug, PartialEq)]
+#[table_name = "izettle_refunds"]
+pub struct NewIZettleRefund {
+    pub izettle_transaction_id: i32,
+    pub terminal: Option<String>,
+    pub requested_by: Option<String>,
+    pub status: String,
+}
+
 pub const TRANSACTION_IN_PROGRESS: &str = "in_progress";
 pub const TRANSACTION_PAID: &str = "paid";
 pub const TRANSACTION_CANCELLED: &str = "cancelled";
 pub const TRANSACTION_FAILED: &str = "failed";
+pub const REFUND_COMPLETED: &str = "refunded";
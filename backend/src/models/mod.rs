@@ -2,9 +2,7 @@ pub mod book_account;
 pub mod event;
 pub mod inventory;
 pub mod izettle_transaction;
-pub mod signup;
+pub mod swish_payment;
 pub mod transaction;
 
-pub use self::event::{Event, EventRange, EventWithSignups, NewEvent};
-
-pub use self::signup::{NewSignup, Signup};
+pub use self::event::EventRange;
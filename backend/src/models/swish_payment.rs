@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::schema::tables::swish_payments;
+
+#[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SwishPayment {
+    pub id: i32,
+    pub time: DateTime<Utc>,
+
+    /// The transaction to commit once the payment is confirmed, as an [object::NewTransaction]
+    ///
+    /// [object::NewTransaction]: strecklistan_api::transaction::NewTransaction
+    pub new_transaction: serde_json::Value,
+    pub amount: i32,
+    pub status: String,
+    pub error: Option<String>,
+    pub payment_reference: Option<String>,
+    pub payer_alias: Option<String>,
+    pub transaction_id: Option<i32>,
+}
+
+#[derive(Insertable, Serialize, Deserialize, Debug, PartialEq)]
+#[table_name = "swish_payments"]
+pub struct NewSwishPayment {
+    pub new_transaction: serde_json::Value,
+    pub amount: i32,
+    pub status: String,
+}
@@ -0,0 +1,92 @@
+use crate::database::{user, DatabasePool};
+use crate::util::auth::{Admin, AuthConfig, AuthUser, SESSION_COOKIE};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use diesel::prelude::*;
+use rocket::http::{Cookie, CookieJar, SameSite, Status};
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use strecklistan_api::user::{Credentials, NewUser, Session, User};
+
+#[post("/login", data = "<credentials>")]
+pub fn login(
+    db_pool: &State<DatabasePool>,
+    config: &State<AuthConfig>,
+    cookies: &CookieJar<'_>,
+    accept: SerAccept,
+    credentials: Json<Credentials>,
+) -> Result<Ser<Session>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let session = user::login(
+        &connection,
+        &credentials.name,
+        &credentials.password,
+        config.session_lifetime,
+    )?
+    .ok_or_else(|| SJ::new(Status::Unauthorized, "Invalid user name or password"))?;
+
+    cookies.add(
+        Cookie::build(SESSION_COOKIE, session.token.clone())
+            .path("/")
+            .http_only(true)
+            .secure(config.secure_cookie)
+            .same_site(SameSite::Strict)
+            .finish(),
+    );
+
+    Ok(accept.ser(session))
+}
+
+#[post("/logout")]
+pub fn logout(
+    db_pool: &State<DatabasePool>,
+    cookies: &CookieJar<'_>,
+    accept: SerAccept,
+    auth: AuthUser,
+) -> Result<Ser<()>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    user::end_session(&connection, &auth.token)?;
+    cookies.remove(Cookie::named(SESSION_COOKIE));
+
+    Ok(accept.ser(()))
+}
+
+#[get("/session")]
+pub fn get_session(accept: SerAccept, auth: AuthUser) -> Ser<User> {
+    accept.ser(auth.user)
+}
+
+#[get("/users")]
+pub fn get_users(
+    db_pool: &State<DatabasePool>,
+    accept: SerAccept,
+    _admin: Admin,
+) -> Result<Ser<Vec<User>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::users::dsl::*;
+
+    Ok(accept.ser(
+        users
+            .select((name, display_name, role))
+            .order_by(name)
+            .load(&connection)?,
+    ))
+}
+
+#[post("/user", data = "<new_user>")]
+pub fn add_user(
+    db_pool: &State<DatabasePool>,
+    accept: SerAccept,
+    _admin: Admin,
+    new_user: Json<NewUser>,
+) -> Result<Ser<User>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    if new_user.password.is_empty() {
+        return Err(SJ::new(Status::BadRequest, "Password must not be empty"));
+    }
+
+    Ok(accept.ser(user::create_user(&connection, &new_user)?))
+}
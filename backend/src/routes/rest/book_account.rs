@@ -1,137 +1,189 @@
-use crate::database::DatabasePool;
+use crate::database::book_account::{
+    find_exceeded_credit_limit, get_credit_policy, set_credit_limit, set_credit_policy,
+};
+use crate::database::{self, DatabaseConn, DatabasePool};
 use crate::models::book_account as relational;
-use crate::models::transaction::relational::Transaction;
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::util::auth::{AuthUser, Cashier, Treasurer};
+use crate::util::param::parse_time;
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
 use diesel::prelude::*;
+use rocket::http::Status;
 use rocket::serde::json::Json;
-use rocket::{get, post, State};
+use rocket::{get, post, put, State};
 use std::collections::HashMap;
 use strecklistan_api::book_account::{
-    BookAccount, BookAccountId, BookAccountType, MasterAccounts, NewBookAccount,
+    BookAccount, BookAccountId, CreditPolicy, MasterAccounts, NewBookAccount,
 };
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::transaction::NewTransaction;
+use strecklistan_api::user::UserRole;
 
-#[get("/book_accounts")]
+/// GET `/book_accounts?<at>`
+///
+/// Returns all book accounts with their balances.
+///
+/// If `at` is set, the balances are computed as of that point in time, in RFC 3339.
+#[get("/book_accounts?<at>")]
 pub fn get_accounts(
     db_pool: &State<DatabasePool>,
+    _user: Cashier,
     accept: SerAccept,
+    at: Option<String>,
 ) -> Result<Ser<HashMap<BookAccountId, BookAccount>>, SJ> {
+    let at = parse_time(at)?;
     let connection = db_pool.inner().get()?;
 
-    let (transactions, accounts) = connection
-        .transaction::<(Vec<Transaction>, Vec<relational::BookAccount>), SJ, _>(|| {
-            use crate::schema::tables::book_accounts::dsl::book_accounts;
-            use crate::schema::tables::transactions::dsl::{deleted_at, transactions};
-            Ok((
-                transactions
-                    .filter(deleted_at.is_null())
-                    .load(&connection)?,
-                book_accounts.load(&connection)?,
-            ))
-        })?;
-
-    let mut accounts: HashMap<_, BookAccount> = accounts
-        .into_iter()
-        .map(|acc| (acc.id, acc.into()))
-        .collect();
-
-    for tr in transactions.iter() {
-        if let Some(account) = accounts.get_mut(&tr.credited_account) {
-            account.credit(tr.amount.into());
-        }
-
-        if let Some(account) = accounts.get_mut(&tr.debited_account) {
-            account.debit(tr.amount.into());
-        }
-    }
+    let (accounts, balances) = connection.transaction::<_, SJ, _>(|| {
+        use crate::schema::tables::book_accounts::dsl::book_accounts;
+        let accounts: Vec<relational::BookAccount> = book_accounts.load(&connection)?;
+        let balances = match at {
+            Some(at) => database::book_account::get_balances_at(&connection, at)?,
+            None => database::book_account::get_balances(&connection)?,
+        };
+        Ok((accounts, balances))
+    })?;
 
-    Ok(accept.ser(accounts))
+    Ok(accept.ser(
+        accounts
+            .into_iter()
+            .map(|acc| {
+                let balance = balances.get(&acc.id).copied().unwrap_or_default();
+                let mut account: BookAccount = acc.into();
+                account.balance = balance;
+                (account.id, account)
+            })
+            .collect(),
+    ))
 }
 
 #[post("/book_account", data = "<account>")]
 pub fn add_account(
     db_pool: &State<DatabasePool>,
+    _user: Treasurer,
     accept: SerAccept,
     account: Json<NewBookAccount>,
 ) -> Result<Ser<i32>, SJ> {
     let connection = db_pool.inner().get()?;
-
-    use crate::schema::tables::book_accounts::dsl::*;
-
-    Ok(accept.ser(
-        diesel::insert_into(book_accounts)
-            .values((
-                name.eq(&account.name),
-                account_type.eq(&account.account_type),
-                creditor.eq(&account.creditor),
-            ))
-            .returning(id)
-            .get_result(&connection)?,
-    ))
+    Ok(accept.ser(database::book_account::insert_account(
+        &connection,
+        &account,
+    )?))
 }
 
 #[get("/book_accounts/masters")]
 pub fn get_master_accounts(
     db_pool: &State<DatabasePool>,
+    _user: Cashier,
     accept: SerAccept,
 ) -> Result<Ser<MasterAccounts>, SJ> {
     let connection = db_pool.inner().get()?;
-    use crate::schema::tables::book_accounts::dsl::*;
-
-    // TODO: Get the values for the master accounts from some configuration.
-    let bank_account_name = "Bankkonto";
-    let cash_account_name = "Kontantkassa";
-    let sales_account_name = "Försäljning";
-    let purchases_account_name = "Inköp";
-
-    connection.transaction::<_, SJ, _>(|| {
-        // Make sure the accounts exist in the database
-        diesel::insert_into(book_accounts)
-            .values((
-                name.eq(bank_account_name),
-                account_type.eq(BookAccountType::Assets),
-            ))
-            .on_conflict_do_nothing()
-            .execute(&connection)?;
-        diesel::insert_into(book_accounts)
-            .values((
-                name.eq(cash_account_name),
-                account_type.eq(BookAccountType::Assets),
-            ))
-            .on_conflict_do_nothing()
-            .execute(&connection)?;
-        diesel::insert_into(book_accounts)
-            .values((
-                name.eq(sales_account_name),
-                account_type.eq(BookAccountType::Revenue),
-            ))
-            .on_conflict_do_nothing()
-            .execute(&connection)?;
-        diesel::insert_into(book_accounts)
-            .values((
-                name.eq(purchases_account_name),
-                account_type.eq(BookAccountType::Expenses),
+    Ok(accept.ser(database::book_account::get_master_accounts(&connection)?))
+}
+
+/// GET `/credit_policy`
+///
+/// Returns the default credit limit of tillgodo accounts
+#[get("/credit_policy")]
+pub fn get_default_credit_limit(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    accept: SerAccept,
+) -> Result<Ser<CreditPolicy>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(get_credit_policy(&connection)?))
+}
+
+/// PUT `/credit_policy`
+///
+/// Change the default credit limit of tillgodo accounts
+#[put("/credit_policy", data = "<policy>")]
+pub fn put_default_credit_limit(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
+    accept: SerAccept,
+    policy: Json<CreditPolicy>,
+) -> Result<Ser<CreditPolicy>, SJ> {
+    let policy = policy.into_inner();
+    check_credit_limit(policy.default_credit_limit)?;
+
+    let connection = db_pool.inner().get()?;
+    set_credit_policy(&connection, policy)?;
+
+    changes.notify(ChangeEvent::CreditLimitsChanged);
+    Ok(accept.ser(policy))
+}
+
+/// PUT `/book_account/<account_id>/credit_limit`
+///
+/// Change the credit limit of a tillgodo account. If the limit is `null`, the account uses the
+/// default credit limit.
+#[put("/book_account/<account_id>/credit_limit", data = "<limit>")]
+pub fn put_credit_limit(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
+    accept: SerAccept,
+    account_id: BookAccountId,
+    limit: Json<Option<Currency>>,
+) -> Result<Ser<Option<Currency>>, SJ> {
+    let limit = limit.into_inner();
+    check_credit_limit(limit)?;
+
+    let connection = db_pool.inner().get()?;
+    set_credit_limit(&connection, account_id, limit)?;
+
+    changes.notify(ChangeEvent::CreditLimitsChanged);
+    Ok(accept.ser(limit))
+}
+
+fn check_credit_limit(limit: Option<Currency>) -> Result<(), SJ> {
+    match limit {
+        Some(limit) if limit < Currency::from(0) => Err(SJ::new(
+            Status::BadRequest,
+            "A credit limit may not be negative",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Return an error if the transactions would overdraw a tillgodo account beyond its credit limit.
+///
+/// The limit is not checked if `override_limit` is set, which requires the
+/// [UserRole::Treasurer] role.
+pub fn enforce_credit_limits(
+    connection: &DatabaseConn,
+    user: &AuthUser,
+    override_limit: bool,
+    transactions: &[NewTransaction],
+) -> Result<(), SJ> {
+    if override_limit {
+        return if user.user.role >= UserRole::Treasurer {
+            Ok(())
+        } else {
+            Err(SJ::new(
+                Status::Forbidden,
+                "Overriding the credit limit requires the Treasurer role",
             ))
-            .on_conflict_do_nothing()
-            .execute(&connection)?;
-
-        Ok(accept.ser(MasterAccounts {
-            bank_account_id: book_accounts
-                .filter(name.eq(bank_account_name))
-                .select(id)
-                .get_result(&connection)?,
-            cash_account_id: book_accounts
-                .filter(name.eq(cash_account_name))
-                .select(id)
-                .get_result(&connection)?,
-            sales_account_id: book_accounts
-                .filter(name.eq(sales_account_name))
-                .select(id)
-                .get_result(&connection)?,
-            purchases_account_id: book_accounts
-                .filter(name.eq(purchases_account_name))
-                .select(id)
-                .get_result(&connection)?,
-        }))
-    })
+        };
+    }
+
+    match find_exceeded_credit_limit(connection, transactions)? {
+        Some(account) => Err(SJ::new(
+            Status::PaymentRequired,
+            credit_limit_exceeded(&account),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Describe why a payment was rejected for overdrawing `account`
+pub fn credit_limit_exceeded(account: &BookAccount) -> String {
+    format!(
+        "The credit limit of {} would be exceeded, its balance is {}:-",
+        account.name, account.balance,
+    )
 }
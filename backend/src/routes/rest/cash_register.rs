@@ -0,0 +1,441 @@
+use crate::database::book_account::{get_balances, get_master_accounts};
+use crate::database::cash_register::{
+    build_z_report, cash_flow, correction_transaction, load_cash_transactions, ZReport,
+};
+use crate::database::transaction::insert_transaction;
+use crate::database::{DatabaseConn, DatabasePool};
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::util::auth::Cashier;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Local, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::response::content::Html;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use strecklistan_api::book_account::BookAccountId;
+use strecklistan_api::cash_register::{
+    CashRegisterSession, CashRegisterSessionId, CloseCashRegisterSession, OpenCashRegisterSession,
+};
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::transaction::TransactionId;
+
+const Z_REPORT_TEMPLATE_NAME: &str = "z_report";
+
+type SessionRow = (
+    CashRegisterSessionId,
+    DateTime<Utc>,
+    Option<String>,
+    i32,
+    Option<TransactionId>,
+    Option<DateTime<Utc>>,
+    Option<String>,
+    Option<i32>,
+    Option<i32>,
+    Option<TransactionId>,
+    Option<serde_json::Value>,
+);
+
+fn load_session(
+    connection: &DatabaseConn,
+    cash_account: BookAccountId,
+    session: CashRegisterSessionId,
+) -> Result<CashRegisterSession, SJ> {
+    use crate::schema::tables::cash_register_sessions::dsl::*;
+
+    let row: SessionRow = cash_register_sessions.find(session).first(connection)?;
+    session_from_row(connection, cash_account, row)
+}
+
+fn session_from_row(
+    connection: &DatabaseConn,
+    cash_account: BookAccountId,
+    row: SessionRow,
+) -> Result<CashRegisterSession, SJ> {
+    let (
+        id,
+        opened_at,
+        opened_by,
+        opening_float,
+        opening_transaction_id,
+        closed_at,
+        closed_by,
+        expected_cash,
+        counted_cash,
+        closing_transaction_id,
+        _z_report,
+    ) = row;
+    let opening_float = Currency::from(opening_float);
+
+    // the expected cash is stored once the session is closed
+    let expected_cash = match expected_cash {
+        Some(expected) => expected.into(),
+        None => {
+            let excluded: Vec<TransactionId> = opening_transaction_id.into_iter().collect();
+            let transactions =
+                load_cash_transactions(connection, cash_account, opened_at, None, &excluded)?;
+            let (cash_in, cash_out) = cash_flow(cash_account, &transactions);
+            opening_float + cash_in - cash_out
+        }
+    };
+
+    Ok(CashRegisterSession {
+        id,
+        opened_at,
+        opened_by,
+        opening_float,
+        opening_transaction_id,
+        closed_at,
+        closed_by,
+        expected_cash,
+        counted_cash: counted_cash.map(Currency::from),
+        closing_transaction_id,
+    })
+}
+
+/// Return an error if the session has already been closed
+fn ensure_open(connection: &DatabaseConn, session: CashRegisterSessionId) -> Result<(), SJ> {
+    use crate::schema::tables::cash_register_sessions::dsl::*;
+
+    let closed: Option<DateTime<Utc>> = cash_register_sessions
+        .find(session)
+        .select(closed_at)
+        .for_update()
+        .first(connection)?;
+
+    match closed {
+        Some(_) => Err(SJ::new(
+            Status::Conflict,
+            "The cash register session has already been closed",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// GET `/cash_register/sessions`
+///
+/// Returns a list of all cash register sessions, newest first
+#[get("/cash_register/sessions")]
+pub fn get_cash_register_sessions(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    accept: SerAccept,
+) -> Result<Ser<Vec<CashRegisterSession>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        let masters = get_master_accounts(&connection)?;
+
+        let rows: Vec<SessionRow> = {
+            use crate::schema::tables::cash_register_sessions::dsl::*;
+            cash_register_sessions
+                .order_by(id.desc())
+                .load(&connection)?
+        };
+
+        let sessions = rows
+            .into_iter()
+            .map(|row| session_from_row(&connection, masters.cash_account_id, row))
+            .collect::<Result<_, _>>()?;
+
+        Ok(accept.ser(sessions))
+    })
+}
+
+/// GET `/cash_register/session/<session_id>`
+#[get("/cash_register/session/<session_id>")]
+pub fn get_cash_register_session(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    accept: SerAccept,
+    session_id: CashRegisterSessionId,
+) -> Result<Ser<CashRegisterSession>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let masters = get_master_accounts(&connection)?;
+    Ok(accept.ser(load_session(
+        &connection,
+        masters.cash_account_id,
+        session_id,
+    )?))
+}
+
+/// POST `/cash_register/session`
+///
+/// Open a new cash register session with the counted float. If the float differs from the balance
+/// of the cash account, a correction transaction is created.
+#[post("/cash_register/session", data = "<open>")]
+pub fn open_cash_register_session(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    user: Cashier,
+    accept: SerAccept,
+    open: Json<OpenCashRegisterSession>,
+) -> Result<Ser<CashRegisterSessionId>, SJ> {
+    if open.opening_float < Currency::from(0) {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "The opening float can not be negative",
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+
+    let (session_id, correction) = connection.transaction::<_, SJ, _>(|| {
+        use crate::schema::tables::cash_register_sessions::dsl::*;
+
+        let open_sessions: i64 = cash_register_sessions
+            .filter(closed_at.is_null())
+            .count()
+            .get_result(&connection)?;
+        if open_sessions > 0 {
+            return Err(SJ::new(
+                Status::Conflict,
+                "A cash register session is already open",
+            ));
+        }
+
+        let session_id: CashRegisterSessionId = diesel::insert_into(cash_register_sessions)
+            .values((
+                opened_by.eq(&user.0.user.name),
+                opening_float.eq(i32::from(open.opening_float)),
+            ))
+            .returning(id)
+            .get_result(&connection)?;
+
+        let masters = get_master_accounts(&connection)?;
+        let balance = get_balances(&connection)?
+            .get(&masters.cash_account_id)
+            .copied()
+            .unwrap_or_default();
+
+        let correction = correction_transaction(
+            &masters,
+            open.opening_float - balance,
+            format!("Kassaöppning #{}", session_id),
+        )
+        .map(|transaction| insert_transaction(&connection, transaction))
+        .transpose()?;
+
+        diesel::update(cash_register_sessions.find(session_id))
+            .set(opening_transaction_id.eq(correction))
+            .execute(&connection)?;
+
+        Ok((session_id, correction))
+    })?;
+
+    if let Some(id) = correction {
+        changes.notify(ChangeEvent::TransactionCreated { id });
+    }
+    Ok(accept.ser(session_id))
+}
+
+/// POST `/cash_register/session/<session_id>/close`
+///
+/// Close a cash register session with the counted cash. If it differs from the expected cash, a
+/// correction transaction is created. Returns the id of the transaction, if one was needed.
+#[post("/cash_register/session/<session_id>/close", data = "<close>")]
+pub fn close_cash_register_session(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    user: Cashier,
+    accept: SerAccept,
+    session_id: CashRegisterSessionId,
+    close: Json<CloseCashRegisterSession>,
+) -> Result<Ser<Option<TransactionId>>, SJ> {
+    if close.counted_cash < Currency::from(0) {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "The counted cash can not be negative",
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+
+    let correction = connection.transaction::<_, SJ, _>(|| {
+        ensure_open(&connection, session_id)?;
+
+        let masters = get_master_accounts(&connection)?;
+        let cash_account = masters.cash_account_id;
+        let session = load_session(&connection, cash_account, session_id)?;
+
+        // the report is stored, so that later changes to the transactions don't change it
+        let excluded: Vec<TransactionId> = session.opening_transaction_id.into_iter().collect();
+        let transactions = load_cash_transactions(
+            &connection,
+            cash_account,
+            session.opened_at,
+            None,
+            &excluded,
+        )?;
+        let report = build_z_report(&connection, cash_account, transactions)?;
+        let report = serde_json::to_value(report)
+            .map_err(|e| SJ::new(Status::InternalServerError, e.to_string()))?;
+
+        let correction = correction_transaction(
+            &masters,
+            close.counted_cash - session.expected_cash,
+            format!("Kassaavslut #{}", session_id),
+        )
+        .map(|transaction| insert_transaction(&connection, transaction))
+        .transpose()?;
+
+        {
+            use crate::schema::tables::cash_register_sessions::dsl::*;
+            diesel::update(cash_register_sessions.find(session_id))
+                .set((
+                    closed_at.eq(Some(Utc::now())),
+                    closed_by.eq(&user.0.user.name),
+                    expected_cash.eq(i32::from(session.expected_cash)),
+                    counted_cash.eq(i32::from(close.counted_cash)),
+                    closing_transaction_id.eq(correction),
+                    z_report.eq(Some(report)),
+                ))
+                .execute(&connection)?;
+        }
+
+        Ok(correction)
+    })?;
+
+    if let Some(id) = correction {
+        changes.notify(ChangeEvent::TransactionCreated { id });
+    }
+    Ok(accept.ser(correction))
+}
+
+#[derive(Debug, Serialize)]
+struct ZReportTemplateData {
+    id: CashRegisterSessionId,
+    opened_at: String,
+    opened_by: String,
+    closed_at: String,
+    closed_by: String,
+    opening_float: String,
+    cash_in: String,
+    cash_out: String,
+    expected_cash: String,
+    counted_cash: String,
+    difference: String,
+    balanced: bool,
+    entries: Vec<ZReportTemplateEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct ZReportTemplateEntry {
+    transaction_id: TransactionId,
+    time: String,
+    description: String,
+    counter_account: String,
+    cash_in: String,
+    cash_out: String,
+}
+
+/// GET `/cash_register/session/<session_id>/z_report`
+///
+/// The end-of-day report of a closed cash register session, as printable HTML.
+#[get("/cash_register/session/<session_id>/z_report")]
+pub fn print_z_report(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    session_id: CashRegisterSessionId,
+) -> Result<Html<Template>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let masters = get_master_accounts(&connection)?;
+    let cash_account = masters.cash_account_id;
+    let session = load_session(&connection, cash_account, session_id)?;
+
+    let (closed_at, counted_cash, difference) = match (
+        session.closed_at,
+        session.counted_cash,
+        session.difference(),
+    ) {
+        (Some(closed_at), Some(counted_cash), Some(difference)) => {
+            (closed_at, counted_cash, difference)
+        }
+        _ => {
+            return Err(SJ::new(
+                Status::Conflict,
+                "The cash register session has not been closed",
+            ))
+        }
+    };
+
+    let stored: Option<serde_json::Value> = {
+        use crate::schema::tables::cash_register_sessions::dsl::*;
+        cash_register_sessions
+            .find(session_id)
+            .select(z_report)
+            .first(&connection)?
+    };
+
+    let report: ZReport = match stored {
+        Some(report) => serde_json::from_value(report)
+            .map_err(|e| SJ::new(Status::InternalServerError, e.to_string()))?,
+
+        // sessions closed before the reports were stored only have their transactions
+        None => {
+            let excluded: Vec<TransactionId> = session
+                .opening_transaction_id
+                .into_iter()
+                .chain(session.closing_transaction_id)
+                .collect();
+            let transactions = load_cash_transactions(
+                &connection,
+                cash_account,
+                session.opened_at,
+                Some(closed_at),
+                &excluded,
+            )?;
+            build_z_report(&connection, cash_account, transactions)?
+        }
+    };
+
+    let format_time = |time: DateTime<Utc>| {
+        time.with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    };
+    let format_amount = |amount: Currency| {
+        if amount == Currency::from(0) {
+            String::new()
+        } else {
+            amount.to_string()
+        }
+    };
+
+    let data = ZReportTemplateData {
+        id: session.id,
+        opened_at: format_time(session.opened_at),
+        opened_by: session.opened_by.unwrap_or_default(),
+        closed_at: format_time(closed_at),
+        closed_by: session.closed_by.unwrap_or_default(),
+        opening_float: session.opening_float.to_string(),
+        cash_in: report.cash_in.to_string(),
+        cash_out: report.cash_out.to_string(),
+        expected_cash: session.expected_cash.to_string(),
+        counted_cash: counted_cash.to_string(),
+        difference: if difference > Currency::from(0) {
+            format!("+{}", difference)
+        } else {
+            difference.to_string()
+        },
+        balanced: difference == Currency::from(0),
+        entries: report
+            .entries
+            .into_iter()
+            .map(|entry| ZReportTemplateEntry {
+                transaction_id: entry.transaction_id,
+                time: format_time(entry.time),
+                description: entry.description.unwrap_or_default(),
+                counter_account: entry.counter_account,
+                cash_in: format_amount(entry.cash_in),
+                cash_out: format_amount(entry.cash_out),
+            })
+            .collect(),
+    };
+
+    Ok(Html(Template::render(Z_REPORT_TEMPLATE_NAME, &data)))
+}
@@ -0,0 +1,61 @@
+use crate::util::auth::Cashier;
+use rocket::response::stream::{Event, EventStream};
+use rocket::{get, Shutdown, State};
+use strecklistan_api::change::ChangeEvent;
+use tokio::select;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// The number of events which a slow subscriber may fall behind before missing events
+const CHANGE_BUFFER_SIZE: usize = 64;
+
+/// A shared state struct for publishing changes to all connected clients
+#[derive(Clone)]
+pub struct ChangeNotifier {
+    sender: broadcast::Sender<ChangeEvent>,
+}
+
+impl Default for ChangeNotifier {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(CHANGE_BUFFER_SIZE);
+        ChangeNotifier { sender }
+    }
+}
+
+impl ChangeNotifier {
+    /// Publish a change to all subscribers
+    pub fn notify(&self, event: ChangeEvent) {
+        // an error just means that no one is listening
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// GET `/changes`
+///
+/// A stream of server-sent events which describe changes made to the data on the server
+#[get("/changes")]
+pub fn get_changes(
+    _user: Cashier,
+    notifier: &State<ChangeNotifier>,
+    mut shutdown: Shutdown,
+) -> EventStream![] {
+    let mut receiver = notifier.subscribe();
+
+    EventStream! {
+        loop {
+            let event = select! {
+                event = receiver.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(_)) => ChangeEvent::Lagged,
+                    Err(RecvError::Closed) => break,
+                },
+                _ = &mut shutdown => break,
+            };
+
+            yield Event::json(&event);
+        }
+    }
+}
@@ -1,9 +1,25 @@
-use crate::database::event::{get_event_ws, get_event_ws_range};
+use crate::database::book_account::get_master_accounts;
+use crate::database::event::{
+    delete_signup, get_all_events, get_event_ws, get_event_ws_range, get_signups, insert_event,
+    insert_signup, load_event, set_event_published, ticket_transaction, update_event,
+};
+use crate::database::member::get_member_account;
 use crate::database::DatabasePool;
-use crate::models::event::EventWithSignups as EventWS;
+use crate::routes::rest::book_account::enforce_credit_limits;
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::util::auth::{Cashier, Treasurer};
+use crate::util::ical::{IcalCalendar, IcalEvent};
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
-use rocket::{get, State};
+use chrono::Utc;
+use diesel::Connection;
+use rocket::http::{ContentType, Header, Status};
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, Responder, State};
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::event::{
+    Event as EventWS, EventId, EventSignupId, EventSignupWithStatus, NewEvent, NewEventSignup,
+};
 
 #[get("/event/<id>")]
 pub fn get_event(
@@ -23,3 +39,267 @@ pub fn get_event_range(
 ) -> Result<Ser<Vec<EventWS>>, SJ> {
     Ok(accept.ser(get_event_ws_range(db_pool.inner().get()?, low, high, true)?))
 }
+
+/// How many events before and after now the calendar feed contains by default
+const DEFAULT_CALENDAR_WINDOW: i64 = 50;
+
+#[derive(Responder)]
+pub struct IcalFile {
+    file: String,
+    content_type: ContentType,
+    disposition: Header<'static>,
+}
+
+/// GET `/events.ics?<low>&<high>`
+///
+/// Returns published events as an iCalendar feed. `low` and `high` select the events in the
+/// same way as for `/events`, and default to the latest and next 50 events.
+#[get("/events.ics?<low>&<high>")]
+pub fn get_events_ical(
+    db_pool: &State<DatabasePool>,
+    low: Option<i64>,
+    high: Option<i64>,
+) -> Result<IcalFile, SJ> {
+    let low = low.unwrap_or(-DEFAULT_CALENDAR_WINDOW);
+    let high = high.unwrap_or(DEFAULT_CALENDAR_WINDOW);
+    if high <= low {
+        return Err(SJ::new(Status::BadRequest, "high must be greater than low"));
+    }
+
+    let events = get_event_ws_range(db_pool.inner().get()?, low, high, true)?;
+    Ok(ical_file(&events, "evenemang.ics"))
+}
+
+/// GET `/event/<id>/event.ics`
+///
+/// Returns a published event as an iCalendar file
+#[get("/event/<id>/event.ics")]
+pub fn get_event_ical(db_pool: &State<DatabasePool>, id: EventId) -> Result<IcalFile, SJ> {
+    let event = get_event_ws(db_pool.inner().get()?, id, true)?;
+    Ok(ical_file(&[event], &format!("evenemang_{}.ics", id)))
+}
+
+fn ical_file(events: &[EventWS], file_name: &str) -> IcalFile {
+    let calendar = IcalCalendar {
+        program_version: env!("CARGO_PKG_VERSION").to_string(),
+        name: "Evenemang".to_string(),
+        generated: Utc::now(),
+        events: events.iter().map(ical_event).collect(),
+    };
+
+    IcalFile {
+        file: calendar.render(),
+        content_type: ContentType::Calendar,
+        disposition: Header::new(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        ),
+    }
+}
+
+fn ical_event(event: &EventWS) -> IcalEvent {
+    let price = format!("Pris: {}:-", event.price);
+    IcalEvent {
+        uid: format!("event-{}@strecklistan", event.id),
+        summary: event.title.clone(),
+        description: match event.background.trim() {
+            "" => price,
+            background => format!("{}\n\n{}", background, price),
+        },
+        location: event.location.clone(),
+        start: event.start_time,
+        end: event.end_time,
+    }
+}
+
+/// GET `/events/all`
+///
+/// Returns all events, including the unpublished ones, the latest first
+#[get("/events/all")]
+pub fn get_all(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+) -> Result<Ser<Vec<EventWS>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(get_all_events(&connection)?))
+}
+
+/// POST `/event`
+///
+/// Create a new event. It is not open for signups until it is published.
+#[post("/event", data = "<event>")]
+pub fn post_event(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+    event: Json<NewEvent>,
+) -> Result<Ser<EventWS>, SJ> {
+    event
+        .validate()
+        .map_err(|e| SJ::new(Status::BadRequest, e))?;
+
+    let connection = db_pool.inner().get()?;
+    let event = connection.transaction::<_, SJ, _>(|| {
+        let event_id = insert_event(&connection, &event)?;
+        Ok(load_event(&connection, event_id)?)
+    })?;
+
+    Ok(accept.ser(event))
+}
+
+/// PUT `/event/<event_id>`
+///
+/// Change the details of an event. If the capacity is lowered, the latest signups are put on the
+/// waitlist and their tickets are refunded. If it is raised, the signups which get a place are
+/// charged for their tickets.
+#[put("/event/<event_id>", data = "<event>")]
+pub fn put_event(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
+    accept: SerAccept,
+    event_id: EventId,
+    event: Json<NewEvent>,
+) -> Result<Ser<EventWS>, SJ> {
+    event
+        .validate()
+        .map_err(|e| SJ::new(Status::BadRequest, e))?;
+
+    let connection = db_pool.inner().get()?;
+    let (event, new_transactions) = connection.transaction::<_, SJ, _>(|| {
+        let new_transactions = update_event(&connection, event_id, &event)?;
+        Ok((load_event(&connection, event_id)?, new_transactions))
+    })?;
+
+    for id in new_transactions {
+        changes.notify(ChangeEvent::TransactionCreated { id });
+    }
+    Ok(accept.ser(event))
+}
+
+/// POST `/event/<event_id>/publish`
+///
+/// Publish an event, which opens it for signups
+#[post("/event/<event_id>/publish")]
+pub fn publish_event(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+    event_id: EventId,
+) -> Result<Ser<EventWS>, SJ> {
+    let connection = db_pool.inner().get()?;
+    set_event_published(&connection, event_id, true)?;
+    Ok(accept.ser(load_event(&connection, event_id)?))
+}
+
+/// POST `/event/<event_id>/unpublish`
+///
+/// Hide an event, and close it for signups. Existing signups are kept.
+#[post("/event/<event_id>/unpublish")]
+pub fn unpublish_event(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+    event_id: EventId,
+) -> Result<Ser<EventWS>, SJ> {
+    let connection = db_pool.inner().get()?;
+    set_event_published(&connection, event_id, false)?;
+    Ok(accept.ser(load_event(&connection, event_id)?))
+}
+
+/// GET `/event/<event_id>/signups`
+///
+/// Returns the signups of an event in the order they were made
+#[get("/event/<event_id>/signups")]
+pub fn get_event_signups(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    accept: SerAccept,
+    event_id: EventId,
+) -> Result<Ser<Vec<EventSignupWithStatus>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(get_signups(&connection, event_id)?))
+}
+
+/// POST `/event/<event_id>/signup`
+///
+/// Sign up for a published event. If the event is full, the signup is put on the waitlist.
+///
+/// If a member is given, the ticket is charged to the tillgodo account of the member once the
+/// signup has a place. This requires a logged in cashier, and fails if the credit limit of the
+/// account would be exceeded.
+#[post("/event/<event_id>/signup", data = "<signup>")]
+pub fn post_event_signup(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    user: Option<Cashier>,
+    accept: SerAccept,
+    event_id: EventId,
+    signup: Json<NewEventSignup>,
+) -> Result<Ser<EventSignupWithStatus>, SJ> {
+    signup
+        .validate()
+        .map_err(|e| SJ::new(Status::BadRequest, e))?;
+
+    let connection = db_pool.inner().get()?;
+    let signup = connection.transaction::<_, SJ, _>(|| {
+        // only published events are open for signups
+        let event = load_event(&connection, event_id)?;
+        if !event.published {
+            return Err(SJ::new(Status::NotFound, "No such event"));
+        }
+
+        let payment = match (signup.member, &user) {
+            (None, _) => None,
+            (Some(_), None) => {
+                return Err(SJ::new(
+                    Status::Unauthorized,
+                    "Only a cashier may charge a ticket to a member",
+                ))
+            }
+            (Some(member), Some(user)) => {
+                let account = get_member_account(&connection, member)?
+                    .ok_or_else(|| SJ::new(Status::BadRequest, "The member has no account"))?;
+                let masters = get_master_accounts(&connection)?;
+                let payment = ticket_transaction(&event, account, &masters)?;
+                enforce_credit_limits(&connection, &user.0, false, payment.as_slice())?;
+                payment
+            }
+        };
+
+        Ok(insert_signup(&connection, event_id, &signup, payment)?)
+    })?;
+
+    if let Some(id) = signup.signup.transaction_id {
+        changes.notify(ChangeEvent::TransactionCreated { id });
+    }
+    Ok(accept.ser(signup))
+}
+
+/// DELETE `/event/<event_id>/signup/<signup_id>`
+///
+/// Cancel a signup. The first signup on the waitlist, if any, takes its place and is charged for
+/// its ticket.
+///
+/// A paid ticket is refunded to the tillgodo account it was charged to, if the signup is
+/// cancelled before the cancellation deadline or is on the waitlist.
+#[delete("/event/<event_id>/signup/<signup_id>")]
+pub fn delete_event_signup(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Cashier,
+    accept: SerAccept,
+    event_id: EventId,
+    signup_id: EventSignupId,
+) -> Result<Ser<EventSignupId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let new_transactions = connection.transaction::<_, SJ, _>(|| {
+        Ok(delete_signup(&connection, event_id, signup_id, Utc::now())?)
+    })?;
+
+    for id in new_transactions {
+        changes.notify(ChangeEvent::TransactionCreated { id });
+    }
+    Ok(accept.ser(signup_id))
+}
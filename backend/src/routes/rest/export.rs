@@ -0,0 +1,241 @@
+use crate::database::bas_account::{get_bas_numbers, set_bas_numbers, DEFAULT_BAS_ACCOUNTS};
+use crate::database::report::{load_accounts, load_transactions};
+use crate::database::{DatabaseConn, DatabasePool};
+use crate::models::transaction::relational::Transaction;
+use crate::util::auth::Treasurer;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::sie::{BasNumber, SieAccount, SieConfig, SieFile, SieVoucher};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{NaiveDate, TimeZone, Utc};
+use diesel::prelude::*;
+use rocket::http::{ContentType, Header, Status};
+use rocket::serde::json::Json;
+use rocket::{get, put, Responder, State};
+use std::collections::{BTreeMap, HashMap};
+use strecklistan_api::book_account::{BookAccount, BookAccountId, BookAccountType};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::report::ReportPeriod;
+
+/// GET `/export/bas_accounts`
+///
+/// Returns the BAS account that each book account is exported to
+#[get("/export/bas_accounts")]
+pub fn get_bas_accounts(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+) -> Result<Ser<HashMap<BookAccountId, BasNumber>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let accounts = load_accounts(&connection)?;
+    Ok(accept.ser(get_bas_numbers(&connection, &accounts)?))
+}
+
+/// PUT `/export/bas_accounts`
+///
+/// Set the BAS account that one or more book accounts are exported to
+#[put("/export/bas_accounts", data = "<numbers>")]
+pub fn put_bas_accounts(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    numbers: Json<HashMap<BookAccountId, BasNumber>>,
+) -> Result<SJ, SJ> {
+    if numbers
+        .values()
+        .any(|number| !(1000..=9999).contains(number))
+    {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "BAS account numbers must have four digits",
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+    set_bas_numbers(&connection, &numbers)?;
+    Ok(Status::Ok.into())
+}
+
+#[derive(Responder)]
+pub struct SieExport {
+    file: Vec<u8>,
+    content_type: ContentType,
+    disposition: Header<'static>,
+}
+
+/// GET `/export/sie4?<year>&<start_month>`
+///
+/// Export all transactions of a fiscal year as an SIE4 file.
+///
+/// The fiscal year starts on the first day of `start_month` (default January) of `year`.
+#[get("/export/sie4?<year>&<start_month>")]
+pub fn export_sie4(
+    db_pool: &State<DatabasePool>,
+    config: &State<SieConfig>,
+    _user: Treasurer,
+    year: i32,
+    start_month: Option<u32>,
+) -> Result<SieExport, SJ> {
+    let connection = db_pool.inner().get()?;
+    let file = export_fiscal_year(
+        &connection,
+        &config.organization_name,
+        year,
+        start_month.unwrap_or(1),
+    )?;
+
+    let file_name = format!("strecklistan_{}.se", year);
+    Ok(SieExport {
+        file: file.to_bytes(),
+        content_type: ContentType::Plain,
+        disposition: Header::new(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        ),
+    })
+}
+
+/// Build an SIE4 file of all transactions of the fiscal year, which starts on the first day of
+/// `start_month` of `year`
+pub fn export_fiscal_year(
+    connection: &DatabaseConn,
+    organization_name: &str,
+    year: i32,
+    start_month: u32,
+) -> Result<SieFile, SJ> {
+    let year_start = NaiveDate::from_ymd_opt(year, start_month, 1)
+        .ok_or_else(|| SJ::new(Status::BadRequest, "Invalid fiscal year"))?;
+    let next_year_start = NaiveDate::from_ymd_opt(year + 1, start_month, 1)
+        .ok_or_else(|| SJ::new(Status::BadRequest, "Invalid fiscal year"))?;
+
+    // midnight always exists
+    let start_of_day = |date: NaiveDate| Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap());
+    let period = ReportPeriod {
+        from: Some(start_of_day(year_start)),
+        to: Some(start_of_day(next_year_start)),
+    };
+
+    let (accounts, transactions, numbers) = connection.transaction::<_, SJ, _>(|| {
+        let accounts = load_accounts(connection)?;
+        let transactions = load_transactions(connection, period)?;
+        let numbers = get_bas_numbers(connection, &accounts)?;
+        Ok((accounts, transactions, numbers))
+    })?;
+
+    Ok(build_sie_file(
+        organization_name,
+        (
+            year_start,
+            next_year_start.pred_opt().unwrap_or(next_year_start),
+        ),
+        period,
+        &accounts,
+        &transactions,
+        &numbers,
+    ))
+}
+
+fn build_sie_file(
+    organization_name: &str,
+    fiscal_year: (NaiveDate, NaiveDate),
+    period: ReportPeriod,
+    accounts: &[BookAccount],
+    transactions: &[Transaction],
+    numbers: &HashMap<BookAccountId, BasNumber>,
+) -> SieFile {
+    // the book accounts exported to each BAS account
+    let mut bas_accounts: BTreeMap<BasNumber, Vec<&BookAccount>> = BTreeMap::new();
+    for account in accounts {
+        bas_accounts
+            .entry(numbers[&account.id])
+            .or_default()
+            .push(account);
+    }
+
+    let sie_accounts = bas_accounts
+        .iter()
+        .map(|(&number, book_accounts)| SieAccount {
+            number,
+            name: match book_accounts.as_slice() {
+                [account] => account.name.clone(),
+                _ => DEFAULT_BAS_ACCOUNTS
+                    .iter()
+                    .find(|(default, _)| *default == number)
+                    .map(|(_, name)| name.to_string())
+                    .unwrap_or_else(|| book_accounts[0].name.clone()),
+            },
+            account_type: book_accounts[0].account_type,
+        })
+        .collect();
+
+    // the sum of debits minus credits of each BAS account, before and during the period
+    let mut opening: BTreeMap<BasNumber, Currency> = BTreeMap::new();
+    let mut change: BTreeMap<BasNumber, Currency> = BTreeMap::new();
+    let mut vouchers = vec![];
+
+    for tr in transactions {
+        let amount: Currency = tr.amount.into();
+        let (debit, credit) = (numbers[&tr.debited_account], numbers[&tr.credited_account]);
+        let during_period = period.from.map(|from| tr.time >= from).unwrap_or(true);
+        let sums = if during_period {
+            &mut change
+        } else {
+            &mut opening
+        };
+        *sums.entry(debit).or_default() += amount;
+        *sums.entry(credit).or_default() -= amount;
+
+        if during_period && amount != Currency::default() {
+            vouchers.push(SieVoucher {
+                date: tr.time.naive_utc().date(),
+                text: match &tr.description {
+                    Some(description) => format!("#{} {}", tr.id, description),
+                    None => format!("#{}", tr.id),
+                },
+                rows: vec![(debit, amount), (credit, -amount)],
+            });
+        }
+    }
+
+    let is_balance_account = |number: &BasNumber| {
+        matches!(
+            bas_accounts
+                .get(number)
+                .map(|accounts| accounts[0].account_type),
+            Some(BookAccountType::Assets | BookAccountType::Liabilities)
+        )
+    };
+
+    let opening_balances: Vec<(BasNumber, Currency)> = opening
+        .iter()
+        .filter(|(number, _)| is_balance_account(number))
+        .map(|(&number, &balance)| (number, balance))
+        .collect();
+
+    let closing_balances = bas_accounts
+        .keys()
+        .filter(|number| is_balance_account(number))
+        .map(|&number| {
+            let balance = opening.get(&number).copied().unwrap_or_default()
+                + change.get(&number).copied().unwrap_or_default();
+            (number, balance)
+        })
+        .filter(|(_, balance)| *balance != Currency::default())
+        .collect();
+
+    let results = change
+        .iter()
+        .filter(|(number, _)| !is_balance_account(number))
+        .map(|(&number, &balance)| (number, balance))
+        .collect();
+
+    SieFile {
+        program_version: env!("CARGO_PKG_VERSION").to_string(),
+        generated: Utc::now().naive_utc().date(),
+        organization_name: organization_name.to_string(),
+        fiscal_year,
+        accounts: sie_accounts,
+        opening_balances,
+        closing_balances,
+        results,
+        vouchers,
+    }
+}
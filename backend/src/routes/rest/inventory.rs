@@ -3,15 +3,18 @@ use crate::models::inventory::{
     InventoryBundle as InventoryBundleRel, InventoryBundleItem,
     NewInventoryBundle as NewInventoryBundleRel, NewInventoryBundleItem,
 };
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::util::auth::{Cashier, Treasurer};
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use itertools::Itertools;
 use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::{delete, get, post, put, State};
 use std::collections::HashMap;
+use strecklistan_api::change::ChangeEvent;
 use strecklistan_api::inventory::InventoryBundle as InventoryBundleObj;
 use strecklistan_api::inventory::{
     InventoryBundleId, InventoryItemId, InventoryItemStock, InventoryItemTag,
@@ -22,6 +25,7 @@ use strecklistan_api::transaction::TransactionId;
 #[get("/inventory/items")]
 pub fn get_items(
     db_pool: &State<DatabasePool>,
+    _user: Cashier,
     accept: SerAccept,
 ) -> Result<Ser<HashMap<InventoryItemId, InventoryItemStock>>, SJ> {
     let connection = db_pool.inner().get()?;
@@ -36,9 +40,31 @@ pub fn get_items(
     ))
 }
 
+/// GET `/inventory/items/deleted`
+///
+/// Returns all items which are marked as deleted, most recently deleted first
+#[get("/inventory/items/deleted")]
+pub fn get_deleted_items(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    accept: SerAccept,
+) -> Result<Ser<Vec<InventoryItemStock>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::views::inventory_stock::dsl::{deleted_at, inventory_stock};
+    Ok(accept.ser(
+        inventory_stock
+            .filter(deleted_at.is_not_null())
+            .order_by(deleted_at.desc())
+            .load(&connection)?,
+    ))
+}
+
 #[post("/inventory/item", data = "<item>")]
 pub fn post_item(
     db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
     accept: SerAccept,
     item: Json<NewInventoryItem>,
 ) -> Result<Ser<InventoryItemId>, SJ> {
@@ -57,12 +83,15 @@ pub fn post_item(
         ))
         .returning(dsl::id)
         .get_result(&connection)?;
+    changes.notify(ChangeEvent::InventoryChanged);
     Ok(accept.ser(id))
 }
 
 #[put("/inventory/item/<id>", data = "<item>")]
 pub fn put_item(
     db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
     id: InventoryItemId,
     item: Json<NewInventoryItem>,
 ) -> Result<SJ, SJ> {
@@ -82,13 +111,19 @@ pub fn put_item(
         ))
         .execute(&connection)?;
 
+    changes.notify(ChangeEvent::InventoryChanged);
     Ok(Status::Ok.into())
 }
 
 #[delete("/inventory/item/<id>")]
-pub fn delete_item(db_pool: &State<DatabasePool>, id: InventoryItemId) -> Result<SJ, SJ> {
+pub fn delete_item(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
+    id: InventoryItemId,
+) -> Result<SJ, SJ> {
     let connection = db_pool.inner().get()?;
-    connection.transaction::<_, SJ, _>(|| {
+    let response = connection.transaction::<_, SJ, _>(|| {
         // check if an existing transaction is referencing this item
         let can_delete = {
             use crate::schema::tables::transaction_items::dsl;
@@ -114,12 +149,44 @@ pub fn delete_item(db_pool: &State<DatabasePool>, id: InventoryItemId) -> Result
         }
 
         Ok(Status::Ok.into())
-    })
+    })?;
+
+    changes.notify(ChangeEvent::InventoryChanged);
+    Ok(response)
+}
+
+/// POST `/inventory/item/<id>/restore`
+///
+/// Undo the deletion of an item.
+/// Only items which were referenced by a transaction when deleted can be restored.
+#[post("/inventory/item/<id>/restore")]
+pub fn restore_item(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
+    id: InventoryItemId,
+) -> Result<SJ, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::inventory::dsl;
+    let restored = diesel::update(dsl::inventory)
+        .filter(dsl::id.eq(id))
+        .filter(dsl::deleted_at.is_not_null())
+        .set(dsl::deleted_at.eq(None::<DateTime<Utc>>))
+        .execute(&connection)?;
+
+    if restored == 0 {
+        return Err(SJ::new(Status::NotFound, "No such deleted item"));
+    }
+
+    changes.notify(ChangeEvent::InventoryChanged);
+    Ok(Status::Ok.into())
 }
 
 #[get("/inventory/tags")]
 pub fn get_tags(
     db_pool: &State<DatabasePool>,
+    _user: Cashier,
     accept: SerAccept,
 ) -> Result<Ser<Vec<InventoryItemTag>>, SJ> {
     let connection = db_pool.inner().get()?;
@@ -131,6 +198,7 @@ pub fn get_tags(
 #[get("/inventory/bundles")]
 pub fn get_bundles(
     db_pool: &State<DatabasePool>,
+    _user: Cashier,
     accept: SerAccept,
 ) -> Result<Ser<HashMap<InventoryBundleId, InventoryBundleObj>>, SJ> {
     let connection = db_pool.inner().get()?;
@@ -169,12 +237,14 @@ pub fn get_bundles(
 #[post("/inventory/bundle", data = "<bundle>")]
 pub fn post_bundle(
     db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
     accept: SerAccept,
     bundle: Json<NewInventoryBundleObj>,
 ) -> Result<Ser<i32>, SJ> {
     let bundle = bundle.into_inner();
     let connection = db_pool.inner().get()?;
-    connection.transaction::<_, SJ, _>(|| {
+    let response = connection.transaction::<_, SJ, _>(|| {
         let bundle_id = {
             use crate::schema::tables::inventory_bundles::dsl::{id, inventory_bundles};
 
@@ -205,17 +275,22 @@ pub fn post_bundle(
         }
 
         Ok(accept.ser(bundle_id))
-    })
+    })?;
+
+    changes.notify(ChangeEvent::InventoryChanged);
+    Ok(response)
 }
 
 #[put("/inventory/bundle/<bundle_id>", data = "<bundle>")]
 pub fn put_bundle(
     db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
     bundle_id: InventoryBundleId,
     bundle: Json<NewInventoryBundleObj>,
 ) -> Result<SJ, SJ> {
     let connection = db_pool.inner().get()?;
-    connection.transaction::<_, SJ, _>(|| {
+    let response = connection.transaction::<_, SJ, _>(|| {
         use crate::schema::tables::inventory_bundles::dsl::{id, inventory_bundles};
 
         let bundle = bundle.into_inner();
@@ -233,16 +308,21 @@ pub fn put_bundle(
         // TODO: handle changed items
 
         Ok(Status::Ok.into())
-    })
+    })?;
+
+    changes.notify(ChangeEvent::InventoryChanged);
+    Ok(response)
 }
 
 #[delete("/inventory/bundle/<id>")]
 pub fn delete_inventory_bundle(
     db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
     id: InventoryBundleId,
 ) -> Result<SJ, SJ> {
     let connection = db_pool.inner().get()?;
-    connection.transaction::<_, SJ, _>(|| {
+    let response = connection.transaction::<_, SJ, _>(|| {
         {
             use crate::schema::tables::inventory_bundle_items::dsl::{
                 bundle_id, inventory_bundle_items,
@@ -260,5 +340,8 @@ pub fn delete_inventory_bundle(
         }
 
         Ok(Status::Ok.into())
-    })
+    })?;
+
+    changes.notify(ChangeEvent::InventoryChanged);
+    Ok(response)
 }
@@ -1,4 +1,7 @@
-use crate::database::DatabasePool;
+use crate::database::sale::{
+    complete_pending_sale, discard_pending_sale, find_exceeded_sale_credit_limit, PendingSale,
+};
+use crate::database::DatabaseConn;
 use crate::diesel::RunQueryDsl;
 use crate::models::izettle_transaction::{
     IZettlePostTransaction, IZettleTransaction, TRANSACTION_CANCELLED, TRANSACTION_FAILED,
@@ -8,51 +11,44 @@ use crate::models::transaction::relational;
 use crate::models::transaction::relational::{
     NewTransaction, NewTransactionBundle, NewTransactionItem,
 };
+use crate::routes::rest::book_account::credit_limit_exceeded;
 use crate::util::status_json::StatusJson as SJ;
-use diesel::r2d2::{ConnectionManager, PooledConnection};
-use diesel::{Connection, ExpressionMethods, JoinOnDsl, PgConnection, QueryDsl};
+use diesel::{Connection, ExpressionMethods, JoinOnDsl, OptionalExtension, QueryDsl};
 use itertools::Itertools;
 use log::info;
 use rocket::http::Status;
-use rocket::serde::json::Json;
-use rocket::{post, State};
-use serde::{Deserialize, Serialize};
 use std::iter;
+use strecklistan_api::izettle_bridge::PaymentResponse;
+use strecklistan_api::transaction::TransactionId;
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
-#[serde(tag = "type")]
-pub enum PaymentResponse {
-    TransactionPaid {
-        /// For example, "MASTERCARD"
-        card_type: Option<String>,
-
-        /// For example, "CONTACTLESS_EMV"
-        card_payment_entry_mode: Option<String>,
-
-        /// For example, "SWEDBANK"
-        card_issuing_bank: Option<String>,
-
-        /// Masked primary account number, e.g. "************1234"
-        masked_pan: Option<String>,
-    },
-    TransactionFailed {
-        reason: String,
-    },
-    TransactionCancelled,
-}
-
-#[post(
-    "/izettle/bridge/payment_response/<reference>",
-    data = "<payment_response>"
-)]
-pub async fn complete_izettle_transaction(
+/// Record the response of the payment terminal to a pending payment
+///
+/// Returns the id of the new transaction if the payment was successful.
+pub fn complete_payment(
+    connection: &DatabaseConn,
     reference: i32,
-    payment_response: Json<PaymentResponse>,
-    db_pool: &State<DatabasePool>,
-) -> Result<SJ, SJ> {
-    let connection = db_pool.inner().get()?;
-
+    payment_response: PaymentResponse,
+) -> Result<Option<TransactionId>, SJ> {
     connection.transaction::<_, SJ, _>(|| {
+        // lock the payment, so that only one of the bridge, the expiry task and a cancellation
+        // can complete it
+        let locked: Option<i32> = {
+            use crate::schema::tables::izettle_transaction::dsl::{id, izettle_transaction};
+            izettle_transaction
+                .find(reference)
+                .select(id)
+                .for_update()
+                .first(connection)
+                .optional()?
+        };
+
+        if locked.is_none() {
+            return Err(SJ::new(
+                Status::NotFound,
+                format!("No pending transaction with reference {}", reference),
+            ));
+        }
+
         let joined: Vec<(
             IZettleTransaction,
             Option<relational::TransactionBundle>,
@@ -71,34 +67,48 @@ pub async fn complete_izettle_transaction(
                 .left_join(izettle_transaction_bundle.on(bundle_trans_id.eq(transaction_id)))
                 .left_join(izettle_transaction_item.on(item_bundle_id.eq(bundle_id)))
                 .filter(transaction_id.eq(reference))
-                .load(&connection)?
+                .load(connection)?
         };
 
+        let credited_account = joined[0].0.credited_account;
+
         let grouped = joined
             .into_iter()
             .group_by(|(transaction, _, _)| transaction.id);
 
-        let (izettle_transaction_id, mut transaction_rows) = match grouped.into_iter().next() {
-            Some(group) => group,
-            None => {
-                return Err(SJ::new(
-                    Status::BadRequest,
-                    format!("No pending transaction with reference {}", reference),
-                ));
-            }
-        };
+        let (izettle_transaction_id, mut transaction_rows) = grouped.into_iter().next().unwrap();
 
-        {
+        let deleted = {
             // Delete the transaction from izettle_transaction
             use crate::schema::tables::izettle_transaction::dsl::{
                 id as iz_id, izettle_transaction,
             };
             diesel::delete(izettle_transaction)
                 .filter(iz_id.eq(izettle_transaction_id))
-                .execute(&connection)?;
+                .execute(connection)?
+        };
+
+        if deleted == 0 {
+            // the payment has already been completed
+            return Ok(None);
         }
 
-        match payment_response.into_inner() {
+        let pending_sale = PendingSale::IZettle(izettle_transaction_id);
+
+        // the balances may have changed since the payment was started
+        let payment_response = match payment_response {
+            PaymentResponse::TransactionPaid { .. } => {
+                match find_exceeded_sale_credit_limit(connection, pending_sale, credited_account)? {
+                    Some(account) => PaymentResponse::TransactionFailed {
+                        reason: credit_limit_exceeded(&account),
+                    },
+                    None => payment_response,
+                }
+            }
+            payment_response => payment_response,
+        };
+
+        match payment_response {
             PaymentResponse::TransactionPaid {
                 card_payment_entry_mode,
                 card_type,
@@ -122,7 +132,7 @@ pub async fn complete_izettle_transaction(
                     diesel::insert_into(transactions)
                         .values(new_transaction)
                         .returning(id)
-                        .get_result(&connection)?
+                        .get_result(connection)?
                 };
 
                 // Iterate over all the joined rows for each *bundle* in the transaction
@@ -146,7 +156,7 @@ pub async fn complete_izettle_transaction(
                         diesel::insert_into(transaction_bundles)
                             .values(new_bundle)
                             .returning(id)
-                            .get_result(&connection)?
+                            .get_result(connection)?
                     };
 
                     // Iterate over all the joined rows for each *item* in the bundle
@@ -163,10 +173,13 @@ pub async fn complete_izettle_transaction(
                         use crate::schema::tables::transaction_items::dsl::*;
                         diesel::insert_into(transaction_items)
                             .values(new_item)
-                            .execute(&connection)?;
+                            .execute(connection)?;
                     }
                 }
 
+                // Record the remaining payments, if the card payment was part of a sale
+                complete_pending_sale(connection, pending_sale, new_transaction_id)?;
+
                 // Mark the transaction in izettle_transaction as paid
                 update_izettle_post_transaction(
                     IZettlePostTransaction {
@@ -179,13 +192,14 @@ pub async fn complete_izettle_transaction(
                         card_issuing_bank,
                         masked_pan,
                     },
-                    &connection,
+                    connection,
                 )?;
 
-                Ok(SJ::new(Status::Ok, "Transcation completed"))
+                Ok(Some(new_transaction_id))
             }
             PaymentResponse::TransactionFailed { reason } => {
                 info!("IZettle failed due to: {}", reason);
+                discard_pending_sale(connection, pending_sale)?;
 
                 // Mark the transaction as failed
                 update_izettle_post_transaction(
@@ -195,12 +209,14 @@ pub async fn complete_izettle_transaction(
                         error: Some(reason),
                         ..Default::default()
                     },
-                    &connection,
+                    connection,
                 )?;
 
-                Ok(SJ::new(Status::Ok, "Transcation cancelled with failure"))
+                Ok(None)
             }
             PaymentResponse::TransactionCancelled => {
+                discard_pending_sale(connection, pending_sale)?;
+
                 // Mark the transaction as cancelled
                 update_izettle_post_transaction(
                     IZettlePostTransaction {
@@ -208,10 +224,10 @@ pub async fn complete_izettle_transaction(
                         status: TRANSACTION_CANCELLED.to_string(),
                         ..Default::default()
                     },
-                    &connection,
+                    connection,
                 )?;
 
-                Ok(SJ::new(Status::Ok, "Transaction cancelled"))
+                Ok(None)
             }
         }
     })
@@ -219,7 +235,7 @@ pub async fn complete_izettle_transaction(
 
 fn update_izettle_post_transaction(
     transaction: IZettlePostTransaction,
-    connection: &PooledConnection<ConnectionManager<PgConnection>>,
+    connection: &DatabaseConn,
 ) -> Result<(), diesel::result::Error> {
     use crate::schema::tables::izettle_post_transaction::dsl::{
         card_issuing_bank as bank, card_payment_entry_mode as payment_mode, card_type as c_type,
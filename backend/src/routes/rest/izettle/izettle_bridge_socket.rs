@@ -0,0 +1,501 @@
+//! The WebSocket protocol spoken with the iZettle payment bridge.
+//!
+//! Rocket can't upgrade connections to WebSockets, so the bridge connects to a separate listener,
+//! on the path `/<terminal id>` of a registered terminal. It authenticates as a cashier with the
+//! `session` cookie or an `Authorization: Bearer <token>` header. Pending payments targeted at the
+//! terminal, or at any terminal, are pushed to the bridge as soon as they are created, and the
+//! bridge answers each one with a [PaymentResponse]. All messages are JSON text frames, and are
+//! defined in [strecklistan_api::izettle_bridge].
+//!
+//! Refunds of card payments are pushed and answered the same way, with a [RefundResponse].
+//!
+//! A payment or refund which may be handled by any terminal is claimed by the first bridge it is
+//! pushed to.
+//!
+//! If a pushed payment or refund expires or is cancelled before the bridge answers, the bridge is
+//! told to abort it. If the bridge disconnects or stops answering pings, the payments and refunds
+//! pushed to it are failed.
+//!
+//! [PaymentResponse]: strecklistan_api::izettle_bridge::PaymentResponse
+//! [RefundResponse]: strecklistan_api::izettle_bridge::RefundResponse
+
+use crate::database::user::get_session_user;
+use crate::database::{DatabaseConn, DatabasePool};
+use crate::diesel::RunQueryDsl;
+use crate::models::izettle_transaction::{IZettleRefundPartial, IZettleTransactionPartial};
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::routes::rest::izettle::izettle_bridge_result::complete_payment;
+use crate::routes::rest::izettle::izettle_refund::complete_refund;
+use crate::routes::rest::izettle::IZettleNotifier;
+use crate::util::auth::SESSION_COOKIE;
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl};
+use futures::{SinkExt, StreamExt};
+use std::collections::HashSet;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::izettle::IZettleTerminalId;
+use strecklistan_api::izettle_bridge::{
+    BridgeMessage, PaymentResponse, PendingPayment, PendingRefund, RefundResponse, ServerMessage,
+};
+use strecklistan_api::user::UserRole;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::select;
+use tokio_tungstenite::tungstenite::handshake::server::{
+    Callback, ErrorResponse, Request, Response,
+};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{accept_hdr_async, WebSocketStream};
+
+/// How often the bridge is pinged
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long the bridge may be silent before it is considered disconnected
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+
+type BridgeResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+type Socket = WebSocketStream<TcpStream>;
+
+/// The payments and refunds which have been pushed to a bridge and not yet answered
+#[derive(Default)]
+struct Pushed {
+    payments: HashSet<i32>,
+    refunds: HashSet<i32>,
+}
+
+pub struct BridgeState {
+    pub db_pool: DatabasePool,
+    pub notifier: IZettleNotifier,
+    pub changes: ChangeNotifier,
+}
+
+/// Accept payment bridges on `address` until the server shuts down
+pub async fn serve(address: SocketAddr, state: BridgeState) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to listen for payment bridges on {}: {}", address, e);
+            return;
+        }
+    };
+
+    info!("Listening for payment bridges on ws://{}", address);
+
+    let state = Arc::new(state);
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Failed to accept payment bridge connection: {}", e);
+                continue;
+            }
+        };
+
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            match handle_bridge(stream, &state).await {
+                Ok(()) => info!("Payment bridge {} disconnected", peer),
+                Err(e) => warn!("Payment bridge {} disconnected: {}", peer, e),
+            }
+        });
+    }
+}
+
+/// Read the session token from the cookie or authorization header of the handshake
+fn session_token(request: &Request) -> Option<&str> {
+    let headers = request.headers();
+
+    let cookie = headers
+        .get_all("Cookie")
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value);
+
+    cookie.or_else(|| {
+        headers
+            .get("Authorization")
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(str::trim)
+    })
+}
+
+/// Read the id of the terminal from the path of the handshake
+fn terminal_id(request: &Request) -> Option<&str> {
+    let id = request.uri().path().trim_start_matches('/');
+    if id.is_empty() || id.contains('/') {
+        None
+    } else {
+        Some(id)
+    }
+}
+
+/// Checks that the handshake of a bridge was made by a cashier, to a registered terminal
+struct Authenticate<'a> {
+    db_pool: &'a DatabasePool,
+
+    /// The id of the terminal which the bridge connected as
+    terminal: &'a mut Option<IZettleTerminalId>,
+}
+
+impl Callback for Authenticate<'_> {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        let reject = |status: StatusCode, message: &str| {
+            let mut response = ErrorResponse::new(Some(message.to_string()));
+            *response.status_mut() = status;
+            response
+        };
+
+        let token = session_token(request)
+            .ok_or_else(|| reject(StatusCode::UNAUTHORIZED, "Not logged in"))?;
+
+        let terminal = terminal_id(request)
+            .ok_or_else(|| reject(StatusCode::NOT_FOUND, "No terminal id in path"))?;
+
+        let connection = self
+            .db_pool
+            .get()
+            .map_err(|_| reject(StatusCode::INTERNAL_SERVER_ERROR, "Database error"))?;
+
+        let user = get_session_user(&connection, token)
+            .map_err(|_| reject(StatusCode::INTERNAL_SERVER_ERROR, "Database error"))?
+            .ok_or_else(|| reject(StatusCode::UNAUTHORIZED, "Invalid or expired session"))?;
+
+        if user.role < UserRole::Cashier {
+            return Err(reject(
+                StatusCode::FORBIDDEN,
+                "Payment bridges must be cashiers",
+            ));
+        }
+
+        let registered = {
+            use crate::schema::tables::izettle_terminals::dsl::{id, izettle_terminals};
+            izettle_terminals
+                .filter(id.eq(terminal))
+                .count()
+                .get_result::<i64>(&connection)
+                .map_err(|_| reject(StatusCode::INTERNAL_SERVER_ERROR, "Database error"))?
+                > 0
+        };
+
+        if !registered {
+            return Err(reject(StatusCode::NOT_FOUND, "No such terminal"));
+        }
+
+        *self.terminal = Some(terminal.to_string());
+        Ok(response)
+    }
+}
+
+async fn handle_bridge(stream: TcpStream, state: &BridgeState) -> BridgeResult<()> {
+    let mut terminal = None;
+    let authenticate = Authenticate {
+        db_pool: &state.db_pool,
+        terminal: &mut terminal,
+    };
+    let mut socket = accept_hdr_async(stream, authenticate).await?;
+    let terminal = terminal.ok_or("The handshake did not set a terminal")?;
+
+    info!("Payment bridge connected as terminal {}", terminal);
+    let _connection = state.notifier.connect_bridge(&terminal);
+
+    let mut pushed = Pushed::default();
+
+    let result = run_bridge(&mut socket, state, &terminal, &mut pushed).await;
+
+    if !pushed.payments.is_empty() || !pushed.refunds.is_empty() {
+        const REASON: &str = "The payment terminal was disconnected";
+        let connection = state.db_pool.get()?;
+
+        // the payments and refunds may have been completed some other way
+        for reference in pushed.payments {
+            let response = PaymentResponse::TransactionFailed {
+                reason: REASON.to_string(),
+            };
+            if let Err(e) = complete_payment(&connection, reference, response) {
+                warn!("Could not fail payment {}: {}", reference, e.description);
+            }
+        }
+
+        for reference in pushed.refunds {
+            let response = RefundResponse::RefundFailed {
+                reason: REASON.to_string(),
+            };
+            if let Err(e) = complete_refund(&connection, reference, response) {
+                warn!("Could not fail refund {}: {}", reference, e.description);
+            }
+        }
+    }
+
+    result
+}
+
+async fn run_bridge(
+    socket: &mut Socket,
+    state: &BridgeState,
+    terminal: &str,
+    pushed: &mut Pushed,
+) -> BridgeResult<()> {
+    let mut last_seen = Instant::now();
+
+    loop {
+        // start waiting before checking for payments, so that no notification is missed
+        let notified = state.notifier.wait(terminal, HEARTBEAT_INTERVAL);
+
+        let connection = state.db_pool.get()?;
+        let pending = load_pending_payments(&connection, terminal)?;
+
+        let cancelled: Vec<i32> = pushed
+            .payments
+            .iter()
+            .copied()
+            .filter(|&reference| !pending.iter().any(|payment| payment.id == reference))
+            .collect();
+        for reference in cancelled {
+            pushed.payments.remove(&reference);
+            send(socket, &ServerMessage::PaymentCancelled { reference }).await?;
+        }
+
+        for payment in pending {
+            if pushed.payments.contains(&payment.id)
+                || !claim_payment(&connection, payment.id, terminal)?
+            {
+                continue;
+            }
+
+            pushed.payments.insert(payment.id);
+            let payment = PendingPayment {
+                id: payment.id,
+                amount: payment.amount.into(),
+            };
+            send(socket, &ServerMessage::PendingPayment(payment)).await?;
+        }
+
+        let pending = load_pending_refunds(&connection, terminal)?;
+
+        let cancelled: Vec<i32> = pushed
+            .refunds
+            .iter()
+            .copied()
+            .filter(|&reference| !pending.iter().any(|refund| refund.id == reference))
+            .collect();
+        for reference in cancelled {
+            pushed.refunds.remove(&reference);
+            send(socket, &ServerMessage::RefundCancelled { reference }).await?;
+        }
+
+        for refund in pending {
+            if pushed.refunds.contains(&refund.id)
+                || !claim_refund(&connection, refund.id, terminal)?
+            {
+                continue;
+            }
+
+            pushed.refunds.insert(refund.id);
+            let refund = PendingRefund {
+                id: refund.id,
+                izettle_transaction_id: refund.izettle_transaction_id,
+                amount: refund.amount.into(),
+            };
+            send(socket, &ServerMessage::PendingRefund(refund)).await?;
+        }
+        drop(connection);
+
+        select! {
+            message = socket.next() => {
+                let message = match message {
+                    Some(message) => message?,
+                    None => return Ok(()),
+                };
+
+                last_seen = Instant::now();
+
+                match message {
+                    Message::Text(text) => {
+                        let message = serde_json::from_str(&text)?;
+                        handle_message(socket, state, pushed, message).await?;
+                    }
+                    Message::Close(_) => return Ok(()),
+
+                    // pings are answered by tungstenite, pongs only show that the bridge is alive
+                    _ => {}
+                }
+            }
+            _ = notified => {
+                if last_seen.elapsed() > HEARTBEAT_TIMEOUT {
+                    return Err("Heartbeat timed out".into());
+                }
+
+                socket.send(Message::Ping(vec![])).await?;
+            }
+        }
+    }
+}
+
+async fn handle_message(
+    socket: &mut Socket,
+    state: &BridgeState,
+    pushed: &mut Pushed,
+    message: BridgeMessage,
+) -> BridgeResult<()> {
+    match message {
+        BridgeMessage::PaymentResponse {
+            reference,
+            response,
+        } => {
+            pushed.payments.remove(&reference);
+
+            let connection = state.db_pool.get()?;
+            let reply = match complete_payment(&connection, reference, response) {
+                Ok(created) => {
+                    if let Some(id) = created {
+                        state.changes.notify(ChangeEvent::TransactionCreated { id });
+                    }
+                    ServerMessage::PaymentRecorded { reference }
+                }
+                Err(e) => ServerMessage::Error {
+                    message: e.description,
+                },
+            };
+
+            send(socket, &reply).await
+        }
+        BridgeMessage::RefundResponse {
+            reference,
+            response,
+        } => {
+            pushed.refunds.remove(&reference);
+
+            let connection = state.db_pool.get()?;
+            let reply = match complete_refund(&connection, reference, response) {
+                Ok(created) => {
+                    if let Some(id) = created {
+                        state.changes.notify(ChangeEvent::TransactionCreated { id });
+                    }
+                    ServerMessage::RefundRecorded { reference }
+                }
+                Err(e) => ServerMessage::Error {
+                    message: e.description,
+                },
+            };
+
+            send(socket, &reply).await
+        }
+    }
+}
+
+/// Load the payments which may be charged by `terminal`
+fn load_pending_payments(
+    connection: &DatabaseConn,
+    terminal_id: &str,
+) -> BridgeResult<Vec<IZettleTransactionPartial>> {
+    use crate::schema::tables::izettle_transaction::dsl::{
+        amount, id, izettle_transaction, terminal, time,
+    };
+
+    Ok(izettle_transaction
+        .filter(terminal.eq(terminal_id).or(terminal.is_null()))
+        .order_by(time.asc())
+        .select((id, amount))
+        .load(connection)?)
+}
+
+/// Load the refunds which may be handled by `terminal`
+fn load_pending_refunds(
+    connection: &DatabaseConn,
+    terminal_id: &str,
+) -> BridgeResult<Vec<IZettleRefundPartial>> {
+    use crate::models::izettle_transaction::TRANSACTION_IN_PROGRESS;
+    use crate::schema::tables::izettle_post_transaction::dsl::izettle_post_transaction;
+    use crate::schema::tables::izettle_refunds::dsl::{
+        id, izettle_refunds, izettle_transaction_id, requested_at, status, terminal,
+    };
+    use crate::schema::tables::transactions::dsl::{amount, transactions};
+
+    Ok(izettle_refunds
+        .inner_join(izettle_post_transaction.inner_join(transactions))
+        .filter(status.eq(TRANSACTION_IN_PROGRESS))
+        .filter(terminal.eq(terminal_id).or(terminal.is_null()))
+        .order_by(requested_at.asc())
+        .select((id, izettle_transaction_id, amount))
+        .load(connection)?)
+}
+
+/// Target a payment at `terminal`, unless another terminal has already claimed it
+///
+/// Returns whether the payment may be charged by `terminal`.
+fn claim_payment(
+    connection: &DatabaseConn,
+    reference: i32,
+    terminal_id: &str,
+) -> BridgeResult<bool> {
+    use crate::schema::tables::izettle_transaction::dsl::{id, izettle_transaction, terminal};
+
+    let claimed = diesel::update(izettle_transaction)
+        .filter(id.eq(reference))
+        .filter(terminal.eq(terminal_id).or(terminal.is_null()))
+        .set(terminal.eq(terminal_id))
+        .execute(connection)?;
+
+    Ok(claimed > 0)
+}
+
+/// Target a refund at `terminal`, unless another terminal has already claimed it
+///
+/// Returns whether the refund may be handled by `terminal`.
+fn claim_refund(
+    connection: &DatabaseConn,
+    reference: i32,
+    terminal_id: &str,
+) -> BridgeResult<bool> {
+    use crate::schema::tables::izettle_refunds::dsl::{id, izettle_refunds, terminal};
+
+    let claimed = diesel::update(izettle_refunds)
+        .filter(id.eq(reference))
+        .filter(terminal.eq(terminal_id).or(terminal.is_null()))
+        .set(terminal.eq(terminal_id))
+        .execute(connection)?;
+
+    Ok(claimed > 0)
+}
+
+async fn send(socket: &mut Socket, message: &ServerMessage) -> BridgeResult<()> {
+    let text = serde_json::to_string(message)?;
+    socket.send(Message::Text(text)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_token() {
+        let request =
+            |header: &str, value: &str| Request::builder().header(header, value).body(()).unwrap();
+
+        let cookie = request("Cookie", "theme=dark; session=abc123");
+        assert_eq!(session_token(&cookie), Some("abc123"));
+
+        let bearer = request("Authorization", "Bearer abc123");
+        assert_eq!(session_token(&bearer), Some("abc123"));
+
+        let other = request("Cookie", "sessions=abc123");
+        assert_eq!(session_token(&other), None);
+    }
+
+    #[test]
+    fn test_terminal_id() {
+        let request = |uri: &str| Request::builder().uri(uri).body(()).unwrap();
+
+        assert_eq!(terminal_id(&request("/kassa1")), Some("kassa1"));
+        assert_eq!(terminal_id(&request("/")), None);
+        assert_eq!(terminal_id(&request("/kassa1/extra")), None);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::database::{DatabaseConn, DatabasePool};
+use crate::diesel::RunQueryDsl;
+use crate::models::izettle_transaction::{IZettleTransaction, TRANSACTION_IN_PROGRESS};
+use crate::routes::rest::izettle::izettle_bridge_result::complete_payment;
+use crate::routes::rest::izettle::izettle_refund::complete_refund;
+use crate::routes::rest::izettle::IZettleNotifier;
+use crate::routes::rest::swish::expire_swish_payments;
+use crate::util::auth::Admin;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{Duration, Utc};
+use diesel::{ExpressionMethods, QueryDsl};
+use rocket::http::Status;
+use rocket::{get, post, State};
+use strecklistan_api::izettle::PendingIZettlePayment;
+use strecklistan_api::izettle_bridge::{PaymentResponse, RefundResponse};
+
+/// How often pending payments are checked for expiry
+const EXPIRY_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// GET `/izettle/pending`
+///
+/// List all card payments which are waiting for the payment terminal, oldest first
+#[get("/izettle/pending")]
+pub fn get_pending_payments(
+    db_pool: &State<DatabasePool>,
+    _user: Admin,
+    accept: SerAccept,
+) -> Result<Ser<Vec<PendingIZettlePayment>>, SJ> {
+    use crate::schema::tables::izettle_transaction::dsl::{izettle_transaction, time};
+
+    let connection = db_pool.inner().get()?;
+    let payments: Vec<IZettleTransaction> =
+        izettle_transaction.order_by(time.asc()).load(&connection)?;
+
+    Ok(accept.ser(
+        payments
+            .into_iter()
+            .map(|payment| PendingIZettlePayment {
+                id: payment.id,
+                description: payment.description,
+                time: payment.time,
+                debited_account: payment.debited_account,
+                credited_account: payment.credited_account,
+                amount: payment.amount.into(),
+                terminal: payment.terminal,
+            })
+            .collect(),
+    ))
+}
+
+/// POST `/izettle/pending/<reference>/cancel`
+///
+/// Cancel a pending card payment, e.g. one which is stuck on a crashed payment terminal
+#[post("/izettle/pending/<reference>/cancel")]
+pub fn cancel_pending_payment(
+    db_pool: &State<DatabasePool>,
+    notifier: &State<IZettleNotifier>,
+    _user: Admin,
+    reference: i32,
+) -> Result<SJ, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    complete_payment(
+        &connection,
+        reference,
+        PaymentResponse::TransactionCancelled,
+    )?;
+
+    // let the payment bridges know that the payment should be aborted
+    notifier.notify(None);
+
+    Ok(Status::Ok.into())
+}
+
+/// Periodically mark card payments, refunds and Swish payments which have been pending for too
+/// long as failed
+pub async fn expire_payments(
+    db_pool: DatabasePool,
+    notifier: IZettleNotifier,
+    timeout: Duration,
+    swish_timeout: Duration,
+) {
+    let mut interval = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let expired = db_pool.get().map_err(SJ::from).and_then(|connection| {
+            Ok((
+                expire_stale_payments(&connection, timeout)?,
+                expire_swish_payments(&connection, swish_timeout)?,
+            ))
+        });
+
+        match expired {
+            Ok((card, swish)) => {
+                if card > 0 {
+                    info!("Expired {} pending card payment(s) and refund(s)", card);
+                    notifier.notify(None);
+                }
+                if swish > 0 {
+                    info!("Expired {} pending Swish payment(s)", swish);
+                }
+            }
+            Err(e) => error!("Failed to expire pending payments: {}", e.description),
+        }
+    }
+}
+
+/// Mark payments and refunds which have been pending for longer than `timeout` as failed
+///
+/// Returns the number of expired payments and refunds.
+fn expire_stale_payments(connection: &DatabaseConn, timeout: Duration) -> Result<usize, SJ> {
+    let deadline = Utc::now() - timeout;
+    let reason = format!("Not completed within {} seconds", timeout.num_seconds());
+
+    let stale_payments: Vec<i32> = {
+        use crate::schema::tables::izettle_transaction::dsl::{id, izettle_transaction, time};
+        izettle_transaction
+            .filter(time.lt(deadline))
+            .select(id)
+            .load(connection)?
+    };
+
+    let stale_refunds: Vec<i32> = {
+        use crate::schema::tables::izettle_refunds::dsl::{
+            id, izettle_refunds, requested_at, status,
+        };
+        izettle_refunds
+            .filter(status.eq(TRANSACTION_IN_PROGRESS))
+            .filter(requested_at.lt(deadline))
+            .select(id)
+            .load(connection)?
+    };
+
+    // they may have been completed since they were loaded
+    let ignore_completed = |result: Result<_, SJ>| match result {
+        Ok(_) => Ok(1),
+        Err(e) if e.status == Status::NotFound => Ok(0),
+        Err(e) => Err(e),
+    };
+
+    let mut expired = 0;
+    for reference in stale_payments {
+        let response = PaymentResponse::TransactionFailed {
+            reason: reason.clone(),
+        };
+        expired += ignore_completed(complete_payment(connection, reference, response))?;
+    }
+
+    for reference in stale_refunds {
+        let response = RefundResponse::RefundFailed {
+            reason: reason.clone(),
+        };
+        expired += ignore_completed(complete_refund(connection, reference, response))?;
+    }
+
+    Ok(expired)
+}
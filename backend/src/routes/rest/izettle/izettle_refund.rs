@@ -0,0 +1,267 @@
+use crate::database::transaction::insert_transaction;
+use crate::database::{DatabaseConn, DatabasePool};
+use crate::diesel::RunQueryDsl;
+use crate::models::izettle_transaction::{
+    IZettlePostTransaction, IZettleRefund as IZettleRefundRow, NewIZettleRefund, REFUND_COMPLETED,
+    TRANSACTION_CANCELLED, TRANSACTION_FAILED, TRANSACTION_IN_PROGRESS, TRANSACTION_PAID,
+};
+use crate::models::transaction::{object, relational};
+use crate::routes::rest::izettle::IZettleNotifier;
+use crate::util::auth::{Cashier, Treasurer};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use diesel::{Connection, ExpressionMethods, OptionalExtension, QueryDsl};
+use log::info;
+use rocket::http::Status;
+use rocket::{get, post, State};
+use std::collections::HashMap;
+use strecklistan_api::izettle::{CardPayment, IZettleRefund, IZettleRefundId};
+use strecklistan_api::izettle_bridge::RefundResponse;
+use strecklistan_api::transaction::TransactionId;
+
+/// POST `/transaction/<transaction_id>/refund?<terminal>`
+///
+/// Refund the card payment of a transaction, through `terminal` or any connected terminal
+#[post("/transaction/<transaction_id>/refund?<terminal>")]
+pub fn request_refund(
+    db_pool: &State<DatabasePool>,
+    notifier: &State<IZettleNotifier>,
+    user: Treasurer,
+    accept: SerAccept,
+    transaction_id: TransactionId,
+    terminal: Option<String>,
+) -> Result<Ser<IZettleRefundId>, SJ> {
+    if !notifier.has_bridge(terminal.as_deref()) {
+        return Err(SJ::new(
+            Status::ServiceUnavailable,
+            "No payment terminal is connected",
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+
+    let refund_id = connection.transaction::<_, SJ, _>(|| {
+        let reference: i32 = {
+            use crate::schema::tables::izettle_post_transaction::dsl;
+            dsl::izettle_post_transaction
+                .filter(dsl::transaction_id.eq(transaction_id))
+                .filter(dsl::status.eq(TRANSACTION_PAID))
+                .select(dsl::izettle_transaction_id)
+                .first(&connection)
+                .optional()?
+                .ok_or_else(|| {
+                    SJ::new(Status::BadRequest, "The transaction was not paid by card")
+                })?
+        };
+
+        use crate::schema::tables::izettle_refunds::dsl;
+
+        let active: i64 = dsl::izettle_refunds
+            .filter(dsl::izettle_transaction_id.eq(reference))
+            .filter(dsl::status.eq_any(&[TRANSACTION_IN_PROGRESS, REFUND_COMPLETED]))
+            .count()
+            .get_result(&connection)?;
+
+        if active > 0 {
+            return Err(SJ::new(
+                Status::Conflict,
+                "The payment has already been refunded",
+            ));
+        }
+
+        Ok(diesel::insert_into(dsl::izettle_refunds)
+            .values(NewIZettleRefund {
+                izettle_transaction_id: reference,
+                terminal: terminal.clone(),
+                requested_by: Some(user.0.user.name.clone()),
+                status: TRANSACTION_IN_PROGRESS.to_string(),
+            })
+            .returning(dsl::id)
+            .get_result(&connection)?)
+    })?;
+
+    notifier.notify(terminal.as_deref());
+
+    Ok(accept.ser(refund_id))
+}
+
+/// GET `/izettle/client/refund/<refund_id>`
+///
+/// Get the status of a refund
+#[get("/izettle/client/refund/<refund_id>")]
+pub fn poll_refund(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    accept: SerAccept,
+    refund_id: IZettleRefundId,
+) -> Result<Ser<IZettleRefund>, SJ> {
+    use crate::schema::tables::izettle_refunds::dsl::izettle_refunds;
+
+    let connection = db_pool.inner().get()?;
+    let refund: Option<IZettleRefundRow> = izettle_refunds
+        .find(refund_id)
+        .first(&connection)
+        .optional()?;
+
+    match refund {
+        Some(refund) => Ok(accept.ser(refund_status(&refund)?)),
+        None => Ok(accept.ser(IZettleRefund::NoRefund)),
+    }
+}
+
+fn refund_status(refund: &IZettleRefundRow) -> Result<IZettleRefund, SJ> {
+    match refund.status.as_str() {
+        TRANSACTION_IN_PROGRESS => Ok(IZettleRefund::Pending),
+        TRANSACTION_CANCELLED => Ok(IZettleRefund::Cancelled),
+        TRANSACTION_FAILED => Ok(IZettleRefund::Failed {
+            reason: refund
+                .error
+                .clone()
+                .unwrap_or_else(|| "Unknown error".to_string()),
+        }),
+        REFUND_COMPLETED => match refund.transaction_id {
+            Some(transaction_id) => Ok(IZettleRefund::Refunded { transaction_id }),
+            None => {
+                error!(
+                    "izettle_refund {} marked as refunded, but transaction_id was None",
+                    refund.id
+                );
+                Err(SJ::new(
+                    Status::InternalServerError,
+                    "Internal Server Error",
+                ))
+            }
+        },
+        status => Err(SJ::new(
+            Status::InternalServerError,
+            format!("Invalid refund status {}", status),
+        )),
+    }
+}
+
+/// Get the card payments of the given transactions, for those which were paid by card
+pub fn load_card_payments(
+    connection: &DatabaseConn,
+    transactions: &[TransactionId],
+) -> Result<HashMap<TransactionId, CardPayment>, SJ> {
+    let payments: Vec<IZettlePostTransaction> = {
+        use crate::schema::tables::izettle_post_transaction::dsl::*;
+        izettle_post_transaction
+            .filter(transaction_id.eq_any(transactions))
+            .filter(status.eq(TRANSACTION_PAID))
+            .load(connection)?
+    };
+
+    let refunds: Vec<IZettleRefundRow> = {
+        use crate::schema::tables::izettle_refunds::dsl::*;
+        izettle_refunds
+            .filter(
+                izettle_transaction_id.eq_any(payments.iter().map(|p| p.izettle_transaction_id)),
+            )
+            .order_by(id.asc())
+            .load(connection)?
+    };
+
+    // only the latest refund of each payment is relevant
+    let mut latest_refunds = HashMap::new();
+    for refund in &refunds {
+        latest_refunds.insert(refund.izettle_transaction_id, refund);
+    }
+
+    payments
+        .into_iter()
+        .filter_map(|payment| payment.transaction_id.map(|id| (id, payment)))
+        .map(|(id, payment)| {
+            let refund = latest_refunds
+                .get(&payment.izettle_transaction_id)
+                .map(|refund| refund_status(refund))
+                .transpose()?;
+
+            Ok((
+                id,
+                CardPayment {
+                    reference: payment.izettle_transaction_id,
+                    card_type: payment.card_type,
+                    masked_pan: payment.masked_pan,
+                    refund,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Record the response of the payment terminal to a pending refund
+///
+/// Returns the id of the reversing transaction if the refund was successful.
+pub fn complete_refund(
+    connection: &DatabaseConn,
+    reference: IZettleRefundId,
+    response: RefundResponse,
+) -> Result<Option<TransactionId>, SJ> {
+    connection.transaction::<_, SJ, _>(|| {
+        use crate::schema::tables::izettle_refunds::dsl;
+
+        let refund: IZettleRefundRow = dsl::izettle_refunds
+            .find(reference)
+            .filter(dsl::status.eq(TRANSACTION_IN_PROGRESS))
+            .for_update()
+            .first(connection)
+            .optional()?
+            .ok_or_else(|| {
+                SJ::new(
+                    Status::NotFound,
+                    format!("No pending refund with reference {}", reference),
+                )
+            })?;
+
+        let (status, error, transaction_id) = match response {
+            RefundResponse::RefundCompleted => {
+                let original: relational::Transaction = {
+                    use crate::schema::tables::izettle_post_transaction::dsl as post;
+                    use crate::schema::tables::transactions::dsl::transactions;
+                    let id: Option<TransactionId> = post::izettle_post_transaction
+                        .find(refund.izettle_transaction_id)
+                        .select(post::transaction_id)
+                        .first(connection)?;
+                    transactions
+                        .find(id.ok_or_else(|| {
+                            SJ::new(
+                                Status::InternalServerError,
+                                "The payment has no transaction",
+                            )
+                        })?)
+                        .first(connection)?
+                };
+
+                // the money is returned, but the items are not restocked
+                let reversal = insert_transaction(
+                    connection,
+                    object::NewTransaction {
+                        description: Some(format!("Återbetalning av #{}", original.id)),
+                        bundles: vec![],
+                        debited_account: original.credited_account,
+                        credited_account: original.debited_account,
+                        amount: original.amount.into(),
+                    },
+                )?;
+
+                (REFUND_COMPLETED, None, Some(reversal))
+            }
+            RefundResponse::RefundFailed { reason } => {
+                info!("IZettle refund failed due to: {}", reason);
+                (TRANSACTION_FAILED, Some(reason), None)
+            }
+            RefundResponse::RefundCancelled => (TRANSACTION_CANCELLED, None, None),
+        };
+
+        diesel::update(dsl::izettle_refunds.find(reference))
+            .set((
+                dsl::status.eq(status),
+                dsl::error.eq(error),
+                dsl::transaction_id.eq(transaction_id),
+            ))
+            .execute(connection)?;
+
+        Ok(transaction_id)
+    })
+}
@@ -0,0 +1,106 @@
+use crate::database::DatabasePool;
+use crate::diesel::RunQueryDsl;
+use crate::models::izettle_transaction::IZettleTerminalRow;
+use crate::routes::rest::izettle::IZettleNotifier;
+use crate::util::auth::{Admin, Cashier};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use diesel::{ExpressionMethods, QueryDsl};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use strecklistan_api::izettle::{IZettleTerminal, NewIZettleTerminal};
+
+/// GET `/izettle/terminals`
+///
+/// List all registered card terminals, and whether a payment bridge is connected as each of them
+#[get("/izettle/terminals")]
+pub fn get_terminals(
+    db_pool: &State<DatabasePool>,
+    notifier: &State<IZettleNotifier>,
+    _user: Cashier,
+    accept: SerAccept,
+) -> Result<Ser<Vec<IZettleTerminal>>, SJ> {
+    use crate::schema::tables::izettle_terminals::dsl::{id, izettle_terminals};
+
+    let connection = db_pool.inner().get()?;
+    let terminals: Vec<IZettleTerminalRow> =
+        izettle_terminals.order_by(id.asc()).load(&connection)?;
+
+    Ok(accept.ser(
+        terminals
+            .into_iter()
+            .map(|terminal| IZettleTerminal {
+                connected: notifier.has_bridge(Some(&terminal.id)),
+                id: terminal.id,
+                name: terminal.name,
+            })
+            .collect(),
+    ))
+}
+
+/// POST `/izettle/terminals`
+///
+/// Register a card terminal which a payment bridge can connect as
+#[post("/izettle/terminals", data = "<terminal>")]
+pub fn post_terminal(
+    db_pool: &State<DatabasePool>,
+    _user: Admin,
+    terminal: Json<NewIZettleTerminal>,
+) -> Result<SJ, SJ> {
+    let NewIZettleTerminal { id, name } = terminal.into_inner();
+
+    // the id is part of the url which the bridge connects to
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "Terminal ids may only contain letters, digits, '-' and '_'",
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+    let inserted = {
+        use crate::schema::tables::izettle_terminals::dsl::izettle_terminals;
+        diesel::insert_into(izettle_terminals)
+            .values(IZettleTerminalRow { id, name })
+            .on_conflict_do_nothing()
+            .execute(&connection)?
+    };
+
+    if inserted == 0 {
+        return Err(SJ::new(Status::Conflict, "The terminal already exists"));
+    }
+
+    Ok(Status::Ok.into())
+}
+
+/// DELETE `/izettle/terminal/<terminal_id>`
+///
+/// Unregister a card terminal.
+/// Payments targeted at the terminal may be charged by any terminal instead.
+#[delete("/izettle/terminal/<terminal_id>")]
+pub fn delete_terminal(
+    db_pool: &State<DatabasePool>,
+    notifier: &State<IZettleNotifier>,
+    _user: Admin,
+    terminal_id: String,
+) -> Result<SJ, SJ> {
+    use crate::schema::tables::izettle_terminals::dsl::{id, izettle_terminals};
+
+    let connection = db_pool.inner().get()?;
+    let deleted =
+        diesel::delete(izettle_terminals.filter(id.eq(&terminal_id))).execute(&connection)?;
+
+    if deleted == 0 {
+        return Err(SJ::new(Status::NotFound, "No such terminal"));
+    }
+
+    // the payments of the terminal are now available to all terminals
+    notifier.notify(None);
+
+    Ok(Status::Ok.into())
+}
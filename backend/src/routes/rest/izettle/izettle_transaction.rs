@@ -1,32 +1,107 @@
-use crate::database::DatabasePool;
+use crate::database::sale::{insert_pending_sale_payments, PendingSale};
+use crate::database::{DatabaseConn, DatabasePool};
 use crate::models::izettle_transaction::{
     NewIZettlePostTransaction, NewIZettleTransaction, NewIZettleTransactionBundle,
     NewIZettleTransactionItem, TRANSACTION_IN_PROGRESS,
 };
 use crate::models::transaction::object;
+use crate::routes::rest::book_account::enforce_credit_limits;
 use crate::routes::rest::izettle::IZettleNotifier;
+use crate::util::auth::Cashier;
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
 use diesel::{Connection, RunQueryDsl};
+use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::{post, State};
+use strecklistan_api::sale::{NewSale, SalePayment};
 
-#[post("/izettle/client/transaction", data = "<transaction>")]
+/// POST `/izettle/client/transaction?<terminal>`
+///
+/// Start a card payment, to be charged by `terminal` or by any connected terminal
+#[post("/izettle/client/transaction?<terminal>", data = "<transaction>")]
 pub async fn begin_izettle_transaction(
     db_pool: &State<DatabasePool>,
+    _user: Cashier,
     notifier: &State<IZettleNotifier>,
     accept: SerAccept,
+    terminal: Option<String>,
     transaction: Json<object::NewTransaction>,
 ) -> Result<Ser<i32>, SJ> {
+    ensure_bridge(notifier, terminal.as_deref())?;
+
     let connection = db_pool.inner().get()?;
+    let reference = begin_payment(&connection, terminal.clone(), transaction.into_inner(), &[])?;
+
+    notifier.notify(terminal.as_deref());
+    Ok(accept.ser(reference))
+}
+
+/// POST `/izettle/client/sale?<terminal>`
+///
+/// Start a card payment for the first payment of a sale. The remaining payments of the sale are
+/// recorded together with the card payment, once it has been paid.
+#[post("/izettle/client/sale?<terminal>", data = "<sale>")]
+pub async fn begin_izettle_sale(
+    db_pool: &State<DatabasePool>,
+    user: Cashier,
+    notifier: &State<IZettleNotifier>,
+    accept: SerAccept,
+    terminal: Option<String>,
+    sale: Json<NewSale>,
+) -> Result<Ser<i32>, SJ> {
+    let sale = sale.into_inner();
+    sale.validate()
+        .map_err(|e| SJ::new(Status::BadRequest, e))?;
+
+    ensure_bridge(notifier, terminal.as_deref())?;
 
+    let transactions = sale.clone().into_transactions();
+    let (transaction, remaining) = sale
+        .split_first()
+        .ok_or_else(|| SJ::new(Status::BadRequest, "The sale has no payments"))?;
+
+    let connection = db_pool.inner().get()?;
+    let reference = connection.transaction::<_, SJ, _>(|| {
+        enforce_credit_limits(&connection, &user.0, false, &transactions)?;
+        begin_payment(&connection, terminal.clone(), transaction, &remaining)
+    })?;
+
+    notifier.notify(terminal.as_deref());
+    Ok(accept.ser(reference))
+}
+
+/// Return an error if no payment bridge is connected to charge the payment
+fn ensure_bridge(notifier: &IZettleNotifier, terminal: Option<&str>) -> Result<(), SJ> {
+    if notifier.has_bridge(terminal) {
+        Ok(())
+    } else {
+        Err(SJ::new(
+            Status::ServiceUnavailable,
+            match terminal {
+                Some(terminal) => format!("Payment terminal {} is not connected", terminal),
+                None => "No payment terminal is connected".to_string(),
+            },
+        ))
+    }
+}
+
+/// Store a pending card payment, along with the remaining payments of its sale, if any
+///
+/// The payment bridges should be notified once the payment has been committed.
+fn begin_payment(
+    connection: &DatabaseConn,
+    terminal: Option<String>,
+    transaction: object::NewTransaction,
+    remaining_payments: &[SalePayment],
+) -> Result<i32, SJ> {
     let object::NewTransaction {
         description,
         bundles,
         debited_account,
         credited_account,
         amount,
-    } = transaction.into_inner();
+    } = transaction;
 
     let transaction = NewIZettleTransaction {
         description,
@@ -34,6 +109,7 @@ pub async fn begin_izettle_transaction(
         debited_account,
         credited_account,
         amount: amount.into(),
+        terminal,
     };
 
     connection.transaction::<_, SJ, _>(|| {
@@ -42,7 +118,7 @@ pub async fn begin_izettle_transaction(
             diesel::insert_into(izettle_transaction)
                 .values(transaction)
                 .returning(id)
-                .get_result(&connection)?
+                .get_result(connection)?
         };
 
         for bundle in bundles.into_iter() {
@@ -58,7 +134,7 @@ pub async fn begin_izettle_transaction(
                 diesel::insert_into(izettle_transaction_bundle)
                     .values(&new_bundle)
                     .returning(id)
-                    .get_result(&connection)?
+                    .get_result(connection)?
             };
 
             let item_ids: Vec<_> = bundle
@@ -72,7 +148,7 @@ pub async fn begin_izettle_transaction(
                 use crate::schema::tables::izettle_transaction_item::dsl::*;
                 diesel::insert_into(izettle_transaction_item)
                     .values(&item_ids)
-                    .execute(&connection)?;
+                    .execute(connection)?;
             }
         }
 
@@ -91,11 +167,17 @@ pub async fn begin_izettle_transaction(
             use crate::schema::tables::izettle_post_transaction::dsl::*;
             diesel::insert_into(izettle_post_transaction)
                 .values(post_tran)
-                .execute(&connection)?;
+                .execute(connection)?;
         }
 
-        notifier.notify();
+        if !remaining_payments.is_empty() {
+            insert_pending_sale_payments(
+                connection,
+                PendingSale::IZettle(transactions_id),
+                remaining_payments,
+            )?;
+        }
 
-        Ok(accept.ser(transactions_id))
+        Ok(transactions_id)
     })
 }
@@ -4,6 +4,7 @@ use crate::models::izettle_transaction::{
     IZettlePostTransaction, TRANSACTION_CANCELLED, TRANSACTION_FAILED, TRANSACTION_IN_PROGRESS,
     TRANSACTION_PAID,
 };
+use crate::util::auth::Cashier;
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::StatusJson;
 use diesel::{ExpressionMethods, QueryDsl};
@@ -21,6 +22,7 @@ pub struct IZettleResult {
 #[get("/izettle/client/poll/<izettle_transaction_id>")]
 pub async fn poll_for_izettle(
     db_pool: &State<DatabasePool>,
+    _user: Cashier,
     accept: SerAccept,
     izettle_transaction_id: i32,
 ) -> Result<Ser<IZettlePayment>, StatusJson> {
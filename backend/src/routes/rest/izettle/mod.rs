@@ -1,39 +1,97 @@
-pub mod izettle_bridge_poll;
 pub mod izettle_bridge_result;
+pub mod izettle_bridge_socket;
+pub mod izettle_pending;
+pub mod izettle_refund;
+pub mod izettle_terminal;
 pub mod izettle_transaction;
 pub mod izettle_transaction_poll;
 
+use std::collections::HashMap;
 use std::future::Future;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use strecklistan_api::izettle::IZettleTerminalId;
 use tokio::sync::Notify;
 use tokio::time::timeout;
 
-/// A shared state struct that enables long-polling for izettle transactions
-#[derive(Default)]
+/// A shared state struct that notifies the connected payment bridges of new izettle transactions
+///
+/// Each terminal has its own queue, so that a payment targeted at one terminal only wakes the
+/// bridge connected as that terminal.
+#[derive(Clone, Default)]
 pub struct IZettleNotifier {
-    inner: Arc<InnerNotifier>,
+    terminals: Arc<Mutex<HashMap<IZettleTerminalId, Arc<TerminalQueue>>>>,
 }
 
 #[derive(Default)]
-struct InnerNotifier {
+struct TerminalQueue {
     ticker: AtomicU64,
     notifier: Notify,
+
+    /// The number of payment bridges which are currently connected as the terminal
+    bridges: AtomicUsize,
+}
+
+impl TerminalQueue {
+    fn notify(&self) {
+        self.ticker.fetch_add(1, Ordering::SeqCst);
+        self.notifier.notify_waiters();
+    }
+}
+
+/// Marks a payment bridge as connected for as long as it is held
+pub struct BridgeConnection {
+    queue: Arc<TerminalQueue>,
+}
+
+impl Drop for BridgeConnection {
+    fn drop(&mut self) {
+        self.queue.bridges.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl IZettleNotifier {
+    fn queue(&self, terminal: &str) -> Arc<TerminalQueue> {
+        let mut terminals = self.terminals.lock().unwrap();
+        Arc::clone(terminals.entry(terminal.to_string()).or_default())
+    }
+
     /// Call notify to notify waiters that a pending transaction is ready
-    pub fn notify(&self) {
-        self.inner.ticker.fetch_add(1, Ordering::SeqCst);
-        self.inner.notifier.notify_waiters();
+    ///
+    /// If `terminal` is `None`, the bridges of all terminals are notified.
+    pub fn notify(&self, terminal: Option<&str>) {
+        match terminal {
+            Some(terminal) => self.queue(terminal).notify(),
+            None => {
+                let terminals = self.terminals.lock().unwrap();
+                terminals.values().for_each(|queue| queue.notify());
+            }
+        }
+    }
+
+    /// Register a payment bridge connected as `terminal`
+    pub fn connect_bridge(&self, terminal: &str) -> BridgeConnection {
+        let queue = self.queue(terminal);
+        queue.bridges.fetch_add(1, Ordering::SeqCst);
+        BridgeConnection { queue }
+    }
+
+    /// Whether a payment bridge is connected as `terminal`, or as any terminal if `None`
+    pub fn has_bridge(&self, terminal: Option<&str>) -> bool {
+        let terminals = self.terminals.lock().unwrap();
+        let connected = |queue: &Arc<TerminalQueue>| queue.bridges.load(Ordering::SeqCst) > 0;
+        match terminal {
+            Some(terminal) => terminals.get(terminal).map(connected).unwrap_or(false),
+            None => terminals.values().any(connected),
+        }
     }
 
-    /// Wait for a maximum of `millis` for a pending transaction
+    /// Wait for a maximum of `duration` for a pending transaction for `terminal`
     ///
     /// Returns true is the wait was ended by a call to notify, otherwise returns false
-    pub fn wait(&self, duration: Duration) -> impl Future<Output = bool> + 'static {
-        let state = Arc::clone(&self.inner);
+    pub fn wait(&self, terminal: &str, duration: Duration) -> impl Future<Output = bool> + 'static {
+        let state = self.queue(terminal);
 
         let start_tick = state.ticker.load(Ordering::SeqCst);
 
@@ -50,3 +108,22 @@ impl IZettleNotifier {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_bridge() {
+        let notifier = IZettleNotifier::default();
+        assert!(!notifier.has_bridge(None));
+
+        let connection = notifier.connect_bridge("kassa1");
+        assert!(notifier.has_bridge(None));
+        assert!(notifier.has_bridge(Some("kassa1")));
+        assert!(!notifier.has_bridge(Some("kassa2")));
+
+        drop(connection);
+        assert!(!notifier.has_bridge(Some("kassa1")));
+    }
+}
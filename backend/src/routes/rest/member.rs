@@ -1,26 +1,32 @@
-use crate::database::DatabasePool;
+use crate::database::member::{
+    find_merge_conflict, insert_member, merge_members, set_member_active, update_member,
+};
+use crate::database::{self, DatabasePool};
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::util::auth::{Cashier, Treasurer};
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
 use diesel::prelude::*;
+use rocket::http::Status;
 use rocket::serde::json::Json;
-use rocket::{get, post, State};
+use rocket::{delete, get, post, put, State};
 use std::collections::HashMap;
-use strecklistan_api::book_account::{BookAccountId, BookAccountType};
-use strecklistan_api::member::{Member, MemberId, NewMember};
+use strecklistan_api::book_account::BookAccountId;
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::member::{EditMember, Member, MemberId, NewMember};
 
 #[get("/members")]
 pub fn get_members(
     db_pool: &State<DatabasePool>,
+    _user: Cashier,
     accept: SerAccept,
 ) -> Result<Ser<HashMap<MemberId, Member>>, SJ> {
     let connection = db_pool.inner().get()?;
-    use crate::schema::tables::members::dsl::*;
 
     Ok(accept.ser(
-        members
-            .load(&connection)?
+        database::member::get_members(&connection)?
             .into_iter()
-            .map(|member: Member| (member.id, member))
+            .map(|member| (member.id, member))
             .collect(),
     ))
 }
@@ -28,6 +34,8 @@ pub fn get_members(
 #[post("/add_member_with_book_account", data = "<data>")]
 pub fn add_member_with_book_account(
     db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Cashier,
     accept: SerAccept,
     data: Json<(NewMember, String)>,
 ) -> Result<Ser<(MemberId, BookAccountId)>, SJ> {
@@ -35,33 +43,114 @@ pub fn add_member_with_book_account(
 
     let (new_member, account_name) = data.into_inner();
 
-    connection.transaction::<_, SJ, _>(|| {
-        let member_id = {
-            use crate::schema::tables::members::dsl::*;
-
-            diesel::insert_into(members)
-                .values((
-                    first_name.eq(&new_member.first_name),
-                    last_name.eq(&new_member.last_name),
-                    nickname.eq(&new_member.nickname),
-                ))
-                .returning(id)
-                .get_result(&connection)?
-        };
-
-        let acc_id = {
-            use crate::schema::tables::book_accounts::dsl::*;
-
-            diesel::insert_into(book_accounts)
-                .values((
-                    name.eq(&account_name),
-                    account_type.eq(&BookAccountType::Liabilities),
-                    creditor.eq(&Some(member_id)),
-                ))
-                .returning(id)
-                .get_result(&connection)?
-        };
-
-        Ok(accept.ser((member_id, acc_id)))
-    })
+    let (member_id, acc_id) = connection
+        .transaction::<_, SJ, _>(|| Ok(insert_member(&connection, &new_member, &account_name)?))?;
+
+    changes.notify(ChangeEvent::MemberAdded { id: member_id });
+    Ok(accept.ser((member_id, acc_id)))
+}
+
+/// PUT `/member/<member_id>`
+///
+/// Change the details of a member, and optionally rename its tillgodo account
+#[put("/member/<member_id>", data = "<edit>")]
+pub fn put_member(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Cashier,
+    accept: SerAccept,
+    member_id: MemberId,
+    edit: Json<EditMember>,
+) -> Result<Ser<Member>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let member =
+        connection.transaction::<_, SJ, _>(|| Ok(update_member(&connection, member_id, &edit)?))?;
+
+    changes.notify(ChangeEvent::MemberChanged { id: member_id });
+    Ok(accept.ser(member))
+}
+
+/// DELETE `/member/<member_id>`
+///
+/// Deactivate a member, which hides it from the store. Its account and transactions are kept.
+#[delete("/member/<member_id>")]
+pub fn deactivate_member(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
+    accept: SerAccept,
+    member_id: MemberId,
+) -> Result<Ser<Member>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let member = set_member_active(&connection, member_id, false)?;
+
+    changes.notify(ChangeEvent::MemberChanged { id: member_id });
+    Ok(accept.ser(member))
+}
+
+/// POST `/member/<member_id>/reactivate`
+///
+/// Undo the deactivation of a member
+#[post("/member/<member_id>/reactivate")]
+pub fn reactivate_member(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
+    accept: SerAccept,
+    member_id: MemberId,
+) -> Result<Ser<Member>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let member = set_member_active(&connection, member_id, true)?;
+
+    changes.notify(ChangeEvent::MemberChanged { id: member_id });
+    Ok(accept.ser(member))
+}
+
+/// POST `/member/<member_id>/merge?<into>`
+///
+/// Merge a duplicate member into the member `into`. The balance and transactions of the
+/// duplicate are moved to the other member, and the duplicate is deleted.
+///
+/// Fails if both members are signed up for the same event, or if a card payment between them is
+/// pending.
+#[post("/member/<member_id>/merge?<into>")]
+pub fn merge_member(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    user: Treasurer,
+    accept: SerAccept,
+    member_id: MemberId,
+    into: MemberId,
+) -> Result<Ser<MemberId>, SJ> {
+    if member_id == into {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "A member can not be merged into itself",
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+    let merged = connection.transaction::<_, SJ, _>(|| {
+        if let Some(conflict) = find_merge_conflict(&connection, member_id, into)? {
+            return Err(SJ::new(Status::Conflict, conflict));
+        }
+
+        Ok(merge_members(
+            &connection,
+            member_id,
+            into,
+            &user.0.user.name,
+        )?)
+    })?;
+
+    for id in merged.moved {
+        changes.notify(ChangeEvent::TransactionChanged { id });
+    }
+    for id in merged.deleted {
+        changes.notify(ChangeEvent::TransactionDeleted { id });
+    }
+    changes.notify(ChangeEvent::MemberChanged { id: member_id });
+    changes.notify(ChangeEvent::MemberChanged { id: into });
+    Ok(accept.ser(into))
 }
@@ -1,9 +1,18 @@
+pub mod auth;
 pub mod book_account;
+pub mod cash_register;
+pub mod changes;
 pub mod event;
+pub mod export;
 pub mod inventory;
 pub mod izettle;
 pub mod member;
+pub mod purchase;
 pub mod receipt;
+pub mod report;
+pub mod sale;
+pub mod stock_take;
+pub mod swish;
 pub mod transaction;
 
 use rocket::get;
@@ -0,0 +1,259 @@
+use crate::database::book_account::get_master_accounts;
+use crate::database::transaction::insert_transaction;
+use crate::database::DatabasePool;
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::util::auth::Treasurer;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use std::collections::HashMap;
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::purchase::{NewPurchase, Purchase, PurchaseId, PurchaseLine};
+use strecklistan_api::transaction::{NewTransaction, TransactionBundle, TransactionId};
+
+/// POST `/purchase`
+///
+/// Record a purchase of inventory from a supplier.
+///
+/// The purchases master account is debited, and the stock of each item is increased.
+#[post("/purchase", data = "<purchase>")]
+pub fn post_purchase(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    user: Treasurer,
+    accept: SerAccept,
+    purchase: Json<NewPurchase>,
+) -> Result<Ser<PurchaseId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let purchase = purchase.into_inner();
+
+    if purchase.lines.is_empty() {
+        return Err(SJ::new(Status::BadRequest, "A purchase must have items"));
+    }
+
+    if purchase.lines.iter().any(|line| line.quantity == 0) {
+        return Err(SJ::new(Status::BadRequest, "Quantities must be positive"));
+    }
+
+    if purchase.lines.iter().any(|line| line.unit_cost < 0.into()) {
+        return Err(SJ::new(Status::BadRequest, "Costs must not be negative"));
+    }
+
+    let quantities: Vec<i32> = purchase
+        .lines
+        .iter()
+        .map(|line| i32::try_from(line.quantity))
+        .collect::<Result<_, _>>()
+        .map_err(|_| SJ::new(Status::BadRequest, "A quantity is too large"))?;
+
+    let total = purchase
+        .total()
+        .ok_or_else(|| SJ::new(Status::BadRequest, "The total cost is too large"))?;
+
+    let (new_purchase_id, transaction) = connection.transaction::<_, SJ, _>(|| {
+        let masters = get_master_accounts(&connection)?;
+
+        if purchase.credited_account == masters.purchases_account_id {
+            return Err(SJ::new(
+                Status::BadRequest,
+                "The purchases account can not pay for a purchase",
+            ));
+        }
+
+        let bundles = purchase
+            .lines
+            .iter()
+            .zip(&quantities)
+            .map(|(line, &quantity)| TransactionBundle {
+                description: None,
+                price: Some(line.unit_cost),
+                change: quantity,
+                item_ids: vec![(line.item_id, 1)].into_iter().collect(),
+            })
+            .collect();
+
+        let transaction = insert_transaction(
+            &connection,
+            NewTransaction {
+                description: Some(format!("Inköp: {}", purchase.supplier)),
+                bundles,
+                debited_account: masters.purchases_account_id,
+                credited_account: purchase.credited_account,
+                amount: total,
+            },
+        )?;
+
+        let new_purchase_id: PurchaseId = {
+            use crate::schema::tables::purchases::dsl::*;
+            diesel::insert_into(purchases)
+                .values((
+                    transaction_id.eq(transaction),
+                    supplier.eq(&purchase.supplier),
+                    invoice_reference.eq(&purchase.invoice_reference),
+                    recorded_by.eq(&user.0.user.name),
+                ))
+                .returning(id)
+                .get_result(&connection)?
+        };
+
+        {
+            use crate::schema::tables::purchase_lines::dsl::*;
+            let lines: Vec<_> = purchase
+                .lines
+                .iter()
+                .zip(&quantities)
+                .map(|(line, &line_quantity)| {
+                    (
+                        purchase_id.eq(new_purchase_id),
+                        item_id.eq(line.item_id),
+                        quantity.eq(line_quantity),
+                        unit_cost.eq(i32::from(line.unit_cost)),
+                    )
+                })
+                .collect();
+            diesel::insert_into(purchase_lines)
+                .values(&lines)
+                .execute(&connection)?;
+        }
+
+        Ok((new_purchase_id, transaction))
+    })?;
+
+    changes.notify(ChangeEvent::TransactionCreated { id: transaction });
+    Ok(accept.ser(new_purchase_id))
+}
+
+type PurchaseRow = (
+    PurchaseId,
+    TransactionId,
+    String,
+    Option<String>,
+    Option<String>,
+    DateTime<Utc>,
+    i32,
+    i32,
+);
+
+/// GET `/purchases`
+///
+/// Returns a list of all purchases whose transaction hasn't been deleted, newest first
+#[get("/purchases")]
+pub fn get_purchases(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+) -> Result<Ser<Vec<Purchase>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        let rows: Vec<PurchaseRow> = {
+            use crate::schema::tables::purchases::dsl::*;
+            use crate::schema::tables::transactions::dsl as tr;
+            purchases
+                .inner_join(tr::transactions)
+                .filter(tr::deleted_at.is_null())
+                .select((
+                    id,
+                    transaction_id,
+                    supplier,
+                    invoice_reference,
+                    recorded_by,
+                    tr::time,
+                    tr::credited_account,
+                    tr::amount,
+                ))
+                .order_by(id.desc())
+                .load(&connection)?
+        };
+
+        let mut lines: HashMap<PurchaseId, Vec<PurchaseLine>> = HashMap::new();
+        {
+            use crate::schema::tables::purchase_lines::dsl::*;
+            let line_rows: Vec<(PurchaseId, InventoryItemId, i32, i32)> = purchase_lines
+                .select((purchase_id, item_id, quantity, unit_cost))
+                .filter(purchase_id.eq_any(rows.iter().map(|row| row.0).collect::<Vec<_>>()))
+                .order_by(id)
+                .load(&connection)?;
+
+            for (purchase, item, count, cost) in line_rows {
+                lines.entry(purchase).or_default().push(PurchaseLine {
+                    item_id: item,
+                    quantity: count as u32,
+                    unit_cost: cost.into(),
+                });
+            }
+        }
+
+        Ok(accept.ser(
+            rows.into_iter()
+                .map(
+                    |(
+                        id,
+                        transaction_id,
+                        supplier,
+                        invoice_reference,
+                        recorded_by,
+                        time,
+                        credited_account,
+                        amount,
+                    )| {
+                        Purchase {
+                            id,
+                            transaction_id,
+                            time,
+                            supplier,
+                            invoice_reference,
+                            recorded_by,
+                            credited_account,
+                            amount: amount.into(),
+                            lines: lines.remove(&id).unwrap_or_default(),
+                        }
+                    },
+                )
+                .collect(),
+        ))
+    })
+}
+
+/// GET `/inventory/costs`
+///
+/// Returns the average unit cost of every purchased inventory item, weighted by quantity
+#[get("/inventory/costs")]
+pub fn get_item_costs(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+) -> Result<Ser<HashMap<InventoryItemId, Currency>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::purchase_lines::dsl::*;
+    use crate::schema::tables::purchases::dsl::purchases;
+    use crate::schema::tables::transactions::dsl::{deleted_at, transactions};
+
+    let line_rows: Vec<(InventoryItemId, i32, i32)> = purchase_lines
+        .inner_join(purchases.inner_join(transactions))
+        .filter(deleted_at.is_null())
+        .select((item_id, quantity, unit_cost))
+        .load(&connection)?;
+
+    // item -> (total quantity, total cost)
+    let mut totals: HashMap<InventoryItemId, (i64, i64)> = HashMap::new();
+    for (item, count, cost) in line_rows {
+        let (total_count, total_cost) = totals.entry(item).or_default();
+        *total_count += count as i64;
+        *total_cost += count as i64 * cost as i64;
+    }
+
+    Ok(accept.ser(
+        totals
+            .into_iter()
+            .map(|(item, (count, cost))| (item, ((cost / count) as i32).into()))
+            .collect(),
+    ))
+}
@@ -1,6 +1,7 @@
 use crate::database::transaction::{objectify_transations, query_transaction, TransactionFilter};
 use crate::database::DatabasePool;
 use crate::models::izettle_transaction::IZettlePostTransaction;
+use crate::util::auth::Cashier;
 use crate::util::status_json::StatusJson as SJ;
 use diesel::prelude::*;
 use rocket::http::Status;
@@ -39,6 +40,7 @@ struct ReceiptMetaItem {
 #[get("/receipt/<transaction_id>")]
 pub async fn get_receipt(
     db_pool: &State<DatabasePool>,
+    _user: Cashier,
     transaction_id: TransactionId,
 ) -> Result<Html<Template>, SJ> {
     let connection = db_pool.inner().get()?;
@@ -0,0 +1,296 @@
+use crate::database::report::{
+    financial_statement, general_ledger, load_accounts, load_transactions, trial_balance,
+};
+use crate::database::{DatabaseConn, DatabasePool};
+use crate::models::transaction::relational::Transaction;
+use crate::util::auth::Treasurer;
+use crate::util::param::parse_time;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::response::content::Html;
+use rocket::{get, State};
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+use strecklistan_api::book_account::{BookAccount, BookAccountId};
+use strecklistan_api::report::{
+    AccountAmount, FinancialStatement, GeneralLedger, ReportPeriod, TrialBalance,
+};
+
+const LEDGER_TEMPLATE_NAME: &str = "ledger";
+const TRIAL_BALANCE_TEMPLATE_NAME: &str = "trial_balance";
+const STATEMENT_TEMPLATE_NAME: &str = "statement";
+
+type ReportData = (ReportPeriod, Vec<BookAccount>, Vec<Transaction>);
+
+/// Load the accounts and transactions needed to build a report for the period
+fn load_report_data(
+    connection: &DatabaseConn,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<ReportData, SJ> {
+    let period = ReportPeriod {
+        from: parse_time(from)?,
+        to: parse_time(to)?,
+    };
+
+    connection.transaction::<_, SJ, _>(|| {
+        Ok((
+            period,
+            load_accounts(connection)?,
+            load_transactions(connection, period)?,
+        ))
+    })
+}
+
+fn load_ledger(
+    connection: &DatabaseConn,
+    account_id: BookAccountId,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<(GeneralLedger, Vec<BookAccount>), SJ> {
+    let (period, accounts, transactions) = load_report_data(connection, from, to)?;
+
+    let account = accounts
+        .iter()
+        .find(|account| account.id == account_id)
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such book account"))?;
+
+    Ok((general_ledger(account, &transactions, period), accounts))
+}
+
+/// GET `/report/ledger/<account_id>?<from>&<to>`
+///
+/// The general ledger of a book account. `from` and `to` are RFC 3339 timestamps.
+#[get("/report/ledger/<account_id>?<from>&<to>")]
+pub fn get_ledger(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+    account_id: BookAccountId,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Ser<GeneralLedger>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let (ledger, _) = load_ledger(&connection, account_id, from, to)?;
+    Ok(accept.ser(ledger))
+}
+
+/// GET `/report/trial_balance?<from>&<to>`
+#[get("/report/trial_balance?<from>&<to>")]
+pub fn get_trial_balance(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Ser<TrialBalance>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let (period, accounts, transactions) = load_report_data(&connection, from, to)?;
+    Ok(accept.ser(trial_balance(&accounts, &transactions, period)))
+}
+
+/// GET `/report/statement?<from>&<to>`
+///
+/// The income statement for the period, and the balance sheet at the end of it.
+#[get("/report/statement?<from>&<to>")]
+pub fn get_statement(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Ser<FinancialStatement>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let (period, accounts, transactions) = load_report_data(&connection, from, to)?;
+    Ok(accept.ser(financial_statement(&accounts, &transactions, period)))
+}
+
+#[derive(Debug, Serialize)]
+struct LedgerTemplateData {
+    period: String,
+    account: String,
+    opening_balance: String,
+    closing_balance: String,
+    entries: Vec<LedgerTemplateEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct LedgerTemplateEntry {
+    transaction_id: i32,
+    date: String,
+    description: String,
+    counter_account: String,
+    debit: String,
+    credit: String,
+    balance: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TrialBalanceTemplateData {
+    period: String,
+    rows: Vec<TrialBalanceTemplateRow>,
+    total_debit: String,
+    total_credit: String,
+    balanced: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct TrialBalanceTemplateRow {
+    account: String,
+    debit: String,
+    credit: String,
+    balance: String,
+}
+
+#[derive(Debug, Serialize)]
+struct StatementTemplateData {
+    period: String,
+    revenue: Vec<StatementTemplateRow>,
+    expenses: Vec<StatementTemplateRow>,
+    assets: Vec<StatementTemplateRow>,
+    liabilities: Vec<StatementTemplateRow>,
+    total_revenue: String,
+    total_expenses: String,
+    total_assets: String,
+    total_liabilities: String,
+    net_income: String,
+    balanced: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct StatementTemplateRow {
+    account: String,
+    amount: String,
+}
+
+/// Format the period for display, e.g. "2022-01-01 – 2022-07-01"
+fn format_period(period: &ReportPeriod) -> String {
+    let format = |time: Option<DateTime<Utc>>| {
+        time.map(|time| time.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    format!("{} – {}", format(period.from), format(period.to))
+}
+
+/// GET `/report/ledger/<account_id>/print?<from>&<to>`
+///
+/// The general ledger of a book account, as printable HTML.
+#[get("/report/ledger/<account_id>/print?<from>&<to>")]
+pub fn print_ledger(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    account_id: BookAccountId,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Html<Template>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let (ledger, accounts) = load_ledger(&connection, account_id, from, to)?;
+
+    let account_name = |id| {
+        accounts
+            .iter()
+            .find(|account| account.id == id)
+            .map(|account| account.name.clone())
+            .unwrap_or_default()
+    };
+
+    let data = LedgerTemplateData {
+        period: format_period(&ledger.period),
+        account: ledger.account_name,
+        opening_balance: ledger.opening_balance.to_string(),
+        closing_balance: ledger.closing_balance.to_string(),
+        entries: ledger
+            .entries
+            .into_iter()
+            .map(|entry| LedgerTemplateEntry {
+                transaction_id: entry.transaction_id,
+                date: entry.time.format("%Y-%m-%d").to_string(),
+                description: entry.description.unwrap_or_default(),
+                counter_account: account_name(entry.counter_account),
+                debit: entry.debit.to_string(),
+                credit: entry.credit.to_string(),
+                balance: entry.balance.to_string(),
+            })
+            .collect(),
+    };
+
+    Ok(Html(Template::render(LEDGER_TEMPLATE_NAME, &data)))
+}
+
+/// GET `/report/trial_balance/print?<from>&<to>`
+///
+/// The trial balance, as printable HTML.
+#[get("/report/trial_balance/print?<from>&<to>")]
+pub fn print_trial_balance(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Html<Template>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let (period, accounts, transactions) = load_report_data(&connection, from, to)?;
+    let report = trial_balance(&accounts, &transactions, period);
+
+    let data = TrialBalanceTemplateData {
+        period: format_period(&report.period),
+        rows: report
+            .rows
+            .into_iter()
+            .map(|row| TrialBalanceTemplateRow {
+                account: row.account_name,
+                debit: row.debit.to_string(),
+                credit: row.credit.to_string(),
+                balance: row.balance.to_string(),
+            })
+            .collect(),
+        total_debit: report.total_debit.to_string(),
+        total_credit: report.total_credit.to_string(),
+        balanced: report.balanced,
+    };
+
+    Ok(Html(Template::render(TRIAL_BALANCE_TEMPLATE_NAME, &data)))
+}
+
+/// GET `/report/statement/print?<from>&<to>`
+///
+/// The income statement and balance sheet, as printable HTML.
+#[get("/report/statement/print?<from>&<to>")]
+pub fn print_statement(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Html<Template>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let (period, accounts, transactions) = load_report_data(&connection, from, to)?;
+    let report = financial_statement(&accounts, &transactions, period);
+
+    let rows = |amounts: Vec<AccountAmount>| -> Vec<StatementTemplateRow> {
+        amounts
+            .into_iter()
+            .map(|row| StatementTemplateRow {
+                account: row.account_name,
+                amount: row.amount.to_string(),
+            })
+            .collect()
+    };
+
+    let data = StatementTemplateData {
+        period: format_period(&report.period),
+        revenue: rows(report.revenue),
+        expenses: rows(report.expenses),
+        assets: rows(report.assets),
+        liabilities: rows(report.liabilities),
+        total_revenue: report.total_revenue.to_string(),
+        total_expenses: report.total_expenses.to_string(),
+        total_assets: report.total_assets.to_string(),
+        total_liabilities: report.total_liabilities.to_string(),
+        net_income: report.net_income.to_string(),
+        balanced: report.balanced,
+    };
+
+    Ok(Html(Template::render(STATEMENT_TEMPLATE_NAME, &data)))
+}
@@ -0,0 +1,62 @@
+use crate::database::sale::{insert_sale, load_sale};
+use crate::database::DatabasePool;
+use crate::routes::rest::book_account::enforce_credit_limits;
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::util::auth::Cashier;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use diesel::Connection;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::sale::{NewSale, Sale, SaleId};
+
+/// POST `/sale?<override_credit_limit>`
+///
+/// Record a purchase which is paid from several accounts. Every payment is recorded as a separate
+/// transaction, all of them or none.
+///
+/// Fails if a tillgodo account would be overdrawn beyond its credit limit, unless
+/// `override_credit_limit` is set by a treasurer.
+#[post("/sale?<override_credit_limit>", data = "<sale>")]
+pub fn post_sale(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    user: Cashier,
+    accept: SerAccept,
+    override_credit_limit: Option<bool>,
+    sale: Json<NewSale>,
+) -> Result<Ser<Sale>, SJ> {
+    let sale = sale.into_inner();
+    sale.validate()
+        .map_err(|e| SJ::new(Status::BadRequest, e))?;
+
+    let connection = db_pool.inner().get()?;
+    let sale = connection.transaction::<_, SJ, _>(|| {
+        enforce_credit_limits(
+            &connection,
+            &user.0,
+            override_credit_limit.unwrap_or(false),
+            &sale.clone().into_transactions(),
+        )?;
+        Ok(insert_sale(&connection, sale)?)
+    })?;
+
+    for &id in &sale.transaction_ids {
+        changes.notify(ChangeEvent::TransactionCreated { id });
+    }
+    Ok(accept.ser(sale))
+}
+
+/// GET `/sale/<sale_id>`
+#[get("/sale/<sale_id>")]
+pub fn get_sale(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    accept: SerAccept,
+    sale_id: SaleId,
+) -> Result<Ser<Sale>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_sale(&connection, sale_id)?))
+}
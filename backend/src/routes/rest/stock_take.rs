@@ -0,0 +1,316 @@
+use crate::database::book_account::get_master_accounts;
+use crate::database::transaction::insert_transaction;
+use crate::database::{DatabaseConn, DatabasePool};
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::util::auth::Treasurer;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Utc};
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use std::collections::HashMap;
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::stock_take::{CommitStockTake, StockTake, StockTakeDiff, StockTakeId};
+use strecklistan_api::transaction::{NewTransaction, TransactionBundle, TransactionId};
+
+type StockTakeRow = (
+    StockTakeId,
+    DateTime<Utc>,
+    Option<String>,
+    Option<DateTime<Utc>>,
+    Option<TransactionId>,
+);
+
+fn load_counts(
+    connection: &DatabaseConn,
+    stock_take: StockTakeId,
+) -> QueryResult<HashMap<InventoryItemId, i32>> {
+    use crate::schema::tables::stock_take_counts::dsl::*;
+
+    Ok(stock_take_counts
+        .filter(stock_take_id.eq(stock_take))
+        .select((item_id, counted))
+        .load(connection)?
+        .into_iter()
+        .collect())
+}
+
+fn load_stock_take(connection: &DatabaseConn, stock_take: StockTakeId) -> Result<StockTake, SJ> {
+    use crate::schema::tables::stock_takes::dsl::*;
+
+    let (row_id, start, starter, commit, transaction): StockTakeRow =
+        stock_takes.find(stock_take).first(connection)?;
+
+    Ok(StockTake {
+        id: row_id,
+        started_at: start,
+        started_by: starter,
+        committed_at: commit,
+        transaction_id: transaction,
+        counts: load_counts(connection, row_id)?,
+    })
+}
+
+/// Return an error if the stock take has already been committed
+fn ensure_not_committed(connection: &DatabaseConn, stock_take: StockTakeId) -> Result<(), SJ> {
+    use crate::schema::tables::stock_takes::dsl::*;
+
+    let commit: Option<DateTime<Utc>> = stock_takes
+        .find(stock_take)
+        .select(committed_at)
+        .for_update()
+        .first(connection)?;
+
+    match commit {
+        Some(_) => Err(SJ::new(
+            Status::Conflict,
+            "The stock take has already been committed",
+        )),
+        None => Ok(()),
+    }
+}
+
+fn compute_diff(
+    connection: &DatabaseConn,
+    stock_take: StockTakeId,
+) -> Result<Vec<StockTakeDiff>, SJ> {
+    let counts = load_counts(connection, stock_take)?;
+
+    let stocks: HashMap<InventoryItemId, i32> = {
+        use crate::schema::views::inventory_stock::dsl::*;
+        inventory_stock
+            .filter(id.eq_any(counts.keys().copied().collect::<Vec<_>>()))
+            .select((id, stock))
+            .load(connection)?
+            .into_iter()
+            .collect()
+    };
+
+    let mut diff: Vec<_> = counts
+        .into_iter()
+        .map(|(item_id, counted)| StockTakeDiff {
+            item_id,
+            expected: stocks.get(&item_id).copied().unwrap_or(0),
+            counted,
+        })
+        .collect();
+    diff.sort_by_key(|d| d.item_id);
+
+    Ok(diff)
+}
+
+/// GET `/stock_takes`
+///
+/// Returns a list of all stock takes, newest first
+#[get("/stock_takes")]
+pub fn get_stock_takes(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+) -> Result<Ser<Vec<StockTake>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        let ids: Vec<StockTakeId> = {
+            use crate::schema::tables::stock_takes::dsl::*;
+            stock_takes
+                .select(id)
+                .order_by(id.desc())
+                .load(&connection)?
+        };
+
+        let stock_takes = ids
+            .into_iter()
+            .map(|id| load_stock_take(&connection, id))
+            .collect::<Result<_, _>>()?;
+
+        Ok(accept.ser(stock_takes))
+    })
+}
+
+/// POST `/stock_take`
+///
+/// Start a new stock take
+#[post("/stock_take")]
+pub fn post_stock_take(
+    db_pool: &State<DatabasePool>,
+    user: Treasurer,
+    accept: SerAccept,
+) -> Result<Ser<StockTakeId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::stock_takes::dsl::*;
+
+    Ok(accept.ser(
+        diesel::insert_into(stock_takes)
+            .values(started_by.eq(&user.0.user.name))
+            .returning(id)
+            .get_result(&connection)?,
+    ))
+}
+
+/// GET `/stock_take/<stock_take_id>`
+#[get("/stock_take/<stock_take_id>")]
+pub fn get_stock_take(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+    stock_take_id: StockTakeId,
+) -> Result<Ser<StockTake>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_stock_take(&connection, stock_take_id)?))
+}
+
+/// DELETE `/stock_take/<stock_take_id>`
+///
+/// Abandon a stock take which hasn't been committed
+#[delete("/stock_take/<stock_take_id>")]
+pub fn delete_stock_take(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    stock_take_id: StockTakeId,
+) -> Result<SJ, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        ensure_not_committed(&connection, stock_take_id)?;
+
+        use crate::schema::tables::stock_takes::dsl::*;
+        diesel::delete(stock_takes.find(stock_take_id)).execute(&connection)?;
+
+        Ok(Status::Ok.into())
+    })
+}
+
+/// PUT `/stock_take/<stock_take_id>/counts`
+///
+/// Set the counted quantity of one or more items
+#[put("/stock_take/<stock_take_id>/counts", data = "<counts>")]
+pub fn put_stock_take_counts(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    stock_take_id: StockTakeId,
+    counts: Json<HashMap<InventoryItemId, u32>>,
+) -> Result<SJ, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::stock_take_counts::dsl;
+
+    let rows = counts
+        .iter()
+        .map(|(&item_id, &count)| {
+            let count = i32::try_from(count)
+                .map_err(|_| SJ::new(Status::BadRequest, "Count is too large"))?;
+            Ok((
+                dsl::stock_take_id.eq(stock_take_id),
+                dsl::item_id.eq(item_id),
+                dsl::counted.eq(count),
+            ))
+        })
+        .collect::<Result<Vec<_>, SJ>>()?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        ensure_not_committed(&connection, stock_take_id)?;
+
+        diesel::insert_into(dsl::stock_take_counts)
+            .values(&rows)
+            .on_conflict((dsl::stock_take_id, dsl::item_id))
+            .do_update()
+            .set(dsl::counted.eq(excluded(dsl::counted)))
+            .execute(&connection)?;
+
+        Ok(Status::Ok.into())
+    })
+}
+
+/// GET `/stock_take/<stock_take_id>/diff`
+///
+/// Compare the counted quantities against the current stock
+#[get("/stock_take/<stock_take_id>/diff")]
+pub fn get_stock_take_diff(
+    db_pool: &State<DatabasePool>,
+    _user: Treasurer,
+    accept: SerAccept,
+    stock_take_id: StockTakeId,
+) -> Result<Ser<Vec<StockTakeDiff>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(compute_diff(&connection, stock_take_id)?))
+}
+
+/// POST `/stock_take/<stock_take_id>/commit`
+///
+/// Create a correction transaction for all counted items whose stock differ from the count.
+/// Returns the id of the transaction, if one was needed.
+#[post("/stock_take/<stock_take_id>/commit", data = "<commit>")]
+pub fn commit_stock_take(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
+    accept: SerAccept,
+    stock_take_id: StockTakeId,
+    commit: Json<CommitStockTake>,
+) -> Result<Ser<Option<TransactionId>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let correction = connection.transaction::<_, SJ, _>(|| {
+        ensure_not_committed(&connection, stock_take_id)?;
+
+        let bundles: Vec<TransactionBundle> = compute_diff(&connection, stock_take_id)?
+            .into_iter()
+            .filter(|diff| diff.change() != 0)
+            .map(|diff| TransactionBundle {
+                description: None,
+                price: None,
+                change: diff.change(),
+                item_ids: vec![(diff.item_id, 1)].into_iter().collect(),
+            })
+            .collect();
+
+        let correction = if bundles.is_empty() {
+            None
+        } else {
+            let masters = get_master_accounts(&connection)?;
+            let debited_account = commit
+                .shrinkage_account
+                .unwrap_or(masters.shrinkage_account_id);
+
+            if debited_account == masters.purchases_account_id {
+                return Err(SJ::new(
+                    Status::BadRequest,
+                    "The shrinkage account can not be the purchases account",
+                ));
+            }
+
+            Some(insert_transaction(
+                &connection,
+                NewTransaction {
+                    description: Some(format!("Inventering #{}", stock_take_id)),
+                    bundles,
+                    debited_account,
+                    credited_account: masters.purchases_account_id,
+                    amount: Currency::from(0),
+                },
+            )?)
+        };
+
+        {
+            use crate::schema::tables::stock_takes::dsl::*;
+            diesel::update(stock_takes.find(stock_take_id))
+                .set((
+                    committed_at.eq(Some(Utc::now())),
+                    transaction_id.eq(correction),
+                ))
+                .execute(&connection)?;
+        }
+
+        Ok(correction)
+    })?;
+
+    if let Some(id) = correction {
+        changes.notify(ChangeEvent::TransactionCreated { id });
+    }
+    Ok(accept.ser(correction))
+}
@@ -0,0 +1,419 @@
+use crate::database::sale::{
+    complete_pending_sale, discard_pending_sale, find_exceeded_sale_credit_limit,
+    insert_pending_sale_payments, PendingSale,
+};
+use crate::database::transaction::insert_transaction;
+use crate::database::{DatabaseConn, DatabasePool};
+use crate::diesel::RunQueryDsl;
+use crate::models::izettle_transaction::{
+    TRANSACTION_CANCELLED, TRANSACTION_FAILED, TRANSACTION_IN_PROGRESS, TRANSACTION_PAID,
+};
+use crate::models::swish_payment::{NewSwishPayment, SwishPayment as SwishPaymentRow};
+use crate::models::transaction::object;
+use crate::routes::rest::book_account::{credit_limit_exceeded, enforce_credit_limits};
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::util::auth::Cashier;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{Duration, Utc};
+use diesel::{Connection, ExpressionMethods, OptionalExtension, QueryDsl};
+use log::info;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use serde::Deserialize;
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::sale::{NewSale, SalePayment};
+use strecklistan_api::swish::{
+    payment_id, payment_message, qr_payload, SwishPayment, SwishPaymentId, SwishPaymentRequest,
+};
+use strecklistan_api::transaction::TransactionId;
+
+pub struct SwishConfig {
+    /// The Swish number which payments are made to, Swish payments are disabled if `None`
+    pub payee: Option<String>,
+
+    /// The secret part of the callback url, callbacks are disabled if `None`
+    pub callback_secret: Option<String>,
+}
+
+/// The body of a payment callback from Swish
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwishCallback {
+    /// The message of the payment, which identifies it
+    pub message: String,
+    pub amount: f64,
+    pub status: SwishStatus,
+
+    /// The reference of the payment at Swish
+    pub payment_reference: Option<String>,
+
+    /// The phone number of the payer
+    pub payer_alias: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SwishStatus {
+    Paid,
+    Declined,
+    Error,
+    Cancelled,
+}
+
+/// The outcome of a pending Swish payment
+enum Outcome {
+    Paid {
+        payment_reference: Option<String>,
+        payer_alias: Option<String>,
+    },
+    Failed {
+        reason: String,
+    },
+    Cancelled,
+}
+
+/// POST `/swish/client/payment`
+///
+/// Start a Swish payment, which is committed as a transaction once it has been paid
+#[post("/swish/client/payment", data = "<transaction>")]
+pub fn begin_swish_payment(
+    db_pool: &State<DatabasePool>,
+    config: &State<SwishConfig>,
+    _user: Cashier,
+    accept: SerAccept,
+    transaction: Json<object::NewTransaction>,
+) -> Result<Ser<SwishPaymentRequest>, SJ> {
+    let payee = configured_payee(config)?;
+    let connection = db_pool.inner().get()?;
+    let request = begin_payment(&connection, payee, transaction.into_inner(), &[])?;
+    Ok(accept.ser(request))
+}
+
+/// POST `/swish/client/sale`
+///
+/// Start a Swish payment for the first payment of a sale. The remaining payments of the sale are
+/// recorded together with the Swish payment, once it has been paid.
+#[post("/swish/client/sale", data = "<sale>")]
+pub fn begin_swish_sale(
+    db_pool: &State<DatabasePool>,
+    config: &State<SwishConfig>,
+    user: Cashier,
+    accept: SerAccept,
+    sale: Json<NewSale>,
+) -> Result<Ser<SwishPaymentRequest>, SJ> {
+    let sale = sale.into_inner();
+    sale.validate()
+        .map_err(|e| SJ::new(Status::BadRequest, e))?;
+
+    let payee = configured_payee(config)?;
+
+    let transactions = sale.clone().into_transactions();
+    let (transaction, remaining) = sale
+        .split_first()
+        .ok_or_else(|| SJ::new(Status::BadRequest, "The sale has no payments"))?;
+
+    let connection = db_pool.inner().get()?;
+    let request = connection.transaction::<_, SJ, _>(|| {
+        enforce_credit_limits(&connection, &user.0, false, &transactions)?;
+        begin_payment(&connection, payee, transaction, &remaining)
+    })?;
+    Ok(accept.ser(request))
+}
+
+fn configured_payee(config: &SwishConfig) -> Result<&str, SJ> {
+    config.payee.as_deref().ok_or_else(|| {
+        SJ::new(
+            Status::ServiceUnavailable,
+            "Swish payments are not configured",
+        )
+    })
+}
+
+/// Store a pending Swish payment, along with the remaining payments of its sale, if any
+fn begin_payment(
+    connection: &DatabaseConn,
+    payee: &str,
+    transaction: object::NewTransaction,
+    remaining_payments: &[SalePayment],
+) -> Result<SwishPaymentRequest, SJ> {
+    let amount = transaction.amount;
+    let new_transaction = serde_json::to_value(&transaction)
+        .map_err(|e| SJ::new(Status::InternalServerError, e.to_string()))?;
+
+    let id: SwishPaymentId = {
+        use crate::schema::tables::swish_payments::dsl;
+        diesel::insert_into(dsl::swish_payments)
+            .values(NewSwishPayment {
+                new_transaction,
+                amount: amount.into(),
+                status: TRANSACTION_IN_PROGRESS.to_string(),
+            })
+            .returning(dsl::id)
+            .get_result(connection)?
+    };
+
+    if !remaining_payments.is_empty() {
+        insert_pending_sale_payments(connection, PendingSale::Swish(id), remaining_payments)?;
+    }
+
+    Ok(SwishPaymentRequest {
+        id,
+        qr_payload: qr_payload(payee, amount, &payment_message(id)),
+    })
+}
+
+/// GET `/swish/client/poll/<payment_id>`
+///
+/// Get the status of a Swish payment
+#[get("/swish/client/poll/<payment_id>")]
+pub fn poll_for_swish(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    accept: SerAccept,
+    payment_id: SwishPaymentId,
+) -> Result<Ser<SwishPayment>, SJ> {
+    use crate::schema::tables::swish_payments::dsl::swish_payments;
+
+    let connection = db_pool.inner().get()?;
+    let payment: Option<SwishPaymentRow> = swish_payments
+        .find(payment_id)
+        .first(&connection)
+        .optional()?;
+
+    let payment = match payment {
+        Some(payment) => payment,
+        None => return Ok(accept.ser(SwishPayment::NoTransaction)),
+    };
+
+    match payment.status.as_str() {
+        TRANSACTION_IN_PROGRESS => Ok(accept.ser(SwishPayment::Pending)),
+        TRANSACTION_CANCELLED => Ok(accept.ser(SwishPayment::Cancelled)),
+        TRANSACTION_FAILED => Ok(accept.ser(SwishPayment::Failed {
+            reason: payment.error.unwrap_or_else(|| "Unknown error".to_string()),
+        })),
+        TRANSACTION_PAID => match payment.transaction_id {
+            Some(transaction_id) => Ok(accept.ser(SwishPayment::Paid { transaction_id })),
+            None => {
+                error!(
+                    "swish_payment {} marked as paid, but transaction_id was None",
+                    payment.id
+                );
+                Err(SJ::new(
+                    Status::InternalServerError,
+                    "Internal Server Error",
+                ))
+            }
+        },
+        status => Err(SJ::new(
+            Status::InternalServerError,
+            format!("Invalid payment status {}", status),
+        )),
+    }
+}
+
+/// POST `/swish/client/payment/<payment_id>/cancel`
+///
+/// Cancel a Swish payment which has not yet been paid
+#[post("/swish/client/payment/<payment_id>/cancel")]
+pub fn cancel_swish_payment(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    payment_id: SwishPaymentId,
+) -> Result<SJ, SJ> {
+    let connection = db_pool.inner().get()?;
+    complete_swish_payment(&connection, payment_id, Outcome::Cancelled)?;
+    Ok(Status::Ok.into())
+}
+
+/// POST `/swish/callback/<secret>`
+///
+/// Confirm a Swish payment. Called by Swish, or by a local stand-in, once the customer has paid.
+#[post("/swish/callback/<secret>", data = "<callback>")]
+pub fn swish_callback(
+    db_pool: &State<DatabasePool>,
+    config: &State<SwishConfig>,
+    changes: &State<ChangeNotifier>,
+    secret: String,
+    callback: Json<SwishCallback>,
+) -> Result<SJ, SJ> {
+    if config.callback_secret.as_deref() != Some(secret.as_str()) {
+        return Err(Status::NotFound.into());
+    }
+
+    let callback = callback.into_inner();
+    let payment_id = payment_id(&callback.message).ok_or_else(|| {
+        SJ::new(
+            Status::BadRequest,
+            format!("Unknown payment message \"{}\"", callback.message),
+        )
+    })?;
+
+    let outcome = match callback.status {
+        SwishStatus::Paid => Outcome::Paid {
+            payment_reference: callback.payment_reference,
+            payer_alias: callback.payer_alias,
+        },
+        SwishStatus::Cancelled => Outcome::Cancelled,
+        SwishStatus::Declined | SwishStatus::Error => Outcome::Failed {
+            reason: callback
+                .error_message
+                .unwrap_or_else(|| "The payment was declined".to_string()),
+        },
+    };
+
+    let connection = db_pool.inner().get()?;
+
+    // the amount is locked in the QR code, but the callback is the only proof of what was paid
+    let paid_amount = (callback.amount * 100.0).round() as i32;
+    let outcome = match outcome {
+        Outcome::Paid { .. } => {
+            use crate::schema::tables::swish_payments::dsl::{amount, swish_payments};
+            let expected: i32 = swish_payments
+                .find(payment_id)
+                .select(amount)
+                .first(&connection)?;
+
+            if paid_amount == expected {
+                outcome
+            } else {
+                Outcome::Failed {
+                    reason: format!(
+                        "Paid {} kr instead of {} kr",
+                        Currency::from(paid_amount),
+                        Currency::from(expected),
+                    ),
+                }
+            }
+        }
+        outcome => outcome,
+    };
+
+    if let Some(id) = complete_swish_payment(&connection, payment_id, outcome)? {
+        changes.notify(ChangeEvent::TransactionCreated { id });
+    }
+
+    Ok(Status::Ok.into())
+}
+
+/// Record the outcome of a pending Swish payment
+///
+/// Returns the id of the new transaction if the payment was successful.
+fn complete_swish_payment(
+    connection: &DatabaseConn,
+    payment_id: SwishPaymentId,
+    outcome: Outcome,
+) -> Result<Option<TransactionId>, SJ> {
+    connection.transaction::<_, SJ, _>(|| {
+        use crate::schema::tables::swish_payments::dsl;
+
+        let payment: SwishPaymentRow = dsl::swish_payments
+            .find(payment_id)
+            .filter(dsl::status.eq(TRANSACTION_IN_PROGRESS))
+            .for_update()
+            .first(connection)
+            .optional()?
+            .ok_or_else(|| {
+                SJ::new(
+                    Status::NotFound,
+                    format!("No pending Swish payment with id {}", payment_id),
+                )
+            })?;
+
+        let transaction: object::NewTransaction =
+            serde_json::from_value(payment.new_transaction)
+                .map_err(|e| SJ::new(Status::InternalServerError, e.to_string()))?;
+
+        let pending_sale = PendingSale::Swish(payment_id);
+
+        // the balances may have changed since the payment was started
+        let outcome = match outcome {
+            Outcome::Paid { .. } => match find_exceeded_sale_credit_limit(
+                connection,
+                pending_sale,
+                transaction.credited_account,
+            )? {
+                Some(account) => Outcome::Failed {
+                    reason: credit_limit_exceeded(&account),
+                },
+                None => outcome,
+            },
+            outcome => outcome,
+        };
+
+        let query = diesel::update(
+            dsl::swish_payments
+                .find(payment_id)
+                .filter(dsl::status.eq(TRANSACTION_IN_PROGRESS)),
+        );
+
+        match outcome {
+            Outcome::Paid {
+                payment_reference,
+                payer_alias,
+            } => {
+                let transaction_id = insert_transaction(connection, transaction)?;
+                complete_pending_sale(connection, pending_sale, transaction_id)?;
+
+                query
+                    .set((
+                        dsl::status.eq(TRANSACTION_PAID),
+                        dsl::transaction_id.eq(transaction_id),
+                        dsl::payment_reference.eq(payment_reference),
+                        dsl::payer_alias.eq(payer_alias),
+                    ))
+                    .execute(connection)?;
+
+                Ok(Some(transaction_id))
+            }
+            Outcome::Failed { reason } => {
+                info!("Swish payment {} failed due to: {}", payment_id, reason);
+                discard_pending_sale(connection, pending_sale)?;
+                query
+                    .set((dsl::status.eq(TRANSACTION_FAILED), dsl::error.eq(reason)))
+                    .execute(connection)?;
+                Ok(None)
+            }
+            Outcome::Cancelled => {
+                discard_pending_sale(connection, pending_sale)?;
+                query
+                    .set(dsl::status.eq(TRANSACTION_CANCELLED))
+                    .execute(connection)?;
+                Ok(None)
+            }
+        }
+    })
+}
+
+/// Mark Swish payments which have been pending for longer than `timeout` as failed
+///
+/// Returns the number of expired payments.
+pub fn expire_swish_payments(connection: &DatabaseConn, timeout: Duration) -> Result<usize, SJ> {
+    use crate::schema::tables::swish_payments::dsl::{id, status, swish_payments, time};
+
+    let deadline = Utc::now() - timeout;
+    let stale_payments: Vec<SwishPaymentId> = swish_payments
+        .filter(status.eq(TRANSACTION_IN_PROGRESS))
+        .filter(time.lt(deadline))
+        .select(id)
+        .load(connection)?;
+
+    let mut expired = 0;
+    for payment_id in stale_payments {
+        let outcome = Outcome::Failed {
+            reason: format!("Not paid within {} seconds", timeout.num_seconds()),
+        };
+
+        // it may have been completed since it was loaded
+        match complete_swish_payment(connection, payment_id, outcome) {
+            Ok(_) => expired += 1,
+            Err(e) if e.status == Status::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(expired)
+}
@@ -1,154 +1,268 @@
-use crate::database::transaction::query_transaction;
+use crate::database::book_account::amendment_debits;
+use crate::database::transaction::{
+    self, amend_transaction, find_linked_record, insert_transaction, load_contents,
+    objectify_transations, query_revisions, query_transaction, query_transaction_page,
+    DeletedFilter, TransactionFilter,
+};
 use crate::database::DatabasePool;
-use crate::models::transaction::{object, relational};
+use crate::models::transaction::object;
+use crate::routes::rest::book_account::enforce_credit_limits;
+use crate::routes::rest::changes::ChangeNotifier;
+use crate::routes::rest::izettle::izettle_refund::load_card_payments;
+use crate::util::auth::{Cashier, Treasurer};
+use crate::util::param::{parse_amount, parse_time};
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
 use diesel::prelude::*;
-use itertools::Itertools;
+use rocket::http::Status;
 use rocket::serde::json::Json;
-use rocket::{delete, get, post, State};
-use std::collections::HashMap;
+use rocket::{delete, get, post, put, FromForm, State};
+use strecklistan_api::book_account::BookAccountId;
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::transaction::{
+    TransactionId, TransactionPage, TransactionRevision, TransactionRevisionId,
+};
 
-/// POST `/transaction`
+/// POST `/transaction?<override_credit_limit>`
 ///
 /// Create a new transaction
-#[post("/transaction", data = "<transaction>")]
+///
+/// Fails if a tillgodo account would be overdrawn beyond its credit limit, unless
+/// `override_credit_limit` is set by a treasurer.
+#[post("/transaction?<override_credit_limit>", data = "<transaction>")]
 pub fn post_transaction(
     db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    user: Cashier,
     accept: SerAccept,
+    override_credit_limit: Option<bool>,
     transaction: Json<object::NewTransaction>,
 ) -> Result<Ser<i32>, SJ> {
+    let transaction = transaction.into_inner();
     let connection = db_pool.inner().get()?;
 
-    let object::NewTransaction {
-        description,
-        bundles,
-        debited_account,
-        credited_account,
-        amount,
-    } = transaction.into_inner();
-
-    let transaction = relational::NewTransaction {
-        description,
-        time: None,
-        debited_account,
-        credited_account,
-        amount: amount.into(),
-    };
-
-    connection.transaction::<_, SJ, _>(|| {
-        let transaction_id = {
-            use crate::schema::tables::transactions::dsl::*;
-            diesel::insert_into(transactions)
-                .values(transaction)
-                .returning(id)
-                .get_result(&connection)?
-        };
-
-        for bundle in bundles.into_iter() {
-            let new_bundle = relational::NewTransactionBundle {
-                transaction_id,
-                description: bundle.description,
-                price: bundle.price.map(|p| p.into()),
-                change: bundle.change,
-            };
-
-            let bundle_id = {
-                use crate::schema::tables::transaction_bundles::dsl::*;
-                diesel::insert_into(transaction_bundles)
-                    .values(&new_bundle)
-                    .returning(id)
-                    .get_result(&connection)?
-            };
-
-            let item_ids: Vec<_> = bundle
-                .item_ids
-                .into_iter()
-                .flat_map(|(item_id, count)| std::iter::repeat(item_id).take(count as usize))
-                .map(|item_id| relational::NewTransactionItem { bundle_id, item_id })
-                .collect();
-
-            {
-                use crate::schema::tables::transaction_items::dsl::*;
-                diesel::insert_into(transaction_items)
-                    .values(&item_ids)
-                    .execute(&connection)?;
-            }
+    let transaction_id = connection.transaction::<_, SJ, _>(|| {
+        enforce_credit_limits(
+            &connection,
+            &user.0,
+            override_credit_limit.unwrap_or(false),
+            std::slice::from_ref(&transaction),
+        )?;
+        Ok(insert_transaction(&connection, transaction)?)
+    })?;
+
+    changes.notify(ChangeEvent::TransactionCreated { id: transaction_id });
+    Ok(accept.ser(transaction_id))
+}
+
+/// PUT `/transaction/<transaction_id>`
+///
+/// Amend the description, accounts, amount and bundles of a transaction.
+/// The change is recorded in the revision history of the transaction.
+///
+/// Transactions which are the bookkeeping of a purchase, a payment, a sale, an event signup, a
+/// stock take or a cash register correction can't be amended. Fails if the change would overdraw
+/// a tillgodo account beyond its credit limit, unless `override_credit_limit` is set.
+#[put(
+    "/transaction/<transaction_id>?<override_credit_limit>",
+    data = "<transaction>"
+)]
+pub fn put_transaction(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    user: Treasurer,
+    accept: SerAccept,
+    transaction_id: TransactionId,
+    override_credit_limit: Option<bool>,
+    transaction: Json<object::NewTransaction>,
+) -> Result<Ser<TransactionRevisionId>, SJ> {
+    let transaction = transaction.into_inner();
+    let connection = db_pool.inner().get()?;
+
+    let revision_id = connection.transaction::<_, SJ, _>(|| {
+        if let Some(record) = find_linked_record(&connection, transaction_id)? {
+            return Err(SJ::new(
+                Status::Conflict,
+                format!("The transaction records a {}, and can't be amended", record),
+            ));
         }
 
-        Ok(accept.ser(transaction_id))
-    })
+        let old = load_contents(&connection, transaction_id)?;
+        enforce_credit_limits(
+            &connection,
+            &user.0,
+            override_credit_limit.unwrap_or(false),
+            &amendment_debits(&old, &transaction),
+        )?;
+
+        Ok(amend_transaction(
+            &connection,
+            transaction_id,
+            &old,
+            transaction,
+            &user.0.user.name,
+        )?)
+    })?;
+
+    changes.notify(ChangeEvent::TransactionChanged { id: transaction_id });
+    Ok(accept.ser(revision_id))
+}
+
+/// GET `/transaction/<transaction_id>/history`
+///
+/// Returns all changes made to a transaction, oldest first
+#[get("/transaction/<transaction_id>/history")]
+pub fn get_transaction_history(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    accept: SerAccept,
+    transaction_id: TransactionId,
+) -> Result<Ser<Vec<TransactionRevision>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(query_revisions(&connection, transaction_id)?))
 }
 
 /// DELETE `/transaction/<transaction_id>`
 #[delete("/transaction/<transaction_id>")]
 pub fn delete_transaction(
     db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
     accept: SerAccept,
     transaction_id: i32,
 ) -> Result<Ser<i32>, SJ> {
     let connection = db_pool.inner().get()?;
 
-    use crate::schema::tables::transactions::dsl::{deleted_at, id, transactions};
-    let deleted_id = diesel::update(transactions)
-        .set(deleted_at.eq(Some(chrono::Utc::now().naive_utc())))
-        .filter(id.eq(transaction_id))
-        .returning(id)
-        .get_result(&connection)?;
+    let deleted_id = transaction::delete_transaction(&connection, transaction_id)?;
 
+    changes.notify(ChangeEvent::TransactionDeleted { id: deleted_id });
     Ok(accept.ser(deleted_id))
 }
 
+/// POST `/transaction/<transaction_id>/restore`
+///
+/// Undo the deletion of a transaction
+#[post("/transaction/<transaction_id>/restore")]
+pub fn restore_transaction(
+    db_pool: &State<DatabasePool>,
+    changes: &State<ChangeNotifier>,
+    _user: Treasurer,
+    accept: SerAccept,
+    transaction_id: TransactionId,
+) -> Result<Ser<TransactionId>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let restored_id = transaction::restore_transaction(&connection, transaction_id)?;
+
+    changes.notify(ChangeEvent::TransactionChanged { id: restored_id });
+    Ok(accept.ser(restored_id))
+}
+
+/// Filters of GET `/transactions` and GET `/transactions/page`
+#[derive(FromForm)]
+pub struct TransactionQuery {
+    /// Only include the transaction with this id, e.g. to find out if a changed transaction
+    /// matches the other filters
+    id: Option<TransactionId>,
+
+    /// Only include transactions made at or after this time, in RFC 3339
+    from: Option<String>,
+
+    /// Only include transactions made before this time, in RFC 3339
+    to: Option<String>,
+
+    /// Only include transactions which debit or credit this account
+    account: Option<BookAccountId>,
+
+    /// Only include transactions which contain this inventory item
+    item: Option<InventoryItemId>,
+
+    /// Only include transactions with an amount of at least this
+    min_amount: Option<String>,
+
+    /// Only include transactions with an amount of at most this
+    max_amount: Option<String>,
+
+    /// Only include transactions whose description contains this string
+    description: Option<String>,
+
+    /// Only include transactions which have been deleted
+    deleted: Option<bool>,
+}
+
+impl TransactionQuery {
+    fn into_filter(self) -> Result<TransactionFilter, SJ> {
+        Ok(TransactionFilter {
+            id: self.id,
+            from: parse_time(self.from)?,
+            to: parse_time(self.to)?,
+            account: self.account,
+            item: self.item,
+            min_amount: parse_amount(self.min_amount)?,
+            max_amount: parse_amount(self.max_amount)?,
+            description: self.description.filter(|d| !d.is_empty()),
+            deleted: match self.deleted {
+                Some(true) => DeletedFilter::Only,
+                _ => DeletedFilter::Exclude,
+            },
+            ..Default::default()
+        })
+    }
+}
+
 /// GET `/transactions`
 ///
-/// Returns a list of all transactions
-#[get("/transactions")]
+/// Returns all transactions matching the query, newest first
+#[get("/transactions?<query..>")]
 pub fn get_transactions(
     db_pool: &State<DatabasePool>,
+    _user: Cashier,
     accept: SerAccept,
+    query: TransactionQuery,
 ) -> Result<Ser<Vec<object::Transaction>>, SJ> {
     let connection = db_pool.inner().get()?;
+    let joined = query_transaction(&connection, query.into_filter()?)?;
+    Ok(accept.ser(objectify_transations(joined)))
+}
 
-    let joined = query_transaction(&connection, Default::default())?;
-
-    let transactions: Vec<object::Transaction> = joined
-        .into_iter()
-        .group_by(|(tr, _, _)| tr.id)
-        .into_iter()
-        .map(|(_, mut xs)| {
-            let (t0, b0, i0) = xs.next().unwrap();
-
-            object::Transaction {
-                id: t0.id,
-                description: t0.description,
-                time: t0.time,
-                debited_account: t0.debited_account,
-                credited_account: t0.credited_account,
-                amount: t0.amount.into(),
-                bundles: std::iter::once(b0.map(|b0| (b0, i0)))
-                    .chain(xs.map(|(_, bx, ix)| bx.map(|bx| (bx, ix))))
-                    .flatten()
-                    .group_by(|(bx, _)| bx.id)
-                    .into_iter()
-                    .map(|(_, mut xs)| {
-                        let (bundle, i0) = xs.next().unwrap();
-                        let mut item_ids = HashMap::new();
-                        std::iter::once(i0)
-                            .chain(xs.map(|(_, ix)| ix))
-                            .flatten()
-                            .for_each(|i| *item_ids.entry(i.item_id).or_default() += 1);
-
-                        object::TransactionBundle {
-                            description: bundle.description,
-                            price: bundle.price.map(|p| p.into()),
-                            change: bundle.change,
-                            item_ids,
-                        }
-                    })
-                    .collect(),
-            }
-        })
-        .collect();
+/// The largest number of transactions returned in one page
+const MAX_PAGE_SIZE: u32 = 200;
+
+/// GET `/transactions/page?<after>&<limit>`
+///
+/// Returns a page of transactions matching the query, newest first, along with the total number
+/// of matching transactions. The next page starts after the last transaction of this one.
+///
+/// A page holds at most [MAX_PAGE_SIZE] transactions, which is also the default `limit`.
+#[get("/transactions/page?<after>&<limit>&<query..>")]
+pub fn get_transaction_page(
+    db_pool: &State<DatabasePool>,
+    _user: Cashier,
+    accept: SerAccept,
+    after: Option<TransactionId>,
+    limit: Option<u32>,
+    query: TransactionQuery,
+) -> Result<Ser<TransactionPage>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let filter = query.into_filter()?;
+
+    let page = connection.transaction::<_, SJ, _>(|| {
+        Ok(query_transaction_page(
+            &connection,
+            filter,
+            after,
+            Some(i64::from(limit.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE))),
+        )?)
+    })?;
+
+    let transactions = objectify_transations(page.transactions);
+    let ids: Vec<TransactionId> = transactions.iter().map(|tr| tr.id).collect();
 
-    Ok(accept.ser(transactions))
+    Ok(accept.ser(TransactionPage {
+        card_payments: load_card_payments(&connection, &ids)?,
+        transactions,
+        total: page.total as u64,
+        next: page.next,
+    }))
 }
@@ -1,3 +1,17 @@
+table! {
+    bas_accounts (book_account_id) {
+        book_account_id -> Int4,
+        bas_number -> Int4,
+    }
+}
+
+table! {
+    book_account_balances (id) {
+        id -> Int4,
+        balance -> Int4,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use strecklistan_api::book_account::BookAccountTypeMapping;
@@ -6,6 +20,30 @@ table! {
         name -> Text,
         account_type -> BookAccountTypeMapping,
         creditor -> Nullable<Int4>,
+        credit_limit -> Nullable<Int4>,
+    }
+}
+
+table! {
+    cash_register_sessions (id) {
+        id -> Int4,
+        opened_at -> Timestamptz,
+        opened_by -> Nullable<Varchar>,
+        opening_float -> Int4,
+        opening_transaction_id -> Nullable<Int4>,
+        closed_at -> Nullable<Timestamptz>,
+        closed_by -> Nullable<Varchar>,
+        expected_cash -> Nullable<Int4>,
+        counted_cash -> Nullable<Int4>,
+        closing_transaction_id -> Nullable<Int4>,
+        z_report -> Nullable<Jsonb>,
+    }
+}
+
+table! {
+    credit_policy (id) {
+        id -> Bool,
+        default_credit_limit -> Nullable<Int4>,
     }
 }
 
@@ -15,6 +53,8 @@ table! {
         event -> Int4,
         name -> Varchar,
         email -> Varchar,
+        member -> Nullable<Int4>,
+        transaction_id -> Nullable<Int4>,
     }
 }
 
@@ -28,6 +68,8 @@ table! {
         end_time -> Timestamptz,
         price -> Int4,
         published -> Bool,
+        capacity -> Nullable<Int4>,
+        cancellation_deadline -> Nullable<Timestamptz>,
     }
 }
 
@@ -78,6 +120,26 @@ table! {
     }
 }
 
+table! {
+    izettle_refunds (id) {
+        id -> Int4,
+        izettle_transaction_id -> Int4,
+        terminal -> Nullable<Varchar>,
+        requested_at -> Timestamptz,
+        requested_by -> Nullable<Varchar>,
+        status -> Text,
+        error -> Nullable<Text>,
+        transaction_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    izettle_terminals (id) {
+        id -> Varchar,
+        name -> Text,
+    }
+}
+
 table! {
     izettle_transaction (id) {
         id -> Int4,
@@ -86,6 +148,7 @@ table! {
         debited_account -> Int4,
         credited_account -> Int4,
         amount -> Int4,
+        terminal -> Nullable<Varchar>,
     }
 }
 
@@ -113,6 +176,83 @@ table! {
         first_name -> Text,
         last_name -> Text,
         nickname -> Nullable<Text>,
+        deactivated_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    pending_sale_payments (id) {
+        id -> Int4,
+        izettle_transaction_id -> Nullable<Int4>,
+        swish_payment_id -> Nullable<Int4>,
+        debited_account -> Int4,
+        amount -> Int4,
+    }
+}
+
+table! {
+    purchase_lines (id) {
+        id -> Int4,
+        purchase_id -> Int4,
+        item_id -> Int4,
+        quantity -> Int4,
+        unit_cost -> Int4,
+    }
+}
+
+table! {
+    purchases (id) {
+        id -> Int4,
+        transaction_id -> Int4,
+        supplier -> Text,
+        invoice_reference -> Nullable<Text>,
+        recorded_by -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    sale_transactions (transaction_id) {
+        transaction_id -> Int4,
+        sale_id -> Int4,
+    }
+}
+
+table! {
+    sales (id) {
+        id -> Int4,
+        time -> Timestamptz,
+    }
+}
+
+table! {
+    stock_take_counts (stock_take_id, item_id) {
+        stock_take_id -> Int4,
+        item_id -> Int4,
+        counted -> Int4,
+    }
+}
+
+table! {
+    stock_takes (id) {
+        id -> Int4,
+        started_at -> Timestamptz,
+        started_by -> Nullable<Varchar>,
+        committed_at -> Nullable<Timestamptz>,
+        transaction_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    swish_payments (id) {
+        id -> Int4,
+        time -> Timestamptz,
+        new_transaction -> Jsonb,
+        amount -> Int4,
+        status -> Text,
+        error -> Nullable<Text>,
+        payment_reference -> Nullable<Text>,
+        payer_alias -> Nullable<Text>,
+        transaction_id -> Nullable<Int4>,
     }
 }
 
@@ -134,6 +274,17 @@ table! {
     }
 }
 
+table! {
+    transaction_revisions (id) {
+        id -> Int4,
+        transaction_id -> Int4,
+        revised_at -> Timestamptz,
+        revised_by -> Nullable<Varchar>,
+        old_value -> Jsonb,
+        new_value -> Jsonb,
+    }
+}
+
 table! {
     transactions (id) {
         id -> Int4,
@@ -147,29 +298,71 @@ table! {
 }
 
 table! {
+    user_sessions (token_hash) {
+        token_hash -> Varchar,
+        user_name -> Varchar,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use strecklistan_api::user::UserRoleMapping;
     users (name) {
         name -> Varchar,
         display_name -> Nullable<Varchar>,
         salted_pass -> Varchar,
         hash_iterations -> Int4,
+        role -> UserRoleMapping,
     }
 }
 
+joinable!(bas_accounts -> book_accounts (book_account_id));
+joinable!(book_account_balances -> book_accounts (id));
 joinable!(book_accounts -> members (creditor));
 joinable!(event_signups -> events (event));
+joinable!(event_signups -> members (member));
+joinable!(event_signups -> transactions (transaction_id));
 joinable!(inventory_bundle_items -> inventory (item_id));
 joinable!(inventory_bundle_items -> inventory_bundles (bundle_id));
 joinable!(inventory_tags -> inventory (item_id));
 joinable!(izettle_post_transaction -> transactions (transaction_id));
+joinable!(izettle_refunds -> izettle_post_transaction (izettle_transaction_id));
+joinable!(izettle_refunds -> izettle_terminals (terminal));
+joinable!(izettle_refunds -> transactions (transaction_id));
+joinable!(izettle_refunds -> users (requested_by));
+joinable!(izettle_transaction -> izettle_terminals (terminal));
 joinable!(izettle_transaction_bundle -> izettle_transaction (transaction_id));
 joinable!(izettle_transaction_item -> inventory (item_id));
 joinable!(izettle_transaction_item -> izettle_transaction_bundle (bundle_id));
+joinable!(pending_sale_payments -> book_accounts (debited_account));
+joinable!(pending_sale_payments -> izettle_post_transaction (izettle_transaction_id));
+joinable!(pending_sale_payments -> swish_payments (swish_payment_id));
+joinable!(purchase_lines -> inventory (item_id));
+joinable!(purchase_lines -> purchases (purchase_id));
+joinable!(purchases -> transactions (transaction_id));
+joinable!(purchases -> users (recorded_by));
+joinable!(sale_transactions -> sales (sale_id));
+joinable!(sale_transactions -> transactions (transaction_id));
+joinable!(stock_take_counts -> inventory (item_id));
+joinable!(stock_take_counts -> stock_takes (stock_take_id));
+joinable!(stock_takes -> transactions (transaction_id));
+joinable!(stock_takes -> users (started_by));
+joinable!(swish_payments -> transactions (transaction_id));
 joinable!(transaction_bundles -> transactions (transaction_id));
 joinable!(transaction_items -> inventory (item_id));
 joinable!(transaction_items -> transaction_bundles (bundle_id));
+joinable!(transaction_revisions -> transactions (transaction_id));
+joinable!(transaction_revisions -> users (revised_by));
+joinable!(user_sessions -> users (user_name));
 
 allow_tables_to_appear_in_same_query!(
+    bas_accounts,
+    book_account_balances,
     book_accounts,
+    cash_register_sessions,
+    credit_policy,
     event_signups,
     events,
     inventory,
@@ -177,12 +370,24 @@ allow_tables_to_appear_in_same_query!(
     inventory_bundles,
     inventory_tags,
     izettle_post_transaction,
+    izettle_refunds,
+    izettle_terminals,
     izettle_transaction,
     izettle_transaction_bundle,
     izettle_transaction_item,
     members,
+    pending_sale_payments,
+    purchase_lines,
+    purchases,
+    sale_transactions,
+    sales,
+    stock_take_counts,
+    stock_takes,
+    swish_payments,
     transaction_bundles,
     transaction_items,
+    transaction_revisions,
     transactions,
+    user_sessions,
     users,
 );
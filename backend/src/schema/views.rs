@@ -11,6 +11,8 @@ table! {
         end_time -> Timestamptz,
         price -> Int4,
         published -> Bool,
+        capacity -> Nullable<Int4>,
+        cancellation_deadline -> Nullable<Timestamptz>,
         signups -> Int8,
     }
 }
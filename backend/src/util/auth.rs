@@ -0,0 +1,131 @@
+use crate::database::{user::get_session_user, DatabasePool};
+use crate::util::StatusJson;
+use chrono::Duration;
+use rocket::http::Status;
+use rocket::outcome::{try_outcome, Outcome};
+use rocket::request::{self, FromRequest, Request};
+use strecklistan_api::user::{User, UserRole};
+
+/// The name of the cookie holding the session token
+pub const SESSION_COOKIE: &str = "session";
+
+/// Settings for user sessions
+pub struct AuthConfig {
+    /// How long a session is valid after logging in
+    pub session_lifetime: Duration,
+
+    /// Whether the session cookie is only sent over HTTPS
+    pub secure_cookie: bool,
+}
+
+/// Request guard for any logged in user
+///
+/// The session token is read from the `session` cookie, or from an
+/// `Authorization: Bearer <token>` header.
+pub struct AuthUser {
+    pub user: User,
+    pub token: String,
+}
+
+fn session_token(request: &Request<'_>) -> Option<String> {
+    if let Some(cookie) = request.cookies().get(SESSION_COOKIE) {
+        return Some(cookie.value().to_string());
+    }
+
+    request
+        .headers()
+        .get_one("Authorization")
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthUser {
+    type Error = StatusJson;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let token = match session_token(request) {
+            Some(token) => token,
+            None => {
+                let err = StatusJson::new(Status::Unauthorized, "Not logged in");
+                return Outcome::Failure((err.status, err));
+            }
+        };
+
+        let db_pool = match request.rocket().state::<DatabasePool>() {
+            Some(db_pool) => db_pool,
+            None => {
+                let err = StatusJson::new(Status::InternalServerError, "No database pool");
+                return Outcome::Failure((err.status, err));
+            }
+        };
+
+        let user = db_pool
+            .get()
+            .map_err(StatusJson::from)
+            .and_then(|connection| Ok(get_session_user(&connection, &token)?));
+
+        match user {
+            Ok(Some(user)) => Outcome::Success(AuthUser { user, token }),
+            Ok(None) => {
+                let err = StatusJson::new(Status::Unauthorized, "Invalid or expired session");
+                Outcome::Failure((err.status, err))
+            }
+            Err(err) => Outcome::Failure((err.status, err)),
+        }
+    }
+}
+
+async fn require_role(
+    request: &Request<'_>,
+    role: UserRole,
+) -> request::Outcome<AuthUser, StatusJson> {
+    let auth = try_outcome!(request.guard::<AuthUser>().await);
+    if auth.user.role >= role {
+        Outcome::Success(auth)
+    } else {
+        let err = StatusJson::new(
+            Status::Forbidden,
+            format!("This action requires the {:?} role", role),
+        );
+        Outcome::Failure((err.status, err))
+    }
+}
+
+/// Request guard for users with at least the [UserRole::Cashier] role
+pub struct Cashier(pub AuthUser);
+
+/// Request guard for users with at least the [UserRole::Treasurer] role
+pub struct Treasurer(pub AuthUser);
+
+/// Request guard for users with the [UserRole::Admin] role
+pub struct Admin(pub AuthUser);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Cashier {
+    type Error = StatusJson;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        require_role(request, UserRole::Cashier).await.map(Cashier)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Treasurer {
+    type Error = StatusJson;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        require_role(request, UserRole::Treasurer)
+            .await
+            .map(Treasurer)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = StatusJson;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        require_role(request, UserRole::Admin).await.map(Admin)
+    }
+}
@@ -3,7 +3,7 @@ use rocket::http::Status;
 use rocket::{catch, catchers, Catcher, Request};
 
 pub fn catchers() -> Vec<Catcher> {
-    catchers![not_found, unauthorized, bad_request,]
+    catchers![not_found, unauthorized, forbidden, bad_request,]
 }
 
 #[catch(404)]
@@ -19,6 +19,11 @@ pub fn unauthorized(_: &Request) -> StatusJson {
     Status::Unauthorized.into()
 }
 
+#[catch(403)]
+pub fn forbidden(_: &Request) -> StatusJson {
+    Status::Forbidden.into()
+}
+
 #[catch(400)]
 pub fn bad_request(_: &Request) -> StatusJson {
     Status::BadRequest.into()
@@ -0,0 +1,169 @@
+//! Writer for the iCalendar format, used to show events in calendar apps.
+//!
+//! See <https://datatracker.ietf.org/doc/html/rfc5545> for the specification.
+
+use chrono::{DateTime, Utc};
+
+/// Lines longer than this many bytes must be folded
+const MAX_LINE_LENGTH: usize = 75;
+
+pub struct IcalEvent {
+    /// Identifies the event across updates of the calendar, must be globally unique
+    pub uid: String,
+    pub summary: String,
+    pub description: String,
+    pub location: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+pub struct IcalCalendar {
+    pub program_version: String,
+    pub name: String,
+
+    /// When the calendar was generated, written as the `DTSTAMP` of every event
+    pub generated: DateTime<Utc>,
+
+    pub events: Vec<IcalEvent>,
+}
+
+/// Escape a text value
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            // other control characters are not allowed in text values
+            c if c.is_control() => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Split a content line into several lines of at most 75 bytes, without splitting a character.
+///
+/// Every continuation line starts with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut line_length = 0;
+    for c in line.chars() {
+        if line_length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            line_length = 1;
+        }
+        folded.push(c);
+        line_length += c.len_utf8();
+    }
+    folded
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+impl IcalCalendar {
+    /// Render the calendar as text, with CRLF line endings
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut line = |line: String| {
+            out.push_str(&fold(&line));
+            out.push_str("\r\n");
+        };
+
+        line("BEGIN:VCALENDAR".into());
+        line("VERSION:2.0".into());
+        line(format!(
+            "PRODID:-//strecklistan//strecklistan {}//SV",
+            self.program_version
+        ));
+        line("CALSCALE:GREGORIAN".into());
+        line(format!("X-WR-CALNAME:{}", escape(&self.name)));
+
+        for event in &self.events {
+            line("BEGIN:VEVENT".into());
+            line(format!("UID:{}", escape(&event.uid)));
+            line(format!("DTSTAMP:{}", format_time(self.generated)));
+            line(format!("DTSTART:{}", format_time(event.start)));
+            line(format!("DTEND:{}", format_time(event.end)));
+            line(format!("SUMMARY:{}", escape(&event.summary)));
+            line(format!("LOCATION:{}", escape(&event.location)));
+            line(format!("DESCRIPTION:{}", escape(&event.description)));
+            line("END:VEVENT".into());
+        }
+
+        line("END:VCALENDAR".into());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("Sittning"), "Sittning");
+        assert_eq!(escape("Mat, dryck; sång"), "Mat\\, dryck\\; sång");
+        assert_eq!(escape("a\\b"), "a\\\\b");
+        assert_eq!(escape("rad 1\r\nrad 2\n"), "rad 1\\nrad 2\\n");
+        assert_eq!(escape("a\tb"), "a b");
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("SUMMARY:Sittning"), "SUMMARY:Sittning");
+
+        let line = "x".repeat(160);
+        let folded = fold(&line);
+        let lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 75);
+        assert_eq!(lines[1].len(), 75);
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines[2], format!(" {}", "x".repeat(11)));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+
+        // a multi-byte character is moved to the next line rather than split
+        let line = format!("{}å", "x".repeat(74));
+        assert_eq!(fold(&line), format!("{}\r\n å", "x".repeat(74)));
+    }
+
+    #[test]
+    fn test_render() {
+        let time = |hour| format!("2022-08-20T{:02}:00:00Z", hour).parse().unwrap();
+        let calendar = IcalCalendar {
+            program_version: "1.0.0".into(),
+            name: "Evenemang".into(),
+            generated: time(12),
+            events: vec![IcalEvent {
+                uid: "event-1@strecklistan".into(),
+                summary: "Sittning".into(),
+                description: "Pris: 150:-".into(),
+                location: "Hubben, Johanneberg".into(),
+                start: time(18),
+                end: time(23),
+            }],
+        };
+
+        let rendered = calendar.render();
+        assert!(rendered.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(rendered.ends_with("END:VCALENDAR\r\n"));
+        assert!(rendered.contains(
+            "BEGIN:VEVENT\r\n\
+             UID:event-1@strecklistan\r\n\
+             DTSTAMP:20220820T120000Z\r\n\
+             DTSTART:20220820T180000Z\r\n\
+             DTEND:20220820T230000Z\r\n\
+             SUMMARY:Sittning\r\n\
+             LOCATION:Hubben\\, Johanneberg\r\n\
+             DESCRIPTION:Pris: 150:-\r\n\
+             END:VEVENT\r\n"
+        ));
+    }
+}
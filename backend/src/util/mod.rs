@@ -1,7 +1,11 @@
+pub mod auth;
 mod catchers;
 pub mod file;
+pub mod ical;
 pub mod ord;
+pub mod param;
 pub mod ser;
+pub mod sie;
 pub mod status_json;
 pub mod testing;
 
@@ -0,0 +1,27 @@
+//! Parsing of query parameters which rocket can't parse for us
+
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Utc};
+use rocket::http::Status;
+use strecklistan_api::currency::Currency;
+
+/// Parse an optional RFC 3339 timestamp
+pub fn parse_time(time: Option<String>) -> Result<Option<DateTime<Utc>>, SJ> {
+    time.map(|time| {
+        DateTime::parse_from_rfc3339(&time)
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(|_| SJ::new(Status::BadRequest, "Invalid time, expected RFC 3339"))
+    })
+    .transpose()
+}
+
+/// Parse an optional amount of money, e.g. `42` or `123.45`
+pub fn parse_amount(amount: Option<String>) -> Result<Option<Currency>, SJ> {
+    amount
+        .map(|amount| {
+            amount
+                .parse()
+                .map_err(|_| SJ::new(Status::BadRequest, "Invalid amount"))
+        })
+        .transpose()
+}
@@ -21,6 +21,9 @@ use strum_macros::EnumIter;
 ///
 /// ## Usage
 /// ```
+/// use serde::Serialize;
+/// use strecklistan_backend::util::ser::{Ser, SerAccept};
+///
 /// #[derive(Serialize)]
 /// struct MyStruct {
 ///     hello: &'static str,
@@ -0,0 +1,261 @@
+//! Writer for the SIE4 file format, used to import accounting data into bookkeeping programs.
+//!
+//! See <https://sie.se/format/> for the specification.
+
+use chrono::NaiveDate;
+use std::fmt::Write;
+use strecklistan_api::book_account::BookAccountType;
+use strecklistan_api::currency::Currency;
+
+/// An account number in the BAS chart of accounts, e.g. 1930
+pub type BasNumber = u32;
+
+pub struct SieConfig {
+    /// The name of the organization, written as `#FNAMN`
+    pub organization_name: String,
+}
+
+pub struct SieAccount {
+    pub number: BasNumber,
+    pub name: String,
+    pub account_type: BookAccountType,
+}
+
+/// A verification, i.e. a transaction
+pub struct SieVoucher {
+    pub date: NaiveDate,
+    pub text: String,
+
+    /// The amount of each row. Debits are positive and credits are negative.
+    pub rows: Vec<(BasNumber, Currency)>,
+}
+
+pub struct SieFile {
+    pub program_version: String,
+    pub generated: NaiveDate,
+    pub organization_name: String,
+
+    /// The first and last day of the fiscal year
+    pub fiscal_year: (NaiveDate, NaiveDate),
+
+    pub accounts: Vec<SieAccount>,
+
+    /// Balances of asset and liability accounts at the start of the fiscal year
+    pub opening_balances: Vec<(BasNumber, Currency)>,
+
+    /// Balances of asset and liability accounts at the end of the fiscal year
+    pub closing_balances: Vec<(BasNumber, Currency)>,
+
+    /// Balances of revenue and expense accounts for the fiscal year
+    pub results: Vec<(BasNumber, Currency)>,
+
+    pub vouchers: Vec<SieVoucher>,
+}
+
+/// The SIE account type of a book account
+fn account_type_code(account_type: BookAccountType) -> char {
+    match account_type {
+        BookAccountType::Assets => 'T',
+        BookAccountType::Liabilities => 'S',
+        BookAccountType::Revenue => 'I',
+        BookAccountType::Expenses => 'K',
+    }
+}
+
+/// Quote and escape a string field
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            // control characters are not allowed in SIE files
+            c if c.is_control() => quoted.push(' '),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+/// Encode a string as code page 437, which is mandated by the SIE format.
+///
+/// Characters which can't be represented are replaced by `?`.
+pub fn encode_cp437(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            c if c.is_ascii() => c as u8,
+            'Ç' => 0x80,
+            'ü' => 0x81,
+            'é' => 0x82,
+            'â' => 0x83,
+            'ä' => 0x84,
+            'à' => 0x85,
+            'å' => 0x86,
+            'ç' => 0x87,
+            'ê' => 0x88,
+            'ë' => 0x89,
+            'è' => 0x8A,
+            'ï' => 0x8B,
+            'î' => 0x8C,
+            'ì' => 0x8D,
+            'Ä' => 0x8E,
+            'Å' => 0x8F,
+            'É' => 0x90,
+            'æ' => 0x91,
+            'Æ' => 0x92,
+            'ô' => 0x93,
+            'ö' => 0x94,
+            'ò' => 0x95,
+            'û' => 0x96,
+            'ù' => 0x97,
+            'ÿ' => 0x98,
+            'Ö' => 0x99,
+            'Ü' => 0x9A,
+            'á' => 0xA0,
+            'í' => 0xA1,
+            'ó' => 0xA2,
+            'ú' => 0xA3,
+            'ñ' => 0xA4,
+            'Ñ' => 0xA5,
+            _ => b'?',
+        })
+        .collect()
+}
+
+impl SieFile {
+    /// Render the file as text
+    pub fn render(&self) -> String {
+        // writing to a String can't fail
+        let mut out = String::new();
+        let (year_start, year_end) = self.fiscal_year;
+
+        writeln!(out, "#FLAGGA 0").unwrap();
+        writeln!(
+            out,
+            "#PROGRAM {} {}",
+            quote("strecklistan"),
+            quote(&self.program_version)
+        )
+        .unwrap();
+        writeln!(out, "#FORMAT PC8").unwrap();
+        writeln!(out, "#GEN {}", format_date(self.generated)).unwrap();
+        writeln!(out, "#SIETYP 4").unwrap();
+        writeln!(out, "#FNAMN {}", quote(&self.organization_name)).unwrap();
+        writeln!(
+            out,
+            "#RAR 0 {} {}",
+            format_date(year_start),
+            format_date(year_end)
+        )
+        .unwrap();
+
+        for account in &self.accounts {
+            writeln!(out, "#KONTO {} {}", account.number, quote(&account.name)).unwrap();
+            writeln!(
+                out,
+                "#KTYP {} {}",
+                account.number,
+                account_type_code(account.account_type)
+            )
+            .unwrap();
+        }
+
+        for (label, balances) in [
+            ("#IB", &self.opening_balances),
+            ("#UB", &self.closing_balances),
+            ("#RES", &self.results),
+        ] {
+            for (number, balance) in balances {
+                writeln!(out, "{} 0 {} {}", label, number, balance).unwrap();
+            }
+        }
+
+        for (i, voucher) in self.vouchers.iter().enumerate() {
+            writeln!(
+                out,
+                "#VER A {} {} {}",
+                i + 1,
+                format_date(voucher.date),
+                quote(&voucher.text)
+            )
+            .unwrap();
+            writeln!(out, "{{").unwrap();
+            for (number, amount) in &voucher.rows {
+                writeln!(out, "   #TRANS {} {{}} {}", number, amount).unwrap();
+            }
+            writeln!(out, "}}").unwrap();
+        }
+
+        out
+    }
+
+    /// Render the file and encode it as it should be stored
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_cp437(&self.render().replace('\n', "\r\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_cp437() {
+        assert_eq!(encode_cp437("Kaffe"), b"Kaffe");
+        assert_eq!(
+            encode_cp437("åäöÅÄÖ"),
+            vec![0x86, 0x84, 0x94, 0x8F, 0x8E, 0x99]
+        );
+        assert_eq!(encode_cp437("€"), b"?");
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("Bankkonto"), "\"Bankkonto\"");
+        assert_eq!(quote("12\" pizza"), "\"12\\\" pizza\"");
+        assert_eq!(quote("a\tb"), "\"a b\"");
+    }
+
+    #[test]
+    fn test_render() {
+        let date = |d| NaiveDate::from_ymd_opt(2022, 1, d).unwrap();
+        let file = SieFile {
+            program_version: "1.0.0".into(),
+            generated: date(31),
+            organization_name: "Föreningen".into(),
+            fiscal_year: (date(1), date(31)),
+            accounts: vec![SieAccount {
+                number: 1930,
+                name: "Bankkonto".into(),
+                account_type: BookAccountType::Assets,
+            }],
+            opening_balances: vec![(1930, 10000.into())],
+            closing_balances: vec![(1930, 14250.into())],
+            results: vec![(3000, (-4250).into())],
+            vouchers: vec![SieVoucher {
+                date: date(15),
+                text: "Försäljning".into(),
+                rows: vec![(1930, 4250.into()), (3000, (-4250).into())],
+            }],
+        };
+
+        let rendered = file.render();
+        assert!(rendered.starts_with("#FLAGGA 0\n"));
+        assert!(rendered.contains("#RAR 0 20220101 20220131\n"));
+        assert!(rendered.contains("#KONTO 1930 \"Bankkonto\"\n#KTYP 1930 T\n"));
+        assert!(rendered.contains("#IB 0 1930 100\n"));
+        assert!(rendered.contains("#UB 0 1930 142.50\n"));
+        assert!(rendered.contains("#RES 0 3000 -42.50\n"));
+        assert!(rendered.contains(
+            "#VER A 1 20220115 \"Försäljning\"\n{\n   #TRANS 1930 {} 42.50\n   #TRANS 3000 {} -42.50\n}\n"
+        ));
+    }
+}
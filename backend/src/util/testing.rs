@@ -2,10 +2,9 @@ use crate::database::{create_pool, DatabasePool};
 use crate::schema::tables::event_signups;
 use crate::schema::tables::events;
 use crate::schema::tables::users;
-use crate::Opt;
-use clap::Parser;
 use diesel::RunQueryDsl;
 use dotenv::dotenv;
+use std::env;
 
 pub struct DatabaseState {
     db_pool: DatabasePool,
@@ -14,8 +13,8 @@ pub struct DatabaseState {
 impl DatabaseState {
     pub fn new() -> (DatabaseState, DatabasePool) {
         dotenv().ok();
-        let opt = Opt::parse();
-        let db_pool = create_pool(&opt).expect("Could not create database pool");
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let db_pool = create_pool(&database_url).expect("Could not create database pool");
         let state = DatabaseState {
             db_pool: db_pool.clone(),
         };
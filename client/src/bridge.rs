@@ -0,0 +1,73 @@
+use crate::client::Client;
+use crate::error::{Error, Result};
+use futures::{SinkExt, StreamExt};
+use hyper::header::AUTHORIZATION;
+use strecklistan_api::izettle_bridge::{BridgeMessage, ServerMessage};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+/// A connection to the server as the iZettle payment bridge of a terminal.
+///
+/// The server sends the payments and refunds which the terminal should handle as
+/// [`ServerMessage`]s, and the bridge responds with [`BridgeMessage`]s.
+pub struct BridgeConnection {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl Client {
+    /// Connect to the bridge socket at `bridge_url`, e.g. `ws://localhost:8001`, as the bridge of
+    /// a registered terminal.
+    ///
+    /// The connection is authenticated as the current session, which must be a cashier. Its token
+    /// is sent in an `Authorization` header, which is unencrypted over `ws://`.
+    pub async fn connect_bridge(
+        &self,
+        bridge_url: &str,
+        terminal: &str,
+    ) -> Result<BridgeConnection> {
+        let url = format!("{}/{}", bridge_url.trim_end_matches('/'), terminal);
+        let mut request = url.as_str().into_client_request()?;
+
+        if let Some(token) = self.token() {
+            let header = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|e| Error::Request(e.into()))?;
+            request.headers_mut().insert(AUTHORIZATION, header);
+        }
+
+        let (socket, _) = connect_async(request).await?;
+        Ok(BridgeConnection { socket })
+    }
+}
+
+impl BridgeConnection {
+    /// Wait for the next message from the server.
+    ///
+    /// Returns `None` once the server has closed the connection.
+    pub async fn recv(&mut self) -> Result<Option<ServerMessage>> {
+        while let Some(message) = self.socket.next().await {
+            match message? {
+                Message::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+                Message::Close(_) => break,
+                _ => continue,
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Send a response to the server
+    pub async fn send(&mut self, message: &BridgeMessage) -> Result<()> {
+        let text = serde_json::to_string(message)?;
+        self.socket.send(Message::Text(text)).await?;
+        Ok(())
+    }
+
+    /// Close the connection
+    pub async fn close(mut self) -> Result<()> {
+        SinkExt::close(&mut self.socket).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use crate::encoding::Encoding;
+use crate::error::{ApiError, Error, Result};
+use hyper::body::Bytes;
+use hyper::client::HttpConnector;
+use hyper::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use hyper::{Body, Method, Request};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A client for the API of a strecklistan server.
+///
+/// Most routes require a logged in user. Log in with [`Client::login`], or pass the token of an
+/// existing session to [`Client::with_token`].
+#[derive(Clone)]
+pub struct Client {
+    http: hyper::Client<HttpConnector>,
+
+    /// Url of the server, e.g. `http://localhost:8000`
+    server: String,
+
+    encoding: Encoding,
+
+    /// The session token, sent in an `Authorization: Bearer <token>` header
+    token: Option<String>,
+}
+
+impl Client {
+    pub fn new(server: impl Into<String>) -> Self {
+        let server: String = server.into();
+        Client {
+            http: hyper::Client::new(),
+            server: server.trim_end_matches('/').to_string(),
+            encoding: Encoding::default(),
+            token: None,
+        }
+    }
+
+    /// Request responses in this format
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Authenticate as the session with this token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// The token of the current session, if logged in
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    pub(crate) fn set_token(&mut self, token: Option<String>) {
+        self.token = token;
+    }
+
+    /// Send a request to `/api<path>`, and return the body of a successful response
+    pub(crate) async fn execute(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Vec<u8>>,
+    ) -> Result<Bytes> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(format!("{}/api{}", self.server, path))
+            .header(ACCEPT, self.encoding.mime());
+
+        if let Some(token) = &self.token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+
+        let request = match body {
+            Some(body) => request
+                .header(CONTENT_TYPE, Encoding::Json.mime())
+                .body(Body::from(body))?,
+            None => request.body(Body::empty())?,
+        };
+
+        let response = self.http.request(request).await?;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+
+        if !status.is_success() {
+            return Err(Error::Api(ApiError::from_response(status, &body)));
+        }
+
+        Ok(body)
+    }
+
+    /// Send a request without a body, and decode the response
+    pub(crate) async fn send<T: DeserializeOwned>(&self, method: Method, path: &str) -> Result<T> {
+        let body = self.execute(method, path, None).await?;
+        self.encoding.decode(&body)
+    }
+
+    /// Send a request with a JSON body, and decode the response
+    pub(crate) async fn send_json<B, T>(&self, method: Method, path: &str, body: &B) -> Result<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let body = self
+            .execute(method, path, Some(serde_json::to_vec(body)?))
+            .await?;
+        self.encoding.decode(&body)
+    }
+
+    /// Send a request to a route which only responds with a status
+    pub(crate) async fn send_status<B>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<()>
+    where
+        B: Serialize + ?Sized,
+    {
+        let body = body.map(serde_json::to_vec).transpose()?;
+        self.execute(method, path, body).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(Method::GET, path).await
+    }
+}
@@ -0,0 +1,57 @@
+use crate::error::Result;
+use serde::de::DeserializeOwned;
+
+/// The format which responses are requested in, through the `Accept` header.
+///
+/// Request bodies are always sent as JSON.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+impl Encoding {
+    pub fn mime(self) -> &'static str {
+        match self {
+            Encoding::Json => "application/json",
+            Encoding::MsgPack => "application/msgpack",
+        }
+    }
+
+    pub(crate) fn decode<T: DeserializeOwned>(self, body: &[u8]) -> Result<T> {
+        Ok(match self {
+            Encoding::Json => serde_json::from_slice(body)?,
+            Encoding::MsgPack => rmp_serde::from_slice(body)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strecklistan_api::currency::Currency;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_decode() {
+        let transaction = NewTransaction {
+            description: Some("Insättning".to_string()),
+            bundles: vec![],
+            debited_account: 1,
+            credited_account: 2,
+            amount: Currency::from(10050),
+        };
+
+        let json = serde_json::to_vec(&transaction).unwrap();
+        let decoded: NewTransaction = Encoding::Json.decode(&json).unwrap();
+        assert_eq!(decoded, transaction);
+
+        // the server encodes MessagePack the same way
+        let msgpack = rmp_serde::to_vec(&transaction).unwrap();
+        let decoded: NewTransaction = Encoding::MsgPack.decode(&msgpack).unwrap();
+        assert_eq!(decoded, transaction);
+
+        assert!(Encoding::MsgPack.decode::<NewTransaction>(&json).is_err());
+    }
+}
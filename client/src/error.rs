@@ -0,0 +1,179 @@
+use hyper::StatusCode;
+use serde::Deserialize;
+use std::fmt::{self, Display};
+use tokio_tungstenite::tungstenite;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The server responded with an error
+    Api(ApiError),
+
+    /// The request could not be built, e.g. because of an invalid url
+    Request(hyper::http::Error),
+
+    /// The server could not be reached, or the connection failed
+    Http(hyper::Error),
+
+    Json(serde_json::Error),
+    MsgPack(rmp_serde::decode::Error),
+
+    /// The bridge connection could not be established, or it failed
+    ///
+    /// Boxed, since the WebSocket errors are much larger than the other errors.
+    WebSocket(Box<tungstenite::Error>),
+}
+
+/// An error response from the server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiError {
+    pub kind: ApiErrorKind,
+    pub status: StatusCode,
+    pub description: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiErrorKind {
+    BadRequest,
+
+    /// Not logged in, or the session has expired
+    Unauthorized,
+
+    /// A tillgodo account would be overdrawn beyond its credit limit
+    CreditLimitExceeded,
+
+    /// The logged in user does not have the role required by the route
+    Forbidden,
+    NotFound,
+    Conflict,
+    Server,
+    Other,
+}
+
+/// The body of an error response, see `StatusJson` in the backend
+#[derive(Deserialize)]
+struct StatusJson {
+    description: String,
+}
+
+impl ApiErrorKind {
+    pub fn of(status: StatusCode) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => ApiErrorKind::BadRequest,
+            StatusCode::UNAUTHORIZED => ApiErrorKind::Unauthorized,
+            StatusCode::PAYMENT_REQUIRED => ApiErrorKind::CreditLimitExceeded,
+            StatusCode::FORBIDDEN => ApiErrorKind::Forbidden,
+            StatusCode::NOT_FOUND => ApiErrorKind::NotFound,
+            StatusCode::CONFLICT => ApiErrorKind::Conflict,
+            status if status.is_server_error() => ApiErrorKind::Server,
+            _ => ApiErrorKind::Other,
+        }
+    }
+}
+
+impl ApiError {
+    /// Read the description from the body of an error response.
+    ///
+    /// Falls back to the body as text, or the reason of the status if the body is empty.
+    pub fn from_response(status: StatusCode, body: &[u8]) -> Self {
+        let description = match serde_json::from_slice::<StatusJson>(body) {
+            Ok(status_json) => status_json.description,
+            Err(_) if body.is_empty() => status.canonical_reason().unwrap_or_default().to_string(),
+            Err(_) => String::from_utf8_lossy(body).into_owned(),
+        };
+
+        ApiError {
+            kind: ApiErrorKind::of(status),
+            status,
+            description,
+        }
+    }
+}
+
+impl Error {
+    /// The kind of error response, if the server responded with an error
+    pub fn api_kind(&self) -> Option<ApiErrorKind> {
+        match self {
+            Error::Api(error) => Some(error.kind),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.status, self.description)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Api(e) => write!(f, "The server responded with {}", e),
+            Error::Request(e) => write!(f, "Invalid request: {}", e),
+            Error::Http(e) => write!(f, "Request failed: {}", e),
+            Error::Json(e) => write!(f, "Failed to decode JSON response: {}", e),
+            Error::MsgPack(e) => write!(f, "Failed to decode MessagePack response: {}", e),
+            Error::WebSocket(e) => write!(f, "Bridge connection failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<hyper::http::Error> for Error {
+    fn from(e: hyper::http::Error) -> Self {
+        Error::Request(e)
+    }
+}
+
+impl From<hyper::Error> for Error {
+    fn from(e: hyper::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<rmp_serde::decode::Error> for Error {
+    fn from(e: rmp_serde::decode::Error) -> Self {
+        Error::MsgPack(e)
+    }
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(e: tungstenite::Error) -> Self {
+        Error::WebSocket(Box::new(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_response() {
+        let error = ApiError::from_response(
+            StatusCode::PAYMENT_REQUIRED,
+            br#"{"status": 402, "description": "The credit limit of Kalle would be exceeded"}"#,
+        );
+        assert_eq!(error.kind, ApiErrorKind::CreditLimitExceeded);
+        assert_eq!(
+            error.description,
+            "The credit limit of Kalle would be exceeded"
+        );
+
+        let error = ApiError::from_response(StatusCode::NOT_FOUND, b"");
+        assert_eq!(error.kind, ApiErrorKind::NotFound);
+        assert_eq!(error.description, "Not Found");
+
+        let error = ApiError::from_response(StatusCode::BAD_GATEWAY, b"upstream error");
+        assert_eq!(error.kind, ApiErrorKind::Server);
+        assert_eq!(error.description, "upstream error");
+    }
+}
@@ -0,0 +1,40 @@
+//! An async client for the API of strecklistan.
+//!
+//! Every route has a typed method on [`Client`], which takes and returns the models of
+//! `strecklistan_api`. Responses are requested as JSON or MessagePack, see [`Encoding`], and
+//! error responses are turned into an [`ApiError`].
+//!
+//! A payment bridge can also connect to the server as a terminal, see [`Client::connect_bridge`].
+//!
+//! ```no_run
+//! use strecklistan_api::user::Credentials;
+//! use strecklistan_client::{Client, Encoding};
+//!
+//! async fn print_members() -> Result<(), strecklistan_client::Error> {
+//!     let mut client = Client::new("http://localhost:8000").with_encoding(Encoding::MsgPack);
+//!     client
+//!         .login(&Credentials {
+//!             name: "admin".to_string(),
+//!             password: "password".to_string(),
+//!         })
+//!         .await?;
+//!
+//!     for member in client.get_members().await?.values() {
+//!         println!("{} {}", member.first_name, member.last_name);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+mod bridge;
+mod client;
+mod encoding;
+mod error;
+mod query;
+mod routes;
+
+pub use bridge::BridgeConnection;
+pub use client::Client;
+pub use encoding::Encoding;
+pub use error::{ApiError, ApiErrorKind, Error, Result};
+pub use query::TransactionQuery;
@@ -0,0 +1,146 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+use strecklistan_api::book_account::BookAccountId;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::transaction::TransactionId;
+
+/// The query string of a request
+#[derive(Default)]
+pub(crate) struct Query {
+    params: Vec<(&'static str, String)>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Query::default()
+    }
+
+    pub fn param(mut self, key: &'static str, value: impl ToString) -> Self {
+        self.params.push((key, value.to_string()));
+        self
+    }
+
+    pub fn opt_param(self, key: &'static str, value: Option<impl ToString>) -> Self {
+        match value {
+            Some(value) => self.param(key, value),
+            None => self,
+        }
+    }
+
+    /// Append the query string to `path`
+    pub fn to_path(&self, path: &str) -> String {
+        let mut out = path.to_string();
+        for (i, (key, value)) in self.params.iter().enumerate() {
+            let separator = if i == 0 { '?' } else { '&' };
+            // writing to a String can't fail
+            write!(out, "{}{}={}", separator, key, encode(value)).unwrap();
+        }
+        out
+    }
+}
+
+/// Percent-encode everything except the unreserved characters of RFC 3986
+pub(crate) fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => write!(encoded, "%{:02X}", byte).unwrap(),
+        }
+    }
+    encoded
+}
+
+/// Filters of GET `/transactions` and GET `/transactions/page`. Everything is included by default.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionQuery {
+    /// Only include the transaction with this id
+    pub id: Option<TransactionId>,
+
+    /// Only include transactions made at or after this time
+    pub from: Option<DateTime<Utc>>,
+
+    /// Only include transactions made before this time
+    pub to: Option<DateTime<Utc>>,
+
+    /// Only include transactions which debit or credit this account
+    pub account: Option<BookAccountId>,
+
+    /// Only include transactions which contain this inventory item
+    pub item: Option<InventoryItemId>,
+
+    pub min_amount: Option<Currency>,
+    pub max_amount: Option<Currency>,
+
+    /// Only include transactions whose description contains this string
+    pub description: Option<String>,
+
+    /// Only include transactions which have been deleted
+    pub deleted: bool,
+}
+
+impl TransactionQuery {
+    pub(crate) fn to_query(&self) -> Query {
+        Query::new()
+            .opt_param("id", self.id)
+            .opt_param("from", self.from.map(|time| time.to_rfc3339()))
+            .opt_param("to", self.to.map(|time| time.to_rfc3339()))
+            .opt_param("account", self.account)
+            .opt_param("item", self.item)
+            .opt_param("min_amount", self.min_amount)
+            .opt_param("max_amount", self.max_amount)
+            .opt_param("description", self.description.as_ref())
+            .opt_param("deleted", if self.deleted { Some(true) } else { None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("kassa1"), "kassa1");
+        assert_eq!(encode("a b&c=d"), "a%20b%26c%3Dd");
+        assert_eq!(
+            encode("2022-07-16T12:00:00+00:00"),
+            "2022-07-16T12%3A00%3A00%2B00%3A00"
+        );
+        assert_eq!(encode("fika/öl"), "fika%2F%C3%B6l");
+    }
+
+    #[test]
+    fn test_transaction_query() {
+        let query = TransactionQuery::default();
+        assert_eq!(query.to_query().to_path("/transactions"), "/transactions");
+
+        let query = TransactionQuery {
+            from: Some("2022-07-01T00:00:00Z".parse().unwrap()),
+            account: Some(4),
+            min_amount: Some(Currency::from(1050)),
+            description: Some("kaffe & bulle".to_string()),
+            deleted: true,
+            ..TransactionQuery::default()
+        };
+        assert_eq!(
+            query
+                .to_query()
+                .param("limit", 20)
+                .to_path("/transactions/page"),
+            "/transactions/page?from=2022-07-01T00%3A00%3A00%2B00%3A00&account=4\
+             &min_amount=10.50&description=kaffe%20%26%20bulle&deleted=true&limit=20"
+        );
+
+        let query = TransactionQuery {
+            id: Some(7),
+            ..TransactionQuery::default()
+        };
+        assert_eq!(
+            query.to_query().to_path("/transactions/page"),
+            "/transactions/page?id=7"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use crate::client::Client;
+use crate::error::Result;
+use hyper::Method;
+use strecklistan_api::user::{Credentials, NewUser, Session, User};
+
+impl Client {
+    /// POST `/login`
+    ///
+    /// Log in, and authenticate the following requests as the new session
+    pub async fn login(&mut self, credentials: &Credentials) -> Result<Session> {
+        let session: Session = self.send_json(Method::POST, "/login", credentials).await?;
+        self.set_token(Some(session.token.clone()));
+        Ok(session)
+    }
+
+    /// POST `/logout`
+    pub async fn logout(&mut self) -> Result<()> {
+        self.send::<()>(Method::POST, "/logout").await?;
+        self.set_token(None);
+        Ok(())
+    }
+
+    /// GET `/session`
+    ///
+    /// Returns the logged in user
+    pub async fn get_session(&self) -> Result<User> {
+        self.get("/session").await
+    }
+
+    /// GET `/users`
+    pub async fn get_users(&self) -> Result<Vec<User>> {
+        self.get("/users").await
+    }
+
+    /// POST `/user`
+    pub async fn add_user(&self, user: &NewUser) -> Result<User> {
+        self.send_json(Method::POST, "/user", user).await
+    }
+
+    /// GET `/version`
+    ///
+    /// Returns the version of the server
+    pub async fn get_version(&self) -> Result<String> {
+        let body = self.execute(Method::GET, "/version", None).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+}
@@ -0,0 +1,55 @@
+use crate::client::Client;
+use crate::error::Result;
+use crate::query::Query;
+use chrono::{DateTime, Utc};
+use hyper::Method;
+use std::collections::HashMap;
+use strecklistan_api::book_account::{
+    BookAccount, BookAccountId, CreditPolicy, MasterAccounts, NewBookAccount,
+};
+use strecklistan_api::currency::Currency;
+
+impl Client {
+    /// GET `/book_accounts`
+    ///
+    /// Returns all book accounts with their balances, as of `at` if set
+    pub async fn get_accounts(
+        &self,
+        at: Option<DateTime<Utc>>,
+    ) -> Result<HashMap<BookAccountId, BookAccount>> {
+        let query = Query::new().opt_param("at", at.map(|at| at.to_rfc3339()));
+        self.get(&query.to_path("/book_accounts")).await
+    }
+
+    /// GET `/book_accounts/masters`
+    pub async fn get_master_accounts(&self) -> Result<MasterAccounts> {
+        self.get("/book_accounts/masters").await
+    }
+
+    /// POST `/book_account`
+    pub async fn add_account(&self, account: &NewBookAccount) -> Result<BookAccountId> {
+        self.send_json(Method::POST, "/book_account", account).await
+    }
+
+    /// GET `/credit_policy`
+    pub async fn get_credit_policy(&self) -> Result<CreditPolicy> {
+        self.get("/credit_policy").await
+    }
+
+    /// PUT `/credit_policy`
+    pub async fn put_credit_policy(&self, policy: &CreditPolicy) -> Result<CreditPolicy> {
+        self.send_json(Method::PUT, "/credit_policy", policy).await
+    }
+
+    /// PUT `/book_account/<id>/credit_limit`
+    ///
+    /// Set the credit limit of a tillgodo account, or use the default limit if `None`
+    pub async fn put_credit_limit(
+        &self,
+        id: BookAccountId,
+        limit: Option<Currency>,
+    ) -> Result<Option<Currency>> {
+        let path = format!("/book_account/{}/credit_limit", id);
+        self.send_json(Method::PUT, &path, &limit).await
+    }
+}
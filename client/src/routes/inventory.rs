@@ -0,0 +1,75 @@
+use crate::client::Client;
+use crate::error::Result;
+use hyper::Method;
+use std::collections::HashMap;
+use strecklistan_api::inventory::{
+    InventoryBundle, InventoryBundleId, InventoryItemId, InventoryItemStock, InventoryItemTag,
+    NewInventoryBundle, NewInventoryItem,
+};
+
+impl Client {
+    /// GET `/inventory/items`
+    pub async fn get_items(&self) -> Result<HashMap<InventoryItemId, InventoryItemStock>> {
+        self.get("/inventory/items").await
+    }
+
+    /// GET `/inventory/items/deleted`
+    pub async fn get_deleted_items(&self) -> Result<Vec<InventoryItemStock>> {
+        self.get("/inventory/items/deleted").await
+    }
+
+    /// POST `/inventory/item`
+    pub async fn post_item(&self, item: &NewInventoryItem) -> Result<InventoryItemId> {
+        self.send_json(Method::POST, "/inventory/item", item).await
+    }
+
+    /// PUT `/inventory/item/<id>`
+    pub async fn put_item(&self, id: InventoryItemId, item: &NewInventoryItem) -> Result<()> {
+        let path = format!("/inventory/item/{}", id);
+        self.send_status(Method::PUT, &path, Some(item)).await
+    }
+
+    /// DELETE `/inventory/item/<id>`
+    pub async fn delete_item(&self, id: InventoryItemId) -> Result<()> {
+        let path = format!("/inventory/item/{}", id);
+        self.send_status::<()>(Method::DELETE, &path, None).await
+    }
+
+    /// POST `/inventory/item/<id>/restore`
+    pub async fn restore_item(&self, id: InventoryItemId) -> Result<()> {
+        let path = format!("/inventory/item/{}/restore", id);
+        self.send_status::<()>(Method::POST, &path, None).await
+    }
+
+    /// GET `/inventory/tags`
+    pub async fn get_tags(&self) -> Result<Vec<InventoryItemTag>> {
+        self.get("/inventory/tags").await
+    }
+
+    /// GET `/inventory/bundles`
+    pub async fn get_bundles(&self) -> Result<HashMap<InventoryBundleId, InventoryBundle>> {
+        self.get("/inventory/bundles").await
+    }
+
+    /// POST `/inventory/bundle`
+    pub async fn post_bundle(&self, bundle: &NewInventoryBundle) -> Result<InventoryBundleId> {
+        self.send_json(Method::POST, "/inventory/bundle", bundle)
+            .await
+    }
+
+    /// PUT `/inventory/bundle/<id>`
+    pub async fn put_bundle(
+        &self,
+        id: InventoryBundleId,
+        bundle: &NewInventoryBundle,
+    ) -> Result<()> {
+        let path = format!("/inventory/bundle/{}", id);
+        self.send_status(Method::PUT, &path, Some(bundle)).await
+    }
+
+    /// DELETE `/inventory/bundle/<id>`
+    pub async fn delete_bundle(&self, id: InventoryBundleId) -> Result<()> {
+        let path = format!("/inventory/bundle/{}", id);
+        self.send_status::<()>(Method::DELETE, &path, None).await
+    }
+}
@@ -0,0 +1,94 @@
+use crate::client::Client;
+use crate::error::Result;
+use crate::query::{encode, Query};
+use hyper::Method;
+use strecklistan_api::izettle::{
+    IZettlePayment, IZettleRefund, IZettleRefundId, IZettleTerminal, IZettleTerminalId,
+    NewIZettleTerminal, PendingIZettlePayment,
+};
+use strecklistan_api::sale::NewSale;
+use strecklistan_api::transaction::{NewTransaction, TransactionId};
+
+impl Client {
+    /// POST `/izettle/client/transaction`
+    ///
+    /// Start a card payment, charged by `terminal` or by any connected terminal. Returns the
+    /// reference to poll the payment with.
+    pub async fn begin_izettle_transaction(
+        &self,
+        transaction: &NewTransaction,
+        terminal: Option<&str>,
+    ) -> Result<i32> {
+        let path = terminal_query(terminal).to_path("/izettle/client/transaction");
+        self.send_json(Method::POST, &path, transaction).await
+    }
+
+    /// POST `/izettle/client/sale`
+    ///
+    /// Start a sale where the first payment is charged by card. Returns the reference to poll the
+    /// payment with.
+    pub async fn begin_izettle_sale(&self, sale: &NewSale, terminal: Option<&str>) -> Result<i32> {
+        let path = terminal_query(terminal).to_path("/izettle/client/sale");
+        self.send_json(Method::POST, &path, sale).await
+    }
+
+    /// GET `/izettle/client/poll/<reference>`
+    ///
+    /// Wait for the outcome of a card payment
+    pub async fn poll_izettle(&self, reference: i32) -> Result<IZettlePayment> {
+        self.get(&format!("/izettle/client/poll/{}", reference))
+            .await
+    }
+
+    /// GET `/izettle/pending`
+    pub async fn get_pending_izettle_payments(&self) -> Result<Vec<PendingIZettlePayment>> {
+        self.get("/izettle/pending").await
+    }
+
+    /// POST `/izettle/pending/<reference>/cancel`
+    pub async fn cancel_pending_izettle_payment(&self, reference: i32) -> Result<()> {
+        let path = format!("/izettle/pending/{}/cancel", reference);
+        self.send_status::<()>(Method::POST, &path, None).await
+    }
+
+    /// GET `/izettle/terminals`
+    pub async fn get_izettle_terminals(&self) -> Result<Vec<IZettleTerminal>> {
+        self.get("/izettle/terminals").await
+    }
+
+    /// POST `/izettle/terminals`
+    pub async fn add_izettle_terminal(&self, terminal: &NewIZettleTerminal) -> Result<()> {
+        self.send_status(Method::POST, "/izettle/terminals", Some(terminal))
+            .await
+    }
+
+    /// DELETE `/izettle/terminal/<id>`
+    pub async fn delete_izettle_terminal(&self, id: &IZettleTerminalId) -> Result<()> {
+        let path = format!("/izettle/terminal/{}", encode(id));
+        self.send_status::<()>(Method::DELETE, &path, None).await
+    }
+
+    /// POST `/transaction/<id>/refund`
+    ///
+    /// Refund a card payment through `terminal`, or through any connected terminal
+    pub async fn request_refund(
+        &self,
+        transaction_id: TransactionId,
+        terminal: Option<&str>,
+    ) -> Result<IZettleRefundId> {
+        let path =
+            terminal_query(terminal).to_path(&format!("/transaction/{}/refund", transaction_id));
+        self.send(Method::POST, &path).await
+    }
+
+    /// GET `/izettle/client/refund/<id>`
+    ///
+    /// Wait for the outcome of a refund
+    pub async fn poll_refund(&self, id: IZettleRefundId) -> Result<IZettleRefund> {
+        self.get(&format!("/izettle/client/refund/{}", id)).await
+    }
+}
+
+fn terminal_query(terminal: Option<&str>) -> Query {
+    Query::new().opt_param("terminal", terminal)
+}
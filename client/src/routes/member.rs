@@ -0,0 +1,56 @@
+use crate::client::Client;
+use crate::error::Result;
+use crate::query::Query;
+use hyper::Method;
+use std::collections::HashMap;
+use strecklistan_api::book_account::BookAccountId;
+use strecklistan_api::member::{EditMember, Member, MemberId, NewMember};
+
+impl Client {
+    /// GET `/members`
+    pub async fn get_members(&self) -> Result<HashMap<MemberId, Member>> {
+        self.get("/members").await
+    }
+
+    /// POST `/add_member_with_book_account`
+    ///
+    /// Add a member along with a tillgodo account called `account_name`
+    pub async fn add_member(
+        &self,
+        member: &NewMember,
+        account_name: &str,
+    ) -> Result<(MemberId, BookAccountId)> {
+        let path = "/add_member_with_book_account";
+        self.send_json(Method::POST, path, &(member, account_name))
+            .await
+    }
+
+    /// PUT `/member/<id>`
+    pub async fn put_member(&self, id: MemberId, edit: &EditMember) -> Result<Member> {
+        self.send_json(Method::PUT, &format!("/member/{}", id), edit)
+            .await
+    }
+
+    /// DELETE `/member/<id>`
+    ///
+    /// Deactivate a member, its history is kept
+    pub async fn deactivate_member(&self, id: MemberId) -> Result<Member> {
+        self.send(Method::DELETE, &format!("/member/{}", id)).await
+    }
+
+    /// POST `/member/<id>/reactivate`
+    pub async fn reactivate_member(&self, id: MemberId) -> Result<Member> {
+        self.send(Method::POST, &format!("/member/{}/reactivate", id))
+            .await
+    }
+
+    /// POST `/member/<id>/merge`
+    ///
+    /// Merge a duplicate member into the member `into`, which is returned
+    pub async fn merge_member(&self, id: MemberId, into: MemberId) -> Result<MemberId> {
+        let path = Query::new()
+            .param("into", into)
+            .to_path(&format!("/member/{}/merge", id));
+        self.send(Method::POST, &path).await
+    }
+}
@@ -0,0 +1,9 @@
+//! The routes of the API, grouped like the route modules of the backend.
+
+mod auth;
+mod book_account;
+mod inventory;
+mod izettle;
+mod member;
+mod receipt;
+mod transaction;
@@ -0,0 +1,15 @@
+use crate::client::Client;
+use crate::error::Result;
+use hyper::Method;
+use strecklistan_api::transaction::TransactionId;
+
+impl Client {
+    /// GET `/receipt/<transaction_id>`
+    ///
+    /// Returns the receipt of a transaction as a printable HTML page
+    pub async fn get_receipt(&self, transaction_id: TransactionId) -> Result<String> {
+        let path = format!("/receipt/{}", transaction_id);
+        let body = self.execute(Method::GET, &path, None).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+}
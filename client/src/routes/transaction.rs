@@ -0,0 +1,109 @@
+use crate::client::Client;
+use crate::error::Result;
+use crate::query::{Query, TransactionQuery};
+use hyper::Method;
+use strecklistan_api::sale::{NewSale, Sale, SaleId};
+use strecklistan_api::transaction::{
+    NewTransaction, Transaction, TransactionId, TransactionPage, TransactionRevision,
+    TransactionRevisionId,
+};
+
+impl Client {
+    /// GET `/transactions`
+    ///
+    /// Returns all transactions matching the query, newest first
+    pub async fn get_transactions(&self, query: &TransactionQuery) -> Result<Vec<Transaction>> {
+        self.get(&query.to_query().to_path("/transactions")).await
+    }
+
+    /// GET `/transactions/page`
+    ///
+    /// Returns at most `limit` transactions matching the query, newest first. Pass the
+    /// [`next`](TransactionPage::next) of a page as `after` to get the page after it.
+    pub async fn get_transaction_page(
+        &self,
+        query: &TransactionQuery,
+        after: Option<TransactionId>,
+        limit: Option<u32>,
+    ) -> Result<TransactionPage> {
+        let path = query
+            .to_query()
+            .opt_param("after", after)
+            .opt_param("limit", limit)
+            .to_path("/transactions/page");
+        self.get(&path).await
+    }
+
+    /// POST `/transaction`
+    ///
+    /// Fails with [`CreditLimitExceeded`](crate::ApiErrorKind::CreditLimitExceeded) if a tillgodo
+    /// account would be overdrawn, unless `override_credit_limit` is set by a treasurer.
+    pub async fn post_transaction(
+        &self,
+        transaction: &NewTransaction,
+        override_credit_limit: bool,
+    ) -> Result<TransactionId> {
+        let path = override_query(override_credit_limit).to_path("/transaction");
+        self.send_json(Method::POST, &path, transaction).await
+    }
+
+    /// PUT `/transaction/<id>`
+    ///
+    /// Amend a transaction. Returns the id of the revision which holds the previous version.
+    ///
+    /// Fails with [`CreditLimitExceeded`](crate::ApiErrorKind::CreditLimitExceeded) if the change
+    /// would overdraw a tillgodo account, unless `override_credit_limit` is set.
+    pub async fn put_transaction(
+        &self,
+        id: TransactionId,
+        transaction: &NewTransaction,
+        override_credit_limit: bool,
+    ) -> Result<TransactionRevisionId> {
+        let path = override_query(override_credit_limit).to_path(&format!("/transaction/{}", id));
+        self.send_json(Method::PUT, &path, transaction).await
+    }
+
+    /// GET `/transaction/<id>/history`
+    pub async fn get_transaction_history(
+        &self,
+        id: TransactionId,
+    ) -> Result<Vec<TransactionRevision>> {
+        self.get(&format!("/transaction/{}/history", id)).await
+    }
+
+    /// DELETE `/transaction/<id>`
+    pub async fn delete_transaction(&self, id: TransactionId) -> Result<TransactionId> {
+        self.send(Method::DELETE, &format!("/transaction/{}", id))
+            .await
+    }
+
+    /// POST `/transaction/<id>/restore`
+    pub async fn restore_transaction(&self, id: TransactionId) -> Result<TransactionId> {
+        self.send(Method::POST, &format!("/transaction/{}/restore", id))
+            .await
+    }
+
+    /// POST `/sale`
+    ///
+    /// Record a purchase which is paid from several accounts
+    pub async fn post_sale(&self, sale: &NewSale, override_credit_limit: bool) -> Result<Sale> {
+        let path = override_query(override_credit_limit).to_path("/sale");
+        self.send_json(Method::POST, &path, sale).await
+    }
+
+    /// GET `/sale/<id>`
+    pub async fn get_sale(&self, id: SaleId) -> Result<Sale> {
+        self.get(&format!("/sale/{}", id)).await
+    }
+}
+
+fn override_query(override_credit_limit: bool) -> Query {
+    Query::new().opt_param(
+        "override_credit_limit",
+        if override_credit_limit {
+            Some(true)
+        } else {
+            None
+        },
+    )
+}
@@ -33,6 +33,10 @@ pub struct BookAccount {
     pub account_type: BookAccountType,
     pub creditor: Option<MemberId>,
     pub balance: Currency,
+
+    /// How far below zero the balance of a tillgodo account may go.
+    /// If `None`, the default credit limit of the [CreditPolicy] applies.
+    pub credit_limit: Option<Currency>,
 }
 
 impl PartialEq for BookAccount {
@@ -67,9 +71,52 @@ pub struct MasterAccounts {
     pub cash_account_id: BookAccountId,
     pub sales_account_id: BookAccountId,
     pub purchases_account_id: BookAccountId,
+    pub shrinkage_account_id: BookAccountId,
+
+    /// Where differences between the counted and the expected cash are booked
+    pub cash_difference_account_id: BookAccountId,
+
+    /// Where event tickets are booked
+    pub events_account_id: BookAccountId,
+}
+
+/// The credit limit of tillgodo accounts which don't have one of their own
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CreditPolicy {
+    /// How far below zero the balance may go, or `None` if there is no limit
+    pub default_credit_limit: Option<Currency>,
 }
 
 impl BookAccount {
+    /// Whether this is the tillgodo account of a member
+    pub fn is_tillgodo(&self) -> bool {
+        self.creditor.is_some() && self.account_type == BookAccountType::Liabilities
+    }
+
+    /// How far below zero the balance may go, or `None` if there is no limit.
+    ///
+    /// Only tillgodo accounts have a credit limit.
+    pub fn effective_credit_limit(&self, policy: &CreditPolicy) -> Option<Currency> {
+        if !self.is_tillgodo() {
+            return None;
+        }
+
+        self.credit_limit.or(policy.default_credit_limit)
+    }
+
+    /// Whether debiting the account with `amount` would put the balance further below zero than
+    /// the credit limit allows
+    pub fn exceeds_credit_limit(&self, amount: Currency, policy: &CreditPolicy) -> bool {
+        match self.effective_credit_limit(policy) {
+            Some(limit) => {
+                amount > Currency::from(0) && self.balance + self.debit_diff(amount) < -limit
+            }
+            None => false,
+        }
+    }
+
     pub fn credit_diff(&self, amount: Currency) -> Currency {
         self.debit_diff(-amount)
     }
@@ -89,3 +136,49 @@ impl BookAccount {
         self.balance += self.debit_diff(amount);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tillgodo(balance: i32, credit_limit: Option<i32>) -> BookAccount {
+        BookAccount {
+            id: 1,
+            name: "Tillgodo".to_string(),
+            account_type: BookAccountType::Liabilities,
+            creditor: Some(1),
+            balance: balance.into(),
+            credit_limit: credit_limit.map(Currency::from),
+        }
+    }
+
+    #[test]
+    fn test_credit_limit() {
+        let policy = CreditPolicy {
+            default_credit_limit: Some(0.into()),
+        };
+        let unlimited = CreditPolicy {
+            default_credit_limit: None,
+        };
+
+        let account = tillgodo(2000, None);
+        assert!(!account.exceeds_credit_limit(2000.into(), &policy));
+        assert!(account.exceeds_credit_limit(2001.into(), &policy));
+        assert!(!account.exceeds_credit_limit(5000.into(), &unlimited));
+
+        let account = tillgodo(-1000, Some(1500));
+        assert!(!account.exceeds_credit_limit(500.into(), &policy));
+        assert!(account.exceeds_credit_limit(501.into(), &unlimited));
+
+        // crediting is always allowed, even beyond the limit
+        let account = tillgodo(-3000, Some(1500));
+        assert!(!account.exceeds_credit_limit((-1000).into(), &policy));
+
+        let bank = BookAccount {
+            account_type: BookAccountType::Assets,
+            creditor: None,
+            ..tillgodo(0, Some(0))
+        };
+        assert!(!bank.exceeds_credit_limit(100000.into(), &policy));
+    }
+}
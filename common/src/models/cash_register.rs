@@ -0,0 +1,64 @@
+use crate::currency::Currency;
+use crate::transaction::TransactionId;
+use crate::user::UserName;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type CashRegisterSessionId = i32;
+
+/// A shift at the cash register, from counting the opening float to counting the cash at closing
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CashRegisterSession {
+    pub id: CashRegisterSessionId,
+    pub opened_at: DateTime<Utc>,
+    pub opened_by: Option<UserName>,
+
+    /// The cash in the register when the session was opened
+    pub opening_float: Currency,
+
+    /// The correction transaction created if the opening float didn't match the cash account
+    pub opening_transaction_id: Option<TransactionId>,
+
+    pub closed_at: Option<DateTime<Utc>>,
+    pub closed_by: Option<UserName>,
+
+    /// The opening float plus all cash transactions made during the session.
+    /// For an open session this is the amount expected right now.
+    pub expected_cash: Currency,
+
+    /// The cash in the register when the session was closed
+    pub counted_cash: Option<Currency>,
+
+    /// The correction transaction created if the counted cash didn't match the expected cash
+    pub closing_transaction_id: Option<TransactionId>,
+}
+
+impl CashRegisterSession {
+    pub fn is_open(&self) -> bool {
+        self.closed_at.is_none()
+    }
+
+    /// How much the counted cash was over (positive) or short (negative)
+    pub fn difference(&self) -> Option<Currency> {
+        self.counted_cash
+            .map(|counted| counted - self.expected_cash)
+    }
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct OpenCashRegisterSession {
+    pub opening_float: Currency,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CloseCashRegisterSession {
+    pub counted_cash: Currency,
+}
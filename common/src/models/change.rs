@@ -0,0 +1,42 @@
+use crate::member::MemberId;
+use crate::transaction::TransactionId;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// A notification that some data on the server has changed, published on `/api/changes`
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChangeEvent {
+    TransactionCreated {
+        id: TransactionId,
+    },
+
+    /// The transaction was amended or restored
+    TransactionChanged {
+        id: TransactionId,
+    },
+
+    TransactionDeleted {
+        id: TransactionId,
+    },
+
+    /// Items or bundles were created, changed or deleted
+    InventoryChanged,
+
+    MemberAdded {
+        id: MemberId,
+    },
+
+    /// The member was edited, deactivated, reactivated or merged into another member
+    MemberChanged {
+        id: MemberId,
+    },
+
+    /// The default credit limit, or the credit limit of an account, was changed
+    CreditLimitsChanged,
+
+    /// Some events were missed, all resources should be refetched
+    Lagged,
+}
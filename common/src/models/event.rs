@@ -0,0 +1,277 @@
+use crate::currency::Currency;
+use crate::member::MemberId;
+use crate::transaction::TransactionId;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "diesel_impl")]
+use diesel_derives::Queryable;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type EventId = i32;
+pub type EventSignupId = i32;
+
+/// An event, along with the number of people signed up for it
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Event {
+    pub id: EventId,
+    pub title: String,
+    pub background: String,
+    pub location: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub price: i32,
+    pub published: bool,
+
+    /// How many may attend, later signups are put on the waitlist. `None` if there is no limit.
+    pub capacity: Option<i32>,
+
+    /// Paid signups are refunded if cancelled before this time. `None` means until the event
+    /// starts.
+    pub cancellation_deadline: Option<DateTime<Utc>>,
+
+    /// The number of signups, including those on the waitlist
+    pub signups: i64,
+}
+
+impl Event {
+    /// The number of signups which have a place at the event
+    pub fn attendees(&self) -> i64 {
+        match self.capacity {
+            Some(capacity) => self.signups.min(capacity.into()),
+            None => self.signups,
+        }
+    }
+
+    /// The number of signups on the waitlist
+    pub fn waitlisted(&self) -> i64 {
+        self.signups - self.attendees()
+    }
+
+    /// The price of a ticket. The price of the event is in whole kronor.
+    ///
+    /// Fails if the price is too large to be represented in öre.
+    pub fn ticket_price(&self) -> Result<Currency, &'static str> {
+        ticket_price(self.price)
+    }
+
+    /// The last point in time when a cancelled signup is refunded
+    pub fn refund_deadline(&self) -> DateTime<Utc> {
+        self.cancellation_deadline.unwrap_or(self.start_time)
+    }
+
+    /// Whether a signup with `status` which is cancelled at `now` gets its ticket refunded.
+    ///
+    /// Signups on the waitlist are always refunded, since they never got a place.
+    pub fn is_refundable(&self, status: SignupStatus, now: DateTime<Utc>) -> bool {
+        match status {
+            SignupStatus::Attending => now < self.refund_deadline(),
+            SignupStatus::Waitlisted { .. } => true,
+        }
+    }
+}
+
+/// The price of a ticket in öre, from the price of an event in whole kronor
+fn ticket_price(price: i32) -> Result<Currency, &'static str> {
+    price
+        .checked_mul(100)
+        .map(Currency::from)
+        .ok_or("The price of the event is too large")
+}
+
+/// A new event, or new details of an existing event
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewEvent {
+    pub title: String,
+    pub background: String,
+    pub location: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub price: i32,
+    pub capacity: Option<i32>,
+    pub cancellation_deadline: Option<DateTime<Utc>>,
+}
+
+impl NewEvent {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.title.trim().is_empty() {
+            return Err("The event must have a title");
+        }
+
+        if self.end_time < self.start_time {
+            return Err("The event can not end before it starts");
+        }
+
+        if self.price < 0 {
+            return Err("The price of the event can not be negative");
+        }
+
+        ticket_price(self.price)?;
+
+        if matches!(self.capacity, Some(capacity) if capacity <= 0) {
+            return Err("The capacity of the event must be positive");
+        }
+
+        if matches!(self.cancellation_deadline, Some(deadline) if deadline > self.end_time) {
+            return Err("The cancellation deadline can not be after the event has ended");
+        }
+
+        Ok(())
+    }
+}
+
+/// Someone signed up for an event
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct EventSignup {
+    pub id: EventSignupId,
+    pub event: EventId,
+    pub name: String,
+    pub email: String,
+
+    /// The member who signed up, if it was charged to a tillgodo account
+    pub member: Option<MemberId>,
+
+    /// The transaction which paid for the ticket
+    pub transaction_id: Option<TransactionId>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewEventSignup {
+    pub name: String,
+    pub email: String,
+
+    /// Charge the ticket to the tillgodo account of this member
+    pub member: Option<MemberId>,
+}
+
+impl NewEventSignup {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.name.trim().is_empty() {
+            return Err("A name is required to sign up");
+        }
+
+        // members can be reached without an email address
+        if self.member.is_none() && !self.email.contains('@') {
+            return Err("A valid email address is required to sign up");
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a signup has a place at the event
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SignupStatus {
+    Attending,
+
+    /// On the waitlist, where the first in line has position 1
+    Waitlisted {
+        position: usize,
+    },
+}
+
+impl SignupStatus {
+    /// The status of the signup at `index`, counting from the first signup of the event.
+    ///
+    /// The first signups get the places at the event, the rest are put on the waitlist.
+    pub fn of(index: usize, capacity: Option<i32>) -> Self {
+        match capacity {
+            Some(capacity) if index >= capacity.max(0) as usize => SignupStatus::Waitlisted {
+                position: index - capacity.max(0) as usize + 1,
+            },
+            _ => SignupStatus::Attending,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct EventSignupWithStatus {
+    pub signup: EventSignup,
+    pub status: SignupStatus,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_signup_status() {
+        assert_eq!(SignupStatus::of(0, None), SignupStatus::Attending);
+        assert_eq!(SignupStatus::of(500, None), SignupStatus::Attending);
+
+        assert_eq!(SignupStatus::of(0, Some(2)), SignupStatus::Attending);
+        assert_eq!(SignupStatus::of(1, Some(2)), SignupStatus::Attending);
+        assert_eq!(
+            SignupStatus::of(2, Some(2)),
+            SignupStatus::Waitlisted { position: 1 }
+        );
+        assert_eq!(
+            SignupStatus::of(4, Some(2)),
+            SignupStatus::Waitlisted { position: 3 }
+        );
+    }
+
+    #[test]
+    fn test_ticket_price() {
+        let mut event = NewEvent {
+            title: "Sittning".to_string(),
+            background: String::new(),
+            location: "Hubben".to_string(),
+            start_time: "2022-08-20T18:00:00Z".parse().unwrap(),
+            end_time: "2022-08-21T01:00:00Z".parse().unwrap(),
+            price: i32::MAX / 100,
+            capacity: None,
+            cancellation_deadline: None,
+        };
+        assert_eq!(event.validate(), Ok(()));
+        assert!(ticket_price(event.price).is_ok());
+
+        event.price += 1;
+        assert!(event.validate().is_err());
+        assert!(ticket_price(event.price).is_err());
+    }
+
+    #[test]
+    fn test_refundable() {
+        let start_time: DateTime<Utc> = "2022-08-20T18:00:00Z".parse().unwrap();
+        let mut event = Event {
+            id: 1,
+            title: "Sittning".to_string(),
+            background: String::new(),
+            location: "Hubben".to_string(),
+            start_time,
+            end_time: "2022-08-21T01:00:00Z".parse().unwrap(),
+            price: 150,
+            published: true,
+            capacity: Some(1),
+            cancellation_deadline: None,
+            signups: 2,
+        };
+        assert_eq!(event.ticket_price(), Ok(Currency::from(15000)));
+
+        let before: DateTime<Utc> = "2022-08-15T12:00:00Z".parse().unwrap();
+        let waitlisted = SignupStatus::Waitlisted { position: 1 };
+        assert!(event.is_refundable(SignupStatus::Attending, before));
+        assert!(!event.is_refundable(SignupStatus::Attending, start_time));
+        assert!(event.is_refundable(waitlisted, start_time));
+
+        event.cancellation_deadline = Some("2022-08-13T00:00:00Z".parse().unwrap());
+        assert!(!event.is_refundable(SignupStatus::Attending, before));
+        assert!(event.is_refundable(waitlisted, before));
+    }
+}
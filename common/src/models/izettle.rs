@@ -1,4 +1,7 @@
+use crate::book_account::BookAccountId;
+use crate::currency::Currency;
 use crate::transaction::TransactionId;
+use chrono::{DateTime, Utc};
 
 #[cfg(feature = "serde_impl")]
 use serde::{Deserialize, Serialize};
@@ -24,3 +27,94 @@ pub enum IZettlePayment {
     /// No pending payment exists for the given ID
     NoTransaction,
 }
+
+/// A card payment which is waiting for a response from the payment terminal
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct PendingIZettlePayment {
+    pub id: i32,
+    pub description: Option<String>,
+    pub time: DateTime<Utc>,
+    pub debited_account: BookAccountId,
+    pub credited_account: BookAccountId,
+    pub amount: Currency,
+
+    /// The terminal which should charge the payment, if any particular one
+    pub terminal: Option<IZettleTerminalId>,
+}
+
+pub type IZettleTerminalId = String;
+
+/// A card terminal which payment bridges can connect as
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct IZettleTerminal {
+    pub id: IZettleTerminalId,
+    pub name: String,
+
+    /// Whether a payment bridge is currently connected as this terminal
+    pub connected: bool,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewIZettleTerminal {
+    pub id: IZettleTerminalId,
+    pub name: String,
+}
+
+pub type IZettleRefundId = i32;
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub enum IZettleRefund {
+    /// The payment has been refunded
+    Refunded {
+        /// The ID of the transaction which reverses the payment
+        transaction_id: TransactionId,
+    },
+
+    /// The refund is still awaiting the payment terminal
+    Pending,
+
+    /// The refund was intentionally aborted
+    Cancelled,
+
+    /// The refund failed for some reason
+    Failed { reason: String },
+
+    /// No refund exists for the given ID
+    NoRefund,
+}
+
+/// The card payment which a transaction was paid with
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CardPayment {
+    /// The reference of the payment, as given to the payment terminal
+    pub reference: i32,
+
+    /// For example, "MASTERCARD"
+    pub card_type: Option<String>,
+
+    /// Masked primary account number, e.g. "************1234"
+    pub masked_pan: Option<String>,
+
+    /// The status of the latest refund of the payment, if one has been requested
+    pub refund: Option<IZettleRefund>,
+}
+
+impl CardPayment {
+    /// Whether a new refund of the payment may be requested
+    pub fn is_refundable(&self) -> bool {
+        matches!(
+            self.refund,
+            None | Some(IZettleRefund::Cancelled | IZettleRefund::Failed { .. })
+        )
+    }
+}
@@ -0,0 +1,117 @@
+//! The messages of the WebSocket protocol spoken between the server and the iZettle payment bridge.
+//!
+//! All messages are sent as JSON text frames, tagged with their variant name in a `type` field.
+
+use crate::currency::Currency;
+use crate::izettle::IZettleRefundId;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// A payment which should be charged by the terminal
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct PendingPayment {
+    pub id: i32,
+    pub amount: Currency,
+}
+
+/// A payment which should be refunded by the terminal
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct PendingRefund {
+    pub id: IZettleRefundId,
+
+    /// The reference of the payment to refund
+    pub izettle_transaction_id: i32,
+    pub amount: Currency,
+}
+
+/// A message sent from the server to the bridge
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(tag = "type"))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub enum ServerMessage {
+    PendingPayment(PendingPayment),
+
+    /// The payment expired or was cancelled, and should be aborted by the terminal
+    PaymentCancelled {
+        reference: i32,
+    },
+
+    /// The response to a payment has been recorded
+    PaymentRecorded {
+        reference: i32,
+    },
+
+    PendingRefund(PendingRefund),
+
+    /// The refund expired, and should be aborted by the terminal
+    RefundCancelled {
+        reference: IZettleRefundId,
+    },
+
+    /// The response to a refund has been recorded
+    RefundRecorded {
+        reference: IZettleRefundId,
+    },
+
+    Error {
+        message: String,
+    },
+}
+
+/// A message sent from the bridge to the server
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(tag = "type"))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub enum BridgeMessage {
+    PaymentResponse {
+        reference: i32,
+        response: PaymentResponse,
+    },
+    RefundResponse {
+        reference: IZettleRefundId,
+        response: RefundResponse,
+    },
+}
+
+/// The response of the payment terminal to a [PendingPayment]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(tag = "type"))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub enum PaymentResponse {
+    TransactionPaid {
+        /// For example, "MASTERCARD"
+        card_type: Option<String>,
+
+        /// For example, "CONTACTLESS_EMV"
+        card_payment_entry_mode: Option<String>,
+
+        /// For example, "SWEDBANK"
+        card_issuing_bank: Option<String>,
+
+        /// Masked primary account number, e.g. "************1234"
+        masked_pan: Option<String>,
+    },
+    TransactionFailed {
+        reason: String,
+    },
+    TransactionCancelled,
+}
+
+/// The response of the payment terminal to a [PendingRefund]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(tag = "type"))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub enum RefundResponse {
+    RefundCompleted,
+    RefundFailed { reason: String },
+    RefundCancelled,
+}
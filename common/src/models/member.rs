@@ -1,3 +1,5 @@
+use chrono::{DateTime, Utc};
+
 #[cfg(feature = "diesel_impl")]
 use diesel_derives::Queryable;
 
@@ -15,6 +17,15 @@ pub struct Member {
     pub first_name: String,
     pub last_name: String,
     pub nickname: Option<String>,
+
+    /// Deactivated members are hidden from the store, but their history is kept
+    pub deactivated_at: Option<DateTime<Utc>>,
+}
+
+impl Member {
+    pub fn is_active(&self) -> bool {
+        self.deactivated_at.is_none()
+    }
 }
 
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
@@ -25,3 +36,16 @@ pub struct NewMember {
     pub last_name: String,
     pub nickname: Option<String>,
 }
+
+/// New details of a member
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct EditMember {
+    pub first_name: String,
+    pub last_name: String,
+    pub nickname: Option<String>,
+
+    /// The new name of the tillgodo account of the member, if it should be renamed
+    pub account_name: Option<String>,
+}
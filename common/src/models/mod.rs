@@ -1,6 +1,16 @@
 pub mod book_account;
+pub mod cash_register;
+pub mod change;
 pub mod currency;
+pub mod event;
 pub mod inventory;
 pub mod izettle;
+pub mod izettle_bridge;
 pub mod member;
+pub mod purchase;
+pub mod report;
+pub mod sale;
+pub mod stock_take;
+pub mod swish;
 pub mod transaction;
+pub mod user;
@@ -0,0 +1,106 @@
+use crate::book_account::BookAccountId;
+use crate::currency::Currency;
+use crate::inventory::InventoryItemId;
+use crate::transaction::TransactionId;
+use crate::user::UserName;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type PurchaseId = i32;
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct PurchaseLine {
+    pub item_id: InventoryItemId,
+    pub quantity: u32,
+
+    /// The cost price of a single unit
+    pub unit_cost: Currency,
+}
+
+impl PurchaseLine {
+    /// The cost of all units, or `None` if it doesn't fit in a [Currency]
+    pub fn cost(&self) -> Option<Currency> {
+        let quantity = i32::try_from(self.quantity).ok()?;
+        i32::from(self.unit_cost)
+            .checked_mul(quantity)
+            .map(Currency::from)
+    }
+}
+
+/// A purchase of inventory from a supplier
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewPurchase {
+    pub supplier: String,
+    pub invoice_reference: Option<String>,
+
+    /// The account which paid for the purchase, e.g. the bank account
+    pub credited_account: BookAccountId,
+
+    pub lines: Vec<PurchaseLine>,
+}
+
+impl NewPurchase {
+    /// The cost of all lines, or `None` if it doesn't fit in a [Currency]
+    pub fn total(&self) -> Option<Currency> {
+        self.lines
+            .iter()
+            .try_fold(Currency::default(), |total, line| {
+                i32::from(total)
+                    .checked_add(line.cost()?.into())
+                    .map(Currency::from)
+            })
+    }
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Purchase {
+    pub id: PurchaseId,
+    pub transaction_id: TransactionId,
+    pub time: DateTime<Utc>,
+    pub supplier: String,
+    pub invoice_reference: Option<String>,
+    pub recorded_by: Option<UserName>,
+    pub credited_account: BookAccountId,
+    pub amount: Currency,
+    pub lines: Vec<PurchaseLine>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn line(quantity: u32, unit_cost: i32) -> PurchaseLine {
+        PurchaseLine {
+            item_id: 1,
+            quantity,
+            unit_cost: unit_cost.into(),
+        }
+    }
+
+    fn purchase(lines: Vec<PurchaseLine>) -> NewPurchase {
+        NewPurchase {
+            supplier: "Grossisten".to_string(),
+            invoice_reference: None,
+            credited_account: 1,
+            lines,
+        }
+    }
+
+    #[test]
+    fn test_purchase_total() {
+        let total = purchase(vec![line(24, 850), line(3, 12000)]).total();
+        assert_eq!(total, Some(Currency::from(56400)));
+
+        assert_eq!(line(u32::MAX, 0).cost(), None);
+        assert_eq!(line(100_000, 100_000).cost(), None);
+        assert_eq!(purchase(vec![line(1, i32::MAX), line(1, 1)]).total(), None);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::currency::Currency;
+use crate::models::book_account::{BookAccountId, BookAccountType};
+use crate::models::transaction::TransactionId;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// The time span covered by a report
+///
+/// `from` is inclusive and `to` is exclusive. A missing bound means the span is unbounded.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReportPeriod {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// All entries on a single book account during a period
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone)]
+pub struct GeneralLedger {
+    pub period: ReportPeriod,
+    pub account_id: BookAccountId,
+    pub account_name: String,
+    pub account_type: BookAccountType,
+
+    /// The balance of the account at the start of the period
+    pub opening_balance: Currency,
+
+    pub entries: Vec<LedgerEntry>,
+
+    /// The balance of the account at the end of the period
+    pub closing_balance: Currency,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone)]
+pub struct LedgerEntry {
+    pub transaction_id: TransactionId,
+    pub time: DateTime<Utc>,
+    pub description: Option<String>,
+
+    /// The account on the other side of the transaction
+    pub counter_account: BookAccountId,
+
+    pub debit: Currency,
+    pub credit: Currency,
+
+    /// The balance of the account after this entry
+    pub balance: Currency,
+}
+
+/// The total debits and credits of every book account during a period
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone)]
+pub struct TrialBalance {
+    pub period: ReportPeriod,
+    pub rows: Vec<TrialBalanceRow>,
+    pub total_debit: Currency,
+    pub total_credit: Currency,
+
+    /// Whether `expenses + assets = liabilities + revenue` holds for the period
+    pub balanced: bool,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone)]
+pub struct TrialBalanceRow {
+    pub account_id: BookAccountId,
+    pub account_name: String,
+    pub account_type: BookAccountType,
+    pub debit: Currency,
+    pub credit: Currency,
+
+    /// The change of the account balance during the period
+    pub balance: Currency,
+}
+
+/// An income statement for a period, and a balance sheet for the end of it
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone)]
+pub struct FinancialStatement {
+    pub period: ReportPeriod,
+
+    /// Revenue during the period
+    pub revenue: Vec<AccountAmount>,
+
+    /// Expenses during the period
+    pub expenses: Vec<AccountAmount>,
+
+    /// Assets at the end of the period
+    pub assets: Vec<AccountAmount>,
+
+    /// Liabilities at the end of the period
+    pub liabilities: Vec<AccountAmount>,
+
+    pub total_revenue: Currency,
+    pub total_expenses: Currency,
+    pub total_assets: Currency,
+    pub total_liabilities: Currency,
+
+    /// Revenue minus expenses during the period
+    pub net_income: Currency,
+
+    /// Whether `expenses + assets = liabilities + revenue` holds at the end of the period
+    pub balanced: bool,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone)]
+pub struct AccountAmount {
+    pub account_id: BookAccountId,
+    pub account_name: String,
+    pub amount: Currency,
+}
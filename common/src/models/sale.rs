@@ -0,0 +1,163 @@
+use crate::book_account::BookAccountId;
+use crate::currency::Currency;
+use crate::transaction::{NewTransaction, TransactionBundle, TransactionId};
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type SaleId = i32;
+
+/// A purchase which is paid from several sources, e.g. partly from a tillgodo account and partly
+/// by card. Each payment is recorded as a separate transaction.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewSale {
+    pub description: Option<String>,
+    pub bundles: Vec<TransactionBundle>,
+    pub credited_account: BookAccountId,
+
+    /// The parts of the payment, which together add up to the total of the sale.
+    ///
+    /// When paying through iZettle or Swish, the first payment is the one charged by the payment
+    /// provider. The rest are recorded once it has been paid.
+    pub payments: Vec<SalePayment>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SalePayment {
+    pub debited_account: BookAccountId,
+    pub amount: Currency,
+}
+
+/// The transactions which together make up a sale
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Sale {
+    pub id: SaleId,
+    pub time: DateTime<Utc>,
+    pub transaction_ids: Vec<TransactionId>,
+}
+
+impl NewSale {
+    /// The total amount paid
+    pub fn total(&self) -> Currency {
+        self.payments
+            .iter()
+            .fold(Currency::from(0), |total, payment| total + payment.amount)
+    }
+
+    /// Check that there is at least one payment, and that every payment is positive
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.payments.is_empty() {
+            return Err("A sale must have at least one payment");
+        }
+
+        if self
+            .payments
+            .iter()
+            .any(|payment| payment.amount <= Currency::from(0))
+        {
+            return Err("Every payment of a sale must be positive");
+        }
+
+        Ok(())
+    }
+
+    /// Split off the first payment as a transaction, which carries all the bundles.
+    /// Returns the transaction and the remaining payments.
+    pub fn split_first(mut self) -> Option<(NewTransaction, Vec<SalePayment>)> {
+        if self.payments.is_empty() {
+            return None;
+        }
+
+        let rest = self.payments.split_off(1);
+        let first = self.into_transactions().pop()?;
+        Some((first, rest))
+    }
+
+    /// Split the sale into one transaction per payment.
+    ///
+    /// The bundles are put in the first transaction, so that the inventory is only changed once.
+    pub fn into_transactions(self) -> Vec<NewTransaction> {
+        let NewSale {
+            description,
+            bundles,
+            credited_account,
+            payments,
+        } = self;
+
+        let mut bundles = Some(bundles);
+        payments
+            .into_iter()
+            .map(|payment| NewTransaction {
+                description: description.clone(),
+                bundles: bundles.take().unwrap_or_default(),
+                debited_account: payment.debited_account,
+                credited_account,
+                amount: payment.amount,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn sale(amounts: &[i32]) -> NewSale {
+        NewSale {
+            description: Some("Försäljning".to_string()),
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(Currency::from(1500)),
+                change: -2,
+                item_ids: vec![(1, 1)].into_iter().collect::<HashMap<_, _>>(),
+            }],
+            credited_account: 3,
+            payments: amounts
+                .iter()
+                .enumerate()
+                .map(|(i, &amount)| SalePayment {
+                    debited_account: 10 + i as i32,
+                    amount: amount.into(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_sale() {
+        assert!(sale(&[1000, 2000]).validate().is_ok());
+        assert!(sale(&[]).validate().is_err());
+        assert!(sale(&[3000, 0]).validate().is_err());
+        assert!(sale(&[3500, -500]).validate().is_err());
+    }
+
+    #[test]
+    fn test_split_sale() {
+        let sale = sale(&[1000, 2000]);
+        assert_eq!(sale.total(), Currency::from(3000));
+
+        let transactions = sale.clone().into_transactions();
+        assert_eq!(transactions.len(), 2);
+
+        assert_eq!(transactions[0].bundles, sale.bundles);
+        assert_eq!(transactions[0].debited_account, 10);
+        assert_eq!(transactions[0].amount, Currency::from(1000));
+
+        assert!(transactions[1].bundles.is_empty());
+        assert_eq!(transactions[1].debited_account, 11);
+        assert_eq!(transactions[1].credited_account, 3);
+        assert_eq!(transactions[1].amount, Currency::from(2000));
+
+        let (first, rest) = sale.clone().split_first().unwrap();
+        assert_eq!(first, transactions[0]);
+        assert_eq!(rest, sale.payments[1..].to_vec());
+    }
+}
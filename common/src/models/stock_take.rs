@@ -0,0 +1,54 @@
+use crate::book_account::BookAccountId;
+use crate::inventory::InventoryItemId;
+use crate::transaction::TransactionId;
+use crate::user::UserName;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type StockTakeId = i32;
+
+/// A count of the physical inventory
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct StockTake {
+    pub id: StockTakeId,
+    pub started_at: DateTime<Utc>,
+    pub started_by: Option<UserName>,
+    pub committed_at: Option<DateTime<Utc>>,
+
+    /// The correction transaction created when the stock take was committed
+    pub transaction_id: Option<TransactionId>,
+
+    /// The counted quantity of each item. Items which haven't been counted are left out.
+    pub counts: HashMap<InventoryItemId, i32>,
+}
+
+/// The difference between the counted and the expected stock of an item
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct StockTakeDiff {
+    pub item_id: InventoryItemId,
+    pub expected: i32,
+    pub counted: i32,
+}
+
+impl StockTakeDiff {
+    /// The change in stock needed to correct the inventory
+    pub fn change(&self) -> i32 {
+        self.counted - self.expected
+    }
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct CommitStockTake {
+    /// The account to debit for the correction.
+    /// If not set, the shrinkage master account is used.
+    pub shrinkage_account: Option<BookAccountId>,
+}
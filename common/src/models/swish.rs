@@ -0,0 +1,87 @@
+use crate::currency::Currency;
+use crate::transaction::TransactionId;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type SwishPaymentId = i32;
+
+/// A Swish payment which is waiting for the customer to scan the QR code
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct SwishPaymentRequest {
+    pub id: SwishPaymentId,
+
+    /// The text to encode in the QR code, see [qr_payload]
+    pub qr_payload: String,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+pub enum SwishPayment {
+    /// The transaction has been paid
+    Paid {
+        /// The ID of the completed transaction
+        transaction_id: TransactionId,
+    },
+
+    /// The transaction is still awaiting payment
+    Pending,
+
+    /// The payment was intentionally aborted
+    Cancelled,
+
+    /// The payment failed for some reason
+    Failed { reason: String },
+
+    /// No pending payment exists for the given ID
+    NoTransaction,
+}
+
+const MESSAGE_PREFIX: &str = "Strecklistan ";
+
+/// The message of the Swish payment with the given id, which identifies it in the callback
+pub fn payment_message(id: SwishPaymentId) -> String {
+    format!("{}{}", MESSAGE_PREFIX, id)
+}
+
+/// Get the id of a Swish payment from its message, see [payment_message]
+pub fn payment_id(message: &str) -> Option<SwishPaymentId> {
+    message.trim().strip_prefix(MESSAGE_PREFIX)?.parse().ok()
+}
+
+/// Build the contents of a prefilled Swish QR code
+///
+/// The payee, amount and message are all locked, so that the customer can't edit them in the
+/// Swish app.
+pub fn qr_payload(payee: &str, amount: Currency, message: &str) -> String {
+    // the fields are separated by semicolons
+    let message: String = message.chars().filter(|&c| c != ';').collect();
+    format!("C{};{};{};0", payee, amount, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_qr_payload() {
+        assert_eq!(
+            qr_payload("1234567890", Currency::from(15000), &payment_message(42)),
+            "C1234567890;150;Strecklistan 42;0",
+        );
+        assert_eq!(
+            qr_payload("1234567890", Currency::from(1250), "a;b"),
+            "C1234567890;12.50;ab;0",
+        );
+    }
+
+    #[test]
+    fn test_payment_id() {
+        assert_eq!(payment_id(&payment_message(42)), Some(42));
+        assert_eq!(payment_id(" Strecklistan 7 "), Some(7));
+        assert_eq!(payment_id("Strecklistan"), None);
+        assert_eq!(payment_id("Tack för fikat"), None);
+    }
+}
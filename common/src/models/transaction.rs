@@ -1,6 +1,7 @@
 use crate::currency::Currency;
 use crate::models::book_account::BookAccountId;
 use crate::models::inventory::InventoryItemId;
+use crate::models::izettle::CardPayment;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 
@@ -41,6 +42,55 @@ impl PartialEq for Transaction {
 
 impl Eq for Transaction {}
 
+impl From<Transaction> for NewTransaction {
+    fn from(transaction: Transaction) -> Self {
+        NewTransaction {
+            description: transaction.description,
+            bundles: transaction.bundles,
+            debited_account: transaction.debited_account,
+            credited_account: transaction.credited_account,
+            amount: transaction.amount,
+        }
+    }
+}
+
+pub type TransactionRevisionId = i32;
+
+/// A change made to a transaction after it was created
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone)]
+pub struct TransactionRevision {
+    pub id: TransactionRevisionId,
+    pub transaction_id: TransactionId,
+    pub revised_at: DateTime<Utc>,
+    pub revised_by: Option<String>,
+
+    /// The transaction before the change
+    pub old: NewTransaction,
+
+    /// The transaction after the change
+    pub new: NewTransaction,
+}
+
+/// A page of transactions, newest first
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Default)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+
+    /// The number of transactions matching the query, on all pages
+    pub total: u64,
+
+    /// Pass this as `after` to get the next page, if there is one
+    pub next: Option<TransactionId>,
+
+    /// The card payments of the transactions on this page which were paid by card
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub card_payments: HashMap<TransactionId, CardPayment>,
+}
+
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "debug", derive(Debug))]
 #[derive(Clone, PartialEq, Eq)]
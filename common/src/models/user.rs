@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "diesel_impl")]
+use {diesel_derive_enum::DbEnum, diesel_derives::Queryable};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type UserName = String;
+
+/// The access level of a user.
+///
+/// Roles are ordered, and a higher role is granted everything a lower role is.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(DbEnum))]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UserRole {
+    /// May use the store and make deposits
+    Cashier,
+
+    /// May also manage the inventory, book accounts and transactions
+    Treasurer,
+
+    /// May also manage users
+    Admin,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct User {
+    pub name: UserName,
+    pub display_name: Option<String>,
+    pub role: UserRole,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewUser {
+    pub name: UserName,
+    pub display_name: Option<String>,
+    pub password: String,
+    pub role: UserRole,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub name: UserName,
+    pub password: String,
+}
+
+/// A logged in session
+///
+/// The token can be sent either as the `session` cookie, or in an
+/// `Authorization: Bearer <token>` header.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Session {
+    pub token: String,
+    pub user: User,
+    pub expires_at: DateTime<Utc>,
+}
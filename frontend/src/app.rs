@@ -1,11 +1,20 @@
+use crate::change_listener::ChangeListener;
 use crate::generated::css_classes::C;
 use crate::notification_manager::{NotificationManager, NotificationMessage};
 use crate::page::{
     analytics::{AnalyticsMsg, AnalyticsPage},
+    cash_register::{CashRegisterMsg, CashRegisterPage},
     deposit::{DepositionMsg, DepositionPage},
+    events::{EventsMsg, EventsPage},
     inventory::{InventoryMsg, InventoryPage},
+    loading::Loading,
+    login::{LoginMsg, LoginPage},
+    purchase::{PurchaseMsg, PurchasePage},
+    stock_take::{StockTakeMsg, StockTakePage},
     store::{StoreMsg, StorePage},
+    terminals::{TerminalsMsg, TerminalsPage},
     transactions::{TransactionsMsg, TransactionsPage},
+    trash::{TrashMsg, TrashPage},
     Page,
 };
 use crate::util::compare_semver;
@@ -14,6 +23,8 @@ use seed::*;
 use seed_fetcher::{ResourceMsg, ResourceStore};
 use semver::Version;
 use std::fmt::Debug;
+use strecklistan_api::change::ChangeEvent;
+use strecklistan_api::user::User;
 
 const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -22,14 +33,29 @@ pub struct Model {
 
     pub error: Option<(String, String)>,
 
+    /// The logged in user
+    pub user: Option<User>,
+
+    /// Shown instead of the current page if the user is not logged in
+    pub login_page: Option<LoginPage>,
+
     pub store_page: Option<StorePage>,
     pub transactions_page: Option<TransactionsPage>,
     pub analytics_page: Option<AnalyticsPage>,
     pub deposition_page: Option<DepositionPage>,
     pub inventory_page: Option<InventoryPage>,
+    pub stock_take_page: Option<StockTakePage>,
+    pub cash_register_page: Option<CashRegisterPage>,
+    pub purchase_page: Option<PurchasePage>,
+    pub trash_page: Option<TrashPage>,
+    pub terminals_page: Option<TerminalsPage>,
+    pub events_page: Option<EventsPage>,
 
     pub rs: ResourceStore,
     pub notifications: NotificationManager,
+
+    /// Receives changes made by other clients, once the user is logged in
+    pub change_listener: Option<ChangeListener>,
 }
 
 #[derive(Clone, Debug)]
@@ -38,15 +64,32 @@ pub enum Msg {
 
     Resource(ResourceMsg),
 
+    /// Data was changed on the server
+    Change(ChangeEvent),
+
     FetchedApiVersion(String),
 
-    ShowError { header: String, dump: String },
+    FetchedSession(Option<User>),
+    LoggedIn(User),
+    Logout,
+
+    ShowError {
+        header: String,
+        dump: String,
+    },
 
     Analytics(AnalyticsMsg),
     Deposition(DepositionMsg),
     Transactions(TransactionsMsg),
     Store(StoreMsg),
     Inventory(InventoryMsg),
+    StockTake(StockTakeMsg),
+    CashRegister(CashRegisterMsg),
+    Purchase(PurchaseMsg),
+    Trash(TrashMsg),
+    Terminals(TerminalsMsg),
+    Events(EventsMsg),
+    Login(LoginMsg),
 
     Notification(NotificationMessage),
 }
@@ -60,6 +103,12 @@ pub fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
                 ["analytics"] => Page::Analytics,
                 ["deposit"] => Page::Deposit,
                 ["inventory"] => Page::Inventory,
+                ["stock_take"] => Page::StockTake,
+                ["cash_register"] => Page::CashRegister,
+                ["purchases"] => Page::Purchases,
+                ["trash"] => Page::Trash,
+                ["terminals"] => Page::Terminals,
+                ["events"] => Page::Events,
                 _ => Page::NotFound,
             };
 
@@ -79,17 +128,32 @@ pub fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
         }
     });
 
+    orders.perform_cmd(async move {
+        let response: Result<User, FetchError> =
+            async { fetch("/api/session").await?.check_status()?.json().await }.await;
+        Msg::FetchedSession(response.ok())
+    });
+
     let rs = ResourceStore::new(&mut orders.proxy(Msg::Resource));
     Model {
         page: Page::Store,
         error: None,
+        user: None,
+        login_page: None,
         store_page: None,
         transactions_page: None,
         analytics_page: None,
         deposition_page: None,
         inventory_page: None,
+        stock_take_page: None,
+        cash_register_page: None,
+        purchase_page: None,
+        trash_page: None,
+        terminals_page: None,
+        events_page: None,
         rs,
         notifications: Default::default(),
+        change_listener: None,
     }
 }
 
@@ -102,6 +166,11 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
         Msg::ChangePage(page) => {
             model.page = page;
 
+            // pages are created once we know that the user is logged in
+            if model.user.is_none() {
+                return;
+            }
+
             model.transactions_page = None;
 
             match page {
@@ -131,6 +200,36 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
                         InventoryPage::new(rs, &mut orders.proxy(Msg::Inventory))
                     });
                 }
+                Page::StockTake => {
+                    model.stock_take_page.get_or_insert_with(|| {
+                        StockTakePage::new(rs, &mut orders.proxy(Msg::StockTake))
+                    });
+                }
+                Page::CashRegister => {
+                    model.cash_register_page.get_or_insert_with(|| {
+                        CashRegisterPage::new(rs, &mut orders.proxy(Msg::CashRegister))
+                    });
+                }
+                Page::Purchases => {
+                    model.purchase_page.get_or_insert_with(|| {
+                        PurchasePage::new(rs, &mut orders.proxy(Msg::Purchase))
+                    });
+                }
+                Page::Trash => {
+                    model
+                        .trash_page
+                        .get_or_insert_with(|| TrashPage::new(rs, &mut orders.proxy(Msg::Trash)));
+                }
+                Page::Terminals => {
+                    model.terminals_page.get_or_insert_with(|| {
+                        TerminalsPage::new(rs, &mut orders.proxy(Msg::Terminals))
+                    });
+                }
+                Page::Events => {
+                    model
+                        .events_page
+                        .get_or_insert_with(|| EventsPage::new(rs, &mut orders.proxy(Msg::Events)));
+                }
                 Page::NotFound => {}
             }
         }
@@ -139,6 +238,29 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
             model.rs.update(msg, &mut orders.proxy(Msg::Resource));
         }
 
+        Msg::Change(change) => {
+            // the transaction lists fetch their pages by themselves
+            match change {
+                ChangeEvent::TransactionCreated { id } => {
+                    orders.send_msg(Msg::Transactions(TransactionsMsg::Refresh(id)));
+                }
+                ChangeEvent::TransactionChanged { id } | ChangeEvent::TransactionDeleted { id } => {
+                    orders.send_msg(Msg::Transactions(TransactionsMsg::Refresh(id)));
+                    orders.send_msg(Msg::Trash(TrashMsg::Refresh(id)));
+                }
+                // merging members moves their transactions
+                ChangeEvent::MemberChanged { .. } | ChangeEvent::Lagged => {
+                    orders.send_msg(Msg::Transactions(TransactionsMsg::Reload));
+                    orders.send_msg(Msg::Trash(TrashMsg::Reload));
+                }
+                _ => {}
+            }
+
+            for url in ChangeListener::affected_resources(change) {
+                rs.mark_as_dirty(url, orders);
+            }
+        }
+
         Msg::ShowError { header, dump } => {
             model.error = Some((header, dump));
         }
@@ -164,6 +286,81 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
             }
         }
 
+        Msg::FetchedSession(None) => {
+            model.login_page = Some(LoginPage::default());
+        }
+
+        Msg::FetchedSession(Some(user)) | Msg::LoggedIn(user) => {
+            model.user = Some(user);
+            model.login_page = None;
+            if model.change_listener.is_none() {
+                model.change_listener = ChangeListener::connect(orders);
+            }
+            orders.send_msg(Msg::ChangePage(model.page));
+        }
+
+        Msg::Logout => {
+            orders.perform_cmd(async move {
+                let result: fetch::Result<_> = async {
+                    Request::new("/api/logout")
+                        .method(Method::Post)
+                        .fetch()
+                        .await?
+                        .check_status()
+                }
+                .await;
+
+                if let Err(e) = result {
+                    error!("Failed to log out", e);
+                }
+
+                // reload the page to throw away all fetched resources
+                window().location().reload().ok();
+            });
+        }
+
+        Msg::StockTake(msg) => {
+            model
+                .stock_take_page
+                .as_mut()
+                .and_then(|p| p.update(msg, rs, orders).ok());
+        }
+        Msg::CashRegister(msg) => {
+            model
+                .cash_register_page
+                .as_mut()
+                .and_then(|p| p.update(msg, rs, orders).ok());
+        }
+        Msg::Purchase(msg) => {
+            model
+                .purchase_page
+                .as_mut()
+                .and_then(|p| p.update(msg, rs, orders).ok());
+        }
+        Msg::Trash(msg) => {
+            model
+                .trash_page
+                .as_mut()
+                .and_then(|p| p.update(msg, rs, orders).ok());
+        }
+        Msg::Terminals(msg) => {
+            model
+                .terminals_page
+                .as_mut()
+                .and_then(|p| p.update(msg, rs, orders).ok());
+        }
+        Msg::Events(msg) => {
+            model
+                .events_page
+                .as_mut()
+                .and_then(|p| p.update(msg, rs, orders).ok());
+        }
+        Msg::Login(msg) => {
+            if let Some(page) = model.login_page.as_mut() {
+                page.update(msg, orders);
+            }
+        }
+
         Msg::Deposition(msg) => {
             model
                 .deposition_page
@@ -234,14 +431,65 @@ pub fn view(model: &Model) -> Vec<Node<Msg>> {
                         C![C.header_link],
                         attrs! {At::Href => "/inventory"}
                     ],
+                    a![
+                        "kassa",
+                        C![C.header_link],
+                        attrs! {At::Href => "/cash_register"}
+                    ],
+                    a![
+                        "inventering",
+                        C![C.header_link],
+                        attrs! {At::Href => "/stock_take"}
+                    ],
+                    a![
+                        "inköp",
+                        C![C.header_link],
+                        attrs! {At::Href => "/purchases"}
+                    ],
+                    a![
+                        "papperskorg",
+                        C![C.header_link],
+                        attrs! {At::Href => "/trash"}
+                    ],
+                    a![
+                        "terminaler",
+                        C![C.header_link],
+                        attrs! {At::Href => "/terminals"}
+                    ],
+                    a![
+                        "evenemang",
+                        C![C.header_link],
+                        attrs! {At::Href => "/events"}
+                    ],
                     a![
                         "analys",
                         C![C.header_link],
                         attrs! {At::Href => "/analytics"}
                     ],
+                    model.user.as_ref().map(|user| {
+                        div![
+                            C![C.header_link, C.header_user],
+                            user.display_name.as_deref().unwrap_or(&user.name),
+                        ]
+                    }),
+                    model.user.as_ref().map(|_| {
+                        a![
+                            "logga ut",
+                            C![C.header_link],
+                            attrs! {At::Href => "#"},
+                            ev(Ev::Click, |event| {
+                                event.prevent_default();
+                                Msg::Logout
+                            }),
+                        ]
+                    }),
                 ],
             ],
             match &model.error {
+                None if model.user.is_none() => match &model.login_page {
+                    Some(login_page) => login_page.view(),
+                    None => Loading::view(),
+                },
                 None => match model.page {
                     Page::Analytics => model.analytics_page.as_ref().unwrap().view(&model.rs),
                     Page::Store => model.store_page.as_ref().unwrap().view(&model.rs),
@@ -249,6 +497,13 @@ pub fn view(model: &Model) -> Vec<Node<Msg>> {
                     Page::TransactionHistory =>
                         model.transactions_page.as_ref().unwrap().view(&model.rs),
                     Page::Inventory => model.inventory_page.as_ref().unwrap().view(&model.rs),
+                    Page::StockTake => model.stock_take_page.as_ref().unwrap().view(&model.rs),
+                    Page::CashRegister =>
+                        model.cash_register_page.as_ref().unwrap().view(&model.rs),
+                    Page::Purchases => model.purchase_page.as_ref().unwrap().view(&model.rs),
+                    Page::Trash => model.trash_page.as_ref().unwrap().view(&model.rs),
+                    Page::Terminals => model.terminals_page.as_ref().unwrap().view(&model.rs),
+                    Page::Events => model.events_page.as_ref().unwrap().view(&model.rs),
                     Page::NotFound => {
                         div![C![C.not_found_message, C.unselectable], "404"]
                     }
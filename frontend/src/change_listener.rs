@@ -0,0 +1,109 @@
+use crate::app::Msg;
+use seed::prelude::*;
+use seed::*;
+use std::cell::Cell;
+use std::rc::Rc;
+use strecklistan_api::change::ChangeEvent;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, EventSource, MessageEvent};
+
+/// The transaction lists are paged, and are updated by the pages themselves
+const TRANSACTION_RESOURCES: &[&str] = &[
+    "/api/book_accounts",
+    "/api/inventory/items",
+    "/api/purchases",
+];
+
+const INVENTORY_RESOURCES: &[&str] = &[
+    "/api/inventory/items",
+    "/api/inventory/items/deleted",
+    "/api/inventory/bundles",
+];
+
+const MEMBER_RESOURCES: &[&str] = &["/api/members", "/api/book_accounts"];
+
+const CREDIT_LIMIT_RESOURCES: &[&str] = &["/api/credit_policy", "/api/book_accounts"];
+
+/// Listens to the change events published by the server on `/api/changes`
+pub struct ChangeListener {
+    event_source: EventSource,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_open: Closure<dyn FnMut(Event)>,
+}
+
+impl ChangeListener {
+    pub fn connect(orders: &mut impl Orders<Msg>) -> Option<Self> {
+        let event_source = match EventSource::new("/api/changes") {
+            Ok(event_source) => event_source,
+            Err(e) => {
+                error!("Failed to subscribe to changes", e);
+                return None;
+            }
+        };
+
+        let send = orders.msg_sender();
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let change = event
+                .data()
+                .as_string()
+                .and_then(|data| serde_json::from_str(&data).ok());
+
+            match change {
+                Some(change) => send(Some(Msg::Change(change))),
+                None => error!("Failed to parse change event", event.data()),
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        // the browser reconnects by itself if the connection is lost,
+        // but any changes made in the meantime are missed
+        let send = orders.msg_sender();
+        let has_connected = Rc::new(Cell::new(false));
+        let on_open = Closure::wrap(Box::new(move |_: Event| {
+            if has_connected.replace(true) {
+                send(Some(Msg::Change(ChangeEvent::Lagged)));
+            }
+        }) as Box<dyn FnMut(Event)>);
+
+        event_source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        event_source.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+
+        Some(ChangeListener {
+            event_source,
+            _on_message: on_message,
+            _on_open: on_open,
+        })
+    }
+
+    /// The urls of the resources which have to be refetched because of a change
+    pub fn affected_resources(change: ChangeEvent) -> Vec<&'static str> {
+        match change {
+            ChangeEvent::TransactionCreated { .. }
+            | ChangeEvent::TransactionChanged { .. }
+            | ChangeEvent::TransactionDeleted { .. } => TRANSACTION_RESOURCES.to_vec(),
+            ChangeEvent::InventoryChanged => INVENTORY_RESOURCES.to_vec(),
+            ChangeEvent::MemberAdded { .. } | ChangeEvent::MemberChanged { .. } => {
+                MEMBER_RESOURCES.to_vec()
+            }
+            ChangeEvent::CreditLimitsChanged => CREDIT_LIMIT_RESOURCES.to_vec(),
+            ChangeEvent::Lagged => {
+                let mut all = [
+                    TRANSACTION_RESOURCES,
+                    INVENTORY_RESOURCES,
+                    MEMBER_RESOURCES,
+                    CREDIT_LIMIT_RESOURCES,
+                ]
+                .concat();
+                all.sort_unstable();
+                all.dedup();
+                all
+            }
+        }
+    }
+}
+
+impl Drop for ChangeListener {
+    fn drop(&mut self) {
+        self.event_source.close();
+    }
+}
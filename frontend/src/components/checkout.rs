@@ -8,13 +8,14 @@ use seed_fetcher::ResourceStore;
 use seed_fetcher::Resources;
 use std::collections::HashMap;
 use std::convert::TryInto;
-use strecklistan_api::book_account::{BookAccount, BookAccountType};
+use strecklistan_api::book_account::{BookAccount, BookAccountType, CreditPolicy};
 use strecklistan_api::{
     book_account::{BookAccountId, MasterAccounts},
     currency::{AbsCurrency, Currency},
     inventory::{
         InventoryBundle, InventoryBundleId, InventoryItemId, InventoryItemStock as InventoryItem,
     },
+    sale::{NewSale, Sale, SalePayment},
     transaction::{NewTransaction, TransactionBundle, TransactionId},
 };
 
@@ -40,6 +41,32 @@ pub enum CheckoutMsg {
         change: i32,
     },
     ClearCart,
+
+    /// Toggle splitting the payment between several tenders
+    ToggleSplit,
+    TenderInputMsg {
+        tender_index: usize,
+        msg: ParsedInputMsg,
+    },
+    RemoveTender(usize),
+}
+
+/// How a part of a split payment is paid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TenderKind {
+    /// Recorded directly on the debited account, e.g. a tillgodo account
+    Account,
+    IZettle,
+    Swish,
+}
+
+/// A part of a split payment
+#[derive(Clone)]
+pub struct Tender {
+    debited_account: BookAccountId,
+    kind: TenderKind,
+    label: String,
+    amount_input: ParsedInput<AbsCurrency>,
 }
 
 #[derive(Clone)]
@@ -51,6 +78,10 @@ pub struct Checkout {
     pub confirm_button_message: Option<&'static str>,
     pub waiting_for_izettle: bool,
     pub overpay_confirm_enabled: bool,
+
+    /// Whether the payment is split between the tenders, instead of paid from `debited_account`
+    pub split: bool,
+    tenders: Vec<Tender>,
 }
 
 #[derive(Resources)]
@@ -68,6 +99,10 @@ struct Res<'a> {
     #[url = "/api/book_accounts"]
     #[policy = "SilentRefetch"]
     book_accounts: &'a HashMap<BookAccountId, BookAccount>,
+
+    #[url = "/api/credit_policy"]
+    #[policy = "SilentRefetch"]
+    credit_policy: &'a CreditPolicy,
 }
 
 impl Checkout {
@@ -83,6 +118,8 @@ impl Checkout {
             waiting_for_izettle: false,
             confirm_button_message: None,
             overpay_confirm_enabled: false,
+            split: false,
+            tenders: vec![],
         }
     }
 
@@ -99,6 +136,36 @@ impl Checkout {
 
         match msg {
             CheckoutMsg::OverpayConfirmPurchase => self.overpay_confirm_enabled = true,
+            CheckoutMsg::ConfirmPurchase if self.split => {
+                if let Some(sale) = self.build_sale(rs) {
+                    self.remove_cleared_items();
+                    self.waiting_for_izettle = true;
+
+                    orders.perform_cmd(async move {
+                        let result = async {
+                            Request::new("/api/sale")
+                                .method(Method::Post)
+                                .json(&sale)?
+                                .fetch()
+                                .await?
+                                .check_status()?
+                                .json::<Sale>()
+                                .await
+                        }
+                        .await;
+                        match result {
+                            Ok(sale) => sale.transaction_ids.first().map(|&transaction_id| {
+                                CheckoutMsg::PurchaseSent { transaction_id }
+                            }),
+                            Err(e) => {
+                                // TODO: show notification
+                                error!("Failed to post sale", e);
+                                None
+                            }
+                        }
+                    });
+                }
+            }
             CheckoutMsg::ConfirmPurchase => {
                 self.remove_cleared_items();
                 if let Some(transaction) = self.build_transaction(rs) {
@@ -135,6 +202,7 @@ impl Checkout {
                 self.transaction_total_input.set_value(Default::default());
                 self.transaction_bundles = vec![];
                 self.debited_account = None;
+                self.tenders.clear();
                 self.override_transaction_total = false;
             }
             CheckoutMsg::TotalInputMsg(msg) => {
@@ -219,6 +287,22 @@ impl Checkout {
             CheckoutMsg::ClearCart => {
                 self.transaction_bundles.clear();
             }
+            CheckoutMsg::ToggleSplit => {
+                self.split = !self.split;
+                self.debited_account = None;
+                self.tenders.clear();
+                self.overpay_confirm_enabled = false;
+            }
+            CheckoutMsg::TenderInputMsg { tender_index, msg } => {
+                if let Some(tender) = self.tenders.get_mut(tender_index) {
+                    tender.amount_input.update(msg);
+                }
+            }
+            CheckoutMsg::RemoveTender(tender_index) => {
+                if tender_index < self.tenders.len() {
+                    self.tenders.remove(tender_index);
+                }
+            }
         }
 
         self.recompute_new_transaction_total();
@@ -252,6 +336,100 @@ impl Checkout {
             })
     }
 
+    /// Build the sale of a split payment.
+    ///
+    /// Returns `None` unless every tender is positive, they add up to the total, and at most one of
+    /// them is paid through iZettle or Swish. That tender is put first, as required by [NewSale].
+    pub fn build_sale(&self, rs: &ResourceStore) -> Option<NewSale> {
+        let res = Res::acquire_now(rs).ok()?;
+
+        if self.tenders.is_empty() || self.unallocated() != Currency::from(0) {
+            return None;
+        }
+
+        let providers = self
+            .tenders
+            .iter()
+            .filter(|tender| tender.kind != TenderKind::Account)
+            .count();
+        if providers > 1 {
+            return None;
+        }
+
+        let mut tenders: Vec<&Tender> = self.tenders.iter().collect();
+        tenders.sort_by_key(|tender| tender.kind == TenderKind::Account);
+
+        let payments = tenders
+            .into_iter()
+            .map(|tender| {
+                let amount: Currency = (*tender.amount_input.parsed()?).into();
+                (amount > Currency::from(0)).then(|| SalePayment {
+                    debited_account: tender.debited_account,
+                    amount,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(NewSale {
+            description: Some(strings::TRANSACTION_SALE.into()),
+            bundles: self.transaction_bundles.clone(),
+            credited_account: res.master_accounts.sales_account_id,
+            payments,
+        })
+    }
+
+    /// The payment provider which charges the split payment, if any of the tenders use one
+    pub fn provider_tender(&self) -> Option<TenderKind> {
+        self.tenders
+            .iter()
+            .map(|tender| tender.kind)
+            .find(|&kind| kind != TenderKind::Account)
+    }
+
+    /// Add a tender to the split payment, covering as much as possible of the unallocated amount.
+    ///
+    /// `limit` caps the initial amount, e.g. at the balance of a tillgodo account.
+    pub fn add_tender(
+        &mut self,
+        debited_account: BookAccountId,
+        kind: TenderKind,
+        label: String,
+        limit: Option<Currency>,
+    ) {
+        let exists = self
+            .tenders
+            .iter()
+            .any(|tender| tender.debited_account == debited_account && tender.kind == kind);
+        if exists {
+            return;
+        }
+
+        let zero = Currency::from(0);
+        let mut amount = self.unallocated().max(zero);
+        if let Some(limit) = limit {
+            amount = amount.min(limit.max(zero));
+        }
+
+        self.tenders.push(Tender {
+            debited_account,
+            kind,
+            label,
+            amount_input: ParsedInput::new_with_value(amount.try_into().unwrap_or_default())
+                .with_error_message(strings::INVALID_MONEY_MESSAGE_SHORT)
+                .with_input_kind("text"),
+        });
+    }
+
+    /// The part of the total which isn't covered by any tender
+    pub fn unallocated(&self) -> Currency {
+        self.tenders
+            .iter()
+            .filter_map(|tender| tender.amount_input.parsed().copied())
+            .fold(self.transaction_amount(), |unallocated, amount| {
+                unallocated - Currency::from(amount)
+            })
+    }
+
     pub fn transaction_amount(&self) -> Currency {
         self.transaction_total_input
             .parsed()
@@ -280,6 +458,30 @@ impl Checkout {
         }
     }
 
+    fn exceeds_credit_limit(res: &Res, book_account_id: &BookAccountId, amount: Currency) -> bool {
+        res.book_accounts
+            .get(book_account_id)
+            .map(|account| account.exceeds_credit_limit(amount, res.credit_policy))
+            .unwrap_or(false)
+    }
+
+    /// Whether the purchase would overdraw any tillgodo account beyond its credit limit
+    fn exceeds_any_credit_limit(&self, res: &Res, rs: &ResourceStore) -> bool {
+        if self.split {
+            self.build_sale(rs)
+                .map(|sale| {
+                    sale.payments.iter().any(|payment| {
+                        Self::exceeds_credit_limit(res, &payment.debited_account, payment.amount)
+                    })
+                })
+                .unwrap_or(false)
+        } else {
+            self.debited_account
+                .map(|account| Self::exceeds_credit_limit(res, &account, self.transaction_amount()))
+                .unwrap_or(false)
+        }
+    }
+
     fn create_submit_button<M: 'static + Clone>(
         show_penguin: bool,
         style: Attrs,
@@ -305,6 +507,35 @@ impl Checkout {
         ]
     }
 
+    fn view_tenders(&self) -> Node<CheckoutMsg> {
+        div![
+            C![C.split_tender_list],
+            self.tenders
+                .iter()
+                .enumerate()
+                .map(|(tender_index, tender)| {
+                    div![
+                        C![C.split_tender_row],
+                        span![C![C.split_tender_label], &tender.label],
+                        tender
+                            .amount_input
+                            .view(C![C.split_tender_amount_field, C.border_on_focus])
+                            .map_msg(move |msg| CheckoutMsg::TenderInputMsg { tender_index, msg }),
+                        button![
+                            C![C.new_transaction_clear_button, C.border_on_focus],
+                            simple_ev(Ev::Click, CheckoutMsg::RemoveTender(tender_index)),
+                        ],
+                    ]
+                })
+                .collect::<Vec<_>>(),
+            div![
+                C![C.split_tender_row],
+                span![C![C.split_tender_label], strings::UNALLOCATED],
+                span![format!("{}:-", self.unallocated())],
+            ],
+        ]
+    }
+
     pub fn view(&self, rs: &ResourceStore) -> Node<CheckoutMsg> {
         let res = match Res::acquire_now(rs) {
             Ok(res) => res,
@@ -385,12 +616,31 @@ impl Checkout {
                     simple_ev(Ev::Click, CheckoutMsg::ClearCart),
                 ],
             ],
+            button![
+                C![C.split_tender_toggle, C.border_on_focus],
+                IF![self.split => C![C.debit_selected]],
+                simple_ev(Ev::Click, CheckoutMsg::ToggleSplit),
+                strings::SPLIT_PAYMENT,
+            ],
+            IF![self.split => self.view_tenders()],
             if self.waiting_for_izettle {
                 Self::create_submit_button(true, C![], None)
+            } else if self.split {
+                if self.transaction_bundles.is_empty() || self.build_sale(rs).is_none() {
+                    Self::create_submit_button(false, C![C.greyed_out], None)
+                } else if self.exceeds_any_credit_limit(&res, rs) {
+                    // Purchases beyond the credit limit are not allowed.
+                    Self::create_submit_button(false, C![C.button_danger], None)
+                } else {
+                    Self::create_submit_button(false, C![], Some(CheckoutMsg::ConfirmPurchase))
+                }
             } else {
                 match &self.debited_account {
                     Some(account) if !self.transaction_bundles.is_empty() => {
-                        if self.too_expensive(&res, account) {
+                        if Self::exceeds_credit_limit(&res, account, self.transaction_amount()) {
+                            // Purchases beyond the credit limit are not allowed.
+                            Self::create_submit_button(false, C![C.button_danger], None)
+                        } else if self.too_expensive(&res, account) {
                             if self.overpay_confirm_enabled {
                                 // Show foldout button to confirm overpay purchase.
                                 div![
@@ -430,6 +680,8 @@ impl Checkout {
             },
             if let Some(message) = &self.confirm_button_message {
                 div![C![C.wide_button_message], message]
+            } else if !self.waiting_for_izettle && self.exceeds_any_credit_limit(&res, rs) {
+                div![C![C.wide_button_message], strings::CREDIT_LIMIT_EXCEEDED]
             } else {
                 empty![]
             },
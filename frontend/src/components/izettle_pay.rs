@@ -2,13 +2,37 @@ use crate::strings;
 use seed::app::cmds::timeout;
 use seed::prelude::*;
 use seed::*;
+use serde::Serialize;
 use strecklistan_api::{
-    izettle::IZettlePayment,
+    izettle::{IZettlePayment, IZettleTerminalId},
+    sale::NewSale,
     transaction::{NewTransaction, TransactionId},
 };
 
 const POLL_TIMEOUT_MS: u32 = 1000;
 
+/// The key in local storage of the terminal which this checkout station is paired with
+const TERMINAL_STORAGE_KEY: &str = "izettle_terminal";
+
+/// The card terminal which payments from this checkout station are charged by
+///
+/// Returns `None` if any terminal may charge the payments.
+pub fn paired_terminal() -> Option<IZettleTerminalId> {
+    LocalStorage::get(TERMINAL_STORAGE_KEY).ok()
+}
+
+/// Pair this checkout station with a card terminal, or with any terminal if `None`
+pub fn pair_terminal(terminal: Option<&str>) {
+    let result = match terminal {
+        Some(terminal) => LocalStorage::insert(TERMINAL_STORAGE_KEY, &terminal),
+        None => LocalStorage::remove(TERMINAL_STORAGE_KEY),
+    };
+
+    if let Err(e) = result {
+        error!("Failed to store paired terminal", e);
+    }
+}
+
 /// Helper component for handling iZettle payments
 #[derive(Clone)]
 pub struct IZettlePay {
@@ -47,24 +71,47 @@ impl IZettlePay {
         IZettlePay { pending: None }
     }
 
-    pub fn pay(&mut self, transaction: NewTransaction, mut orders: impl Orders<IZettlePayMsg>) {
+    pub fn pay(&mut self, transaction: NewTransaction, orders: impl Orders<IZettlePayMsg>) {
+        self.begin("transaction", transaction, orders);
+    }
+
+    /// Charge the first payment of the sale by card. The rest are recorded once it is paid.
+    pub fn pay_sale(&mut self, sale: NewSale, orders: impl Orders<IZettlePayMsg>) {
+        self.begin("sale", sale, orders);
+    }
+
+    fn begin<T>(&mut self, endpoint: &str, payment: T, mut orders: impl Orders<IZettlePayMsg>)
+    where
+        T: Serialize + 'static,
+    {
         if self.pending.is_some() {
             return;
         }
 
+        let url = match paired_terminal() {
+            Some(terminal) => format!("/api/izettle/client/{}?terminal={}", endpoint, terminal),
+            None => format!("/api/izettle/client/{}", endpoint),
+        };
+
         orders.perform_cmd(async move {
             let result = async {
-                Request::new("/api/izettle/client/transaction")
+                Request::new(url)
                     .method(Method::Post)
-                    .json(&transaction)?
+                    .json(&payment)?
                     .fetch()
                     .await?
+                    .check_status()?
                     .json()
                     .await
             }
             .await;
             match result {
                 Ok(reference) => Some(IZettlePayMsg::PollPendingPayment(reference)),
+                Err(FetchError::StatusError(status)) if status.code == 503 => {
+                    Some(IZettlePayMsg::Error(IZettlePayErr::NetworkError {
+                        reason: strings::NO_PAYMENT_TERMINAL.to_string(),
+                    }))
+                }
                 Err(e) => {
                     error!("Failed to post transaction", e);
                     Some(IZettlePayMsg::Error(IZettlePayErr::NetworkError {
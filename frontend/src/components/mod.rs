@@ -3,3 +3,4 @@ pub mod filter_menu;
 pub mod izettle_pay;
 pub mod parsed_input;
 pub mod select;
+pub mod swish_pay;
@@ -0,0 +1,227 @@
+use crate::generated::css_classes::C;
+use crate::strings;
+use crate::util::simple_ev;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use seed::app::cmds::timeout;
+use seed::prelude::*;
+use seed::*;
+use serde::Serialize;
+use strecklistan_api::{
+    sale::NewSale,
+    swish::{SwishPayment, SwishPaymentId, SwishPaymentRequest},
+    transaction::{NewTransaction, TransactionId},
+};
+
+const POLL_TIMEOUT_MS: u32 = 1000;
+
+/// Helper component for handling Swish payments
+#[derive(Clone)]
+pub struct SwishPay {
+    pending: Option<SwishPaymentRequest>,
+}
+
+#[derive(Clone, Debug)]
+pub enum SwishPayMsg {
+    /// The payment was created, show the QR code and poll for completion
+    PaymentCreated(SwishPaymentRequest),
+
+    /// Poll for payment completion
+    PollPendingPayment(SwishPaymentId),
+
+    /// Abort the pending payment
+    Cancel,
+
+    /// There was an error processing the payment
+    Error(SwishPayErr),
+
+    /// The payment was completed and the transaction committed
+    PaymentCompleted { transaction_id: TransactionId },
+
+    /// The payment was intentionally cancelled
+    PaymentCancelled,
+}
+
+#[derive(Clone, Debug)]
+pub enum SwishPayErr {
+    /// No payment existed with the given ID
+    NoTransaction { reference: SwishPaymentId },
+
+    /// The payment failed for some reason
+    PaymentFailed {
+        reference: SwishPaymentId,
+        reason: String,
+    },
+
+    /// A network request has failed
+    NetworkError { reason: String },
+}
+
+impl SwishPay {
+    pub fn new() -> Self {
+        SwishPay { pending: None }
+    }
+
+    pub fn pay(&mut self, transaction: NewTransaction, orders: impl Orders<SwishPayMsg>) {
+        self.begin("/api/swish/client/payment", transaction, orders);
+    }
+
+    /// Request the first payment of the sale through Swish. The rest are recorded once it is paid.
+    pub fn pay_sale(&mut self, sale: NewSale, orders: impl Orders<SwishPayMsg>) {
+        self.begin("/api/swish/client/sale", sale, orders);
+    }
+
+    fn begin<T>(&mut self, url: &'static str, payment: T, mut orders: impl Orders<SwishPayMsg>)
+    where
+        T: Serialize + 'static,
+    {
+        if self.pending.is_some() {
+            return;
+        }
+
+        orders.perform_cmd(async move {
+            let result = async {
+                Request::new(url)
+                    .method(Method::Post)
+                    .json(&payment)?
+                    .fetch()
+                    .await?
+                    .check_status()?
+                    .json()
+                    .await
+            }
+            .await;
+            match result {
+                Ok(request) => Some(SwishPayMsg::PaymentCreated(request)),
+                Err(FetchError::StatusError(status)) if status.code == 503 => {
+                    Some(SwishPayMsg::Error(SwishPayErr::NetworkError {
+                        reason: strings::SWISH_NOT_CONFIGURED.to_string(),
+                    }))
+                }
+                Err(e) => {
+                    error!("Failed to post transaction", e);
+                    Some(SwishPayMsg::Error(SwishPayErr::NetworkError {
+                        reason: strings::POSTING_TRANSACTION_FAILED.to_string(),
+                    }))
+                }
+            }
+        });
+    }
+
+    pub fn pending(&self) -> Option<SwishPaymentId> {
+        self.pending.as_ref().map(|request| request.id)
+    }
+
+    pub fn update(&mut self, msg: SwishPayMsg, mut orders: impl Orders<SwishPayMsg>) {
+        match msg {
+            SwishPayMsg::PaymentCancelled | SwishPayMsg::PaymentCompleted { .. } => {
+                self.pending = None
+            }
+            SwishPayMsg::Error(error) => {
+                self.pending = None;
+                match error {
+                    SwishPayErr::PaymentFailed { reference, reason } => {
+                        error!("Swish payment {} failed: {}", reference, reason);
+                    }
+                    SwishPayErr::NoTransaction { reference } => {
+                        error!("Swish payment {} does not exist", reference);
+                    }
+                    SwishPayErr::NetworkError { .. } => {}
+                }
+            }
+            SwishPayMsg::PaymentCreated(request) => {
+                orders.send_msg(SwishPayMsg::PollPendingPayment(request.id));
+                self.pending = Some(request);
+            }
+            SwishPayMsg::Cancel => {
+                if let Some(reference) = self.pending() {
+                    orders.perform_cmd(async move {
+                        let result = async {
+                            Request::new(format!("/api/swish/client/payment/{}/cancel", reference))
+                                .method(Method::Post)
+                                .fetch()
+                                .await?
+                                .check_status()
+                        }
+                        .await;
+
+                        // the next poll will notice that the payment was cancelled
+                        if let Err(e) = result {
+                            error!("Failed to cancel Swish payment", e);
+                        }
+                    });
+                }
+            }
+            SwishPayMsg::PollPendingPayment(reference) => {
+                // stop polling if the payment is no longer shown
+                if self.pending() != Some(reference) {
+                    return;
+                }
+
+                orders.perform_cmd(async move {
+                    let result = async {
+                        Request::new(&format!("/api/swish/client/poll/{}", reference))
+                            .method(Method::Get)
+                            .fetch()
+                            .await?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(SwishPayment::Pending) => {
+                            timeout(POLL_TIMEOUT_MS, || ()).await;
+                            Some(SwishPayMsg::PollPendingPayment(reference))
+                        }
+                        Ok(SwishPayment::Paid { transaction_id }) => {
+                            Some(SwishPayMsg::PaymentCompleted { transaction_id })
+                        }
+                        Ok(SwishPayment::Cancelled) => Some(SwishPayMsg::PaymentCancelled),
+                        Ok(SwishPayment::NoTransaction) => {
+                            Some(SwishPayMsg::Error(SwishPayErr::NoTransaction { reference }))
+                        }
+                        Ok(SwishPayment::Failed { reason }) => {
+                            Some(SwishPayMsg::Error(SwishPayErr::PaymentFailed {
+                                reference,
+                                reason,
+                            }))
+                        }
+                        Err(e) => {
+                            error!("Failed to poll for payment", e);
+                            Some(SwishPayMsg::Error(SwishPayErr::NetworkError {
+                                reason: strings::POLLING_TRANSACTION_FAILED.to_string(),
+                            }))
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    /// Show the QR code of the pending payment, if any
+    pub fn view(&self) -> Node<SwishPayMsg> {
+        let request = match &self.pending {
+            Some(request) => request,
+            None => return empty![],
+        };
+
+        let qr_code = match QrCode::new(request.qr_payload.as_bytes()) {
+            Ok(code) => code.render::<svg::Color>().min_dimensions(256, 256).build(),
+            Err(e) => {
+                error!("Failed to render Swish QR code", e.to_string());
+                return empty![];
+            }
+        };
+
+        div![
+            C![C.swish_qr_view],
+            div![strings::SCAN_SWISH_QR],
+            div![C![C.swish_qr_code], raw![&qr_code]],
+            button![
+                C![C.wide_button, C.border_on_focus],
+                simple_ev(Ev::Click, SwishPayMsg::Cancel),
+                strings::ABORT,
+            ],
+        ]
+    }
+}
@@ -2,6 +2,7 @@
 #![allow(clippy::single_match)]
 
 mod app;
+mod change_listener;
 mod components;
 mod fuzzy_search;
 mod generated;
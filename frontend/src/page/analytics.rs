@@ -3,20 +3,26 @@ use crate::generated::css_classes::C;
 use crate::page::loading::Loading;
 use crate::util::{simple_ev, DATE_INPUT_FMT};
 use chrono::{DateTime, Datelike, Duration, IsoWeek, NaiveDate, Utc, Weekday};
+use js_sys::encode_uri_component;
 use seed::app::cmds::timeout;
-use seed::{prelude::*, *};
+use seed::{fetch, prelude::*, *};
 use seed_fetcher::Resources;
 use seed_fetcher::{event, NotAvailable, ResourceStore};
 use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
 use strecklistan_api::{
     inventory::{InventoryItemId, InventoryItemStock},
-    transaction::Transaction,
+    transaction::{Transaction, TransactionPage},
 };
 
+/// The number of transactions fetched per request when computing the charts
+const FETCH_PAGE_SIZE: usize = 200;
+
 #[derive(Clone, Debug)]
 pub enum AnalyticsMsg {
     ComputeCharts,
+    FetchedTransactions(Vec<Transaction>),
+    FetchFailed,
     ChartsComputed(Rc<HashMap<InventoryItemId, Node<AnalyticsMsg>>>),
     SetStartDate(String),
     SetEndDate(String),
@@ -46,9 +52,6 @@ pub struct AnalyticsPage {
 
 #[derive(Resources)]
 struct Res<'a> {
-    #[url = "/api/transactions"]
-    transactions: &'a Vec<Transaction>,
-
     #[url = "/api/inventory/items"]
     inventory: &'a HashMap<InventoryItemId, InventoryItemStock>,
 }
@@ -80,7 +83,13 @@ impl AnalyticsPage {
 
         match msg {
             AnalyticsMsg::ComputeCharts => {
-                self.compute_charts(&res, &mut orders_local);
+                self.fetch_transactions(&mut orders_local);
+            }
+            AnalyticsMsg::FetchedTransactions(transactions) => {
+                self.compute_charts(&res, &transactions, &mut orders_local);
+            }
+            AnalyticsMsg::FetchFailed => {
+                self.charts_job = None;
             }
             AnalyticsMsg::ChartsComputed(charts) => {
                 self.charts = charts;
@@ -159,14 +168,67 @@ impl AnalyticsPage {
         .map_msg(Msg::Analytics)
     }
 
-    fn compute_charts(&mut self, res: &Res, orders: &mut impl Orders<AnalyticsMsg>) {
+    /// Fetch the transactions needed to compute the charts
+    ///
+    /// The sales of a week are computed from the stock at the start of the previous week, so the
+    /// transactions are fetched from a week before the start date.
+    fn fetch_transactions(&mut self, orders: &mut impl Orders<AnalyticsMsg>) {
         if self.charts_job.is_some() {
             return;
         }
 
         self.charts = Rc::new(HashMap::new());
 
-        let inventory_by_week = calculate_inventory_by_week(res.transactions);
+        let from = (self.start_date - Duration::weeks(1)).to_rfc3339();
+        let to = (self.end_date + Duration::weeks(1)).to_rfc3339();
+        let url = format!(
+            "/api/transactions/page?from={}&to={}&limit={}",
+            String::from(encode_uri_component(&from)),
+            String::from(encode_uri_component(&to)),
+            FETCH_PAGE_SIZE,
+        );
+
+        self.charts_job = Some(orders.perform_cmd_with_handle(async move {
+            let result: fetch::Result<Vec<Transaction>> = async {
+                let mut transactions = vec![];
+                let mut after = None;
+                loop {
+                    let url = match after {
+                        Some(after) => format!("{}&after={}", url, after),
+                        None => url.clone(),
+                    };
+                    let page: TransactionPage = Request::new(url)
+                        .fetch()
+                        .await?
+                        .check_status()?
+                        .json()
+                        .await?;
+
+                    transactions.extend(page.transactions);
+                    match page.next {
+                        Some(next) => after = Some(next),
+                        None => break Ok(transactions),
+                    }
+                }
+            }
+            .await;
+            match result {
+                Ok(transactions) => AnalyticsMsg::FetchedTransactions(transactions),
+                Err(e) => {
+                    error!("Failed to fetch transactions", e);
+                    AnalyticsMsg::FetchFailed
+                }
+            }
+        }));
+    }
+
+    fn compute_charts(
+        &mut self,
+        res: &Res,
+        transactions: &[Transaction],
+        orders: &mut impl Orders<AnalyticsMsg>,
+    ) {
+        let inventory_by_week = calculate_inventory_by_week(transactions);
         let inventory = res.inventory.clone();
         let start_date = self.start_date;
         let end_date = self.end_date;
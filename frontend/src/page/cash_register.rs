@@ -0,0 +1,307 @@
+use crate::app::Msg;
+use crate::components::parsed_input::{ParsedInput, ParsedInputMsg};
+use crate::generated::css_classes::C;
+use crate::notification_manager::{Notification, NotificationMessage};
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::simple_ev;
+use chrono::{DateTime, Local, Utc};
+use seed::fetch;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{event, NotAvailable, ResourceStore, Resources};
+use strecklistan_api::{
+    cash_register::{
+        CashRegisterSession, CashRegisterSessionId, CloseCashRegisterSession,
+        OpenCashRegisterSession,
+    },
+    currency::AbsCurrency,
+    transaction::TransactionId,
+};
+
+#[derive(Clone, Debug)]
+pub enum CashRegisterMsg {
+    ResFetched(event::Fetched),
+    ResMarkDirty(event::MarkDirty),
+
+    OpeningFloatInput(ParsedInputMsg),
+    CountedCashInput(ParsedInputMsg),
+
+    Open,
+    Close,
+
+    Opened,
+    Closed(Option<TransactionId>),
+    ServerError(String),
+}
+
+pub struct CashRegisterPage {
+    opening_float: ParsedInput<AbsCurrency>,
+    counted_cash: ParsedInput<AbsCurrency>,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/cash_register/sessions"]
+    #[policy = "SilentRefetch"]
+    sessions: &'a Vec<CashRegisterSession>,
+}
+
+impl Res<'_> {
+    /// The session which is currently open, if any
+    fn open_session(&self) -> Option<&CashRegisterSession> {
+        self.sessions.iter().find(|session| session.is_open())
+    }
+}
+
+impl CashRegisterPage {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<CashRegisterMsg>) -> Self {
+        orders.subscribe(CashRegisterMsg::ResFetched);
+        orders.subscribe(CashRegisterMsg::ResMarkDirty);
+        let _ = Res::acquire(rs, orders);
+        CashRegisterPage {
+            opening_float: money_input(),
+            counted_cash: money_input(),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        msg: CashRegisterMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<Msg>,
+    ) -> Result<(), NotAvailable> {
+        let res = Res::acquire(rs, orders)?;
+
+        let mut orders_local = orders.proxy(Msg::CashRegister);
+
+        match msg {
+            CashRegisterMsg::ResFetched(_) => {}
+            CashRegisterMsg::ResMarkDirty(_) => {}
+            CashRegisterMsg::OpeningFloatInput(msg) => self.opening_float.update(msg),
+            CashRegisterMsg::CountedCashInput(msg) => self.counted_cash.update(msg),
+            CashRegisterMsg::Open => {
+                let opening_float = match self.opening_float.parsed() {
+                    Some(&amount) => amount.into(),
+                    None => return Ok(()),
+                };
+
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<CashRegisterSessionId> = async {
+                        Request::new("/api/cash_register/session")
+                            .method(Method::Post)
+                            .json(&OpenCashRegisterSession { opening_float })?
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+
+                    match result {
+                        Ok(_) => CashRegisterMsg::Opened,
+                        Err(e) => {
+                            error!("Failed to open cash register", e);
+                            CashRegisterMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            CashRegisterMsg::Close => {
+                let id = match res.open_session() {
+                    Some(session) => session.id,
+                    None => return Ok(()),
+                };
+                let counted_cash = match self.counted_cash.parsed() {
+                    Some(&amount) => amount.into(),
+                    None => return Ok(()),
+                };
+
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<Option<TransactionId>> = async {
+                        Request::new(format!("/api/cash_register/session/{}/close", id))
+                            .method(Method::Post)
+                            .json(&CloseCashRegisterSession { counted_cash })?
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+
+                    match result {
+                        Ok(transaction_id) => CashRegisterMsg::Closed(transaction_id),
+                        Err(e) => {
+                            error!("Failed to close cash register", e);
+                            CashRegisterMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            CashRegisterMsg::Opened | CashRegisterMsg::Closed(_) => {
+                let title = match msg {
+                    CashRegisterMsg::Opened => strings::CASH_REGISTER_OPENED,
+                    _ => strings::CASH_REGISTER_CLOSED,
+                };
+                self.opening_float = money_input();
+                self.counted_cash = money_input();
+                rs.mark_as_dirty(Res::sessions_url(), orders);
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 5000,
+                    notification: Notification {
+                        title: title.to_string(),
+                        body: None,
+                    },
+                }));
+            }
+            CashRegisterMsg::ServerError(message) => {
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 10000,
+                    notification: Notification {
+                        title: strings::SERVER_ERROR.to_string(),
+                        body: Some(message),
+                    },
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return Loading::view(),
+        };
+
+        let content = match res.open_session() {
+            Some(session) => self.view_open(session),
+            None => self.view_closed(),
+        };
+
+        div![C![C.inventory_page], content, view_history(&res)].map_msg(Msg::CashRegister)
+    }
+
+    fn view_open(&self, session: &CashRegisterSession) -> Node<CashRegisterMsg> {
+        let table_wide = || attrs! { At::ColSpan => 2 };
+
+        table![
+            td![
+                table_wide(),
+                h1![format!("{} #{}", strings::CASH_REGISTER, session.id)],
+            ],
+            tr![
+                td!["Öppnad"],
+                td![format!(
+                    "{} {}",
+                    format_time(session.opened_at),
+                    session.opened_by.as_deref().unwrap_or_default(),
+                )],
+            ],
+            tr![
+                td![strings::OPENING_FLOAT],
+                td![session.opening_float.to_string()],
+            ],
+            tr![
+                td![strings::EXPECTED_CASH],
+                td![session.expected_cash.to_string()],
+            ],
+            tr![
+                td![strings::COUNTED_CASH],
+                td![self
+                    .counted_cash
+                    .view(C![C.inventory_page_input])
+                    .map_msg(CashRegisterMsg::CountedCashInput)],
+            ],
+            tr![td![
+                table_wide(),
+                button![
+                    C![C.wide_button],
+                    IF!(self.counted_cash.parsed().is_none() => attrs! {At::Disabled => true}),
+                    simple_ev(Ev::Click, CashRegisterMsg::Close),
+                    strings::CLOSE_CASH_REGISTER,
+                ],
+            ]],
+        ]
+    }
+
+    fn view_closed(&self) -> Node<CashRegisterMsg> {
+        let table_wide = || attrs! { At::ColSpan => 2 };
+
+        table![
+            td![table_wide(), h1![strings::CASH_REGISTER]],
+            tr![
+                td![strings::OPENING_FLOAT],
+                td![self
+                    .opening_float
+                    .view(C![C.inventory_page_input])
+                    .map_msg(CashRegisterMsg::OpeningFloatInput)],
+            ],
+            tr![td![
+                table_wide(),
+                button![
+                    C![C.wide_button],
+                    IF!(self.opening_float.parsed().is_none() => attrs! {At::Disabled => true}),
+                    simple_ev(Ev::Click, CashRegisterMsg::Open),
+                    strings::OPEN_CASH_REGISTER,
+                ],
+            ]],
+        ]
+    }
+}
+
+fn money_input() -> ParsedInput<AbsCurrency> {
+    ParsedInput::new()
+        .with_input_kind("number")
+        .with_error_message(strings::INVALID_MONEY_MESSAGE_SHORT)
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+fn view_history(res: &Res) -> Node<CashRegisterMsg> {
+    let table_wide = || attrs! { At::ColSpan => 7 };
+
+    let session_row = |session: &CashRegisterSession| {
+        tr![
+            td![session.id],
+            td![format_time(session.opened_at)],
+            td![session.closed_at.map(format_time).unwrap_or_default()],
+            td![session.expected_cash.to_string()],
+            td![session
+                .counted_cash
+                .map(|counted| counted.to_string())
+                .unwrap_or_default()],
+            td![session
+                .difference()
+                .map(|difference| difference.to_string())
+                .unwrap_or_default()],
+            td![IF!(!session.is_open() => a![
+                strings::Z_REPORT,
+                attrs! {
+                    At::Href => format!("/api/cash_register/session/{}/z_report", session.id),
+                    At::Target => "_blank",
+                },
+            ])],
+        ]
+    };
+
+    table![
+        td![table_wide(), h1!["Historik"]],
+        tr![
+            th!["ID"],
+            th!["Öppnad"],
+            th!["Stängd"],
+            th!["Förväntat"],
+            th!["Räknat"],
+            th![strings::CASH_DIFFERENCE],
+            th![],
+        ],
+        res.sessions.iter().map(session_row),
+    ]
+}
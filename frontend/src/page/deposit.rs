@@ -1,6 +1,7 @@
 use crate::app::Msg;
 use crate::components::izettle_pay::{IZettlePay, IZettlePayErr, IZettlePayMsg};
 use crate::components::parsed_input::{ParsedInput, ParsedInputMsg};
+use crate::components::swish_pay::{SwishPay, SwishPayErr, SwishPayMsg};
 use crate::fuzzy_search::{FuzzyScore, FuzzySearch};
 use crate::generated::css_classes::C;
 use crate::notification_manager::{Notification, NotificationMessage};
@@ -29,6 +30,7 @@ pub struct DepositionPage {
     credit_account: Option<BookAccountId>,
     amount_input: ParsedInput<AbsCurrency>,
     izettle_pay: IZettlePay,
+    swish_pay: SwishPay,
 
     new_member: Option<(String, String, String, Option<String>)>,
 
@@ -55,6 +57,7 @@ pub enum DepositionMsg {
     },
 
     IZettlePay(IZettlePayMsg),
+    SwishPay(SwishPayMsg),
 
     ShowNewMemberMenu,
     NewMember(NewMemberMsg),
@@ -78,6 +81,7 @@ pub enum NewMemberMsg {
 #[derive(Clone, Copy, Debug)]
 pub enum DebitOption {
     IZettleEPay,
+    Swish,
     OtherEPay,
     #[allow(dead_code)]
     Cash,
@@ -103,6 +107,7 @@ impl DepositionPage {
         let mut page = DepositionPage {
             debit: None,
             izettle_pay: IZettlePay::new(),
+            swish_pay: SwishPay::new(),
             credit_account: None,
             search_string: String::new(),
             accs_search: vec![],
@@ -172,9 +177,9 @@ impl DepositionPage {
                         credited_account: credit_acc,
                         debited_account: match debit {
                             DebitOption::Cash => res.master_accounts.cash_account_id,
-                            DebitOption::IZettleEPay | DebitOption::OtherEPay => {
-                                res.master_accounts.bank_account_id
-                            }
+                            DebitOption::IZettleEPay
+                            | DebitOption::Swish
+                            | DebitOption::OtherEPay => res.master_accounts.bank_account_id,
                         },
                         bundles: vec![],
                     };
@@ -184,6 +189,9 @@ impl DepositionPage {
                     if let DebitOption::IZettleEPay = debit {
                         self.izettle_pay
                             .pay(transaction, orders_local.proxy(DepositionMsg::IZettlePay));
+                    } else if let DebitOption::Swish = debit {
+                        self.swish_pay
+                            .pay(transaction, orders_local.proxy(DepositionMsg::SwishPay));
                     } else {
                         orders_local.perform_cmd(async move {
                             let result = async {
@@ -286,6 +294,46 @@ impl DepositionPage {
                     .update(msg, orders_local.proxy(DepositionMsg::IZettlePay));
             }
 
+            DepositionMsg::SwishPay(msg) => {
+                let reaction = match &msg {
+                    &SwishPayMsg::PaymentCompleted { transaction_id } => {
+                        Some(DepositionMsg::DepositSent { transaction_id })
+                    }
+                    SwishPayMsg::PaymentCancelled => Some(DepositionMsg::DepositFailed {
+                        message_title: strings::PAYMENT_CANCELLED.to_string(),
+                        message_body: None,
+                    }),
+                    SwishPayMsg::Error(SwishPayErr::PaymentFailed { reason, .. }) => {
+                        Some(DepositionMsg::DepositFailed {
+                            message_title: strings::PAYMENT_FAILED.to_string(),
+                            message_body: Some(reason.clone()),
+                        })
+                    }
+                    SwishPayMsg::Error(SwishPayErr::NoTransaction { .. }) => {
+                        Some(DepositionMsg::DepositFailed {
+                            message_title: strings::SERVER_ERROR.to_string(),
+                            message_body: Some(strings::NO_PENDING_TRANSACTION.to_string()),
+                        })
+                    }
+                    SwishPayMsg::Error(SwishPayErr::NetworkError { reason }) => {
+                        Some(DepositionMsg::DepositFailed {
+                            message_title: strings::SERVER_ERROR.to_string(),
+                            message_body: Some(reason.clone()),
+                        })
+                    }
+                    SwishPayMsg::PaymentCreated(_)
+                    | SwishPayMsg::PollPendingPayment(_)
+                    | SwishPayMsg::Cancel => None,
+                };
+
+                if let Some(msg) = reaction {
+                    orders_local.send_msg(msg);
+                }
+
+                self.swish_pay
+                    .update(msg, orders_local.proxy(DepositionMsg::SwishPay));
+            }
+
             DepositionMsg::ShowNewMemberMenu => {
                 self.new_member = Some((String::new(), String::new(), String::new(), None));
             }
@@ -485,6 +533,16 @@ impl DepositionPage {
                             ),
                             strings::IZETTLE,
                         ],
+                        button![
+                            if let Some(DebitOption::Swish) = self.debit {
+                                C![C.debit_selected]
+                            } else {
+                                C![]
+                            },
+                            C![C.select_debit_button, C.border_on_focus],
+                            simple_ev(Ev::Click, DepositionMsg::SelectDebit(DebitOption::Swish)),
+                            strings::SWISH,
+                        ],
                         button![
                             if let Some(DebitOption::OtherEPay) = self.debit {
                                 C![C.debit_selected]
@@ -537,11 +595,12 @@ impl DepositionPage {
                             strings::DEPOSIT,
                         ]
                     },
-                    if self.izettle_pay.pending().is_some() {
+                    if self.izettle_pay.pending().is_some() || self.swish_pay.pending().is_some() {
                         div![C![C.wide_button_message], strings::WAITING_FOR_PAYMENT]
                     } else {
                         empty![]
                     },
+                    self.swish_pay.view().map_msg(DepositionMsg::SwishPay),
                 ],
             ]
         }
@@ -0,0 +1,670 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::notification_manager::{Notification, NotificationMessage};
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::simple_ev;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use seed::fetch;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{event, NotAvailable, ResourceStore, Resources};
+use std::collections::HashMap;
+use strecklistan_api::event::{
+    Event, EventId, EventSignupId, EventSignupWithStatus, NewEvent, NewEventSignup, SignupStatus,
+};
+use strecklistan_api::member::{Member, MemberId};
+
+/// The format used by `datetime-local` inputs
+const DATETIME_INPUT_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+#[derive(Clone, Debug)]
+pub enum EventsMsg {
+    ResFetched(event::Fetched),
+    ResMarkDirty(event::MarkDirty),
+
+    TitleInput(String),
+    LocationInput(String),
+    BackgroundInput(String),
+    StartTimeInput(String),
+    EndTimeInput(String),
+    PriceInput(String),
+    CapacityInput(String),
+    DeadlineInput(String),
+
+    /// Fill the form with an existing event, so that it can be edited
+    Edit(EventId),
+    CancelEdit,
+    Save,
+    SetPublished(EventId, bool),
+    Saved,
+    Published,
+
+    /// Show the signups of an event, or hide them if they are already shown
+    ToggleSignups(EventId),
+    FetchedSignups(EventId, Vec<EventSignupWithStatus>),
+    CancelSignup(EventId, EventSignupId),
+    SignupsChanged(EventId),
+
+    /// Sign up a member, and charge the ticket to its tillgodo account
+    SelectMember(String),
+    SignUpMember(EventId),
+
+    ServerError(String),
+}
+
+pub struct EventsPage {
+    /// The event which is being edited, or `None` if the form creates a new event
+    editing: Option<EventId>,
+
+    title: String,
+    location: String,
+    background: String,
+    start_time: String,
+    end_time: String,
+    price: String,
+    capacity: String,
+    cancellation_deadline: String,
+
+    /// The event whose signups are shown, and the signups once they have been fetched
+    signups: Option<(EventId, Option<Vec<EventSignupWithStatus>>)>,
+
+    /// The member to sign up for the event whose signups are shown
+    signup_member: Option<MemberId>,
+
+    request_in_progress: bool,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/events/all"]
+    #[policy = "SilentRefetch"]
+    events: &'a Vec<Event>,
+
+    #[url = "/api/members"]
+    #[policy = "SilentRefetch"]
+    members: &'a HashMap<MemberId, Member>,
+}
+
+impl EventsPage {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<EventsMsg>) -> Self {
+        orders.subscribe(EventsMsg::ResFetched);
+        orders.subscribe(EventsMsg::ResMarkDirty);
+        Res::acquire(rs, orders).ok();
+
+        EventsPage {
+            editing: None,
+            title: String::new(),
+            location: String::new(),
+            background: String::new(),
+            start_time: String::new(),
+            end_time: String::new(),
+            price: String::from("0"),
+            capacity: String::new(),
+            cancellation_deadline: String::new(),
+            signups: None,
+            signup_member: None,
+            request_in_progress: false,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        msg: EventsMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<Msg>,
+    ) -> Result<(), NotAvailable> {
+        let res = Res::acquire(rs, orders)?;
+
+        let mut orders_local = orders.proxy(Msg::Events);
+
+        match msg {
+            EventsMsg::ResFetched(_) => {}
+            EventsMsg::ResMarkDirty(_) => {}
+            EventsMsg::TitleInput(input) => self.title = input,
+            EventsMsg::LocationInput(input) => self.location = input,
+            EventsMsg::BackgroundInput(input) => self.background = input,
+            EventsMsg::StartTimeInput(input) => self.start_time = input,
+            EventsMsg::EndTimeInput(input) => self.end_time = input,
+            EventsMsg::PriceInput(input) => self.price = input,
+            EventsMsg::CapacityInput(input) => self.capacity = input,
+            EventsMsg::DeadlineInput(input) => self.cancellation_deadline = input,
+            EventsMsg::Edit(id) => {
+                if let Some(event) = res.events.iter().find(|event| event.id == id) {
+                    self.editing = Some(id);
+                    self.title = event.title.clone();
+                    self.location = event.location.clone();
+                    self.background = event.background.clone();
+                    self.start_time = format_datetime_input(event.start_time);
+                    self.end_time = format_datetime_input(event.end_time);
+                    self.price = event.price.to_string();
+                    self.capacity = event
+                        .capacity
+                        .map(|capacity| capacity.to_string())
+                        .unwrap_or_default();
+                    self.cancellation_deadline = event
+                        .cancellation_deadline
+                        .map(format_datetime_input)
+                        .unwrap_or_default();
+                }
+            }
+            EventsMsg::CancelEdit => self.clear_form(),
+            EventsMsg::Save => {
+                if self.request_in_progress {
+                    return Ok(());
+                }
+
+                let event = match self.new_event() {
+                    Some(event) => event,
+                    None => {
+                        orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                            duration_ms: 5000,
+                            notification: Notification {
+                                title: strings::INVALID_EVENT.to_string(),
+                                body: None,
+                            },
+                        }));
+                        return Ok(());
+                    }
+                };
+
+                self.request_in_progress = true;
+                let request = match self.editing {
+                    Some(id) => Request::new(format!("/api/event/{}", id)).method(Method::Put),
+                    None => Request::new("/api/event").method(Method::Post),
+                };
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<_> =
+                        async { request.json(&event)?.fetch().await?.check_status() }.await;
+
+                    match result {
+                        Ok(_) => EventsMsg::Saved,
+                        Err(e) => {
+                            error!("Failed to save event", e);
+                            EventsMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            EventsMsg::SetPublished(id, published) => {
+                if self.request_in_progress {
+                    return Ok(());
+                }
+
+                self.request_in_progress = true;
+                let action = if published { "publish" } else { "unpublish" };
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<_> = async {
+                        Request::new(format!("/api/event/{}/{}", id, action))
+                            .method(Method::Post)
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+
+                    match result {
+                        Ok(_) => EventsMsg::Published,
+                        Err(e) => {
+                            error!("Failed to publish event", e);
+                            EventsMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            EventsMsg::Saved => {
+                self.request_in_progress = false;
+                self.clear_form();
+                rs.mark_as_dirty(Res::events_url(), orders);
+            }
+            EventsMsg::Published => {
+                self.request_in_progress = false;
+                rs.mark_as_dirty(Res::events_url(), orders);
+            }
+            EventsMsg::ToggleSignups(id) => {
+                if matches!(self.signups, Some((shown, _)) if shown == id) {
+                    self.signups = None;
+                } else {
+                    self.signups = Some((id, None));
+                    fetch_signups(id, &mut orders_local);
+                }
+            }
+            EventsMsg::FetchedSignups(id, signups) => {
+                if let Some((shown, shown_signups)) = &mut self.signups {
+                    if *shown == id {
+                        *shown_signups = Some(signups);
+                    }
+                }
+            }
+            EventsMsg::CancelSignup(event_id, signup_id) => {
+                if self.request_in_progress {
+                    return Ok(());
+                }
+
+                self.request_in_progress = true;
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<_> = async {
+                        Request::new(format!("/api/event/{}/signup/{}", event_id, signup_id))
+                            .method(Method::Delete)
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+
+                    match result {
+                        Ok(_) => EventsMsg::SignupsChanged(event_id),
+                        Err(e) => {
+                            error!("Failed to cancel signup", e);
+                            EventsMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            EventsMsg::SelectMember(input) => self.signup_member = input.parse().ok(),
+            EventsMsg::SignUpMember(event_id) => {
+                if self.request_in_progress {
+                    return Ok(());
+                }
+
+                let member = match self.signup_member.and_then(|id| res.members.get(&id)) {
+                    Some(member) => member,
+                    None => return Ok(()),
+                };
+
+                self.request_in_progress = true;
+                let signup = NewEventSignup {
+                    name: format!("{} {}", member.first_name, member.last_name),
+                    email: String::new(),
+                    member: Some(member.id),
+                };
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<_> = async {
+                        Request::new(format!("/api/event/{}/signup", event_id))
+                            .method(Method::Post)
+                            .json(&signup)?
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+
+                    match result {
+                        Ok(_) => EventsMsg::SignupsChanged(event_id),
+                        Err(e) => {
+                            error!("Failed to sign up member", e);
+                            EventsMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            EventsMsg::SignupsChanged(id) => {
+                self.request_in_progress = false;
+                fetch_signups(id, &mut orders_local);
+                rs.mark_as_dirty(Res::events_url(), orders);
+            }
+            EventsMsg::ServerError(message) => {
+                self.request_in_progress = false;
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 10000,
+                    notification: Notification {
+                        title: strings::SERVER_ERROR.to_string(),
+                        body: Some(message),
+                    },
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The event described by the form, if it is filled in correctly
+    fn new_event(&self) -> Option<NewEvent> {
+        let capacity = match self.capacity.trim() {
+            "" => None,
+            capacity => Some(capacity.parse().ok()?),
+        };
+
+        let cancellation_deadline = match self.cancellation_deadline.trim() {
+            "" => None,
+            deadline => Some(parse_datetime_input(deadline)?),
+        };
+
+        let event = NewEvent {
+            title: self.title.trim().to_string(),
+            background: self.background.trim().to_string(),
+            location: self.location.trim().to_string(),
+            start_time: parse_datetime_input(&self.start_time)?,
+            end_time: parse_datetime_input(&self.end_time)?,
+            price: self.price.trim().parse().ok()?,
+            capacity,
+            cancellation_deadline,
+        };
+
+        event.validate().ok().map(|_| event)
+    }
+
+    fn clear_form(&mut self) {
+        self.editing = None;
+        self.title.clear();
+        self.location.clear();
+        self.background.clear();
+        self.start_time.clear();
+        self.end_time.clear();
+        self.price = String::from("0");
+        self.capacity.clear();
+        self.cancellation_deadline.clear();
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return Loading::view(),
+        };
+
+        let disabled = || IF!(self.request_in_progress => attrs! {At::Disabled => true});
+
+        let event_row = |event: &Event| {
+            let signups_shown = matches!(self.signups, Some((shown, _)) if shown == event.id);
+            let mut rows = vec![tr![
+                td![&event.title],
+                td![format_time(event.start_time)],
+                td![&event.location],
+                td![format!("{}:-", event.price)],
+                td![match event.capacity {
+                    Some(capacity) => format!("{}/{}", event.attendees(), capacity),
+                    None => event.attendees().to_string(),
+                }],
+                td![event.waitlisted().to_string()],
+                td![button![
+                    C![C.wide_button],
+                    disabled(),
+                    simple_ev(
+                        Ev::Click,
+                        EventsMsg::SetPublished(event.id, !event.published)
+                    ),
+                    if event.published {
+                        strings::UNPUBLISH
+                    } else {
+                        strings::PUBLISH
+                    },
+                ]],
+                td![button![
+                    C![C.wide_button],
+                    simple_ev(Ev::Click, EventsMsg::Edit(event.id)),
+                    strings::EDIT,
+                ]],
+                td![button![
+                    C![C.wide_button],
+                    simple_ev(Ev::Click, EventsMsg::ToggleSignups(event.id)),
+                    strings::SIGNUPS,
+                ]],
+            ]];
+            if signups_shown {
+                rows.extend(self.view_signups(event, res.members));
+            }
+            rows
+        };
+
+        let text_input = |placeholder: &str, value: &str, msg: fn(String) -> EventsMsg| {
+            input![
+                C![C.border_on_focus, C.inventory_page_input],
+                attrs! {At::Placeholder => placeholder},
+                attrs! {At::Value => value},
+                input_ev(Ev::Input, msg),
+            ]
+        };
+
+        let typed_input =
+            |input_type: &str, placeholder: &str, value: &str, msg: fn(String) -> EventsMsg| {
+                input![
+                    C![C.border_on_focus, C.inventory_page_input],
+                    attrs! {At::Type => input_type},
+                    attrs! {At::Placeholder => placeholder},
+                    attrs! {At::Value => value},
+                    input_ev(Ev::Input, msg),
+                ]
+            };
+
+        div![
+            C![C.inventory_page],
+            table![
+                td![attrs! { At::ColSpan => 9 }, h1![strings::EVENTS]],
+                tr![
+                    th![strings::TITLE],
+                    th![strings::START_TIME],
+                    th![strings::LOCATION],
+                    th![strings::PRICE],
+                    th![strings::ATTENDEES],
+                    th![strings::WAITLIST],
+                    th![],
+                    th![],
+                    th![],
+                ],
+                res.events.iter().flat_map(event_row).collect::<Vec<_>>(),
+            ],
+            table![
+                td![
+                    attrs! { At::ColSpan => 2 },
+                    h1![if self.editing.is_some() {
+                        strings::EDIT_EVENT
+                    } else {
+                        strings::NEW_EVENT
+                    }],
+                ],
+                tr![
+                    td![strings::TITLE],
+                    td![text_input(
+                        strings::TITLE,
+                        &self.title,
+                        EventsMsg::TitleInput
+                    )],
+                ],
+                tr![
+                    td![strings::LOCATION],
+                    td![text_input(
+                        strings::LOCATION,
+                        &self.location,
+                        EventsMsg::LocationInput
+                    )],
+                ],
+                tr![
+                    td![strings::DESCRIPTION],
+                    td![textarea![
+                        C![C.border_on_focus, C.inventory_page_input],
+                        attrs! {At::Placeholder => strings::DESCRIPTION},
+                        attrs! {At::Value => self.background},
+                        input_ev(Ev::Input, EventsMsg::BackgroundInput),
+                    ]],
+                ],
+                tr![
+                    td![strings::START_TIME],
+                    td![typed_input(
+                        "datetime-local",
+                        strings::START_TIME,
+                        &self.start_time,
+                        EventsMsg::StartTimeInput
+                    )],
+                ],
+                tr![
+                    td![strings::END_TIME],
+                    td![typed_input(
+                        "datetime-local",
+                        strings::END_TIME,
+                        &self.end_time,
+                        EventsMsg::EndTimeInput
+                    )],
+                ],
+                tr![
+                    td![strings::PRICE],
+                    td![typed_input(
+                        "number",
+                        strings::PRICE,
+                        &self.price,
+                        EventsMsg::PriceInput
+                    )],
+                ],
+                tr![
+                    td![strings::CAPACITY],
+                    td![typed_input(
+                        "number",
+                        strings::UNLIMITED,
+                        &self.capacity,
+                        EventsMsg::CapacityInput
+                    )],
+                ],
+                tr![
+                    td![strings::CANCELLATION_DEADLINE],
+                    td![typed_input(
+                        "datetime-local",
+                        strings::CANCELLATION_DEADLINE,
+                        &self.cancellation_deadline,
+                        EventsMsg::DeadlineInput
+                    )],
+                ],
+                tr![
+                    td![IF!(self.editing.is_some() => button![
+                        C![C.wide_button],
+                        simple_ev(Ev::Click, EventsMsg::CancelEdit),
+                        strings::CANCEL,
+                    ])],
+                    td![button![
+                        C![C.wide_button],
+                        disabled(),
+                        simple_ev(Ev::Click, EventsMsg::Save),
+                        strings::SAVE,
+                    ]],
+                ],
+            ],
+        ]
+        .map_msg(Msg::Events)
+    }
+
+    fn view_signups(
+        &self,
+        event: &Event,
+        members: &HashMap<MemberId, Member>,
+    ) -> Vec<Node<EventsMsg>> {
+        let signups = match &self.signups {
+            Some((_, Some(signups))) => signups,
+            _ => return vec![tr![td![attrs! { At::ColSpan => 9 }, Loading::view()]]],
+        };
+
+        let event_id = event.id;
+        let signup_row = |signup: &EventSignupWithStatus| {
+            tr![
+                td![],
+                td![attrs! { At::ColSpan => 3 }, &signup.signup.name],
+                td![attrs! { At::ColSpan => 2 }, &signup.signup.email],
+                td![match signup.status {
+                    SignupStatus::Attending => strings::ATTENDING.to_string(),
+                    SignupStatus::Waitlisted { position } =>
+                        format!("{} #{}", strings::WAITLIST, position),
+                }],
+                td![IF!(signup.signup.transaction_id.is_some() => strings::PAID)],
+                td![button![
+                    C![C.wide_button],
+                    IF!(self.request_in_progress => attrs! {At::Disabled => true}),
+                    simple_ev(
+                        Ev::Click,
+                        EventsMsg::CancelSignup(event_id, signup.signup.id)
+                    ),
+                    strings::CANCEL_SIGNUP,
+                ]],
+            ]
+        };
+
+        let mut members: Vec<&Member> = members
+            .values()
+            .filter(|member| member.is_active())
+            .collect();
+        members.sort_by(|a, b| (&a.first_name, &a.last_name).cmp(&(&b.first_name, &b.last_name)));
+
+        let member_row = tr![
+            td![],
+            td![
+                attrs! { At::ColSpan => 5 },
+                select![
+                    C![C.inventory_page_input],
+                    input_ev(Ev::Change, EventsMsg::SelectMember),
+                    option![
+                        attrs! {At::Value => ""},
+                        IF!(self.signup_member.is_none() => attrs! {At::Selected => true}),
+                        strings::CHOOSE_MEMBER,
+                    ],
+                    members.into_iter().map(|member| option![
+                        attrs! {At::Value => member.id.to_string()},
+                        IF!(self.signup_member == Some(member.id) => attrs! {At::Selected => true}),
+                        format!("{} {}", member.first_name, member.last_name),
+                    ]),
+                ],
+            ],
+            td![if event.price > 0 {
+                format!("{}:-", event.price)
+            } else {
+                String::new()
+            }],
+            td![],
+            td![button![
+                C![C.wide_button],
+                IF!(self.request_in_progress || self.signup_member.is_none()
+                    => attrs! {At::Disabled => true}),
+                simple_ev(Ev::Click, EventsMsg::SignUpMember(event_id)),
+                strings::SIGN_UP,
+            ]],
+        ];
+
+        let mut rows = if signups.is_empty() {
+            vec![tr![
+                td![],
+                td![attrs! { At::ColSpan => 8 }, strings::NO_SIGNUPS]
+            ]]
+        } else {
+            signups.iter().map(signup_row).collect()
+        };
+        rows.push(member_row);
+        rows
+    }
+}
+
+fn fetch_signups(id: EventId, orders: &mut impl Orders<EventsMsg>) {
+    orders.perform_cmd(async move {
+        let result: fetch::Result<Vec<EventSignupWithStatus>> = async {
+            Request::new(format!("/api/event/{}/signups", id))
+                .fetch()
+                .await?
+                .check_status()?
+                .json()
+                .await
+        }
+        .await;
+
+        match result {
+            Ok(signups) => EventsMsg::FetchedSignups(id, signups),
+            Err(e) => {
+                error!("Failed to fetch signups", e);
+                EventsMsg::ServerError(format!("{:?}", e))
+            }
+        }
+    });
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+fn format_datetime_input(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format(DATETIME_INPUT_FORMAT)
+        .to_string()
+}
+
+fn parse_datetime_input(input: &str) -> Option<DateTime<Utc>> {
+    let time = NaiveDateTime::parse_from_str(input, DATETIME_INPUT_FORMAT).ok()?;
+    Local
+        .from_local_datetime(&time)
+        .single()
+        .map(|time| time.with_timezone(&Utc))
+}
@@ -243,6 +243,7 @@ impl InventoryPage {
             }
             InventoryMsg::ItemsChanged => {
                 rs.mark_as_dirty(Res::items_url(), orders);
+                rs.mark_as_dirty("/api/inventory/items/deleted", orders);
             }
             InventoryMsg::BundlesChanged => {
                 rs.mark_as_dirty(Res::bundles_url(), orders);
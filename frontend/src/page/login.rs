@@ -0,0 +1,106 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::strings;
+use seed::{prelude::*, *};
+use strecklistan_api::user::{Credentials, Session};
+
+#[derive(Clone, Debug)]
+pub enum LoginMsg {
+    NameInput(String),
+    PasswordInput(String),
+    Submit,
+    LoginFailed(String),
+}
+
+#[derive(Default)]
+pub struct LoginPage {
+    name: String,
+    password: String,
+    error: Option<String>,
+    pending: bool,
+}
+
+impl LoginPage {
+    pub fn update(&mut self, msg: LoginMsg, orders: &mut impl Orders<Msg>) {
+        match msg {
+            LoginMsg::NameInput(name) => self.name = name,
+            LoginMsg::PasswordInput(password) => self.password = password,
+            LoginMsg::Submit => {
+                if self.pending {
+                    return;
+                }
+                self.pending = true;
+                self.error = None;
+
+                let credentials = Credentials {
+                    name: self.name.clone(),
+                    password: std::mem::take(&mut self.password),
+                };
+
+                orders.perform_cmd(async move {
+                    let result: fetch::Result<Session> = async {
+                        Request::new("/api/login")
+                            .method(Method::Post)
+                            .json(&credentials)?
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+
+                    match result {
+                        Ok(session) => Msg::LoggedIn(session.user),
+                        Err(FetchError::StatusError(_)) => {
+                            Msg::Login(LoginMsg::LoginFailed(strings::LOGIN_FAILED.to_string()))
+                        }
+                        Err(e) => {
+                            error!("Failed to log in", e);
+                            Msg::Login(LoginMsg::LoginFailed(strings::SERVER_ERROR.to_string()))
+                        }
+                    }
+                });
+            }
+            LoginMsg::LoginFailed(message) => {
+                self.pending = false;
+                self.error = Some(message);
+            }
+        }
+    }
+
+    pub fn view(&self) -> Node<Msg> {
+        form![
+            C![C.login_page],
+            ev(Ev::Submit, |event| {
+                event.prevent_default();
+                LoginMsg::Submit
+            }),
+            input![
+                C![C.border_on_focus, C.login_page_item],
+                attrs! {At::Placeholder => strings::USER_NAME},
+                attrs! {At::Value => self.name},
+                attrs! {At::AutoComplete => "username"},
+                input_ev(Ev::Input, LoginMsg::NameInput),
+            ],
+            input![
+                C![C.border_on_focus, C.login_page_item],
+                attrs! {At::Type => "password"},
+                attrs! {At::Placeholder => strings::PASSWORD},
+                attrs! {At::Value => self.password},
+                attrs! {At::AutoComplete => "current-password"},
+                input_ev(Ev::Input, LoginMsg::PasswordInput),
+            ],
+            button![
+                C![C.border_on_focus, C.wide_button, C.login_page_item],
+                attrs! {At::Type => "submit"},
+                IF!(self.pending || self.name.is_empty() => attrs! {At::Disabled => true}),
+                strings::LOGIN,
+            ],
+            self.error
+                .as_ref()
+                .map(|error| p![C![C.login_page_error], error]),
+        ]
+        .map_msg(Msg::Login)
+    }
+}
@@ -1,16 +1,29 @@
 pub mod analytics;
+pub mod cash_register;
 pub mod deposit;
+pub mod events;
 pub mod inventory;
 pub mod loading;
+pub mod login;
+pub mod purchase;
+pub mod stock_take;
 pub mod store;
+pub mod terminals;
 pub mod transactions;
+pub mod trash;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Page {
     Analytics,
+    CashRegister,
     Deposit,
+    Events,
     Inventory,
     NotFound,
+    Purchases,
+    StockTake,
     Store,
+    Terminals,
     TransactionHistory,
+    Trash,
 }
@@ -0,0 +1,404 @@
+use crate::app::Msg;
+use crate::components::parsed_input::{ParsedInput, ParsedInputMsg};
+use crate::generated::css_classes::C;
+use crate::notification_manager::{Notification, NotificationMessage};
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::simple_ev;
+use chrono::Local;
+use seed::fetch;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{event, NotAvailable, ResourceStore, Resources};
+use std::collections::HashMap;
+use strecklistan_api::{
+    book_account::MasterAccounts,
+    currency::Currency,
+    inventory::{InventoryItemId, InventoryItemStock as InventoryItem},
+    purchase::{NewPurchase, Purchase, PurchaseId, PurchaseLine},
+};
+
+#[derive(Clone, Debug)]
+pub enum PurchaseMsg {
+    ResFetched(event::Fetched),
+    ResMarkDirty(event::MarkDirty),
+
+    SupplierInput(String),
+    InvoiceInput(String),
+    SelectPayment(PaymentOption),
+
+    SelectItem(usize, String),
+    QuantityInput(usize, ParsedInputMsg),
+    UnitCostInput(usize, ParsedInputMsg),
+    AddLine,
+    RemoveLine(usize),
+
+    Submit,
+    Submitted(PurchaseId),
+    ServerError(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaymentOption {
+    Bank,
+    Cash,
+}
+
+struct LineInput {
+    item_id: Option<InventoryItemId>,
+    quantity: ParsedInput<u32>,
+    unit_cost: ParsedInput<Currency>,
+}
+
+pub struct PurchasePage {
+    supplier: String,
+    invoice_reference: String,
+    payment: PaymentOption,
+    lines: Vec<LineInput>,
+    request_in_progress: bool,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/purchases"]
+    #[policy = "SilentRefetch"]
+    purchases: &'a Vec<Purchase>,
+
+    #[url = "/api/inventory/items"]
+    #[policy = "SilentRefetch"]
+    items: &'a HashMap<InventoryItemId, InventoryItem>,
+
+    #[url = "/api/book_accounts/masters"]
+    master_accounts: &'a MasterAccounts,
+}
+
+impl LineInput {
+    fn new() -> Self {
+        LineInput {
+            item_id: None,
+            quantity: ParsedInput::new()
+                .with_input_kind("number")
+                .with_error_message(strings::INVALID_QUANTITY),
+            unit_cost: ParsedInput::new().with_error_message(strings::INVALID_MONEY_MESSAGE_SHORT),
+        }
+    }
+
+    fn parsed(&self) -> Option<PurchaseLine> {
+        Some(PurchaseLine {
+            item_id: self.item_id?,
+            quantity: *self.quantity.parsed().filter(|&&q| q > 0)?,
+            unit_cost: *self.unit_cost.parsed()?,
+        })
+    }
+}
+
+impl PurchasePage {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<PurchaseMsg>) -> Self {
+        orders.subscribe(PurchaseMsg::ResFetched);
+        orders.subscribe(PurchaseMsg::ResMarkDirty);
+        Res::acquire(rs, orders).ok();
+
+        PurchasePage {
+            supplier: String::new(),
+            invoice_reference: String::new(),
+            payment: PaymentOption::Bank,
+            lines: vec![LineInput::new()],
+            request_in_progress: false,
+        }
+    }
+
+    /// Get the purchase, if all fields are valid
+    fn parsed(&self, res: &Res) -> Option<NewPurchase> {
+        if self.supplier.is_empty() {
+            return None;
+        }
+
+        let purchase = NewPurchase {
+            supplier: self.supplier.clone(),
+            invoice_reference: Some(self.invoice_reference.clone()).filter(|r| !r.is_empty()),
+            credited_account: match self.payment {
+                PaymentOption::Bank => res.master_accounts.bank_account_id,
+                PaymentOption::Cash => res.master_accounts.cash_account_id,
+            },
+            lines: self
+                .lines
+                .iter()
+                .map(LineInput::parsed)
+                .collect::<Option<_>>()?,
+        };
+
+        // the server rejects purchases whose total cost overflows
+        purchase.total().map(|_| purchase)
+    }
+
+    pub fn update(
+        &mut self,
+        msg: PurchaseMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<Msg>,
+    ) -> Result<(), NotAvailable> {
+        let res = Res::acquire(rs, orders)?;
+
+        let mut orders_local = orders.proxy(Msg::Purchase);
+
+        match msg {
+            PurchaseMsg::ResFetched(_) => {}
+            PurchaseMsg::ResMarkDirty(_) => {}
+            PurchaseMsg::SupplierInput(supplier) => self.supplier = supplier,
+            PurchaseMsg::InvoiceInput(reference) => self.invoice_reference = reference,
+            PurchaseMsg::SelectPayment(payment) => self.payment = payment,
+            PurchaseMsg::SelectItem(i, item_id) => {
+                if let Some(line) = self.lines.get_mut(i) {
+                    line.item_id = item_id.parse().ok();
+
+                    // suggest the unit cost of the last purchase of the item
+                    let last_cost = line.item_id.and_then(|item_id| {
+                        res.purchases
+                            .iter()
+                            .flat_map(|purchase| purchase.lines.iter())
+                            .find(|purchase_line| purchase_line.item_id == item_id)
+                            .map(|purchase_line| purchase_line.unit_cost)
+                    });
+
+                    if let (Some(cost), true) = (last_cost, line.unit_cost.text().is_empty()) {
+                        line.unit_cost.set_value(cost);
+                    }
+                }
+            }
+            PurchaseMsg::QuantityInput(i, msg) => {
+                if let Some(line) = self.lines.get_mut(i) {
+                    line.quantity.update(msg);
+                }
+            }
+            PurchaseMsg::UnitCostInput(i, msg) => {
+                if let Some(line) = self.lines.get_mut(i) {
+                    line.unit_cost.update(msg);
+                }
+            }
+            PurchaseMsg::AddLine => self.lines.push(LineInput::new()),
+            PurchaseMsg::RemoveLine(i) => {
+                if i < self.lines.len() {
+                    self.lines.remove(i);
+                }
+            }
+            PurchaseMsg::Submit => {
+                let purchase = match self.parsed(&res) {
+                    Some(purchase) if !self.request_in_progress => purchase,
+                    _ => return Ok(()),
+                };
+
+                self.request_in_progress = true;
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<PurchaseId> = async {
+                        Request::new("/api/purchase")
+                            .method(Method::Post)
+                            .json(&purchase)?
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+
+                    match result {
+                        Ok(id) => PurchaseMsg::Submitted(id),
+                        Err(e) => {
+                            error!("Failed to post purchase", e);
+                            PurchaseMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            PurchaseMsg::Submitted(_) => {
+                self.request_in_progress = false;
+                self.supplier.clear();
+                self.invoice_reference.clear();
+                self.lines = vec![LineInput::new()];
+                rs.mark_as_dirty(Res::purchases_url(), orders);
+                rs.mark_as_dirty(Res::items_url(), orders);
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 5000,
+                    notification: Notification {
+                        title: strings::PURCHASE_RECORDED.to_string(),
+                        body: None,
+                    },
+                }));
+            }
+            PurchaseMsg::ServerError(message) => {
+                self.request_in_progress = false;
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 10000,
+                    notification: Notification {
+                        title: strings::SERVER_ERROR.to_string(),
+                        body: Some(message),
+                    },
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return Loading::view(),
+        };
+
+        let mut items: Vec<&InventoryItem> = res
+            .items
+            .values()
+            .filter(|item| item.deleted_at.is_none())
+            .collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let table_wide = || attrs! { At::ColSpan => 5 };
+
+        let line_row = |(i, line): (usize, &LineInput)| {
+            let cost = line
+                .parsed()
+                .and_then(|line| line.cost())
+                .map(|cost| cost.to_string())
+                .unwrap_or_default();
+
+            tr![
+                td![select![
+                    C![C.inventory_page_input],
+                    input_ev(Ev::Change, move |id| PurchaseMsg::SelectItem(i, id)),
+                    option![
+                        attrs! {At::Value => ""},
+                        IF!(line.item_id.is_none() => attrs! {At::Selected => true}),
+                        strings::CHOOSE_ITEM,
+                    ],
+                    items.iter().map(|item| option![
+                        attrs! {At::Value => item.id.to_string()},
+                        IF!(line.item_id == Some(item.id) => attrs! {At::Selected => true}),
+                        &item.name,
+                    ]),
+                ]],
+                td![line
+                    .quantity
+                    .view(C![C.inventory_page_input])
+                    .map_msg(move |msg| PurchaseMsg::QuantityInput(i, msg))],
+                td![line
+                    .unit_cost
+                    .view(C![C.inventory_page_input])
+                    .map_msg(move |msg| PurchaseMsg::UnitCostInput(i, msg))],
+                td![cost],
+                td![button![
+                    C![C.inventory_page_delete_button],
+                    simple_ev(Ev::Click, PurchaseMsg::RemoveLine(i)),
+                ]],
+            ]
+        };
+
+        let payment_button = |option: PaymentOption, label: &str, rounding| {
+            button![
+                IF!(self.payment == option => C![C.debit_selected]),
+                C![C.select_debit_button, C.border_on_focus, rounding],
+                simple_ev(Ev::Click, PurchaseMsg::SelectPayment(option)),
+                label,
+            ]
+        };
+
+        let purchase = self.parsed(&res);
+        let total = purchase
+            .as_ref()
+            .and_then(NewPurchase::total)
+            .map(|total| total.to_string())
+            .unwrap_or_default();
+
+        let purchase_row = |purchase: &Purchase| {
+            tr![
+                td![purchase
+                    .time
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d")
+                    .to_string()],
+                td![&purchase.supplier],
+                td![purchase.invoice_reference.as_deref().unwrap_or_default()],
+                td![purchase
+                    .lines
+                    .iter()
+                    .map(|line| format!(
+                        "{} x{}",
+                        res.items
+                            .get(&line.item_id)
+                            .map(|item| item.name.as_str())
+                            .unwrap_or("?"),
+                        line.quantity,
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")],
+                td![purchase.amount.to_string()],
+            ]
+        };
+
+        div![
+            C![C.inventory_page],
+            table![
+                td![table_wide(), h1![strings::NEW_PURCHASE]],
+                tr![td![
+                    table_wide(),
+                    input![
+                        C![C.border_on_focus, C.inventory_page_input],
+                        attrs! {At::Placeholder => strings::SUPPLIER},
+                        attrs! {At::Value => self.supplier},
+                        input_ev(Ev::Input, PurchaseMsg::SupplierInput),
+                    ],
+                    input![
+                        C![C.border_on_focus, C.inventory_page_input],
+                        attrs! {At::Placeholder => strings::INVOICE_REFERENCE},
+                        attrs! {At::Value => self.invoice_reference},
+                        input_ev(Ev::Input, PurchaseMsg::InvoiceInput),
+                    ],
+                ]],
+                tr![td![
+                    table_wide(),
+                    div![
+                        C![C.select_debit_container],
+                        payment_button(PaymentOption::Bank, strings::BANK, C.rounded_l),
+                        payment_button(PaymentOption::Cash, strings::CASH, C.rounded_r),
+                    ],
+                ]],
+                tr![
+                    th!["Vara"],
+                    th!["Antal"],
+                    th!["Styckpris"],
+                    th!["Summa"],
+                    th![],
+                ],
+                self.lines.iter().enumerate().map(line_row),
+                tr![td![
+                    table_wide(),
+                    button![
+                        C![C.wide_button],
+                        simple_ev(Ev::Click, PurchaseMsg::AddLine),
+                        strings::ADD_PURCHASE_LINE,
+                    ],
+                ]],
+                tr![td![
+                    table_wide(),
+                    button![
+                        C![C.wide_button],
+                        IF!(purchase.is_none() || self.request_in_progress =>
+                            attrs! {At::Disabled => true}),
+                        simple_ev(Ev::Click, PurchaseMsg::Submit),
+                        format!("{} {}", strings::RECORD_PURCHASE, total),
+                    ],
+                ]],
+                td![table_wide(), h1![strings::PURCHASES]],
+                tr![
+                    th!["Datum"],
+                    th!["Leverantör"],
+                    th!["Referens"],
+                    th!["Varor"],
+                    th!["Summa"],
+                ],
+                res.purchases.iter().map(purchase_row),
+            ],
+        ]
+        .map_msg(Msg::Purchase)
+    }
+}
@@ -0,0 +1,362 @@
+use crate::app::Msg;
+use crate::components::parsed_input::{ParsedInput, ParsedInputMsg};
+use crate::generated::css_classes::C;
+use crate::notification_manager::{Notification, NotificationMessage};
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::simple_ev;
+use chrono::Local;
+use seed::fetch;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{event, NotAvailable, ResourceStore, Resources};
+use std::collections::{BTreeMap, HashMap};
+use strecklistan_api::{
+    inventory::{InventoryItemId, InventoryItemStock as InventoryItem},
+    stock_take::{CommitStockTake, StockTake, StockTakeDiff, StockTakeId},
+    transaction::TransactionId,
+};
+
+#[derive(Clone, Debug)]
+pub enum StockTakeMsg {
+    ResFetched(event::Fetched),
+    ResMarkDirty(event::MarkDirty),
+
+    Start,
+    SaveCounts,
+    Commit,
+    Abandon,
+
+    CountInput(InventoryItemId, ParsedInputMsg),
+
+    StockTakesChanged,
+    Committed(Option<TransactionId>),
+    ServerError(String),
+}
+
+pub struct StockTakePage {
+    /// The counts entered for the open stock take, which may not yet be saved
+    counts: BTreeMap<InventoryItemId, ParsedInput<u32>>,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/stock_takes"]
+    #[policy = "SilentRefetch"]
+    stock_takes: &'a Vec<StockTake>,
+
+    #[url = "/api/inventory/items"]
+    #[policy = "SilentRefetch"]
+    items: &'a HashMap<InventoryItemId, InventoryItem>,
+}
+
+impl Res<'_> {
+    /// The newest stock take that hasn't been committed
+    fn open_stock_take(&self) -> Option<&StockTake> {
+        self.stock_takes
+            .iter()
+            .find(|stock_take| stock_take.committed_at.is_none())
+    }
+}
+
+impl StockTakePage {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<StockTakeMsg>) -> Self {
+        orders.subscribe(StockTakeMsg::ResFetched);
+        orders.subscribe(StockTakeMsg::ResMarkDirty);
+        let mut p = StockTakePage {
+            counts: Default::default(),
+        };
+        if let Ok(res) = Res::acquire(rs, orders) {
+            p.rebuild_data(&res);
+        }
+        p
+    }
+
+    pub fn update(
+        &mut self,
+        msg: StockTakeMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<Msg>,
+    ) -> Result<(), NotAvailable> {
+        let res = Res::acquire(rs, orders)?;
+
+        let mut orders_local = orders.proxy(Msg::StockTake);
+
+        match msg {
+            StockTakeMsg::ResFetched(_) => self.rebuild_data(&res),
+            StockTakeMsg::ResMarkDirty(_) => {}
+            StockTakeMsg::Start => {
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<StockTakeId> = async {
+                        Request::new("/api/stock_take")
+                            .method(Method::Post)
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+
+                    match result {
+                        Ok(_) => StockTakeMsg::StockTakesChanged,
+                        Err(e) => {
+                            error!("Failed to start stock take", e);
+                            StockTakeMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            StockTakeMsg::SaveCounts | StockTakeMsg::Commit => {
+                let stock_take = match res.open_stock_take() {
+                    Some(stock_take) => stock_take,
+                    None => return Ok(()),
+                };
+                let id = stock_take.id;
+                let counts = self.unsaved_counts(stock_take);
+                let commit = matches!(msg, StockTakeMsg::Commit);
+
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<Option<Option<TransactionId>>> = async {
+                        if !counts.is_empty() {
+                            Request::new(format!("/api/stock_take/{}/counts", id))
+                                .method(Method::Put)
+                                .json(&counts)?
+                                .fetch()
+                                .await?
+                                .check_status()?;
+                        }
+
+                        if commit {
+                            let transaction_id =
+                                Request::new(format!("/api/stock_take/{}/commit", id))
+                                    .method(Method::Post)
+                                    .json(&CommitStockTake::default())?
+                                    .fetch()
+                                    .await?
+                                    .check_status()?
+                                    .json()
+                                    .await?;
+                            Ok(Some(transaction_id))
+                        } else {
+                            Ok(None)
+                        }
+                    }
+                    .await;
+
+                    match result {
+                        Ok(Some(transaction_id)) => StockTakeMsg::Committed(transaction_id),
+                        Ok(None) => StockTakeMsg::StockTakesChanged,
+                        Err(e) => {
+                            error!("Failed to save stock take", e);
+                            StockTakeMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            StockTakeMsg::Abandon => {
+                if let Some(stock_take) = res.open_stock_take() {
+                    let id = stock_take.id;
+                    orders_local.perform_cmd(async move {
+                        let result: fetch::Result<()> = async {
+                            Request::new(format!("/api/stock_take/{}", id))
+                                .method(Method::Delete)
+                                .fetch()
+                                .await?
+                                .check_status()?;
+                            Ok(())
+                        }
+                        .await;
+
+                        match result {
+                            Ok(_) => StockTakeMsg::StockTakesChanged,
+                            Err(e) => {
+                                error!("Failed to abandon stock take", e);
+                                StockTakeMsg::ServerError(format!("{:?}", e))
+                            }
+                        }
+                    });
+                }
+            }
+            StockTakeMsg::CountInput(item_id, msg) => {
+                self.counts
+                    .entry(item_id)
+                    .or_insert_with(count_input)
+                    .update(msg);
+            }
+            StockTakeMsg::StockTakesChanged => {
+                rs.mark_as_dirty(Res::stock_takes_url(), orders);
+            }
+            StockTakeMsg::Committed(_) => {
+                self.counts.clear();
+                rs.mark_as_dirty(Res::stock_takes_url(), orders);
+                rs.mark_as_dirty(Res::items_url(), orders);
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 5000,
+                    notification: Notification {
+                        title: strings::STOCK_TAKE_COMPLETE.to_string(),
+                        body: None,
+                    },
+                }));
+            }
+            StockTakeMsg::ServerError(message) => {
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 10000,
+                    notification: Notification {
+                        title: strings::SERVER_ERROR.to_string(),
+                        body: Some(message),
+                    },
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the entered counts which differ from what is stored on the server
+    fn unsaved_counts(&self, stock_take: &StockTake) -> HashMap<InventoryItemId, u32> {
+        self.counts
+            .iter()
+            .filter_map(|(&item_id, input)| Some((item_id, *input.parsed()?)))
+            .filter(|(item_id, count)| stock_take.counts.get(item_id) != Some(&(*count as i32)))
+            .collect()
+    }
+
+    fn rebuild_data(&mut self, res: &Res) {
+        let stock_take = match res.open_stock_take() {
+            Some(stock_take) => stock_take,
+            None => {
+                self.counts.clear();
+                return;
+            }
+        };
+
+        for (&item_id, &count) in stock_take.counts.iter() {
+            self.counts
+                .entry(item_id)
+                .or_insert_with(|| count_input_with_value(count as u32));
+        }
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return Loading::view(),
+        };
+
+        let content = match res.open_stock_take() {
+            Some(stock_take) => self.view_open(&res, stock_take),
+            None => view_history(&res),
+        };
+
+        div![C![C.inventory_page], content].map_msg(Msg::StockTake)
+    }
+
+    fn view_open(&self, res: &Res, stock_take: &StockTake) -> Node<StockTakeMsg> {
+        let table_wide = || attrs! { At::ColSpan => 4 };
+
+        let mut items: Vec<&InventoryItem> = res
+            .items
+            .values()
+            .filter(|item| item.deleted_at.is_none())
+            .collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let item_row = |item: &InventoryItem| {
+            let input = self.counts.get(&item.id);
+            let diff = input.and_then(|input| input.parsed()).map(|&count| {
+                StockTakeDiff {
+                    item_id: item.id,
+                    expected: item.stock,
+                    counted: count as i32,
+                }
+                .change()
+            });
+            let saved = stock_take.counts.contains_key(&item.id);
+
+            tr![
+                td![&item.name],
+                td![item.stock],
+                td![match input {
+                    Some(input) => input.view(C![C.inventory_page_input]),
+                    None => count_input().view(C![C.inventory_page_input]),
+                }
+                .map_msg({
+                    let item_id = item.id;
+                    move |msg| StockTakeMsg::CountInput(item_id, msg)
+                })],
+                td![
+                    IF!(!saved => C![C.greyed_out]),
+                    diff.map(|diff| format!("{:+}", diff)).unwrap_or_default(),
+                ],
+            ]
+        };
+
+        let wide_button = |label: &str, msg: StockTakeMsg| {
+            tr![td![
+                table_wide(),
+                button![C![C.wide_button], simple_ev(Ev::Click, msg), label],
+            ]]
+        };
+
+        table![
+            td![
+                table_wide(),
+                h1![format!("{} #{}", strings::STOCK_TAKE, stock_take.id)],
+            ],
+            tr![th!["Namn"], th!["Lager"], th!["Räknat"], th!["Skillnad"],],
+            items.into_iter().map(item_row),
+            wide_button(strings::SAVE, StockTakeMsg::SaveCounts),
+            wide_button(strings::COMMIT_STOCK_TAKE, StockTakeMsg::Commit),
+            tr![td![
+                table_wide(),
+                button![
+                    C![C.wide_button, C.button_danger],
+                    simple_ev(Ev::Click, StockTakeMsg::Abandon),
+                    strings::ABANDON_STOCK_TAKE,
+                ],
+            ]],
+        ]
+    }
+}
+
+fn count_input() -> ParsedInput<u32> {
+    ParsedInput::new().with_input_kind("number")
+}
+
+fn count_input_with_value(count: u32) -> ParsedInput<u32> {
+    ParsedInput::new_with_value(count).with_input_kind("number")
+}
+
+fn view_history(res: &Res) -> Node<StockTakeMsg> {
+    let table_wide = || attrs! { At::ColSpan => 4 };
+
+    let stock_take_row = |stock_take: &StockTake| {
+        tr![
+            td![stock_take.id],
+            td![stock_take
+                .committed_at
+                .map(|time| time
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string())
+                .unwrap_or_default()],
+            td![stock_take.started_by.as_deref().unwrap_or_default()],
+            td![stock_take.counts.len().to_string()],
+        ]
+    };
+
+    table![
+        tr![td![
+            table_wide(),
+            button![
+                C![C.wide_button],
+                simple_ev(Ev::Click, StockTakeMsg::Start),
+                strings::START_STOCK_TAKE,
+            ],
+        ]],
+        td![table_wide(), h1![strings::STOCK_TAKE]],
+        tr![th!["ID"], th!["Datum"], th!["Användare"], th!["Varor"]],
+        res.stock_takes.iter().map(stock_take_row),
+    ]
+}
@@ -1,6 +1,7 @@
 use crate::app::Msg;
-use crate::components::checkout::{Checkout, CheckoutMsg};
+use crate::components::checkout::{Checkout, CheckoutMsg, TenderKind};
 use crate::components::izettle_pay::{IZettlePay, IZettlePayErr, IZettlePayMsg};
+use crate::components::swish_pay::{SwishPay, SwishPayErr, SwishPayMsg};
 use crate::fuzzy_search::{FuzzyScore, FuzzySearch};
 use crate::generated::css_classes::C;
 use crate::notification_manager::{Notification, NotificationMessage};
@@ -10,7 +11,7 @@ use crate::util::{compare_fuzzy, simple_ev};
 use crate::views::{view_inventory_bundle, view_inventory_item, view_tillgodo};
 use seed::prelude::*;
 use seed::*;
-use seed_fetcher::{event, DontFetch, NotAvailable, ResourceStore, Resources};
+use seed_fetcher::{event, NotAvailable, ResourceStore, Resources};
 use std::collections::HashMap;
 use strecklistan_api::{
     book_account::{BookAccount, BookAccountId, MasterAccounts},
@@ -87,7 +88,8 @@ pub enum StoreMsg {
     DebitSelect(SelectedDebit),
 
     IZettleMsg(IZettlePayMsg),
-    CancelIZettle {
+    SwishMsg(SwishPayMsg),
+    CancelPayment {
         message_title: String,
         message_body: Option<String>,
     },
@@ -110,11 +112,13 @@ pub struct StorePage {
     selected_debit: Option<SelectedDebit>,
 
     izettle_pay: IZettlePay,
+    swish_pay: SwishPay,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SelectedDebit {
     IZettleEPay,
+    Swish,
     OtherEPay,
     Tillgodo(BookAccountId),
 
@@ -140,10 +144,6 @@ struct Res<'a> {
 
     #[url = "/api/members"]
     members: &'a HashMap<MemberId, Member>,
-
-    #[url = "/api/transactions"]
-    #[allow(dead_code)]
-    transactions: DontFetch,
 }
 
 impl StorePage {
@@ -162,6 +162,7 @@ impl StorePage {
             selected_debit: None,
 
             izettle_pay: IZettlePay::new(),
+            swish_pay: SwishPay::new(),
         };
         if let Ok(state) = Res::acquire(rs, orders) {
             p.rebuild_data(&state);
@@ -199,6 +200,25 @@ impl StorePage {
                 }
                 _ => {}
             },
+            StoreMsg::DebitSelect(selected) if self.checkout.split => {
+                self.tillgodolista_search_string = String::new();
+                let acc_id = selected.acc_id(&res);
+                let (kind, label, limit) = match selected {
+                    SelectedDebit::IZettleEPay => {
+                        (TenderKind::IZettle, strings::IZETTLE.into(), None)
+                    }
+                    SelectedDebit::Swish => (TenderKind::Swish, strings::SWISH.into(), None),
+                    SelectedDebit::OtherEPay => {
+                        (TenderKind::Account, strings::OTHER_EPAY.into(), None)
+                    }
+                    SelectedDebit::Cash => (TenderKind::Account, strings::CASH.into(), None),
+                    SelectedDebit::Tillgodo(_) => match res.book_accounts.get(&acc_id) {
+                        Some(acc) => (TenderKind::Account, acc.name.clone(), Some(acc.balance)),
+                        None => return Ok(()),
+                    },
+                };
+                self.checkout.add_tender(acc_id, kind, label, limit);
+            }
             StoreMsg::DebitSelect(selected) => {
                 self.selected_debit = Some(selected);
                 self.tillgodolista_search_string = String::new();
@@ -236,24 +256,24 @@ impl StorePage {
                             transaction_id,
                         }))
                     }
-                    IZettlePayMsg::PaymentCancelled => Some(StoreMsg::CancelIZettle {
+                    IZettlePayMsg::PaymentCancelled => Some(StoreMsg::CancelPayment {
                         message_title: strings::PAYMENT_CANCELLED.to_string(),
                         message_body: None,
                     }),
                     IZettlePayMsg::Error(IZettlePayErr::PaymentFailed { reason, .. }) => {
-                        Some(StoreMsg::CancelIZettle {
+                        Some(StoreMsg::CancelPayment {
                             message_title: strings::PAYMENT_FAILED.to_string(),
                             message_body: Some(reason.clone()),
                         })
                     }
                     IZettlePayMsg::Error(IZettlePayErr::NoTransaction { .. }) => {
-                        Some(StoreMsg::CancelIZettle {
+                        Some(StoreMsg::CancelPayment {
                             message_title: strings::SERVER_ERROR.to_string(),
                             message_body: Some(strings::NO_PENDING_TRANSACTION.to_string()),
                         })
                     }
                     IZettlePayMsg::Error(IZettlePayErr::NetworkError { reason }) => {
-                        Some(StoreMsg::CancelIZettle {
+                        Some(StoreMsg::CancelPayment {
                             message_title: strings::SERVER_ERROR.to_string(),
                             message_body: Some(reason.clone()),
                         })
@@ -268,8 +288,49 @@ impl StorePage {
                 self.izettle_pay
                     .update(msg, orders_local.proxy(StoreMsg::IZettleMsg));
             }
+            StoreMsg::SwishMsg(msg) => {
+                let reaction = match &msg {
+                    &SwishPayMsg::PaymentCompleted { transaction_id } => {
+                        Some(StoreMsg::CheckoutMsg(CheckoutMsg::PurchaseSent {
+                            transaction_id,
+                        }))
+                    }
+                    SwishPayMsg::PaymentCancelled => Some(StoreMsg::CancelPayment {
+                        message_title: strings::PAYMENT_CANCELLED.to_string(),
+                        message_body: None,
+                    }),
+                    SwishPayMsg::Error(SwishPayErr::PaymentFailed { reason, .. }) => {
+                        Some(StoreMsg::CancelPayment {
+                            message_title: strings::PAYMENT_FAILED.to_string(),
+                            message_body: Some(reason.clone()),
+                        })
+                    }
+                    SwishPayMsg::Error(SwishPayErr::NoTransaction { .. }) => {
+                        Some(StoreMsg::CancelPayment {
+                            message_title: strings::SERVER_ERROR.to_string(),
+                            message_body: Some(strings::NO_PENDING_TRANSACTION.to_string()),
+                        })
+                    }
+                    SwishPayMsg::Error(SwishPayErr::NetworkError { reason }) => {
+                        Some(StoreMsg::CancelPayment {
+                            message_title: strings::SERVER_ERROR.to_string(),
+                            message_body: Some(reason.clone()),
+                        })
+                    }
+                    SwishPayMsg::PaymentCreated(_)
+                    | SwishPayMsg::PollPendingPayment(_)
+                    | SwishPayMsg::Cancel => None,
+                };
+
+                if let Some(msg) = reaction {
+                    orders_local.send_msg(msg);
+                }
+
+                self.swish_pay
+                    .update(msg, orders_local.proxy(StoreMsg::SwishMsg));
+            }
 
-            StoreMsg::CancelIZettle {
+            StoreMsg::CancelPayment {
                 message_title,
                 message_body,
             } => {
@@ -286,6 +347,27 @@ impl StorePage {
 
             StoreMsg::CheckoutMsg(msg) => {
                 let forward_msg = match msg {
+                    // split payments through iZettle or Swish are also handled here
+                    CheckoutMsg::ConfirmPurchase
+                        if self.checkout.split && self.checkout.provider_tender().is_some() =>
+                    {
+                        if let Some(sale) = self.checkout.build_sale(rs) {
+                            self.checkout.waiting_for_izettle = true;
+                            self.checkout.remove_cleared_items();
+                            self.checkout.confirm_button_message =
+                                Some(strings::WAITING_FOR_PAYMENT);
+                            match self.checkout.provider_tender() {
+                                Some(TenderKind::IZettle) => self
+                                    .izettle_pay
+                                    .pay_sale(sale, orders_local.proxy(StoreMsg::IZettleMsg)),
+                                Some(TenderKind::Swish) => self
+                                    .swish_pay
+                                    .pay_sale(sale, orders_local.proxy(StoreMsg::SwishMsg)),
+                                Some(TenderKind::Account) | None => {}
+                            }
+                        }
+                        None // don't forward the message
+                    }
                     // if iZettle integration is enabled we intercept and handle the purchase here
                     CheckoutMsg::ConfirmPurchase
                         if self.selected_debit == Some(SelectedDebit::IZettleEPay) =>
@@ -300,11 +382,27 @@ impl StorePage {
                         }
                         None // don't forward the message
                     }
+                    CheckoutMsg::ConfirmPurchase
+                        if self.selected_debit == Some(SelectedDebit::Swish) =>
+                    {
+                        if let Some(transaction) = self.checkout.build_transaction(rs) {
+                            self.checkout.waiting_for_izettle = true;
+                            self.checkout.remove_cleared_items();
+                            self.checkout.confirm_button_message =
+                                Some(strings::WAITING_FOR_PAYMENT);
+                            self.swish_pay
+                                .pay(transaction, orders_local.proxy(StoreMsg::SwishMsg));
+                        }
+                        None // don't forward the message
+                    }
+                    CheckoutMsg::ToggleSplit => {
+                        self.selected_debit = None;
+                        Some(CheckoutMsg::ToggleSplit)
+                    }
                     // show a notification & reload inventory when a purchase completes
                     CheckoutMsg::PurchaseSent { .. } => {
                         rs.mark_as_dirty(Res::inventory_url(), orders);
                         rs.mark_as_dirty(Res::book_accounts_url(), orders);
-                        rs.mark_as_dirty(Res::transactions_url(), orders);
                         orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
                             duration_ms: 5000,
                             notification: Notification {
@@ -362,6 +460,13 @@ impl StorePage {
                 acc.creditor
                     .map(|member_id| (Default::default(), acc.id, member_id))
             })
+            // Don't show deactivated members
+            .filter(|(_, _, member_id)| {
+                res.members
+                    .get(member_id)
+                    .map(|member| member.is_active())
+                    .unwrap_or(false)
+            })
             .collect();
 
         self.sort_tillgodolista_search(res);
@@ -482,6 +587,15 @@ impl StorePage {
                             ),
                             strings::IZETTLE,
                         ],
+                        button![
+                            apply_selection_class_on(&|sd| sd == SelectedDebit::Swish),
+                            C![C.select_debit_button, C.border_on_focus],
+                            simple_ev(
+                                Ev::Click,
+                                Msg::Store(StoreMsg::DebitSelect(SelectedDebit::Swish))
+                            ),
+                            strings::SWISH,
+                        ],
                         button![
                             apply_selection_class_on(&|sd| sd == SelectedDebit::OtherEPay),
                             C![C.select_debit_button, C.border_on_focus, C.rounded_br],
@@ -527,6 +641,10 @@ impl StorePage {
                 .view(rs)
                 .map_msg(StoreMsg::CheckoutMsg)
                 .map_msg(Msg::Store),
+            self.swish_pay
+                .view()
+                .map_msg(StoreMsg::SwishMsg)
+                .map_msg(Msg::Store),
         ]
     }
 }
@@ -536,6 +654,7 @@ impl SelectedDebit {
         match self {
             SelectedDebit::Cash => res.master_accounts.cash_account_id,
             SelectedDebit::IZettleEPay => res.master_accounts.bank_account_id,
+            SelectedDebit::Swish => res.master_accounts.bank_account_id,
             SelectedDebit::OtherEPay => res.master_accounts.bank_account_id,
             &SelectedDebit::Tillgodo(acc_id) => acc_id,
         }
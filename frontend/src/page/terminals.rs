@@ -0,0 +1,234 @@
+use crate::app::Msg;
+use crate::components::izettle_pay::{pair_terminal, paired_terminal};
+use crate::generated::css_classes::C;
+use crate::notification_manager::{Notification, NotificationMessage};
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::simple_ev;
+use seed::fetch;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{event, NotAvailable, ResourceStore, Resources};
+use strecklistan_api::izettle::{IZettleTerminal, IZettleTerminalId, NewIZettleTerminal};
+
+#[derive(Clone, Debug)]
+pub enum TerminalsMsg {
+    ResFetched(event::Fetched),
+    ResMarkDirty(event::MarkDirty),
+
+    /// Pair this checkout station with a terminal, or with any terminal
+    Pair(Option<IZettleTerminalId>),
+
+    IdInput(String),
+    NameInput(String),
+    AddTerminal,
+    DeleteTerminal(IZettleTerminalId),
+    Saved,
+    ServerError(String),
+}
+
+pub struct TerminalsPage {
+    paired: Option<IZettleTerminalId>,
+    new_id: String,
+    new_name: String,
+    request_in_progress: bool,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/izettle/terminals"]
+    #[policy = "SilentRefetch"]
+    terminals: &'a Vec<IZettleTerminal>,
+}
+
+impl TerminalsPage {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<TerminalsMsg>) -> Self {
+        orders.subscribe(TerminalsMsg::ResFetched);
+        orders.subscribe(TerminalsMsg::ResMarkDirty);
+        Res::acquire(rs, orders).ok();
+
+        TerminalsPage {
+            paired: paired_terminal(),
+            new_id: String::new(),
+            new_name: String::new(),
+            request_in_progress: false,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        msg: TerminalsMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<Msg>,
+    ) -> Result<(), NotAvailable> {
+        let _res = Res::acquire(rs, orders)?;
+
+        let mut orders_local = orders.proxy(Msg::Terminals);
+
+        match msg {
+            TerminalsMsg::ResFetched(_) => {}
+            TerminalsMsg::ResMarkDirty(_) => {}
+            TerminalsMsg::Pair(terminal) => {
+                pair_terminal(terminal.as_deref());
+                self.paired = terminal;
+            }
+            TerminalsMsg::IdInput(input) => self.new_id = input,
+            TerminalsMsg::NameInput(input) => self.new_name = input,
+            TerminalsMsg::AddTerminal => {
+                if self.request_in_progress || self.new_id.is_empty() {
+                    return Ok(());
+                }
+
+                self.request_in_progress = true;
+                let terminal = NewIZettleTerminal {
+                    id: self.new_id.trim().to_string(),
+                    name: self.new_name.trim().to_string(),
+                };
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<_> = async {
+                        Request::new("/api/izettle/terminals")
+                            .method(Method::Post)
+                            .json(&terminal)?
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+
+                    match result {
+                        Ok(_) => TerminalsMsg::Saved,
+                        Err(e) => {
+                            error!("Failed to add terminal", e);
+                            TerminalsMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            TerminalsMsg::DeleteTerminal(id) => {
+                if self.request_in_progress {
+                    return Ok(());
+                }
+
+                if self.paired.as_ref() == Some(&id) {
+                    pair_terminal(None);
+                    self.paired = None;
+                }
+
+                self.request_in_progress = true;
+                orders_local.perform_cmd(async move {
+                    let result: fetch::Result<_> = async {
+                        Request::new(format!("/api/izettle/terminal/{}", id))
+                            .method(Method::Delete)
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+
+                    match result {
+                        Ok(_) => TerminalsMsg::Saved,
+                        Err(e) => {
+                            error!("Failed to delete terminal", e);
+                            TerminalsMsg::ServerError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            TerminalsMsg::Saved => {
+                self.request_in_progress = false;
+                self.new_id.clear();
+                self.new_name.clear();
+                rs.mark_as_dirty(Res::terminals_url(), orders);
+            }
+            TerminalsMsg::ServerError(message) => {
+                self.request_in_progress = false;
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 10000,
+                    notification: Notification {
+                        title: strings::SERVER_ERROR.to_string(),
+                        body: Some(message),
+                    },
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return Loading::view(),
+        };
+
+        let pair_button = |terminal: Option<IZettleTerminalId>| {
+            let is_paired = self.paired == terminal;
+            button![
+                C![C.wide_button],
+                IF!(is_paired => attrs! {At::Disabled => true}),
+                simple_ev(Ev::Click, TerminalsMsg::Pair(terminal)),
+                if is_paired {
+                    strings::PAIRED
+                } else {
+                    strings::PAIR_TERMINAL
+                },
+            ]
+        };
+
+        let terminal_row = |terminal: &IZettleTerminal| {
+            tr![
+                td![&terminal.name],
+                td![&terminal.id],
+                td![if terminal.connected {
+                    strings::TERMINAL_CONNECTED
+                } else {
+                    strings::TERMINAL_DISCONNECTED
+                }],
+                td![pair_button(Some(terminal.id.clone()))],
+                td![button![
+                    C![C.wide_button],
+                    IF!(self.request_in_progress => attrs! {At::Disabled => true}),
+                    simple_ev(Ev::Click, TerminalsMsg::DeleteTerminal(terminal.id.clone())),
+                    strings::DELETE,
+                ]],
+            ]
+        };
+
+        div![
+            C![C.inventory_page],
+            table![
+                td![attrs! { At::ColSpan => 5 }, h1![strings::PAYMENT_TERMINALS]],
+                tr![th!["Namn"], th!["Id"], th!["Status"], th![], th![]],
+                tr![
+                    td![attrs! { At::ColSpan => 3 }, strings::ANY_TERMINAL],
+                    td![pair_button(None)],
+                    td![],
+                ],
+                res.terminals.iter().map(terminal_row),
+                tr![
+                    td![input![
+                        C![C.border_on_focus, C.inventory_page_input],
+                        attrs! {At::Placeholder => "Namn"},
+                        attrs! {At::Value => self.new_name},
+                        input_ev(Ev::Input, TerminalsMsg::NameInput),
+                    ]],
+                    td![input![
+                        C![C.border_on_focus, C.inventory_page_input],
+                        attrs! {At::Placeholder => "Id"},
+                        attrs! {At::Value => self.new_id},
+                        input_ev(Ev::Input, TerminalsMsg::IdInput),
+                    ]],
+                    td![],
+                    td![button![
+                        C![C.wide_button],
+                        IF!(self.request_in_progress => attrs! {At::Disabled => true}),
+                        simple_ev(Ev::Click, TerminalsMsg::AddTerminal),
+                        strings::ADD_TERMINAL,
+                    ]],
+                    td![],
+                ],
+            ],
+        ]
+        .map_msg(Msg::Terminals)
+    }
+}
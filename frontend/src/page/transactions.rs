@@ -1,10 +1,15 @@
 use crate::app::Msg;
 use crate::components::filter_menu::{FilterMenu, FilterMenuMsg};
+use crate::components::izettle_pay::paired_terminal;
 use crate::generated::css_classes::C;
+use crate::notification_manager::{Notification, NotificationMessage};
 use crate::page::loading::Loading;
+use crate::strings;
 use crate::util::export::{download_file, make_csv_transaction_list, CSVStyleTransaction};
-use crate::util::simple_ev;
-use chrono::{FixedOffset, Local};
+use crate::util::{simple_ev, DATE_INPUT_FMT};
+use chrono::{Datelike, FixedOffset, Local, NaiveDate, TimeZone};
+use js_sys::encode_uri_component;
+use seed::app::cmds::timeout;
 use seed::prelude::*;
 use seed::*;
 use seed_fetcher::Resources;
@@ -14,15 +19,22 @@ use strecklistan_api::{
     book_account::{BookAccount, BookAccountId, MasterAccounts},
     currency::Currency,
     inventory::{InventoryItemId, InventoryItemStock},
-    transaction::{Transaction, TransactionId},
+    izettle::{CardPayment, IZettleRefund, IZettleRefundId},
+    transaction::{Transaction, TransactionId, TransactionPage, TransactionRevision},
 };
 
-const VIEW_COUNT_CHUNK: usize = 50;
+const PAGE_SIZE: usize = 50;
+const REFUND_POLL_TIMEOUT_MS: u32 = 1000;
 
 #[derive(Copy, Clone, Debug)]
 pub enum ExportFormat {
     Json,
     Csv(CSVStyleTransaction),
+
+    /// An SIE4 file for bookkeeping, generated by the server for a fiscal year
+    Sie4 {
+        year: i32,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -31,35 +43,107 @@ pub enum TransactionsMsg {
     TransactionDeleted(TransactionId),
     SetShowDelete(bool),
     SetShowLeftPanel(bool),
+    SetFilter(FilterField, String),
+    ClearFilter,
     FilterMenuMsg(FilterMenuMsg),
-    IncreaseViewLimit,
+
+    /// Fetch the transactions matching the filter again, e.g. because they have changed
+    Reload,
+    LoadMore,
+    FetchedPage {
+        query_id: u32,
+        append: bool,
+        page: TransactionPage,
+    },
+    /// Fetch a transaction which has been created, changed or deleted, and update the list
+    Refresh(TransactionId),
+    FetchedChange {
+        query_id: u32,
+        id: TransactionId,
+
+        /// The transaction, if it matches the filter
+        page: TransactionPage,
+
+        /// The number of transactions matching the filter after the change
+        total: u64,
+    },
+    FetchFailed(String),
+
     ExportData(ExportFormat),
+    Export(ExportFormat, Vec<Transaction>),
     ShowReceipt(TransactionId),
+    ToggleHistory(TransactionId),
+    FetchedHistory(TransactionId, Vec<TransactionRevision>),
+
+    /// Refund the card payment of a transaction
+    Refund(TransactionId),
+    PollRefund(IZettleRefundId),
+    RefundFinished(IZettleRefund),
+    RefundError(String),
 
     ResFetched(event::Fetched),
     ResMarkDirty(event::MarkDirty),
 }
 
+/// A field of the transaction filter in the left panel
+#[derive(Clone, Copy, Debug)]
+pub enum FilterField {
+    FromDate,
+    ToDate,
+    Account,
+    MinAmount,
+    MaxAmount,
+    Description,
+}
+
+/// The transaction filter, as entered in the left panel
+#[derive(Clone, Default)]
+struct Filter {
+    from_date: String,
+    to_date: String,
+    account: Option<BookAccountId>,
+    min_amount: String,
+    max_amount: String,
+    description: String,
+}
+
 #[derive(Clone)]
 pub struct TransactionsPage {
     show_delete: bool,
     show_left_panel: bool,
-    view_limit: usize,
+    filter: Filter,
+
+    /// Filters the fetched transactions further, e.g. by the time of day
     filter_menu: FilterMenu,
     timezone: FixedOffset,
 
-    /// Only show transactions in this list
-    filtered_transactions: Vec<usize>,
+    /// The transactions matching the filter which have been fetched so far, newest first
+    transactions: Vec<Transaction>,
+
+    /// The card payments of the fetched transactions
+    card_payments: HashMap<TransactionId, CardPayment>,
+
+    /// The number of transactions matching the filter
+    total: u64,
 
-    /// The balance of all accounts based on the filtered transactions
-    accounts_balance: HashMap<BookAccountId, Currency>,
+    /// Where the next page starts, if there is one
+    next: Option<TransactionId>,
+
+    /// Identifies the latest query, so that responses to outdated ones can be ignored
+    query_id: u32,
+
+    /// Whether a page is being fetched
+    loading: bool,
+
+    /// The revision history of the transactions which have it expanded
+    history: HashMap<TransactionId, Vec<TransactionRevision>>,
+
+    /// Whether a refund is waiting for the payment terminal
+    refund_in_progress: bool,
 }
 
 #[derive(Resources)]
 struct Res<'a> {
-    #[url = "/api/transactions"]
-    transactions: &'a Vec<Transaction>,
-
     #[url = "/api/inventory/items"]
     #[policy = "SilentRefetch"]
     inventory: &'a HashMap<InventoryItemId, InventoryItemStock>,
@@ -71,62 +155,176 @@ struct Res<'a> {
     master_accounts: &'a MasterAccounts,
 }
 
+impl Filter {
+    /// The query string of GET `/api/transactions` for this filter
+    ///
+    /// Inputs which can't be parsed are left out, the dates are in the local timezone, and the
+    /// end date is inclusive.
+    fn to_query(&self, timezone: FixedOffset) -> String {
+        let date = |input: &str| NaiveDate::parse_from_str(input, DATE_INPUT_FMT).ok();
+        let midnight = |date: NaiveDate| {
+            timezone
+                .from_local_date(&date)
+                .and_hms_opt(0, 0, 0)
+                .single()
+                .map(|time| time.to_rfc3339())
+        };
+        let amount = |input: &str| {
+            input
+                .trim()
+                .parse::<Currency>()
+                .ok()
+                .map(|amount| amount.to_string())
+        };
+
+        let params = [
+            ("from", date(&self.from_date).and_then(midnight)),
+            (
+                "to",
+                date(&self.to_date).and_then(|date| midnight(date.succ())),
+            ),
+            ("account", self.account.map(|id| id.to_string())),
+            ("min_amount", amount(&self.min_amount)),
+            ("max_amount", amount(&self.max_amount)),
+            (
+                "description",
+                Some(self.description.trim().to_string()).filter(|d| !d.is_empty()),
+            ),
+        ];
+
+        params
+            .iter()
+            .filter_map(|(key, value)| value.as_ref().map(|value| (key, value)))
+            .enumerate()
+            .map(|(i, (key, value))| {
+                let value: String = encode_uri_component(value).into();
+                format!("{}{}={}", if i == 0 { '?' } else { '&' }, key, value)
+            })
+            .collect()
+    }
+}
+
 impl TransactionsPage {
     pub fn new(rs: &ResourceStore, orders: &mut impl Orders<TransactionsMsg>) -> Self {
         let mut page = TransactionsPage {
             show_delete: false,
             show_left_panel: false,
             timezone: *Local::now().offset(),
-            view_limit: VIEW_COUNT_CHUNK,
+            filter: Filter::default(),
             filter_menu: FilterMenu::new(vec!["datum", "klockslag", "summa", "debet", "kredit"]),
-            filtered_transactions: vec![],
-            accounts_balance: HashMap::new(),
+            transactions: vec![],
+            card_payments: HashMap::new(),
+            total: 0,
+            next: None,
+            query_id: 0,
+            loading: false,
+            history: HashMap::new(),
+            refund_in_progress: false,
         };
 
         orders.subscribe(TransactionsMsg::ResFetched);
         orders.subscribe(TransactionsMsg::ResMarkDirty);
 
-        Res::acquire(rs, orders)
-            .map(|res| page.filter_transactions(&res))
-            .ok();
+        Res::acquire(rs, orders).ok();
+        page.fetch_page(None, PAGE_SIZE, orders);
         page
     }
 
-    /// Rebuild self.filtered_transactions
-    fn filter_transactions(&mut self, res: &Res) {
-        self.filtered_transactions = res
-            .transactions
-            .iter()
-            .enumerate()
-            .filter(|(_, tr)| {
-                self.filter_menu.filter(&[
-                    &tr.time.with_timezone(&self.timezone).format("%Y-%m-%d"), // datum
-                    &tr.time.with_timezone(&self.timezone).format("%H:%M:%S"), // klockslag
-                    &tr.amount,                                                // summa
-                    &res.book_accounts.get(&tr.debited_account).unwrap().name, // debet
-                    &res.book_accounts.get(&tr.credited_account).unwrap().name, // kredit
-                ])
-            })
-            .map(|(i, _)| i)
-            .collect();
+    /// Fetch the transactions matching the filter again, as many as have been fetched so far
+    fn reload(&mut self, orders: &mut impl Orders<TransactionsMsg>) {
+        let limit = self.transactions.len().max(PAGE_SIZE);
+        self.fetch_page(None, limit, orders);
+    }
 
-        self.accounts_balance.clear();
-        for tr in self
-            .filtered_transactions
-            .iter()
-            .map(|&i| &res.transactions[i])
-        {
-            if let Some(acc) = res.book_accounts.get(&tr.debited_account) {
-                *self.accounts_balance.entry(tr.debited_account).or_default() +=
-                    acc.debit_diff(tr.amount);
+    /// Fetch the transactions matching the filter, starting after the transaction `after`
+    ///
+    /// The page replaces the fetched transactions, unless it starts after them.
+    fn fetch_page(
+        &mut self,
+        after: Option<TransactionId>,
+        limit: usize,
+        orders: &mut impl Orders<TransactionsMsg>,
+    ) {
+        let append = after.is_some();
+        if !append {
+            // responses to earlier queries are now outdated
+            self.query_id = self.query_id.wrapping_add(1);
+        }
+
+        self.loading = true;
+        let query_id = self.query_id;
+        let mut url = format!(
+            "/api/transactions/page{}",
+            self.filter.to_query(self.timezone)
+        );
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(&format!("limit={}", limit));
+        if let Some(after) = after {
+            url.push_str(&format!("&after={}", after));
+        }
+
+        orders.perform_cmd(async move {
+            let result = async {
+                Request::new(url)
+                    .fetch()
+                    .await?
+                    .check_status()?
+                    .json()
+                    .await
             }
-            if let Some(acc) = res.book_accounts.get(&tr.credited_account) {
-                *self
-                    .accounts_balance
-                    .entry(tr.credited_account)
-                    .or_default() += acc.credit_diff(tr.amount);
+            .await;
+            match result {
+                Ok(page) => TransactionsMsg::FetchedPage {
+                    query_id,
+                    append,
+                    page,
+                },
+                Err(e) => {
+                    error!("Failed to fetch transactions", e);
+                    TransactionsMsg::FetchFailed(format!("{:?}", e))
+                }
             }
-        }
+        });
+    }
+
+    /// Fetch the transaction `id` and the new number of transactions matching the filter
+    fn fetch_change(&mut self, id: TransactionId, orders: &mut impl Orders<TransactionsMsg>) {
+        let query_id = self.query_id;
+        let query = self.filter.to_query(self.timezone);
+        let separator = if query.is_empty() { '?' } else { '&' };
+        let row_url = format!("/api/transactions/page{}{}id={}", query, separator, id);
+        let total_url = format!("/api/transactions/page{}{}limit=0", query, separator);
+
+        orders.perform_cmd(async move {
+            let result: Result<_, FetchError> = async {
+                let page: TransactionPage = Request::new(row_url)
+                    .fetch()
+                    .await?
+                    .check_status()?
+                    .json()
+                    .await?;
+                let total: TransactionPage = Request::new(total_url)
+                    .fetch()
+                    .await?
+                    .check_status()?
+                    .json()
+                    .await?;
+                Ok((page, total.total))
+            }
+            .await;
+            match result {
+                Ok((page, total)) => TransactionsMsg::FetchedChange {
+                    query_id,
+                    id,
+                    page,
+                    total,
+                },
+                Err(e) => {
+                    error!("Failed to fetch transaction", e);
+                    TransactionsMsg::FetchFailed(format!("{:?}", e))
+                }
+            }
+        });
     }
 
     pub fn update(
@@ -135,15 +333,12 @@ impl TransactionsPage {
         rs: &ResourceStore,
         orders: &mut impl Orders<Msg>,
     ) -> Result<(), NotAvailable> {
-        let res = Res::acquire(rs, orders)?;
+        // the fetched pages are handled even if the resources are not yet available
+        Res::acquire(rs, orders).ok();
 
         let mut orders_local = orders.proxy(Msg::Transactions);
         match msg {
-            TransactionsMsg::ResFetched(event::Fetched(resource)) => {
-                if Res::has_resource(resource) {
-                    self.filter_transactions(&res);
-                }
-            }
+            TransactionsMsg::ResFetched(_) => {}
             TransactionsMsg::ResMarkDirty(_) => {}
             TransactionsMsg::DeleteTransaction(id) => {
                 self.show_delete = false;
@@ -168,7 +363,7 @@ impl TransactionsPage {
 
             TransactionsMsg::TransactionDeleted(id) => {
                 log!(format!("Transaction {} deleted", id));
-                rs.mark_as_dirty(Res::transactions_url(), orders);
+                self.reload(&mut orders_local);
                 rs.mark_as_dirty(Res::book_accounts_url(), orders);
                 rs.mark_as_dirty(Res::inventory_url(), orders);
             }
@@ -179,37 +374,256 @@ impl TransactionsPage {
             TransactionsMsg::SetShowLeftPanel(show_left_panel) => {
                 self.show_left_panel = show_left_panel;
             }
+            TransactionsMsg::SetFilter(field, input) => {
+                let filter = &mut self.filter;
+                match field {
+                    FilterField::FromDate => filter.from_date = input,
+                    FilterField::ToDate => filter.to_date = input,
+                    FilterField::Account => filter.account = input.parse().ok(),
+                    FilterField::MinAmount => filter.min_amount = input,
+                    FilterField::MaxAmount => filter.max_amount = input,
+                    FilterField::Description => filter.description = input,
+                }
+                self.fetch_page(None, PAGE_SIZE, &mut orders_local);
+            }
+            TransactionsMsg::ClearFilter => {
+                self.filter = Filter::default();
+                self.fetch_page(None, PAGE_SIZE, &mut orders_local);
+            }
             TransactionsMsg::FilterMenuMsg(msg) => {
                 self.filter_menu
                     .update(msg, &mut orders_local.proxy(TransactionsMsg::FilterMenuMsg));
-                self.view_limit = VIEW_COUNT_CHUNK; // reset view limit
-                self.filter_transactions(&res);
             }
-            TransactionsMsg::IncreaseViewLimit => {
-                self.view_limit += VIEW_COUNT_CHUNK;
-                self.filter_transactions(&res);
+            TransactionsMsg::Reload => self.reload(&mut orders_local),
+            TransactionsMsg::LoadMore => {
+                if let (Some(next), false) = (self.next, self.loading) {
+                    self.fetch_page(Some(next), PAGE_SIZE, &mut orders_local);
+                }
             }
-            TransactionsMsg::ExportData(format) => {
-                let transactions: Vec<_> = self
-                    .filtered_transactions
-                    .iter()
-                    .map(|&index| res.transactions[index].clone())
+            TransactionsMsg::FetchedPage {
+                query_id,
+                append,
+                page,
+            } => {
+                if query_id != self.query_id {
+                    return Ok(());
+                }
+
+                self.loading = false;
+                if !append {
+                    self.transactions.clear();
+                    self.card_payments.clear();
+                }
+                // a changed transaction may have been fetched by itself already
+                let fetched = &self.transactions;
+                let transactions: Vec<_> = page
+                    .transactions
+                    .into_iter()
+                    .filter(|tr| fetched.iter().all(|f| f.id != tr.id))
                     .collect();
-                match format {
-                    ExportFormat::Json => {
-                        let serialized = serde_json::to_string(&transactions).unwrap();
-                        download_file("transactions.json", mime::APPLICATION_JSON, &serialized)
-                            .ok();
-                    }
-                    ExportFormat::Csv(style) => {
-                        let serialized = make_csv_transaction_list(&transactions, style);
-                        download_file("transactions.csv", mime::TEXT_CSV, &serialized).ok();
+                self.transactions.extend(transactions);
+                self.card_payments.extend(page.card_payments);
+                self.total = page.total;
+                self.next = page.next;
+            }
+            TransactionsMsg::Refresh(id) => self.fetch_change(id, &mut orders_local),
+            TransactionsMsg::FetchedChange {
+                query_id,
+                id,
+                page,
+                total,
+            } => {
+                if query_id != self.query_id {
+                    return Ok(());
+                }
+
+                self.transactions.retain(|tr| tr.id != id);
+                self.card_payments.remove(&id);
+                self.total = total;
+
+                let mut card_payments = page.card_payments;
+                for tr in page.transactions {
+                    // keep the list ordered by time and id, newest first
+                    let position = self
+                        .transactions
+                        .iter()
+                        .position(|other| (other.time, other.id) < (tr.time, tr.id))
+                        .unwrap_or(self.transactions.len());
+
+                    // a transaction after the fetched ones is on a later page
+                    if position < self.transactions.len() || self.next.is_none() {
+                        if let Some(payment) = card_payments.remove(&tr.id) {
+                            self.card_payments.insert(tr.id, payment);
+                        }
+                        self.transactions.insert(position, tr);
                     }
                 }
             }
+            TransactionsMsg::FetchFailed(message) => {
+                self.loading = false;
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 10000,
+                    notification: Notification {
+                        title: strings::SERVER_ERROR.to_string(),
+                        body: Some(message),
+                    },
+                }));
+            }
+            TransactionsMsg::ExportData(ExportFormat::Sie4 { year }) => {
+                window()
+                    .open_with_url(&format!("/api/export/sie4?year={year}"))
+                    .ok();
+            }
+            TransactionsMsg::ExportData(format) => {
+                // the export contains all transactions matching the filter, not only those which
+                // have been fetched
+                let url = format!("/api/transactions{}", self.filter.to_query(self.timezone));
+                orders_local.perform_cmd(async move {
+                    let result = async {
+                        Request::new(url)
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    result
+                        .map_err(|e| {
+                            error!("Failed to fetch transactions for export", e);
+                        })
+                        .map(|transactions| TransactionsMsg::Export(format, transactions))
+                        .ok()
+                });
+            }
+            TransactionsMsg::Export(format, transactions) => match format {
+                ExportFormat::Json => {
+                    let serialized = serde_json::to_string(&transactions).unwrap();
+                    download_file("transactions.json", mime::APPLICATION_JSON, &serialized).ok();
+                }
+                ExportFormat::Csv(style) => {
+                    let serialized = make_csv_transaction_list(&transactions, style);
+                    download_file("transactions.csv", mime::TEXT_CSV, &serialized).ok();
+                }
+                ExportFormat::Sie4 { .. } => {}
+            },
             TransactionsMsg::ShowReceipt(id) => {
                 window().open_with_url(&format!("/api/receipt/{id}")).ok();
             }
+            TransactionsMsg::ToggleHistory(id) => {
+                if self.history.remove(&id).is_none() {
+                    orders_local.perform_cmd(async move {
+                        let result = async {
+                            Request::new(format!("/api/transaction/{}/history", id))
+                                .fetch()
+                                .await?
+                                .check_status()?
+                                .json()
+                                .await
+                        }
+                        .await;
+                        result
+                            .map_err(|e| {
+                                error!("Failed to fetch transaction history", e);
+                            })
+                            .map(|history| TransactionsMsg::FetchedHistory(id, history))
+                            .ok()
+                    });
+                }
+            }
+            TransactionsMsg::FetchedHistory(id, history) => {
+                self.history.insert(id, history);
+            }
+            TransactionsMsg::Refund(id) => {
+                if self.refund_in_progress {
+                    return Ok(());
+                }
+
+                self.refund_in_progress = true;
+                let url = match paired_terminal() {
+                    Some(terminal) => {
+                        format!("/api/transaction/{}/refund?terminal={}", id, terminal)
+                    }
+                    None => format!("/api/transaction/{}/refund", id),
+                };
+                orders_local.perform_cmd(async move {
+                    let result = async {
+                        Request::new(url)
+                            .method(Method::Post)
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(refund_id) => TransactionsMsg::PollRefund(refund_id),
+                        Err(FetchError::StatusError(status)) if status.code == 503 => {
+                            TransactionsMsg::RefundError(strings::NO_PAYMENT_TERMINAL.to_string())
+                        }
+                        Err(e) => {
+                            error!("Failed to request refund", e);
+                            TransactionsMsg::RefundError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            TransactionsMsg::PollRefund(refund_id) => {
+                orders_local.perform_cmd(async move {
+                    let result = async {
+                        Request::new(format!("/api/izettle/client/refund/{}", refund_id))
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(IZettleRefund::Pending) => {
+                            timeout(REFUND_POLL_TIMEOUT_MS, || ()).await;
+                            TransactionsMsg::PollRefund(refund_id)
+                        }
+                        Ok(refund) => TransactionsMsg::RefundFinished(refund),
+                        Err(e) => {
+                            error!("Failed to poll for refund", e);
+                            TransactionsMsg::RefundError(format!("{:?}", e))
+                        }
+                    }
+                });
+            }
+            TransactionsMsg::RefundFinished(refund) => {
+                self.refund_in_progress = false;
+                self.reload(&mut orders_local);
+                rs.mark_as_dirty(Res::book_accounts_url(), orders);
+
+                let (title, body) = match refund {
+                    IZettleRefund::Refunded { .. } => (strings::REFUNDED, None),
+                    IZettleRefund::Cancelled => (strings::REFUND_CANCELLED, None),
+                    IZettleRefund::Failed { reason } => (strings::REFUND_FAILED, Some(reason)),
+                    IZettleRefund::Pending | IZettleRefund::NoRefund => {
+                        (strings::REFUND_FAILED, None)
+                    }
+                };
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 5000,
+                    notification: Notification {
+                        title: title.to_string(),
+                        body,
+                    },
+                }));
+            }
+            TransactionsMsg::RefundError(message) => {
+                self.refund_in_progress = false;
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 10000,
+                    notification: Notification {
+                        title: strings::REFUND_FAILED.to_string(),
+                        body: Some(message),
+                    },
+                }));
+            }
         }
 
         Ok(())
@@ -230,25 +644,43 @@ impl TransactionsPage {
                 span![format!("{}:-", balance)],
             ]
         };
-        let show_acc = |id: &BookAccountId| {
-            show_acc_entry(
-                res.book_accounts
-                    .get(id)
-                    .map(|acc| acc.name.as_str())
-                    .unwrap_or("[missing]"),
-                self.accounts_balance
-                    .get(id)
-                    .copied()
-                    .unwrap_or_else(|| 0.into()),
-            )
+        let show_acc = |id: &BookAccountId| match res.book_accounts.get(id) {
+            Some(acc) => show_acc_entry(&acc.name, acc.balance),
+            None => show_acc_entry("[missing]", 0.into()),
+        };
+
+        let this_year = Local::now().year();
+
+        let account_name = |id: &BookAccountId| {
+            res.book_accounts
+                .get(id)
+                .map(|acc| acc.name.clone())
+                .unwrap_or_default()
         };
 
         let transaction_list: Vec<_> = self
-            .filtered_transactions
+            .transactions
             .iter()
-            .take(self.view_limit)
-            .map(|&i| &res.transactions[i])
-            .map(|tr| view_transaction(self.timezone, &res, tr, self.show_delete))
+            .filter(|tr| {
+                self.filter_menu.filter(&[
+                    &tr.time.with_timezone(&self.timezone).format("%Y-%m-%d"), // datum
+                    &tr.time.with_timezone(&self.timezone).format("%H:%M:%S"), // klockslag
+                    &tr.amount,                                                // summa
+                    &account_name(&tr.debited_account),                        // debet
+                    &account_name(&tr.credited_account),                       // kredit
+                ])
+            })
+            .map(|tr| {
+                view_transaction(
+                    self.timezone,
+                    &res,
+                    tr,
+                    self.history.get(&tr.id),
+                    self.card_payments.get(&tr.id),
+                    self.show_delete,
+                    self.refund_in_progress,
+                )
+            })
             .collect();
 
         div![
@@ -270,27 +702,30 @@ impl TransactionsPage {
                     show_acc(&res.master_accounts.cash_account_id),
                     show_acc(&res.master_accounts.sales_account_id),
                     show_acc(&res.master_accounts.purchases_account_id),
+                    show_acc(&res.master_accounts.shrinkage_account_id),
+                    show_acc(&res.master_accounts.cash_difference_account_id),
                     show_acc_entry(
                         "Tillgodo Totalt",
-                        self.accounts_balance
-                            .iter()
-                            .filter_map(|(id, balance)| res
-                                .book_accounts
-                                .get(id)
-                                .map(|acc| (acc, balance)))
-                            .filter(|(acc, _)| acc.creditor.is_some())
-                            .map(|(_, balance)| *balance)
+                        res.book_accounts
+                            .values()
+                            .filter(|acc| acc.creditor.is_some())
+                            .map(|acc| acc.balance)
                             .fold(0.into(), |a: Currency, b| a + b)
                     ),
                 ],
                 hr![C![C.left_panel_entry]],
                 div![
                     C![C.left_panel_entry],
-                    h2![C![C.left_panel_entry_header], "Filtrera (WIP)"],
+                    h2![C![C.left_panel_entry_header], "Filtrera"],
+                ],
+                self.view_filter(&res),
+                div![
+                    C![C.left_panel_entry],
+                    h2![C![C.left_panel_entry_header], "Filtrera hämtade"],
+                    self.filter_menu
+                        .view()
+                        .map_msg(TransactionsMsg::FilterMenuMsg),
                 ],
-                self.filter_menu
-                    .view()
-                    .map_msg(TransactionsMsg::FilterMenuMsg),
                 div![
                     C![C.left_panel_entry],
                     h2![C![C.left_panel_entry_header], "Exportera Data"],
@@ -310,6 +745,26 @@ impl TransactionsPage {
                             ))
                         ),
                     ],
+                    button![
+                        C![C.wide_button],
+                        C![C.space_above],
+                        format!("SIE4 (Bokföring {})", this_year),
+                        simple_ev(
+                            Ev::Click,
+                            TransactionsMsg::ExportData(ExportFormat::Sie4 { year: this_year })
+                        ),
+                    ],
+                    button![
+                        C![C.wide_button],
+                        C![C.space_above],
+                        format!("SIE4 (Bokföring {})", this_year - 1),
+                        simple_ev(
+                            Ev::Click,
+                            TransactionsMsg::ExportData(ExportFormat::Sie4 {
+                                year: this_year - 1
+                            })
+                        ),
+                    ],
                 ],
                 // TODO: implement this
                 /*
@@ -345,12 +800,18 @@ impl TransactionsPage {
                         simple_ev(Ev::Click, TransactionsMsg::SetShowDelete(!self.show_delete)),
                     ],
                 ],
+                p![format!(
+                    "Visar {} av {} transaktioner",
+                    transaction_list.len(),
+                    self.total
+                )],
                 transaction_list,
-                if self.view_limit < self.filtered_transactions.len() {
+                if self.next.is_some() {
                     button![
                         C![C.wide_button],
+                        IF!(self.loading => attrs! {At::Disabled => true}),
                         "Visa fler",
-                        simple_ev(Ev::Click, TransactionsMsg::IncreaseViewLimit),
+                        simple_ev(Ev::Click, TransactionsMsg::LoadMore),
                     ]
                 } else {
                     empty![]
@@ -359,14 +820,141 @@ impl TransactionsPage {
         ]
         .map_msg(Msg::Transactions)
     }
+
+    fn view_filter(&self, res: &Res) -> Node<TransactionsMsg> {
+        let filter_input = |label: &str, input_type: &str, value: &str, field: FilterField| {
+            div![
+                C![C.filter_menu_item],
+                span![C![C.filter_menu_item_elem, C.filter_menu_field], label],
+                input![
+                    C![C.filter_menu_item_elem, C.filter_menu_value],
+                    attrs! {At::Type => input_type, At::Value => value},
+                    input_ev(Ev::Change, move |input| TransactionsMsg::SetFilter(
+                        field, input
+                    )),
+                ],
+            ]
+        };
+
+        let mut accounts: Vec<_> = res.book_accounts.values().collect();
+        accounts.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let filter = &self.filter;
+        div![
+            filter_input("Från", "date", &filter.from_date, FilterField::FromDate),
+            filter_input("Till", "date", &filter.to_date, FilterField::ToDate),
+            div![
+                C![C.filter_menu_item],
+                span![C![C.filter_menu_item_elem, C.filter_menu_field], "Konto"],
+                select![
+                    C![C.filter_menu_item_elem, C.filter_menu_value],
+                    input_ev(Ev::Change, |input| TransactionsMsg::SetFilter(
+                        FilterField::Account,
+                        input
+                    )),
+                    option![
+                        attrs! {At::Value => ""},
+                        IF!(filter.account.is_none() => attrs! {At::Selected => true}),
+                        "Alla konton",
+                    ],
+                    accounts.into_iter().map(|acc| option![
+                        attrs! {At::Value => acc.id.to_string()},
+                        IF!(filter.account == Some(acc.id) => attrs! {At::Selected => true}),
+                        &acc.name,
+                    ]),
+                ],
+            ],
+            filter_input(
+                "Min summa",
+                "text",
+                &filter.min_amount,
+                FilterField::MinAmount
+            ),
+            filter_input(
+                "Max summa",
+                "text",
+                &filter.max_amount,
+                FilterField::MaxAmount
+            ),
+            filter_input(
+                "Beskrivning",
+                "text",
+                &filter.description,
+                FilterField::Description
+            ),
+            button![
+                C![C.wide_button, C.space_above],
+                "Rensa filter",
+                simple_ev(Ev::Click, TransactionsMsg::ClearFilter),
+            ],
+        ]
+    }
 }
 
 fn view_transaction(
     timezone: FixedOffset,
     res: &Res,
     transaction: &Transaction,
+    history: Option<&Vec<TransactionRevision>>,
+    card_payment: Option<&CardPayment>,
     show_delete: bool,
+    refund_in_progress: bool,
 ) -> Node<TransactionsMsg> {
+    let account_name = |id: &BookAccountId| {
+        res.book_accounts
+            .get(id)
+            .map(|acc| acc.name.as_str())
+            .unwrap_or("[MISSING]")
+    };
+
+    let view_revision = |revision: &TransactionRevision| {
+        let (old, new) = (&revision.old, &revision.new);
+        let mut changes = vec![];
+        if old.description != new.description {
+            changes.push(format!(
+                "Beskrivning: {} → {}",
+                old.description.as_deref().unwrap_or("-"),
+                new.description.as_deref().unwrap_or("-"),
+            ));
+        }
+        if old.debited_account != new.debited_account {
+            changes.push(format!(
+                "Debet: {} → {}",
+                account_name(&old.debited_account),
+                account_name(&new.debited_account),
+            ));
+        }
+        if old.credited_account != new.credited_account {
+            changes.push(format!(
+                "Kredit: {} → {}",
+                account_name(&old.credited_account),
+                account_name(&new.credited_account),
+            ));
+        }
+        if old.amount != new.amount {
+            changes.push(format!("Summa: {}:- → {}:-", old.amount, new.amount));
+        }
+        if old.bundles != new.bundles {
+            changes.push("Varor ändrade".to_string());
+        }
+        if changes.is_empty() {
+            changes.push("Inga ändringar".to_string());
+        }
+
+        div![
+            C![C.transaction_revision],
+            p![format!(
+                "Ändrad {} av {}",
+                revision
+                    .revised_at
+                    .with_timezone(&timezone)
+                    .format("%Y-%m-%d %H:%M:%S"),
+                revision.revised_by.as_deref().unwrap_or("okänd"),
+            )],
+            changes.into_iter().map(|change| p![change]),
+        ]
+    };
+
     div![
         C![C.transaction_view],
         p![
@@ -377,6 +965,12 @@ fn view_transaction(
                 C![C.transaction_view_receipt_button],
                 simple_ev(Ev::Click, TransactionsMsg::ShowReceipt(transaction.id)),
             ],
+            button![
+                C![C.transaction_view_history_button],
+                attrs! {At::Title => "Historik"},
+                simple_ev(Ev::Click, TransactionsMsg::ToggleHistory(transaction.id)),
+                "⟲",
+            ],
             IF![show_delete => {
                 button![
                     C![C.transaction_view_delete_button],
@@ -398,28 +992,50 @@ fn view_transaction(
                     .format("%Y-%m-%d %H:%M:%S %Z"),
             )
         ],
+        history.map(|history| {
+            if history.is_empty() {
+                div![C![C.transaction_revision], "Inga ändringar"]
+            } else {
+                div![history.iter().map(view_revision)]
+            }
+        }),
         p![
             C![C.transaction_line],
             span!["Debet: "],
-            span![
-                C![C.font_bold],
-                res.book_accounts
-                    .get(&transaction.debited_account)
-                    .map(|acc| acc.name.as_str())
-                    .unwrap_or("[MISSING]")
-            ],
+            span![C![C.font_bold], account_name(&transaction.debited_account)],
         ],
         p![
             C![C.transaction_line],
             span!["Kredit: "],
-            span![
-                C![C.font_bold],
-                res.book_accounts
-                    .get(&transaction.credited_account)
-                    .map(|acc| acc.name.as_str())
-                    .unwrap_or("[MISSING]")
-            ],
+            span![C![C.font_bold], account_name(&transaction.credited_account)],
         ],
+        card_payment.map(|payment| {
+            p![
+                C![C.transaction_line],
+                span!["Kort: "],
+                span![
+                    C![C.font_bold],
+                    payment.card_type.as_deref().unwrap_or("okänt"),
+                    " ",
+                    payment.masked_pan.as_deref().unwrap_or_default(),
+                ],
+                match &payment.refund {
+                    Some(IZettleRefund::Refunded { transaction_id }) => {
+                        span![format!(" ({} #{})", strings::REFUNDED, transaction_id)]
+                    }
+                    Some(IZettleRefund::Pending) => {
+                        span![format!(" ({})", strings::REFUND_PENDING)]
+                    }
+                    _ if payment.is_refundable() => button![
+                        C![C.transaction_view_refund_button],
+                        IF!(refund_in_progress => attrs! {At::Disabled => true}),
+                        simple_ev(Ev::Click, TransactionsMsg::Refund(transaction.id)),
+                        strings::REFUND,
+                    ],
+                    _ => empty![],
+                },
+            ]
+        }),
         transaction
             .bundles
             .iter()
@@ -0,0 +1,375 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::notification_manager::{Notification, NotificationMessage};
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::simple_ev;
+use chrono::Local;
+use seed::fetch;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{event, NotAvailable, ResourceStore, Resources};
+use std::collections::HashMap;
+use strecklistan_api::{
+    book_account::{BookAccount, BookAccountId},
+    currency::Currency,
+    inventory::{InventoryItemId, InventoryItemStock as InventoryItem},
+    transaction::{Transaction, TransactionId, TransactionPage},
+};
+
+const PAGE_SIZE: usize = 50;
+
+#[derive(Clone, Debug)]
+pub enum TrashMsg {
+    ResFetched(event::Fetched),
+    ResMarkDirty(event::MarkDirty),
+
+    /// Fetch the deleted transactions again, e.g. because they have changed
+    Reload,
+    LoadMore,
+    FetchedPage {
+        append: bool,
+        page: TransactionPage,
+    },
+    /// Fetch a transaction which has been deleted, restored or changed, and update the list
+    Refresh(TransactionId),
+    FetchedChange {
+        id: TransactionId,
+
+        /// The transaction, if it is deleted
+        page: TransactionPage,
+
+        /// The number of deleted transactions after the change
+        total: u64,
+    },
+
+    RestoreTransaction(TransactionId),
+    RestoreItem(InventoryItemId),
+    Restored,
+    ServerError(String),
+}
+
+pub struct TrashPage {
+    request_in_progress: bool,
+
+    /// The deleted transactions which have been fetched so far, newest first
+    deleted_transactions: Vec<Transaction>,
+
+    /// The number of deleted transactions
+    total: u64,
+
+    /// Where the next page starts, if there is one
+    next: Option<TransactionId>,
+
+    /// Whether a page is being fetched
+    loading: bool,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/inventory/items/deleted"]
+    #[policy = "SilentRefetch"]
+    deleted_items: &'a Vec<InventoryItem>,
+
+    #[url = "/api/book_accounts"]
+    book_accounts: &'a HashMap<BookAccountId, BookAccount>,
+}
+
+impl TrashPage {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<TrashMsg>) -> Self {
+        orders.subscribe(TrashMsg::ResFetched);
+        orders.subscribe(TrashMsg::ResMarkDirty);
+        Res::acquire(rs, orders).ok();
+
+        let mut page = TrashPage {
+            request_in_progress: false,
+            deleted_transactions: vec![],
+            total: 0,
+            next: None,
+            loading: false,
+        };
+        page.fetch_page(None, PAGE_SIZE, orders);
+        page
+    }
+
+    /// Fetch the deleted transactions, starting after the transaction `after`
+    ///
+    /// The page replaces the fetched transactions, unless it starts after them.
+    fn fetch_page(
+        &mut self,
+        after: Option<TransactionId>,
+        limit: usize,
+        orders: &mut impl Orders<TrashMsg>,
+    ) {
+        self.loading = true;
+        let append = after.is_some();
+        let url = match after {
+            Some(after) => format!(
+                "/api/transactions/page?deleted=true&limit={}&after={}",
+                limit, after
+            ),
+            None => format!("/api/transactions/page?deleted=true&limit={}", limit),
+        };
+
+        orders.perform_cmd(async move {
+            let result = async {
+                Request::new(url)
+                    .fetch()
+                    .await?
+                    .check_status()?
+                    .json()
+                    .await
+            }
+            .await;
+            match result {
+                Ok(page) => TrashMsg::FetchedPage { append, page },
+                Err(e) => {
+                    error!("Failed to fetch deleted transactions", e);
+                    TrashMsg::ServerError(format!("{:?}", e))
+                }
+            }
+        });
+    }
+
+    /// Fetch the transaction `id`, if it is deleted, and the new number of deleted transactions
+    fn fetch_change(&mut self, id: TransactionId, orders: &mut impl Orders<TrashMsg>) {
+        let row_url = format!("/api/transactions/page?deleted=true&id={}", id);
+        let total_url = "/api/transactions/page?deleted=true&limit=0";
+
+        orders.perform_cmd(async move {
+            let result: fetch::Result<_> = async {
+                let page: TransactionPage = Request::new(row_url)
+                    .fetch()
+                    .await?
+                    .check_status()?
+                    .json()
+                    .await?;
+                let total: TransactionPage = Request::new(total_url)
+                    .fetch()
+                    .await?
+                    .check_status()?
+                    .json()
+                    .await?;
+                Ok((page, total.total))
+            }
+            .await;
+            match result {
+                Ok((page, total)) => TrashMsg::FetchedChange { id, page, total },
+                Err(e) => {
+                    error!("Failed to fetch deleted transaction", e);
+                    TrashMsg::ServerError(format!("{:?}", e))
+                }
+            }
+        });
+    }
+
+    pub fn update(
+        &mut self,
+        msg: TrashMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<Msg>,
+    ) -> Result<(), NotAvailable> {
+        // the fetched pages are handled even if the resources are not yet available
+        Res::acquire(rs, orders).ok();
+
+        let mut orders_local = orders.proxy(Msg::Trash);
+
+        match msg {
+            TrashMsg::ResFetched(_) => {}
+            TrashMsg::ResMarkDirty(_) => {}
+            TrashMsg::Reload => {
+                let limit = self.deleted_transactions.len().max(PAGE_SIZE);
+                self.fetch_page(None, limit, &mut orders_local);
+            }
+            TrashMsg::LoadMore => {
+                if let (Some(next), false) = (self.next, self.loading) {
+                    self.fetch_page(Some(next), PAGE_SIZE, &mut orders_local);
+                }
+            }
+            TrashMsg::FetchedPage { append, page } => {
+                self.loading = false;
+                if !append {
+                    self.deleted_transactions.clear();
+                }
+                // a changed transaction may have been fetched by itself already
+                let fetched = &self.deleted_transactions;
+                let transactions: Vec<_> = page
+                    .transactions
+                    .into_iter()
+                    .filter(|tr| fetched.iter().all(|f| f.id != tr.id))
+                    .collect();
+                self.deleted_transactions.extend(transactions);
+                self.total = page.total;
+                self.next = page.next;
+            }
+            TrashMsg::Refresh(id) => self.fetch_change(id, &mut orders_local),
+            TrashMsg::FetchedChange { id, page, total } => {
+                self.deleted_transactions.retain(|tr| tr.id != id);
+                self.total = total;
+
+                for tr in page.transactions {
+                    // keep the list ordered by time and id, newest first
+                    let position = self
+                        .deleted_transactions
+                        .iter()
+                        .position(|other| (other.time, other.id) < (tr.time, tr.id))
+                        .unwrap_or(self.deleted_transactions.len());
+
+                    // a transaction after the fetched ones is on a later page
+                    if position < self.deleted_transactions.len() || self.next.is_none() {
+                        self.deleted_transactions.insert(position, tr);
+                    }
+                }
+            }
+            TrashMsg::RestoreTransaction(id) => {
+                self.restore(
+                    format!("/api/transaction/{}/restore", id),
+                    &mut orders_local,
+                );
+            }
+            TrashMsg::RestoreItem(id) => {
+                self.restore(
+                    format!("/api/inventory/item/{}/restore", id),
+                    &mut orders_local,
+                );
+            }
+            TrashMsg::Restored => {
+                self.request_in_progress = false;
+                orders_local.send_msg(TrashMsg::Reload);
+                rs.mark_as_dirty(Res::deleted_items_url(), orders);
+                rs.mark_as_dirty(Res::book_accounts_url(), orders);
+                rs.mark_as_dirty("/api/inventory/items", orders);
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 5000,
+                    notification: Notification {
+                        title: strings::RESTORED.to_string(),
+                        body: None,
+                    },
+                }));
+            }
+            TrashMsg::ServerError(message) => {
+                self.request_in_progress = false;
+                self.loading = false;
+                orders.send_msg(Msg::Notification(NotificationMessage::ShowNotification {
+                    duration_ms: 10000,
+                    notification: Notification {
+                        title: strings::SERVER_ERROR.to_string(),
+                        body: Some(message),
+                    },
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn restore(&mut self, url: String, orders: &mut impl Orders<TrashMsg>) {
+        if self.request_in_progress {
+            return;
+        }
+
+        self.request_in_progress = true;
+        orders.perform_cmd(async move {
+            let result: fetch::Result<_> = async {
+                Request::new(url)
+                    .method(Method::Post)
+                    .fetch()
+                    .await?
+                    .check_status()
+            }
+            .await;
+
+            match result {
+                Ok(_) => TrashMsg::Restored,
+                Err(e) => {
+                    error!("Failed to restore", e);
+                    TrashMsg::ServerError(format!("{:?}", e))
+                }
+            }
+        });
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return Loading::view(),
+        };
+
+        let account_name = |id: &BookAccountId| {
+            res.book_accounts
+                .get(id)
+                .map(|acc| acc.name.as_str())
+                .unwrap_or("[MISSING]")
+        };
+
+        let restore_button = |msg: TrashMsg| {
+            button![
+                C![C.wide_button],
+                IF!(self.request_in_progress => attrs! {At::Disabled => true}),
+                simple_ev(Ev::Click, msg),
+                strings::RESTORE,
+            ]
+        };
+
+        let transaction_row = |tr: &Transaction| {
+            tr![
+                td![tr
+                    .time
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()],
+                td![tr.description.as_deref().unwrap_or_default()],
+                td![account_name(&tr.debited_account)],
+                td![account_name(&tr.credited_account)],
+                td![format!("{}:-", tr.amount)],
+                td![restore_button(TrashMsg::RestoreTransaction(tr.id))],
+            ]
+        };
+
+        let item_row = |item: &InventoryItem| {
+            tr![
+                td![attrs! { At::ColSpan => 4 }, &item.name],
+                td![item
+                    .price
+                    .map(|price| format!("{}:-", Currency::from(price)))
+                    .unwrap_or_default()],
+                td![restore_button(TrashMsg::RestoreItem(item.id))],
+            ]
+        };
+
+        let table_wide = || attrs! { At::ColSpan => 6 };
+
+        div![
+            C![C.inventory_page],
+            table![
+                td![table_wide(), h1![strings::DELETED_TRANSACTIONS]],
+                tr![
+                    th!["Datum"],
+                    th!["Beskrivning"],
+                    th!["Debet"],
+                    th!["Kredit"],
+                    th!["Summa"],
+                    th![],
+                ],
+                self.deleted_transactions.iter().map(transaction_row),
+                IF!(self.next.is_some() => tr![td![
+                    table_wide(),
+                    button![
+                        C![C.wide_button],
+                        IF!(self.loading => attrs! {At::Disabled => true}),
+                        format!(
+                            "Visa fler ({} av {})",
+                            self.deleted_transactions.len(),
+                            self.total
+                        ),
+                        simple_ev(Ev::Click, TrashMsg::LoadMore),
+                    ],
+                ]]),
+                td![table_wide(), h1![strings::DELETED_ITEMS]],
+                tr![th![attrs! { At::ColSpan => 4 }, "Namn"], th!["Pris"], th![]],
+                res.deleted_items.iter().map(item_row),
+            ],
+        ]
+        .map_msg(Msg::Trash)
+    }
+}
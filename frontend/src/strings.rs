@@ -9,6 +9,7 @@ pub const DEPOSIT: &str = "Sätt in";
 pub const CHOOSE_TILLGODO_ACC: &str = "Välj Tillgodokonto";
 
 pub const IZETTLE: &str = "iZettle";
+pub const SWISH: &str = "Swish";
 pub const OTHER_EPAY: &str = "Annat";
 
 pub const FIRST_NAME: &str = "Förnamn";
@@ -30,10 +31,98 @@ pub const PAYMENT_CANCELLED: &str = "Betalning avbruten";
 pub const NO_PENDING_TRANSACTION: &str = "Ingen pågående transaktion";
 pub const POSTING_TRANSACTION_FAILED: &str = "Misslyckades med att skicka transaktion";
 pub const POLLING_TRANSACTION_FAILED: &str = "Misslyckades med att polla transaktion";
+pub const NO_PAYMENT_TERMINAL: &str = "Ingen betalterminal är ansluten";
+pub const SWISH_NOT_CONFIGURED: &str = "Swish är inte konfigurerat";
+pub const SCAN_SWISH_QR: &str = "Skanna QR-koden med Swish";
 
 pub const TRANSACTION_TOTAL: &str = "Totalt:";
+pub const SPLIT_PAYMENT: &str = "Dela betalning";
+pub const UNALLOCATED: &str = "Kvar att fördela:";
+pub const CREDIT_LIMIT_EXCEEDED: &str = "Köpet överskrider kontots kreditgräns";
 
 pub const INVENTORY_ITEMS: &str = "Varor";
 pub const INVENTORY_BUNDLES: &str = "Paket";
 pub const NEW_ITEM: &str = "Ny vara";
 pub const NEW_BUNDLE: &str = "Nytt paket";
+
+pub const LOGIN: &str = "Logga in";
+pub const USER_NAME: &str = "Användarnamn";
+pub const PASSWORD: &str = "Lösenord";
+pub const LOGIN_FAILED: &str = "Fel användarnamn eller lösenord";
+
+pub const SAVE: &str = "Spara";
+pub const STOCK_TAKE: &str = "Inventering";
+pub const START_STOCK_TAKE: &str = "Starta inventering";
+pub const COMMIT_STOCK_TAKE: &str = "Slutför inventering";
+pub const ABANDON_STOCK_TAKE: &str = "Avbryt inventering";
+pub const STOCK_TAKE_COMPLETE: &str = "Inventering slutförd";
+
+pub const CASH_REGISTER: &str = "Kassa";
+pub const OPENING_FLOAT: &str = "Växelkassa";
+pub const EXPECTED_CASH: &str = "Förväntat i kassan";
+pub const COUNTED_CASH: &str = "Räknat i kassan";
+pub const CASH_DIFFERENCE: &str = "Kassadifferens";
+pub const OPEN_CASH_REGISTER: &str = "Öppna kassa";
+pub const CLOSE_CASH_REGISTER: &str = "Stäng kassa";
+pub const CASH_REGISTER_OPENED: &str = "Kassan öppnad";
+pub const CASH_REGISTER_CLOSED: &str = "Kassan stängd";
+pub const Z_REPORT: &str = "Z-rapport";
+
+pub const PURCHASES: &str = "Inköp";
+pub const NEW_PURCHASE: &str = "Nytt inköp";
+pub const SUPPLIER: &str = "Leverantör";
+pub const INVOICE_REFERENCE: &str = "Fakturanummer";
+pub const BANK: &str = "Bank";
+pub const CASH: &str = "Kontant";
+pub const CHOOSE_ITEM: &str = "Välj vara";
+pub const INVALID_QUANTITY: &str = "Ogiltigt antal";
+pub const ADD_PURCHASE_LINE: &str = "Lägg till rad";
+pub const RECORD_PURCHASE: &str = "Registrera inköp";
+pub const PURCHASE_RECORDED: &str = "Inköp registrerat";
+
+pub const DELETED_TRANSACTIONS: &str = "Raderade transaktioner";
+pub const DELETED_ITEMS: &str = "Raderade varor";
+pub const RESTORE: &str = "Återställ";
+pub const RESTORED: &str = "Återställd";
+
+pub const PAYMENT_TERMINALS: &str = "Betalterminaler";
+pub const ANY_TERMINAL: &str = "Valfri ansluten terminal";
+pub const PAIR_TERMINAL: &str = "Använd vid denna kassa";
+pub const PAIRED: &str = "Används vid denna kassa";
+pub const TERMINAL_CONNECTED: &str = "Ansluten";
+pub const TERMINAL_DISCONNECTED: &str = "Ej ansluten";
+pub const ADD_TERMINAL: &str = "Lägg till terminal";
+pub const DELETE: &str = "Radera";
+
+pub const EVENTS: &str = "Evenemang";
+pub const NEW_EVENT: &str = "Nytt evenemang";
+pub const EDIT_EVENT: &str = "Redigera evenemang";
+pub const INVALID_EVENT: &str = "Ogiltigt evenemang";
+pub const TITLE: &str = "Titel";
+pub const LOCATION: &str = "Plats";
+pub const DESCRIPTION: &str = "Beskrivning";
+pub const START_TIME: &str = "Börjar";
+pub const END_TIME: &str = "Slutar";
+pub const PRICE: &str = "Pris";
+pub const CAPACITY: &str = "Antal platser";
+pub const UNLIMITED: &str = "Obegränsat";
+pub const PUBLISH: &str = "Publicera";
+pub const UNPUBLISH: &str = "Avpublicera";
+pub const EDIT: &str = "Redigera";
+pub const CANCEL: &str = "Avbryt";
+pub const SIGNUPS: &str = "Anmälningar";
+pub const NO_SIGNUPS: &str = "Inga anmälningar";
+pub const ATTENDEES: &str = "Deltagare";
+pub const ATTENDING: &str = "Deltar";
+pub const WAITLIST: &str = "Reservlista";
+pub const CANCEL_SIGNUP: &str = "Avanmäl";
+pub const CANCELLATION_DEADLINE: &str = "Sista avanmälan";
+pub const CHOOSE_MEMBER: &str = "Välj medlem";
+pub const SIGN_UP: &str = "Anmäl";
+pub const PAID: &str = "Betald";
+
+pub const REFUND: &str = "Återbetala";
+pub const REFUNDED: &str = "Återbetald";
+pub const REFUND_PENDING: &str = "Återbetalning pågår";
+pub const REFUND_FAILED: &str = "Återbetalningen misslyckades";
+pub const REFUND_CANCELLED: &str = "Återbetalningen avbröts";
@@ -0,0 +1,139 @@
+//! A stand-in for the iZettle payment bridge, for developing and testing card payments without a
+//! card reader.
+//!
+//! The simulator connects to the server as a registered terminal, and answers every pending
+//! payment and refund according to a script, after a random delay.
+
+pub mod script;
+
+use crate::script::Script;
+use log::{error, info};
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::Duration;
+use strecklistan_api::izettle_bridge::{BridgeMessage, ServerMessage};
+use tokio::select;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+
+/// A payment or refund which is waiting for a response
+///
+/// Not named `Pending`, since older versions of `tokio::select!` import `Poll::Pending` into the
+/// scope of its branches.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Awaiting {
+    Payment(i32),
+    Refund(i32),
+}
+
+/// A simulated payment terminal
+pub struct Simulator {
+    script: Script,
+
+    /// Minimum number of milliseconds to wait before responding
+    min_delay: u64,
+
+    /// Maximum number of milliseconds to wait before responding
+    max_delay: u64,
+}
+
+impl Simulator {
+    pub fn new(script: Script, min_delay: u64, max_delay: u64) -> Self {
+        Simulator {
+            script,
+            min_delay,
+            max_delay: max_delay.max(min_delay),
+        }
+    }
+
+    /// Answer the payments and refunds received from the server on `messages` by sending the
+    /// responses on `responses`, until `messages` is closed.
+    ///
+    /// A payment or refund which is cancelled before the delay has passed is not answered.
+    pub async fn run(
+        mut self,
+        mut messages: UnboundedReceiver<ServerMessage>,
+        responses: UnboundedSender<BridgeMessage>,
+    ) {
+        let (delayed_tx, mut delayed) = mpsc::unbounded_channel();
+        let mut waiting: HashMap<Awaiting, JoinHandle<()>> = HashMap::new();
+
+        loop {
+            select! {
+                message = messages.recv() => {
+                    let message = match message {
+                        Some(message) => message,
+                        None => break,
+                    };
+
+                    let (pending, response) = match message {
+                        ServerMessage::PendingPayment(payment) => {
+                            let behaviour = self.script.next_behaviour();
+                            info!("Payment {} of {}: {:?}", payment.id, payment.amount, behaviour);
+                            let response = BridgeMessage::PaymentResponse {
+                                reference: payment.id,
+                                response: behaviour.payment_response(),
+                            };
+                            (Awaiting::Payment(payment.id), response)
+                        }
+                        ServerMessage::PendingRefund(refund) => {
+                            let behaviour = self.script.next_behaviour();
+                            info!("Refund {} of {}: {:?}", refund.id, refund.amount, behaviour);
+                            let response = BridgeMessage::RefundResponse {
+                                reference: refund.id,
+                                response: behaviour.refund_response(),
+                            };
+                            (Awaiting::Refund(refund.id), response)
+                        }
+                        ServerMessage::PaymentCancelled { reference } => {
+                            info!("Payment {} was cancelled", reference);
+                            abort(&mut waiting, Awaiting::Payment(reference));
+                            continue;
+                        }
+                        ServerMessage::RefundCancelled { reference } => {
+                            info!("Refund {} was cancelled", reference);
+                            abort(&mut waiting, Awaiting::Refund(reference));
+                            continue;
+                        }
+                        ServerMessage::PaymentRecorded { reference } => {
+                            info!("Payment {} was recorded", reference);
+                            continue;
+                        }
+                        ServerMessage::RefundRecorded { reference } => {
+                            info!("Refund {} was recorded", reference);
+                            continue;
+                        }
+                        ServerMessage::Error { message } => {
+                            error!("Server error: {}", message);
+                            continue;
+                        }
+                    };
+
+                    let delay = rand::thread_rng().gen_range(self.min_delay..=self.max_delay);
+                    let delayed_tx = delayed_tx.clone();
+                    let task = tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                        let _ = delayed_tx.send((pending, response));
+                    });
+                    waiting.insert(pending, task);
+                }
+                Some((pending, response)) = delayed.recv() => {
+                    if waiting.remove(&pending).is_some() {
+                        let _ = responses.send(response);
+                    }
+                }
+            }
+        }
+
+        for (_, task) in waiting {
+            task.abort();
+        }
+    }
+}
+
+/// Stop waiting to respond to a payment or refund which is no longer pending
+fn abort(waiting: &mut HashMap<Awaiting, JoinHandle<()>>, pending: Awaiting) {
+    if let Some(task) = waiting.remove(&pending) {
+        task.abort();
+    }
+}
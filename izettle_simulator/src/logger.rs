@@ -0,0 +1,24 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Logs every message of the simulator to stderr
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target().starts_with("strecklistan")
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Log the messages up to `level` to stderr
+pub fn init(level: LevelFilter) {
+    log::set_logger(&StderrLogger).expect("A logger is already set");
+    log::set_max_level(level);
+}
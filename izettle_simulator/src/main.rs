@@ -0,0 +1,121 @@
+//! Connects the simulated iZettle payment bridge to a strecklistan server
+
+mod logger;
+
+use clap::Parser;
+use log::{error, info, LevelFilter};
+use std::error::Error;
+use strecklistan_api::user::Credentials;
+use strecklistan_client::Client;
+use strecklistan_izettle_simulator::script::Script;
+use strecklistan_izettle_simulator::Simulator;
+use tokio::select;
+use tokio::sync::mpsc;
+
+type SimResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Parser)]
+pub struct Opt {
+    /// Url of the strecklistan server, used to log in
+    #[clap(
+        long,
+        env = "STRECKLISTAN_URL",
+        default_value = "http://localhost:8000"
+    )]
+    server: String,
+
+    /// Url which the server listens for payment bridges on
+    #[clap(
+        long,
+        env = "IZETTLE_BRIDGE_URL",
+        default_value = "ws://localhost:8001"
+    )]
+    bridge: String,
+
+    /// Id of the registered terminal to connect as
+    #[clap(long, short, env = "IZETTLE_TERMINAL")]
+    terminal: String,
+
+    /// Name of the cashier to log in as
+    #[clap(long, short, env = "SIMULATOR_USER", required_unless_present = "token")]
+    user: Option<String>,
+
+    /// Password of the cashier to log in as
+    #[clap(long, short, env = "SIMULATOR_PASSWORD", default_value = "")]
+    password: String,
+
+    /// Session token to authenticate with, instead of logging in
+    #[clap(long, env = "SIMULATOR_TOKEN", conflicts_with = "user")]
+    token: Option<String>,
+
+    /// Comma-separated responses to cycle through: pay, cancel, fail or fail:<reason>
+    #[clap(long, short, default_value = "pay")]
+    script: Script,
+
+    /// Minimum number of milliseconds to wait before responding
+    #[clap(long, default_value_t)]
+    min_delay: u64,
+
+    /// Maximum number of milliseconds to wait before responding
+    #[clap(long, default_value_t)]
+    max_delay: u64,
+
+    /// The most detailed messages to log: off, error, warn, info, debug or trace
+    #[clap(long, env = "LOG_LEVEL", default_value = "info")]
+    log_level: LevelFilter,
+}
+
+#[tokio::main]
+async fn main() {
+    let opt = Opt::parse();
+    logger::init(opt.log_level);
+
+    if let Err(e) = run(opt).await {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(mut opt: Opt) -> SimResult<()> {
+    let client = match (opt.token.take(), opt.user.take()) {
+        (Some(token), _) => Client::new(&opt.server).with_token(token),
+        (None, Some(name)) => {
+            let mut client = Client::new(&opt.server);
+            let credentials = Credentials {
+                name,
+                password: opt.password.clone(),
+            };
+            client
+                .login(&credentials)
+                .await
+                .map_err(|e| format!("Failed to log in: {}", e))?;
+            client
+        }
+        (None, None) => return Err("Either a user or a token is required".into()),
+    };
+
+    let mut bridge = client.connect_bridge(&opt.bridge, &opt.terminal).await?;
+    info!("Connected to {} as terminal {}", opt.bridge, opt.terminal);
+
+    let (messages, messages_rx) = mpsc::unbounded_channel();
+    let (responses_tx, mut responses) = mpsc::unbounded_channel();
+    let simulator = Simulator::new(opt.script, opt.min_delay, opt.max_delay);
+    let simulation = tokio::spawn(simulator.run(messages_rx, responses_tx));
+
+    loop {
+        select! {
+            message = bridge.recv() => match message? {
+                Some(message) => {
+                    let _ = messages.send(message);
+                }
+                None => break,
+            },
+            Some(response) = responses.recv() => bridge.send(&response).await?,
+        }
+    }
+
+    drop(messages);
+    simulation.await?;
+    info!("Disconnected");
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use std::str::FromStr;
+use strecklistan_api::izettle_bridge::{PaymentResponse, RefundResponse};
+
+/// How the simulated terminal responds to a payment or refund
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Behaviour {
+    Pay,
+    Fail(String),
+    Cancel,
+}
+
+/// A list of behaviours which are cycled through, one for each payment or refund
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Script {
+    steps: Vec<Behaviour>,
+    next: usize,
+}
+
+impl Behaviour {
+    pub fn payment_response(&self) -> PaymentResponse {
+        match self {
+            Behaviour::Pay => PaymentResponse::TransactionPaid {
+                card_type: Some("MASTERCARD".to_string()),
+                card_payment_entry_mode: Some("CONTACTLESS_EMV".to_string()),
+                card_issuing_bank: Some("SIMULATOR".to_string()),
+                masked_pan: Some("************1234".to_string()),
+            },
+            Behaviour::Fail(reason) => PaymentResponse::TransactionFailed {
+                reason: reason.clone(),
+            },
+            Behaviour::Cancel => PaymentResponse::TransactionCancelled,
+        }
+    }
+
+    pub fn refund_response(&self) -> RefundResponse {
+        match self {
+            Behaviour::Pay => RefundResponse::RefundCompleted,
+            Behaviour::Fail(reason) => RefundResponse::RefundFailed {
+                reason: reason.clone(),
+            },
+            Behaviour::Cancel => RefundResponse::RefundCancelled,
+        }
+    }
+}
+
+impl Script {
+    /// Get the behaviour for the next payment or refund
+    pub fn next_behaviour(&mut self) -> &Behaviour {
+        let behaviour = &self.steps[self.next];
+        self.next = (self.next + 1) % self.steps.len();
+        behaviour
+    }
+}
+
+impl FromStr for Behaviour {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "pay" => Ok(Behaviour::Pay),
+            "cancel" => Ok(Behaviour::Cancel),
+            "fail" => Ok(Behaviour::Fail("Simulated failure".to_string())),
+            s => match s.strip_prefix("fail:") {
+                Some(reason) => Ok(Behaviour::Fail(reason.to_string())),
+                None => Err(format!(
+                    "Invalid behaviour \"{}\", expected pay, cancel, fail or fail:<reason>",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Parse a comma-separated list of behaviours, e.g. `pay,fail:Card declined,cancel`
+impl FromStr for Script {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .split(',')
+            .map(Behaviour::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Script { steps, next: 0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() {
+        let script: Script = "pay, fail:Card declined,cancel,fail".parse().unwrap();
+        assert_eq!(
+            script.steps,
+            vec![
+                Behaviour::Pay,
+                Behaviour::Fail("Card declined".to_string()),
+                Behaviour::Cancel,
+                Behaviour::Fail("Simulated failure".to_string()),
+            ]
+        );
+
+        assert!("pay,refuse".parse::<Script>().is_err());
+        assert!("".parse::<Script>().is_err());
+    }
+
+    #[test]
+    fn test_cycle_script() {
+        let mut script: Script = "pay,cancel".parse().unwrap();
+        assert_eq!(script.next_behaviour(), &Behaviour::Pay);
+        assert_eq!(script.next_behaviour(), &Behaviour::Cancel);
+        assert_eq!(script.next_behaviour(), &Behaviour::Pay);
+    }
+}
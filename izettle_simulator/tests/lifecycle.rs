@@ -0,0 +1,138 @@
+use std::time::Duration;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::izettle_bridge::{
+    BridgeMessage, PaymentResponse, PendingPayment, PendingRefund, RefundResponse, ServerMessage,
+};
+use strecklistan_izettle_simulator::Simulator;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+/// Run a simulator with `script` in the background
+fn start(
+    script: &str,
+    delay: u64,
+) -> (
+    UnboundedSender<ServerMessage>,
+    UnboundedReceiver<BridgeMessage>,
+    JoinHandle<()>,
+) {
+    let (messages, messages_rx) = mpsc::unbounded_channel();
+    let (responses_tx, responses) = mpsc::unbounded_channel();
+    let simulator = Simulator::new(script.parse().unwrap(), delay, delay);
+    let task = tokio::spawn(simulator.run(messages_rx, responses_tx));
+    (messages, responses, task)
+}
+
+fn payment(id: i32) -> ServerMessage {
+    ServerMessage::PendingPayment(PendingPayment {
+        id,
+        amount: Currency::from(1500),
+    })
+}
+
+fn refund(id: i32) -> ServerMessage {
+    ServerMessage::PendingRefund(PendingRefund {
+        id,
+        izettle_transaction_id: 1,
+        amount: Currency::from(1500),
+    })
+}
+
+async fn next_response(responses: &mut UnboundedReceiver<BridgeMessage>) -> BridgeMessage {
+    timeout(Duration::from_secs(5), responses.recv())
+        .await
+        .expect("The simulator did not respond")
+        .expect("The simulator stopped")
+}
+
+#[tokio::test]
+async fn test_payments_follow_script() {
+    let (messages, mut responses, task) = start("pay,fail:Card declined,cancel", 0);
+
+    for id in 1..=4 {
+        messages.send(payment(id)).unwrap();
+        let response = next_response(&mut responses).await;
+        let expected = match id {
+            1 | 4 => PaymentResponse::TransactionPaid {
+                card_type: Some("MASTERCARD".to_string()),
+                card_payment_entry_mode: Some("CONTACTLESS_EMV".to_string()),
+                card_issuing_bank: Some("SIMULATOR".to_string()),
+                masked_pan: Some("************1234".to_string()),
+            },
+            2 => PaymentResponse::TransactionFailed {
+                reason: "Card declined".to_string(),
+            },
+            _ => PaymentResponse::TransactionCancelled,
+        };
+        assert_eq!(
+            response,
+            BridgeMessage::PaymentResponse {
+                reference: id,
+                response: expected,
+            }
+        );
+
+        // the server confirms that it has recorded the response
+        messages
+            .send(ServerMessage::PaymentRecorded { reference: id })
+            .unwrap();
+    }
+
+    drop(messages);
+    task.await.unwrap();
+    assert!(responses.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_refunds_follow_script() {
+    let (messages, mut responses, task) = start("pay,fail", 0);
+
+    messages.send(refund(7)).unwrap();
+    assert_eq!(
+        next_response(&mut responses).await,
+        BridgeMessage::RefundResponse {
+            reference: 7,
+            response: RefundResponse::RefundCompleted,
+        }
+    );
+
+    messages.send(refund(8)).unwrap();
+    assert_eq!(
+        next_response(&mut responses).await,
+        BridgeMessage::RefundResponse {
+            reference: 8,
+            response: RefundResponse::RefundFailed {
+                reason: "Simulated failure".to_string(),
+            },
+        }
+    );
+
+    drop(messages);
+    task.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cancelled_payment_is_not_answered() {
+    let (messages, mut responses, task) = start("pay", 200);
+
+    messages.send(payment(1)).unwrap();
+    messages
+        .send(ServerMessage::PaymentCancelled { reference: 1 })
+        .unwrap();
+    messages.send(refund(2)).unwrap();
+    messages
+        .send(ServerMessage::RefundCancelled { reference: 2 })
+        .unwrap();
+    messages.send(payment(3)).unwrap();
+
+    // only the payment which is still pending is answered
+    match next_response(&mut responses).await {
+        BridgeMessage::PaymentResponse { reference, .. } => assert_eq!(reference, 3),
+        response => panic!("Unexpected response {:?}", response),
+    }
+
+    drop(messages);
+    task.await.unwrap();
+    assert!(responses.recv().await.is_none());
+}